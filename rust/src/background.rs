@@ -0,0 +1,79 @@
+use std::fmt::Debug;
+
+use crate::{Sampler, Vec3};
+
+mod environment;
+
+pub use environment::EnvironmentMap;
+
+/// Public Trait for what the camera sees when a ray misses the world
+/// The background also lights the scene, as diffuse rays that escape pick up its color
+pub trait Background: Sync + Send + Debug {
+    /// Radiance arriving from the direction (Not normalized)
+    fn color(&self, direction: &Vec3) -> Vec3;
+    /// If the background can be importance sampled with [Background::random] and [Background::pdf_value]
+    /// Sampled backgrounds are sampled directly at every diffuse bounce, like the lights in a [LightList](crate::LightList)
+    fn is_sampled(&self) -> bool {
+        false
+    }
+    /// Probability density (over solid angle) of [Background::random] generating the direction
+    #[allow(unused_variables)]
+    fn pdf_value(&self, direction: &Vec3) -> f64 {
+        0_f64
+    }
+    /// Generates a random direction towards the background (Unit vector)
+    #[allow(unused_variables)]
+    fn random(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        Vec3::new_int(1, 0, 0)
+    }
+}
+
+/// Constant color in every direction
+#[derive(Debug)]
+pub struct SolidBackground {
+    pub color: Vec3,
+}
+impl Background for SolidBackground {
+    fn color(&self, _: &Vec3) -> Vec3 {
+        self.color.clone()
+    }
+}
+
+/// Blends from the bottom color (Looking straight down) to the top color (Looking straight up)
+#[derive(Debug)]
+pub struct GradientBackground {
+    pub bottom: Vec3,
+    pub top: Vec3,
+}
+impl GradientBackground {
+    /// The classic blue sky from the first book
+    pub fn sky() -> Self {
+        Self {
+            bottom: Vec3::new(1.0, 1.0, 1.0),
+            top: Vec3::new(0.5, 0.7, 1.0),
+        }
+    }
+}
+impl Background for GradientBackground {
+    fn color(&self, direction: &Vec3) -> Vec3 {
+        let a = 0.5_f64 * (direction.unit_vector().y() + 1_f64);
+        (1_f64 - a) * self.bottom.clone() + a * self.top.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_gradient_background() {
+        let sky = GradientBackground::sky();
+        assert_eq!(sky.color(&Vec3::new_int(0, -2, 0)), sky.bottom);
+        assert_eq!(sky.color(&Vec3::new_int(0, 3, 0)), sky.top);
+        assert_eq!(
+            sky.color(&Vec3::new_int(1, 0, 0)),
+            Vec3::new(0.75, 0.85, 1.0)
+        );
+        assert!(!sky.is_sampled());
+    }
+}
@@ -0,0 +1,312 @@
+use std::{
+    f64::consts::PI,
+    fmt::{Debug, Display},
+    fs::File,
+    io::BufReader,
+    path::Path,
+};
+
+use super::Background;
+use crate::{Sampler, Vec3, Vec3Axis};
+use image::{
+    codecs::hdr::HdrDecoder,
+    error::{LimitError, LimitErrorKind},
+    io::Reader as ImageReader,
+    ImageError, Rgb32FImage,
+};
+
+/// Environment map from an equirectangular (latitude-longitude) image, usually a `.hdr` or `.exr`
+/// The top row of the image is straight up (+y), and the center of the image looks towards -x
+///
+/// Importance sampled by the luminance of each pixel, so small bright areas (i.e. the sun) are found quickly
+pub struct EnvironmentMap {
+    width: usize,
+    height: usize,
+    /// Linear colors, row by row from the top of the image
+    pixels: Vec<Vec3>,
+    /// Rotation (Degrees 360) of the map around the y axis
+    rotation: f64,
+    /// Multiplier for the colors of the image
+    intensity: f64,
+
+    /// Cumulative distribution of picking each row (height)
+    row_cdf: Vec<f64>,
+    /// Cumulative distribution of picking each pixel in its row (width * height)
+    column_cdfs: Vec<f64>,
+    /// Probability of picking each pixel (width * height)
+    pixel_probabilities: Vec<f64>,
+    /// Completely black maps can not be sampled
+    sampled: bool,
+}
+impl EnvironmentMap {
+    /// Loads the image at image_path, returns an error if it can not be opened, decoded or is empty
+    /// rotation (Degrees 360) rotates the map around the y axis, intensity scales its brightness
+    pub fn new(image_path: &str, rotation: f64, intensity: f64) -> Result<Self, ImageError> {
+        let is_hdr = Path::new(image_path)
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("hdr"));
+        let image = if is_hdr {
+            load_hdr(image_path)?
+        } else {
+            ImageReader::open(image_path)?.decode()?.to_rgb32f()
+        };
+        if image.width() == 0 || image.height() == 0 {
+            return Err(ImageError::Limits(LimitError::from_kind(
+                LimitErrorKind::DimensionError,
+            )));
+        }
+        Ok(Self::from_image(&image, rotation, intensity))
+    }
+    /// Creates the environment map from a linear float image
+    /// Panics if the image has no dimensions
+    pub fn from_image(image: &Rgb32FImage, rotation: f64, intensity: f64) -> Self {
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        if width == 0 || height == 0 {
+            panic!("Environment map has no dimensions");
+        }
+        let pixels: Vec<Vec3> = image
+            .pixels()
+            .map(|pixel| {
+                let [r, g, b] = pixel.0;
+                Vec3::new(r as f64, g as f64, b as f64)
+            })
+            .collect();
+
+        // Rows near the poles cover less of the sphere, so they are weighted down by sin(theta)
+        let weights: Vec<f64> = pixels
+            .iter()
+            .enumerate()
+            .map(|(index, color)| {
+                let theta = ((index / width) as f64 + 0.5_f64) / height as f64 * PI;
+                color.luminance().max(0_f64) * theta.sin()
+            })
+            .collect();
+        let total_weight: f64 = weights.iter().sum();
+
+        let mut row_cdf = Vec::with_capacity(height);
+        let mut column_cdfs = Vec::with_capacity(width * height);
+        let mut accumulated_rows = 0_f64;
+        for row in weights.chunks_exact(width) {
+            let row_weight: f64 = row.iter().sum();
+            let mut accumulated = 0_f64;
+            for weight in row {
+                accumulated += weight;
+                column_cdfs.push(if row_weight > 0_f64 {
+                    accumulated / row_weight
+                } else {
+                    1_f64
+                });
+            }
+            accumulated_rows += row_weight;
+            row_cdf.push(if total_weight > 0_f64 {
+                accumulated_rows / total_weight
+            } else {
+                1_f64
+            });
+        }
+        let pixel_probabilities = weights
+            .iter()
+            .map(|weight| {
+                if total_weight > 0_f64 {
+                    weight / total_weight
+                } else {
+                    0_f64
+                }
+            })
+            .collect();
+
+        Self {
+            width,
+            height,
+            pixels,
+            rotation,
+            intensity,
+            row_cdf,
+            column_cdfs,
+            pixel_probabilities,
+            sampled: total_weight > 0_f64,
+        }
+    }
+
+    /// Image cordinates (u, v) of the direction, both between [0, 1] with v = 0 at the top of the image
+    fn direction_to_uv(&self, direction: &Vec3) -> (f64, f64) {
+        let d = direction
+            .rotate_about_axis(&Vec3Axis::Y, -self.rotation)
+            .unit_vector();
+        let phi = f64::atan2(-d.z(), d.x()) + PI;
+        let theta = d.y().clamp(-1_f64, 1_f64).acos();
+        (phi / (2_f64 * PI), theta / PI)
+    }
+    fn uv_to_direction(&self, u: f64, v: f64) -> Vec3 {
+        let phi = u * 2_f64 * PI;
+        let theta = v * PI;
+        Vec3::new(
+            -phi.cos() * theta.sin(),
+            theta.cos(),
+            phi.sin() * theta.sin(),
+        )
+        .rotate_about_axis(&Vec3Axis::Y, self.rotation)
+    }
+    /// Index of the pixel that (u, v) falls into
+    fn pixel_index(&self, u: f64, v: f64) -> usize {
+        let x = ((u * self.width as f64) as usize).min(self.width - 1);
+        let y = ((v * self.height as f64) as usize).min(self.height - 1);
+        y * self.width + x
+    }
+}
+// Implementation Details:
+// The generic decoder turns Radiance files into 8-bit images (Clamping the sun to 1), so they are read with the HdrDecoder directly
+fn load_hdr(image_path: &str) -> Result<Rgb32FImage, ImageError> {
+    let decoder = HdrDecoder::new(BufReader::new(File::open(image_path)?))?;
+    let metadata = decoder.metadata();
+    let (width, height) = (metadata.width, metadata.height);
+    let pixels = decoder.read_image_hdr()?;
+    Ok(Rgb32FImage::from_fn(width, height, |x, y| {
+        pixels[(y * width + x) as usize]
+    }))
+}
+
+impl Background for EnvironmentMap {
+    fn color(&self, direction: &Vec3) -> Vec3 {
+        let (u, v) = self.direction_to_uv(direction);
+        self.intensity * self.pixels[self.pixel_index(u, v)].clone()
+    }
+    fn is_sampled(&self) -> bool {
+        self.sampled
+    }
+    // Implementation Details:
+    // The pixels are picked with a piecewise constant pdf over (u, v), which has an area of 1 / (width * height) per pixel
+    // Mapping (u, v) onto the sphere stretches the area by 2 * PI * PI * sin(theta), so the pdf over solid angle is divided by it
+    fn pdf_value(&self, direction: &Vec3) -> f64 {
+        let (u, v) = self.direction_to_uv(direction);
+        let sin_theta = (v * PI).sin();
+        if sin_theta <= 0_f64 {
+            return 0_f64;
+        }
+        let uv_pdf =
+            self.pixel_probabilities[self.pixel_index(u, v)] * (self.width * self.height) as f64;
+        uv_pdf / (2_f64 * PI * PI * sin_theta)
+    }
+    fn random(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        // Float rounding can leave the last cdf value slightly under 1
+        let (r1, r2) = sampler.get_2d();
+        let y = self
+            .row_cdf
+            .partition_point(|cumulative| *cumulative <= r1)
+            .min(self.height - 1);
+        let row = &self.column_cdfs[y * self.width..(y + 1) * self.width];
+        let x = row
+            .partition_point(|cumulative| *cumulative <= r2)
+            .min(self.width - 1);
+
+        // The position inside the pixel reuses where r1 and r2 fell inside the cdf step of the pixel, to keep their stratification
+        let row_start = if y == 0 { 0_f64 } else { self.row_cdf[y - 1] };
+        let column_start = if x == 0 { 0_f64 } else { row[x - 1] };
+        let u = (x as f64 + remap(r2, column_start, row[x])) / self.width as f64;
+        let v = (y as f64 + remap(r1, row_start, self.row_cdf[y])) / self.height as f64;
+        self.uv_to_direction(u, v)
+    }
+}
+/// Where value falls in [start, end), as a fraction of the range
+fn remap(value: f64, start: f64, end: f64) -> f64 {
+    if end > start {
+        ((value - start) / (end - start)).clamp(0_f64, 1_f64)
+    } else {
+        0.5_f64
+    }
+}
+
+impl Debug for EnvironmentMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+impl Display for EnvironmentMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "EnvironmentMap(width: {}, height: {}, rotation: {}, intensity: {})",
+            self.width, self.height, self.rotation, self.intensity
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::IndependentSampler;
+    use image::Rgb;
+
+    /// Dim map with a single bright pixel (the sun)
+    fn sun_map(rotation: f64) -> EnvironmentMap {
+        let mut image = Rgb32FImage::from_pixel(16, 8, Rgb([0.125, 0.125, 0.125]));
+        image.put_pixel(4, 2, Rgb([100.0, 90.0, 80.0]));
+        EnvironmentMap::from_image(&image, rotation, 2.0)
+    }
+
+    #[test]
+    fn test_environment_map_uv() {
+        let map = sun_map(30.0);
+        for direction in [
+            Vec3::new(0.3, 0.5, -0.2),
+            Vec3::new(-1.0, 0.1, 0.4),
+            Vec3::new(0.0, -1.0, 0.5),
+        ] {
+            let (u, v) = map.direction_to_uv(&direction);
+            let round_trip = map.uv_to_direction(u, v);
+            assert!((round_trip - direction.unit_vector()).length() < 1e-9);
+        }
+        assert_eq!(
+            map.color(&Vec3::new_int(0, 1, 0)),
+            Vec3::new(0.25, 0.25, 0.25)
+        );
+    }
+
+    #[test]
+    fn test_environment_map_sampling() {
+        let map = sun_map(45.0);
+        assert!(map.is_sampled());
+        // The sun is picked far more often than its share of the image
+        let samples = 2000;
+        let mut sampler = IndependentSampler::new(0);
+        let sun_samples = (0..samples)
+            .filter(|_| map.color(&map.random(&mut sampler)).x() > 100.0)
+            .count();
+        assert!(sun_samples > samples / 2);
+
+        // The pdf integrates to 1 over the sphere (Midpoint rule over u, v)
+        let (steps_u, steps_v) = (256, 128);
+        let mut integral = 0_f64;
+        for i in 0..steps_u {
+            for j in 0..steps_v {
+                let (u, v) = (
+                    (i as f64 + 0.5) / steps_u as f64,
+                    (j as f64 + 0.5) / steps_v as f64,
+                );
+                let solid_angle = 2.0 * PI * PI * (v * PI).sin() / (steps_u * steps_v) as f64;
+                integral += map.pdf_value(&map.uv_to_direction(u, v)) * solid_angle;
+            }
+        }
+        assert!((integral - 1.0).abs() < 1e-6, "{}", integral);
+    }
+
+    #[test]
+    fn test_environment_map_from_file() {
+        let map = EnvironmentMap::new("assets/environments/sky.hdr", 0.0, 1.0).unwrap();
+        assert!(EnvironmentMap::new("assets/environments/does-not-exist.hdr", 0.0, 1.0).is_err());
+        assert!(EnvironmentMap::new("assets/models/cube.obj", 0.0, 1.0).is_err());
+        assert!(map.is_sampled());
+        // The sun only covers a handful of pixels, but is sampled far more often than that (~7% of the samples)
+        let mut sampler = IndependentSampler::new(0);
+        let bright_samples = (0..2000)
+            .filter(|_| map.color(&map.random(&mut sampler)).luminance() > 100.0)
+            .count();
+        assert!(bright_samples > 50, "{}", bright_samples);
+    }
+
+    #[test]
+    fn test_environment_map_black() {
+        let map = EnvironmentMap::from_image(&Rgb32FImage::new(4, 2), 0.0, 1.0);
+        assert!(!map.is_sampled());
+    }
+}
@@ -1,13 +1,13 @@
-use std::sync::Arc;
+use std::{io, sync::Arc};
 
 use clap::Parser;
-use image::RgbImage;
 use rust_simple_raytracer::{
     construct_planar_quad_box, Camera, CameraParams, Cli, Diffuse, HittablesList, Lambertain,
-    Materials, Quad, Rotation, SolidColor, Translation, Vec3, Vec3Axis, BVH,
+    LightList, Materials, Quad, RenderOutput, Rotation, SolidBackground, SolidColor, Translation,
+    Vec3, Vec3Axis, BVH,
 };
 
-fn scene() -> RgbImage {
+fn scene(cli: &Cli) -> io::Result<RenderOutput> {
     let red = Materials::ScatterMaterial(Arc::new(Lambertain {
         albedo: Arc::new(SolidColor {
             color: Vec3::new(0.65, 0.05, 0.05),
@@ -38,12 +38,16 @@ fn scene() -> RgbImage {
         Vec3::new_int(0, 0, 555),
         red.clone(),
     )));
-    hittable_list.add(Arc::new(Quad::new(
+    let ceiling_light = Arc::new(Quad::new(
         Vec3::new_int(343, 554, 332),
         Vec3::new_int(-130, 0, 0),
         Vec3::new_int(0, 0, -105),
         light.clone(),
-    )));
+    ));
+    hittable_list.add(ceiling_light.clone());
+    // Lights are sampled directly, so far fewer samples are needed
+    let mut lights = LightList::new();
+    lights.add(ceiling_light);
     hittable_list.add(Arc::new(Quad::new(
         Vec3::new_int(0, 0, 0),
         Vec3::new_int(555, 0, 0),
@@ -82,9 +86,9 @@ fn scene() -> RgbImage {
     hittable_list.add(Arc::new(box2));
     let world = BVH::from_hittables_list(hittable_list.v);
 
-    let camera_params = CameraParams {
+    let mut camera_params = CameraParams {
         aspect_ratio: 1.0,
-        samples_per_pixel: 500,
+        samples_per_pixel: 50,
         max_depth: 60,
         image_width: 600,
         fov: 40_f64,
@@ -92,18 +96,21 @@ fn scene() -> RgbImage {
         look_from: Vec3::new_int(278, 278, -800),
         look_at: Vec3::new_int(278, 278, 0),
         v_up: Vec3::new(0.0, 1.0, 0.0),
-        background: Vec3::new(0.0, 0.0, 0.0),
+        background: Arc::new(SolidBackground {
+            color: Vec3::new(0.0, 0.0, 0.0),
+        }),
         // focus_distance: 0.4,
         ..Default::default()
     };
+    cli.apply_to(&mut camera_params);
     let camera = Camera::new(camera_params);
 
     eprintln!("{:?}", camera);
     // eprintln!("{}", world);
-    camera.render_rgbimage(&world)
+    camera.render_output(&world, &lights)
 }
 
 fn main() {
     let cli = Cli::parse();
-    cli.save_image(scene());
+    cli.save_render_or_exit(scene(&cli));
 }
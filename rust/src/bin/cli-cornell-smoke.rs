@@ -0,0 +1,125 @@
+use std::{io, sync::Arc};
+
+use clap::Parser;
+use rust_simple_raytracer::{
+    construct_planar_quad_box, Camera, CameraParams, Cli, ConstantMedium, Diffuse, HittablesList,
+    Lambertain, LightList, Materials, Quad, RenderOutput, Rotation, SolidBackground, SolidColor,
+    Translation, Vec3, Vec3Axis, BVH,
+};
+
+fn scene(cli: &Cli) -> io::Result<RenderOutput> {
+    let red = Materials::ScatterMaterial(Arc::new(Lambertain {
+        albedo: Arc::new(SolidColor {
+            color: Vec3::new(0.65, 0.05, 0.05),
+        }),
+    }));
+    let white = Materials::ScatterMaterial(Arc::new(Lambertain {
+        albedo: Arc::new(SolidColor {
+            color: Vec3::new(0.73, 0.73, 0.73),
+        }),
+    }));
+    let green = Materials::ScatterMaterial(Arc::new(Lambertain {
+        albedo: Arc::new(SolidColor {
+            color: Vec3::new(0.12, 0.45, 0.15),
+        }),
+    }));
+    let light = Materials::LightMaterial(Arc::new(Diffuse { power: 7.0 }));
+
+    let mut hittable_list = HittablesList::new();
+    hittable_list.add(Arc::new(Quad::new(
+        Vec3::new_int(555, 0, 0),
+        Vec3::new_int(0, 555, 0),
+        Vec3::new_int(0, 0, 555),
+        green.clone(),
+    )));
+    hittable_list.add(Arc::new(Quad::new(
+        Vec3::new_int(0, 0, 0),
+        Vec3::new_int(0, 555, 0),
+        Vec3::new_int(0, 0, 555),
+        red.clone(),
+    )));
+    let ceiling_light = Arc::new(Quad::new(
+        Vec3::new_int(113, 554, 127),
+        Vec3::new_int(330, 0, 0),
+        Vec3::new_int(0, 0, 305),
+        light.clone(),
+    ));
+    hittable_list.add(ceiling_light.clone());
+    // Lights are sampled directly, so far fewer samples are needed
+    let mut lights = LightList::new();
+    lights.add(ceiling_light);
+    hittable_list.add(Arc::new(Quad::new(
+        Vec3::new_int(0, 0, 0),
+        Vec3::new_int(555, 0, 0),
+        Vec3::new_int(0, 0, 555),
+        white.clone(),
+    )));
+    hittable_list.add(Arc::new(Quad::new(
+        Vec3::new_int(555, 555, 555),
+        Vec3::new_int(-555, 0, 0),
+        Vec3::new_int(0, 0, -555),
+        white.clone(),
+    )));
+    hittable_list.add(Arc::new(Quad::new(
+        Vec3::new_int(0, 0, 555),
+        Vec3::new_int(555, 0, 0),
+        Vec3::new_int(0, 555, 0),
+        white.clone(),
+    )));
+    let box1 = construct_planar_quad_box(
+        &Vec3::new_int(0, 0, 0),
+        &Vec3::new_int(165, 330, 165),
+        white.clone(),
+    );
+    let box1 = Rotation::new(Arc::new(box1), Vec3Axis::Y, 15.0);
+    let box1 = Translation::new(Arc::new(box1), Vec3::new_int(265, 0, 295));
+    // The boxes are only the boundaries of the smoke
+    hittable_list.add(Arc::new(ConstantMedium::new(
+        Arc::new(box1),
+        0.01,
+        Arc::new(SolidColor {
+            color: Vec3::new_int(0, 0, 0),
+        }),
+    )));
+    let box2 = construct_planar_quad_box(
+        &Vec3::new_int(0, 0, 0),
+        &Vec3::new_int(165, 165, 165),
+        white.clone(),
+    );
+    let box2 = Rotation::new(Arc::new(box2), Vec3Axis::Y, -18.0);
+    let box2 = Translation::new(Arc::new(box2), Vec3::new_int(130, 0, 65));
+    hittable_list.add(Arc::new(ConstantMedium::new(
+        Arc::new(box2),
+        0.01,
+        Arc::new(SolidColor {
+            color: Vec3::new_int(1, 1, 1),
+        }),
+    )));
+    let world = BVH::from_hittables_list(hittable_list.v);
+
+    let mut camera_params = CameraParams {
+        aspect_ratio: 1.0,
+        samples_per_pixel: 200,
+        max_depth: 60,
+        image_width: 600,
+        fov: 40_f64,
+        focus_angle: 0_f64,
+        look_from: Vec3::new_int(278, 278, -800),
+        look_at: Vec3::new_int(278, 278, 0),
+        v_up: Vec3::new(0.0, 1.0, 0.0),
+        background: Arc::new(SolidBackground {
+            color: Vec3::new(0.0, 0.0, 0.0),
+        }),
+        ..Default::default()
+    };
+    cli.apply_to(&mut camera_params);
+    let camera = Camera::new(camera_params);
+
+    eprintln!("{:?}", camera);
+    camera.render_output(&world, &lights)
+}
+
+fn main() {
+    let cli = Cli::parse();
+    cli.save_render_or_exit(scene(&cli));
+}
@@ -1,12 +1,12 @@
-use std::sync::Arc;
+use std::{io, sync::Arc};
 
 use clap::Parser;
-use image::RgbImage;
 use rust_simple_raytracer::{
-    Camera, CameraParams, Cli, HittableWithBBox, Image, Lambertain, Materials, Sphere, Vec3, BVH,
+    Camera, CameraParams, Cli, HittableWithBBox, Image, Lambertain, LightList, Materials,
+    RenderOutput, Sphere, Vec3, BVH,
 };
 
-fn scene() -> RgbImage {
+fn scene(cli: &Cli) -> io::Result<RenderOutput> {
     let checkered = Materials::ScatterMaterial(Arc::new(Lambertain {
         albedo: Arc::new(Image::new_with_color(
             1.0,
@@ -22,7 +22,7 @@ fn scene() -> RgbImage {
 
     let world = BVH::from_hittables_list(hittable_list);
 
-    let camera_params = CameraParams {
+    let mut camera_params = CameraParams {
         samples_per_pixel: 100,
         max_depth: 50,
         image_width: 600,
@@ -34,13 +34,14 @@ fn scene() -> RgbImage {
         focus_distance: 2.0,
         ..Default::default()
     };
+    cli.apply_to(&mut camera_params);
     let camera = Camera::new(camera_params);
 
     eprintln!("{:?}", camera);
-    camera.render_rgbimage(&world)
+    camera.render_output(&world, &LightList::new())
 }
 
 fn main() {
     let cli = Cli::parse();
-    cli.save_image(scene());
+    cli.save_render_or_exit(scene(&cli));
 }
@@ -1,13 +1,12 @@
-use std::sync::Arc;
+use std::{io, sync::Arc};
 
 use clap::Parser;
-use image::RgbImage;
 use rust_simple_raytracer::{
-    Camera, CameraParams, CheckeredTexture, Cli, Diffuse, HittablesList, Lambertain, Materials,
-    Quad, Sphere, Vec3, BVH,
+    Camera, CameraParams, CheckeredTexture, Cli, Diffuse, HittablesList, Lambertain, LightList,
+    Materials, Quad, RenderOutput, SolidBackground, Sphere, Vec3, BVH,
 };
 
-fn scene() -> RgbImage {
+fn scene(cli: &Cli) -> io::Result<RenderOutput> {
     let checkered_red = Materials::ScatterMaterial(Arc::new(Lambertain {
         albedo: Arc::new(CheckeredTexture::from_colors(
             30.0,
@@ -48,7 +47,7 @@ fn scene() -> RgbImage {
     )));
     let world = BVH::from_hittables_list(hittable_list.v);
 
-    let camera_params = CameraParams {
+    let mut camera_params = CameraParams {
         samples_per_pixel: 500,
         max_depth: 40,
         fov: 80_f64,
@@ -56,16 +55,19 @@ fn scene() -> RgbImage {
         image_width: 1000,
         look_from: Vec3::new(-1.0, 3.0, 6.0),
         look_at: Vec3::new(0.0, 2.0, 0.0),
-        background: Vec3::new(0.0, 0.0, 0.0),
+        background: Arc::new(SolidBackground {
+            color: Vec3::new(0.0, 0.0, 0.0),
+        }),
         ..Default::default()
     };
+    cli.apply_to(&mut camera_params);
     let camera = Camera::new(camera_params);
 
     eprintln!("{:?}", camera);
-    camera.render_rgbimage(&world)
+    camera.render_output(&world, &LightList::new())
 }
 
 fn main() {
     let cli = Cli::parse();
-    cli.save_image(scene());
+    cli.save_render_or_exit(scene(&cli));
 }
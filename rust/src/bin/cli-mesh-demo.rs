@@ -0,0 +1,63 @@
+use std::{io, sync::Arc};
+
+use clap::Parser;
+use rust_simple_raytracer::{
+    Camera, CameraParams, CheckeredTexture, Cli, HittableWithBBox, Lambertain, LightList,
+    Materials, RenderOutput, Rotation, SolidBackground, SolidColor, Sphere, TriangleMesh, Vec3,
+    Vec3Axis, BVH,
+};
+
+fn scene(cli: &Cli) -> io::Result<RenderOutput> {
+    let checkered_ground = Materials::ScatterMaterial(Arc::new(Lambertain {
+        albedo: Arc::new(CheckeredTexture::from_colors(
+            20.0,
+            Vec3::new(0.2, 0.3, 0.1),
+            Vec3::new(0.9, 0.9, 0.9),
+        )),
+    }));
+    let grey = Materials::ScatterMaterial(Arc::new(Lambertain {
+        albedo: Arc::new(SolidColor {
+            color: Vec3::new(0.5, 0.5, 0.5),
+        }),
+    }));
+
+    let mut hittable_list: Vec<Arc<dyn HittableWithBBox>> = Vec::new();
+    // Materials are read from the .mtl file, grey is used for faces without one
+    let cube = TriangleMesh::from_obj("assets/models/cube.obj", grey).unwrap();
+    let mut lights = LightList::new();
+    for triangle in cube.triangles() {
+        if let Some(Materials::LightMaterial(_)) = triangle.material() {
+            lights.add(triangle.clone());
+        }
+    }
+    hittable_list.push(Arc::new(Rotation::new(Arc::new(cube), Vec3Axis::Y, 30.0)));
+    hittable_list.push(Arc::new(Sphere::new(
+        Vec3::new(0_f64, -1001_f64, 0_f64),
+        1000_f64,
+        checkered_ground,
+    )));
+    let world = BVH::from_hittables_list(hittable_list);
+
+    let mut camera_params = CameraParams {
+        samples_per_pixel: 100,
+        max_depth: 40,
+        fov: 40_f64,
+        image_width: 600,
+        look_from: Vec3::new(4.0, 4.0, 8.0),
+        look_at: Vec3::new(0.0, 0.0, 0.0),
+        background: Arc::new(SolidBackground {
+            color: Vec3::new(0.1, 0.1, 0.15),
+        }),
+        ..Default::default()
+    };
+    cli.apply_to(&mut camera_params);
+    let camera = Camera::new(camera_params);
+
+    eprintln!("{:?}", camera);
+    camera.render_output(&world, &lights)
+}
+
+fn main() {
+    let cli = Cli::parse();
+    cli.save_render_or_exit(scene(&cli));
+}
@@ -1,13 +1,12 @@
-use std::sync::Arc;
+use std::{io, sync::Arc};
 
 use clap::Parser;
-use image::RgbImage;
 use rust_simple_raytracer::{
-    Camera, CameraParams, Cli, Dielectric, Disk, HittablesList, Lambertain, Materials, Metal, Quad,
-    SolidColor, Sphere, Triangle, Vec3, BVH,
+    Camera, CameraParams, Cli, Disk, HittablesList, Lambertain, LightList, Materials, Metal, Quad,
+    RenderOutput, SolidColor, Sphere, Triangle, Vec3, BVH,
 };
 
-fn scene() -> RgbImage {
+fn scene(cli: &Cli) -> io::Result<RenderOutput> {
     let left_red = Arc::new(Lambertain {
         albedo: Arc::new(SolidColor {
             color: Vec3::new(1.0, 0.2, 0.2),
@@ -18,11 +17,6 @@ fn scene() -> RgbImage {
             color: Vec3::new(0.2, 1.0, 0.2),
         }),
     });
-    let right_blue = Arc::new(Lambertain {
-        albedo: Arc::new(SolidColor {
-            color: Vec3::new(0.2, 0.2, 1.0),
-        }),
-    });
     let upper_orange = Arc::new(Lambertain {
         albedo: Arc::new(SolidColor {
             color: Vec3::new(1.0, 0.5, 0.0),
@@ -46,9 +40,6 @@ fn scene() -> RgbImage {
         }),
         0.3_f64,
     ));
-    let material_glass = Arc::new(Dielectric {
-        index_of_reflectance: 2.0,
-    });
 
     let mut hittable_list = HittablesList::new();
     hittable_list.add(Arc::new(Disk::new(
@@ -90,7 +81,7 @@ fn scene() -> RgbImage {
     )));
     let world = BVH::from_hittables_list(hittable_list.v);
 
-    let camera_params = CameraParams {
+    let mut camera_params = CameraParams {
         aspect_ratio: 1.0,
         samples_per_pixel: 200,
         max_depth: 50,
@@ -103,14 +94,15 @@ fn scene() -> RgbImage {
         // focus_distance: 0.4,
         ..Default::default()
     };
+    cli.apply_to(&mut camera_params);
     let camera = Camera::new(camera_params);
 
     eprintln!("{:?}", camera);
     // eprintln!("{}", world);
-    camera.render_rgbimage(&world)
+    camera.render_output(&world, &LightList::new())
 }
 
 fn main() {
     let cli = Cli::parse();
-    cli.save_image(scene());
+    cli.save_render_or_exit(scene(&cli));
 }
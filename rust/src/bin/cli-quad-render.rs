@@ -1,14 +1,13 @@
-use std::sync::Arc;
+use std::{io, sync::Arc};
 
 use clap::Parser;
-use image::RgbImage;
 use rust_simple_raytracer::{
-    Camera, CameraParams, Cli, Dielectric, HittableWithBBox, Lambertain, Materials, Metal, Quad,
-    SolidColor, Sphere, Vec3, BVH,
+    Camera, CameraParams, Cli, HittableWithBBox, Lambertain, LightList, Materials, Metal, Quad,
+    RenderOutput, SolidColor, Sphere, Vec3, BVH,
 };
 
 #[allow(clippy::vec_init_then_push)]
-fn scene() -> RgbImage {
+fn scene(cli: &Cli) -> io::Result<RenderOutput> {
     let left_red = Arc::new(Lambertain {
         albedo: Arc::new(SolidColor {
             color: Vec3::new(1.0, 0.2, 0.2),
@@ -41,15 +40,6 @@ fn scene() -> RgbImage {
         }),
         0.0_f64,
     ));
-    let material_metal_fuzzy = Arc::new(Metal::new(
-        Arc::new(SolidColor {
-            color: Vec3::new(0.1_f64, 0.2_f64, 0.5_f64),
-        }),
-        0.3_f64,
-    ));
-    let material_glass = Arc::new(Dielectric {
-        index_of_reflectance: 1.4,
-    });
 
     let mut hittable_list: Vec<Arc<dyn HittableWithBBox>> = Vec::new();
     hittable_list.push(Arc::new(Quad::new(
@@ -89,7 +79,7 @@ fn scene() -> RgbImage {
     )));
     let world = BVH::from_hittables_list(hittable_list);
 
-    let camera_params = CameraParams {
+    let mut camera_params = CameraParams {
         aspect_ratio: 1.0,
         samples_per_pixel: 200,
         max_depth: 50,
@@ -102,14 +92,15 @@ fn scene() -> RgbImage {
         // focus_distance: 0.4,
         ..Default::default()
     };
+    cli.apply_to(&mut camera_params);
     let camera = Camera::new(camera_params);
 
     eprintln!("{:?}", camera);
     // eprintln!("{}", world);
-    camera.render_rgbimage(&world)
+    camera.render_output(&world, &LightList::new())
 }
 
 fn main() {
     let cli = Cli::parse();
-    cli.save_image(scene());
+    cli.save_render_or_exit(scene(&cli));
 }
@@ -1,14 +1,13 @@
-use std::sync::Arc;
+use std::{io, sync::Arc};
 
 use clap::Parser;
-use image::RgbImage;
 use rust_simple_raytracer::{
-    Camera, CameraParams, Cli, Dielectric, HittableWithBBox, Lambertain, Materials, Metal,
-    SolidColor, SpatialCheckeredTexture, Sphere, Vec3, BVH,
+    Camera, CameraParams, Cli, Dielectric, HittableWithBBox, Lambertain, LightList, Materials,
+    Metal, RenderOutput, SolidColor, SpatialCheckeredTexture, Sphere, Vec3, BVH,
 };
 
 #[allow(clippy::vec_init_then_push)]
-fn scene() -> RgbImage {
+fn scene(cli: &Cli) -> io::Result<RenderOutput> {
     let material_red = Arc::new(Lambertain {
         albedo: Arc::new(SolidColor {
             color: Vec3::new(0.8_f64, 0.0_f64, 0.0_f64),
@@ -107,7 +106,7 @@ fn scene() -> RgbImage {
 
     let world = BVH::from_hittables_list(hittable_list);
 
-    let camera_params = CameraParams {
+    let mut camera_params = CameraParams {
         samples_per_pixel: 200,
         max_depth: 50,
         image_width: 600,
@@ -119,13 +118,14 @@ fn scene() -> RgbImage {
         // focus_distance: 0.4,
         ..Default::default()
     };
+    cli.apply_to(&mut camera_params);
     let camera = Camera::new(camera_params);
 
     eprintln!("{:?}", camera);
-    camera.render_rgbimage(&world)
+    camera.render_output(&world, &LightList::new())
 }
 
 fn main() {
     let cli = Cli::parse();
-    cli.save_image(scene());
+    cli.save_render_or_exit(scene(&cli));
 }
@@ -1,16 +1,16 @@
 use clap::Parser;
-use image::RgbImage;
-use rand::{thread_rng, Rng};
-use std::sync::Arc;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{io, sync::Arc};
 
 use rust_simple_raytracer::{
-    construct_planar_quad_box, Camera, CameraParams, Cli, Dielectric, Diffuse, HittableWithBBox,
-    Image, Lambertain, Materials, Metal, Quad, Rotation, SolidColor, Sphere, Translation, Vec3,
+    construct_planar_quad_box, Camera, CameraParams, Cli, ConstantMedium, Dielectric, Diffuse,
+    HittableWithBBox, Image, Lambertain, LightList, MarbleTexture, Materials, Metal, Quad,
+    RenderOutput, Rotation, SolidBackground, SolidColor, Sphere, TextureSpace, Translation, Vec3,
     Vec3Axis, BVH,
 };
 
-fn scene() -> RgbImage {
-    let mut rng = thread_rng();
+fn scene(cli: &Cli) -> io::Result<RenderOutput> {
+    let mut rng = StdRng::seed_from_u64(cli.seed().unwrap_or_default());
     let mut hittable_list: Vec<Arc<dyn HittableWithBBox>> = Vec::new();
 
     let ground_material = Arc::new(Lambertain {
@@ -71,6 +71,37 @@ fn scene() -> RgbImage {
             1.0,
         ))),
     )));
+    // Glass sphere filled with a blue subsurface-like medium
+    let boundary = Arc::new(Sphere::new(
+        Vec3::new_int(360, 150, 145),
+        70.0,
+        Materials::ScatterMaterial(Arc::new(Dielectric {
+            index_of_reflectance: 1.5,
+        })),
+    ));
+    hittable_list.push(boundary.clone());
+    hittable_list.push(Arc::new(ConstantMedium::new(
+        boundary,
+        0.2,
+        Arc::new(SolidColor {
+            color: Vec3::new(0.2, 0.4, 0.9),
+        }),
+    )));
+    // Thin mist over the whole scene
+    let boundary = Arc::new(Sphere::new(
+        Vec3::new_int(0, 0, 0),
+        5000.0,
+        Materials::ScatterMaterial(Arc::new(Dielectric {
+            index_of_reflectance: 1.5,
+        })),
+    ));
+    hittable_list.push(Arc::new(ConstantMedium::new(
+        boundary,
+        0.0001,
+        Arc::new(SolidColor {
+            color: Vec3::new_int(1, 1, 1),
+        }),
+    )));
     let earth_material = Arc::new(Lambertain {
         albedo: Arc::new(Image::new_with_color(
             1.0,
@@ -83,6 +114,14 @@ fn scene() -> RgbImage {
         100.0,
         Materials::ScatterMaterial(earth_material.clone()),
     )));
+    let marble_material = Arc::new(Lambertain {
+        albedo: Arc::new(MarbleTexture::new(0, TextureSpace::Solid, 0.2)),
+    });
+    hittable_list.push(Arc::new(Sphere::new(
+        Vec3::new_int(220, 280, 300),
+        80.0,
+        Materials::ScatterMaterial(marble_material),
+    )));
 
     let mut box_of_spheres: Vec<Arc<dyn HittableWithBBox>> = Vec::new();
     let white = Arc::new(Lambertain {
@@ -93,7 +132,7 @@ fn scene() -> RgbImage {
     let ns = 1000;
     for _ in 0..ns {
         box_of_spheres.push(Arc::new(Sphere::new(
-            Vec3::random(0.0, 165.0),
+            Vec3::random(0.0, 165.0, &mut rng),
             10.0,
             Materials::ScatterMaterial(white.clone()),
         )));
@@ -109,7 +148,7 @@ fn scene() -> RgbImage {
 
     let world = BVH::from_hittables_list(hittable_list);
 
-    let camera_params = CameraParams {
+    let mut camera_params = CameraParams {
         aspect_ratio: 1.0,
         samples_per_pixel: 500,
         max_depth: 50,
@@ -118,17 +157,20 @@ fn scene() -> RgbImage {
         focus_angle: 0_f64,
         look_from: Vec3::new_int(478, 278, -600),
         look_at: Vec3::new_int(278, 278, 0),
-        background: Vec3::new_int(0, 0, 0),
+        background: Arc::new(SolidBackground {
+            color: Vec3::new_int(0, 0, 0),
+        }),
         focus_distance: 10.0,
         ..Default::default()
     };
+    cli.apply_to(&mut camera_params);
     let camera = Camera::new(camera_params);
 
     eprintln!("{:?}", camera);
-    camera.render_rgbimage(&world)
+    camera.render_output(&world, &LightList::new())
 }
 
 fn main() {
     let cli = Cli::parse();
-    cli.save_image(scene());
+    cli.save_render_or_exit(scene(&cli));
 }
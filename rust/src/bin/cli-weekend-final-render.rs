@@ -1,14 +1,16 @@
 use std::sync::Arc;
 
 use clap::Parser;
-use rand::{thread_rng, Rng};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use rust_simple_raytracer::{
-    materials::Dielectric, Camera, CameraParams, Cli, HittableWithBBox, Lambertain, Materials,
-    Metal, SolidColor, Sphere, Vec3, BVH,
+    materials::Dielectric, Camera, CameraParams, Cli, HittableWithBBox, Lambertain, LightList,
+    Materials, Metal, SolidColor, Sphere, Vec3, BVH,
 };
 
 fn main() {
-    let mut rng = thread_rng();
+    let cli = Cli::parse();
+    // The scene is generated from the same seed as the samples, so the whole render can be reproduced
+    let mut rng = StdRng::seed_from_u64(cli.seed().unwrap_or_default());
 
     let mut world: Vec<Arc<dyn HittableWithBBox>> = Vec::new();
     let ground_material = Arc::new(Lambertain {
@@ -35,7 +37,7 @@ fn main() {
                 let sphere_material = if choose_mat < 0.8 {
                     // Lambertain
                     let albedo = Arc::new(SolidColor {
-                        color: Vec3::random(0.0, 1.0) * Vec3::random(0.0, 1.0),
+                        color: Vec3::random(0.0, 1.0, &mut rng) * Vec3::random(0.0, 1.0, &mut rng),
                     });
                     Materials::ScatterMaterial(Arc::new(Lambertain {
                         albedo: albedo.clone(),
@@ -43,7 +45,7 @@ fn main() {
                 } else if choose_mat < 0.95 {
                     // Metal
                     let albedo = Arc::new(SolidColor {
-                        color: Vec3::random(0.5, 1.0),
+                        color: Vec3::random(0.5, 1.0, &mut rng),
                     });
                     let fuzz = rng.gen_range(0.0..0.5);
                     Materials::ScatterMaterial(Arc::new(Metal::new(albedo.clone(), fuzz)))
@@ -86,7 +88,7 @@ fn main() {
     )));
     let world = BVH::from_hittables_list(world);
 
-    let camera_params = CameraParams {
+    let mut camera_params = CameraParams {
         samples_per_pixel: 400,
         max_depth: 50,
         image_width: 1200,
@@ -98,10 +100,10 @@ fn main() {
         focus_distance: 10.0,
         ..Default::default()
     };
+    cli.apply_to(&mut camera_params);
     let camera = Camera::new(camera_params);
 
     eprintln!("{:?}", camera);
 
-    let cli = Cli::parse();
-    cli.save_image(camera.render_rgbimage(&world));
+    cli.save_render_or_exit(camera.render_output(&world, &LightList::new()));
 }
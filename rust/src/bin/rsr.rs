@@ -0,0 +1,25 @@
+use clap::Parser;
+use rust_simple_raytracer::{Camera, Scene, SceneCli, SceneCommand};
+
+fn main() {
+    let cli = SceneCli::parse();
+    match cli.command {
+        SceneCommand::Render { scene, output } => {
+            let loaded_scene = match Scene::from_file(&scene) {
+                Ok(loaded_scene) => loaded_scene,
+                Err(err) => {
+                    eprintln!("{}: {}", scene.display(), err);
+                    std::process::exit(1);
+                }
+            };
+            let mut camera_params = loaded_scene.camera_params;
+            // The CLI arguments take priority over the scene file
+            output.apply_to(&mut camera_params);
+            let camera = Camera::new(camera_params);
+            eprintln!("{:?}", camera);
+            output.save_render_or_exit(
+                camera.render_output(&loaded_scene.world, &loaded_scene.lights),
+            );
+        }
+    }
+}
@@ -1,20 +1,34 @@
-use image::{Rgb, RgbImage};
+use std::{io, path::PathBuf, sync::Arc, time::Instant};
+
+use image::{Rgb, Rgb32FImage, RgbImage};
 use rayon::prelude::*;
-use std::f64::INFINITY;
 
 use indicatif::ProgressBar;
-use rand::prelude::thread_rng;
-use rand::Rng;
 
-use crate::{Hittable, Interval, Materials};
+use crate::{
+    materials::Scattered, Background, HitRecord, Hittable, Interval, Light, LightList, Materials,
+    Sampler, SamplerType, SolidBackground, PDF,
+};
 
-use super::helper::color_to_rgb;
+use super::helper::framebuffer_to_rgbimage;
 use super::ray::Ray;
 
 use super::helper::from_fdegree_to_fradian;
 
 use super::vec3::Vec3;
 
+mod adaptive;
+mod aov;
+mod checkpoint;
+mod tile;
+use adaptive::PixelStatistics;
+pub use adaptive::{AdaptiveSampling, SampleCounts};
+use aov::Radiance;
+pub use aov::AOV;
+use checkpoint::CheckpointHeader;
+pub use checkpoint::Checkpointing;
+pub use tile::Tile;
+
 /// Camera Parameters defined by the user, which is then used to generate a [Camera].
 /// Should be used in conjuection with [Camera::new]
 pub struct CameraParams {
@@ -42,8 +56,28 @@ pub struct CameraParams {
     pub focus_angle: f64,
     /// Distance from Camera center to focus plane
     pub focus_distance: f64,
-    /// Background color (When the ray misses the world)
-    pub background: Vec3,
+    /// What the ray sees when it misses the world (i.e. [SolidBackground], [GradientBackground](crate::GradientBackground) or [EnvironmentMap](crate::EnvironmentMap))
+    pub background: Arc<dyn Background>,
+    /// Lights that are not part of the world (i.e. [PointLight](crate::PointLight), [SpotLight](crate::SpotLight) or [DirectionalLight](crate::DirectionalLight))
+    /// Every hit sends a shadow ray towards each of them
+    pub analytic_lights: Vec<Arc<dyn Light>>,
+    /// Size (in pixels) of the square tiles the image is split into when rendering
+    /// Each tile is rendered by a single thread, so smaller tiles balance the load better
+    pub tile_size: i64,
+    /// Seed of the random samples, the same seed always renders the same image (No matter the number of threads)
+    pub seed: u64,
+    /// How the samples of each pixel are spread out, refer to [SamplerType]
+    pub sampler: SamplerType,
+    /// Stops sampling the pixels that are no longer noisy, samples_per_pixel is then the maximum
+    /// None takes samples_per_pixel in every pixel
+    pub adaptive_sampling: Option<AdaptiveSampling>,
+    /// Extra passes rendered alongside the image, refer to [AOV]
+    pub aovs: Vec<AOV>,
+    /// Periodically saves the progress of the render into a checkpoint file
+    pub checkpointing: Option<Checkpointing>,
+    /// Checkpoint file to continue the render from, which needs the same image size, seed, sampler and AOVs (And should be the same world)
+    /// The pixels keep taking samples until samples_per_pixel, so a finished render can also be resumed with more samples
+    pub resume: Option<PathBuf>,
 }
 impl Default for CameraParams {
     fn default() -> Self {
@@ -58,7 +92,17 @@ impl Default for CameraParams {
             v_up: Vec3::new_int(0, 1, 0),
             focus_angle: 0_f64,
             focus_distance: 1_f64,
-            background: Vec3::new(0.7, 0.8, 1.0),
+            background: Arc::new(SolidBackground {
+                color: Vec3::new(0.7, 0.8, 1.0),
+            }),
+            analytic_lights: Vec::new(),
+            tile_size: 32,
+            seed: 0,
+            sampler: SamplerType::default(),
+            adaptive_sampling: None,
+            aovs: Vec::new(),
+            checkpointing: None,
+            resume: None,
         }
     }
 }
@@ -73,7 +117,15 @@ pub struct Camera {
     max_depth: i64,
 
     focus_angle: f64,
-    background: Vec3,
+    background: Arc<dyn Background>,
+    analytic_lights: Vec<Arc<dyn Light>>,
+    tile_size: i64,
+    seed: u64,
+    sampler: SamplerType,
+    adaptive_sampling: Option<AdaptiveSampling>,
+    aovs: Vec<AOV>,
+    checkpointing: Option<Checkpointing>,
+    resume: Option<PathBuf>,
 
     /// Point of the Camera center (Same as [CameraParams.look_from])
     center: Vec3,
@@ -134,6 +186,14 @@ impl Camera {
             max_depth: camera_params.max_depth,
             focus_angle: camera_params.focus_angle,
             background: camera_params.background.clone(),
+            analytic_lights: camera_params.analytic_lights,
+            tile_size: camera_params.tile_size,
+            seed: camera_params.seed,
+            sampler: camera_params.sampler,
+            adaptive_sampling: camera_params.adaptive_sampling,
+            aovs: camera_params.aovs.clone(),
+            checkpointing: camera_params.checkpointing.clone(),
+            resume: camera_params.resume.clone(),
             center: camera_params.look_from.clone(),
             u: u.clone(),
             v: v.clone(),
@@ -149,132 +209,556 @@ impl Camera {
     /// Renders the World with the given camera params.
     /// Ideally, the render function should only be called.
     /// Takes in Any world which implements [Hittable]
+    /// Outputs the image as a ppm3 file to stdout
+    /// Errors if the checkpoint to resume from can not be loaded
     ///
     // Implementation Details:
     // Used a generic type as it will only generate the static dispatch given the actual type that implements `Hittable` is used with this function
     // This way, we can use any `Hittable` world, and have no draw backs (Unless we call this function with 2 different `Hittable` types, then the generated function will be duplicated for the types)
-    pub fn render<T: Hittable + Sync + Send>(&self, world: &T) {
+    pub fn render<T: Hittable + Sync + Send>(&self, world: &T) -> io::Result<()> {
+        let image = self.render_rgbimage(world)?;
+
         println!("P3");
         println!("{} {}", self.image_width, self.image_height);
         println!("255");
+        for pixel in image.pixels() {
+            let [pixel_r, pixel_g, pixel_b] = pixel.0;
+            println!("{}, {}, {}", pixel_r, pixel_g, pixel_b);
+        }
+        Ok(())
+    }
 
-        let progress_bar = ProgressBar::new(self.image_height as u64);
+    /// Generic render function that takes in the world to render and returns an RGBImage
+    pub fn render_rgbimage<T: Hittable + Sync + Send>(&self, world: &T) -> io::Result<RgbImage> {
+        self.render_rgbimage_with_lights(world, &LightList::new())
+    }
 
-        for y in 0..self.image_height {
-            // eprintln!("\r Scanlines remaining: {}", self.image_height - y - 1);
-            progress_bar.inc(1);
-            for x in 0..self.image_width {
-                let mut rays: Vec<Ray> = Vec::new();
-                for _ in 0..self.samples_per_pixel {
-                    rays.push(self.get_ray(y, x));
-                }
-                let pixel_color = rays
-                    .par_iter()
-                    .fold(
-                        || Vec3::new_int(0, 0, 0),
-                        |mut acc, ray| {
-                            acc += self.color_ray(ray, world, self.max_depth);
-                            acc
-                        },
+    /// Same as [render_rgbimage](Self::render_rgbimage), but also samples the given lights directly
+    /// Refer to [render_framebuffer_with_lights](Self::render_framebuffer_with_lights)
+    pub fn render_rgbimage_with_lights<T: Hittable + Sync + Send>(
+        &self,
+        world: &T,
+        lights: &LightList,
+    ) -> io::Result<RgbImage> {
+        Ok(framebuffer_to_rgbimage(
+            &self.render_framebuffer_with_lights(world, lights)?,
+        ))
+    }
+
+    /// Renders the world into a linear float framebuffer, with the average radiance of each pixel
+    /// Unlike [render_rgbimage](Self::render_rgbimage), the colors are not clamped or gamma corrected
+    pub fn render_framebuffer<T: Hittable + Sync + Send>(
+        &self,
+        world: &T,
+    ) -> io::Result<Rgb32FImage> {
+        self.render_framebuffer_with_lights(world, &LightList::new())
+    }
+
+    /// Same as [render_framebuffer](Self::render_framebuffer), but also samples the given lights directly
+    /// At every diffuse bounce, a shadow ray is sent towards a point on one of the lights (Next Event Estimation)
+    /// The lights should also be part of the world, as they are only used to pick the directions to sample
+    pub fn render_framebuffer_with_lights<T: Hittable + Sync + Send>(
+        &self,
+        world: &T,
+        lights: &LightList,
+    ) -> io::Result<Rgb32FImage> {
+        Ok(self.render_output(world, lights)?.framebuffer)
+    }
+
+    /// Same as [render_framebuffer_with_lights](Self::render_framebuffer_with_lights), but also returns the samples taken by each pixel and the [AOV]s
+    /// Without [AdaptiveSampling], every pixel takes samples_per_pixel
+    ///
+    /// Errors if the checkpoint to resume from can not be loaded (i.e. it was rendered with other settings)
+    /// A checkpoint that can not be saved only prints a warning, so the samples taken so far are not thrown away
+    // Implementation Details:
+    // The image is split into tiles of `tile_size`, which are spread across the rayon thread pool
+    // Each tile keeps the running sums of its own pixels, so the threads never share any state while rendering
+    // The samples are taken in passes over the whole image (Instead of finishing each tile on its own), so a time budget is spread evenly over the image
+    // and checkpoints can be saved in between: adaptive sampling takes the minimum samples then its batches, checkpointing takes samples_per_pass at a time
+    // Every value of the [Sampler] only depends on the pixel and sample, and each pixel is summed in the same order,
+    // so the result is bit identical for any number of threads (And no matter how it was split into passes or resumed)
+    pub fn render_output<T: Hittable + Sync + Send>(
+        &self,
+        world: &T,
+        lights: &LightList,
+    ) -> io::Result<RenderOutput> {
+        let start = Instant::now();
+        let out_of_time = || match self
+            .adaptive_sampling
+            .and_then(|adaptive| adaptive.time_budget)
+        {
+            Some(time_budget) => start.elapsed() >= time_budget,
+            None => false,
+        };
+
+        let tiles = Tile::split_image(self.image_width, self.image_height, self.tile_size);
+        let mut tile_statistics: Vec<Vec<PixelStatistics>> = match &self.resume {
+            Some(path) => {
+                checkpoint::load(path, &self.checkpoint_header(), &tiles).map_err(|err| {
+                    io::Error::new(
+                        err.kind(),
+                        format!("could not resume from {}: {}", path.display(), err),
                     )
-                    .reduce(
-                        || Vec3::new_int(0, 0, 0),
-                        |mut acc, color| {
-                            acc += color;
-                            acc
-                        },
+                })?
+            }
+            None => tiles
+                .iter()
+                .map(|tile| vec![PixelStatistics::default(); tile.len()])
+                .collect(),
+        };
+
+        let progress_bar = ProgressBar::new(0);
+        let mut last_checkpoint = Instant::now();
+        while !out_of_time()
+            && tile_statistics
+                .iter()
+                .flatten()
+                .any(|statistics| self.next_pass_samples(statistics) > 0)
+        {
+            progress_bar.inc_length(tiles.len() as u64);
+            self.render_pass(
+                &tiles,
+                &mut tile_statistics,
+                world,
+                lights,
+                &progress_bar,
+                |statistics| {
+                    // Tiles that start after the time budget ran out are skipped
+                    if out_of_time() {
+                        0
+                    } else {
+                        self.next_pass_samples(statistics)
+                    }
+                },
+            );
+            if let Some(checkpointing) = &self.checkpointing {
+                if last_checkpoint.elapsed() >= checkpointing.interval {
+                    self.save_checkpoint(checkpointing, &tiles, &tile_statistics);
+                    last_checkpoint = Instant::now();
+                }
+            }
+        }
+        progress_bar.finish();
+        // The finished render is also saved, so it can be resumed with more samples
+        if let Some(checkpointing) = &self.checkpointing {
+            self.save_checkpoint(checkpointing, &tiles, &tile_statistics);
+        }
+
+        let mut framebuffer = Rgb32FImage::new(self.image_width as u32, self.image_height as u32);
+        let mut sample_counts =
+            SampleCounts::new(self.image_width as u32, self.image_height as u32);
+        let mut aovs: Vec<(AOV, Rgb32FImage)> = self
+            .aovs
+            .iter()
+            .map(|aov| {
+                (
+                    *aov,
+                    Rgb32FImage::new(self.image_width as u32, self.image_height as u32),
+                )
+            })
+            .collect();
+        for (tile, statistics) in tiles.iter().zip(&tile_statistics) {
+            for ((y, x), pixel_statistics) in tile.pixels().zip(statistics) {
+                framebuffer.put_pixel(x as u32, y as u32, to_rgb32f(&pixel_statistics.mean()));
+                sample_counts.set(x as u32, y as u32, pixel_statistics.samples);
+                for (index, (_, pass)) in aovs.iter_mut().enumerate() {
+                    pass.put_pixel(
+                        x as u32,
+                        y as u32,
+                        to_rgb32f(&pixel_statistics.aov_mean(index)),
                     );
-                let (pixel_r, pixel_g, pixel_b) =
-                    color_to_rgb(&pixel_color, self.samples_per_pixel);
-                println!("{}, {}, {}", pixel_r, pixel_g, pixel_b);
+                }
             }
         }
+        Ok(RenderOutput {
+            framebuffer,
+            sample_counts,
+            aovs,
+        })
     }
 
-    /// Generic render function that takes in the world to render and returns an RGBImage
-    pub fn render_rgbimage<T: Hittable + Sync + Send>(&self, world: &T) -> RgbImage {
-        let progress_bar = ProgressBar::new(self.image_height as u64);
-        let mut image = RgbImage::new(self.image_width as u32, self.image_height as u32);
-
-        for y in 0..self.image_height {
-            progress_bar.inc(1);
-            for x in 0..self.image_width {
-                let mut rays: Vec<Ray> = Vec::new();
-                for _ in 0..self.samples_per_pixel {
-                    rays.push(self.get_ray(y, x));
-                }
-                let pixel_color = rays
-                    .par_iter()
-                    .fold(
-                        || Vec3::new_int(0, 0, 0),
-                        |mut acc, ray| {
-                            acc += self.color_ray(ray, world, self.max_depth);
-                            acc
-                        },
-                    )
-                    .reduce(
-                        || Vec3::new_int(0, 0, 0),
-                        |mut acc, color| {
-                            acc += color;
-                            acc
+    /// Number of samples the pixel takes in the next pass over the image
+    fn next_pass_samples(&self, statistics: &PixelStatistics) -> i64 {
+        let remaining = (self.samples_per_pixel - statistics.samples).max(0);
+        match (&self.adaptive_sampling, &self.checkpointing) {
+            (Some(adaptive), _) if statistics.samples < adaptive.min_samples => {
+                (adaptive.min_samples - statistics.samples).min(remaining)
+            }
+            (Some(adaptive), _) => adaptive.next_batch(statistics, self.samples_per_pixel),
+            (None, Some(checkpointing)) => checkpointing.samples_per_pass.max(1).min(remaining),
+            (None, None) => remaining,
+        }
+    }
+    fn checkpoint_header(&self) -> CheckpointHeader {
+        CheckpointHeader {
+            width: self.image_width as u32,
+            height: self.image_height as u32,
+            seed: self.seed,
+            max_depth: self.max_depth,
+            view: checkpoint::view_hash(
+                &[
+                    &self.center,
+                    &self.pixel_00_loc,
+                    &self.pixel_delta_u,
+                    &self.pixel_delta_v,
+                    &self.defocus_disk_u,
+                    &self.defocus_disk_v,
+                ]
+                .map(|v| v.xyz())
+                .concat(),
+            ),
+            sampler: self.sampler,
+            aovs: self.aovs.clone(),
+        }
+    }
+    fn save_checkpoint(
+        &self,
+        checkpointing: &Checkpointing,
+        tiles: &[Tile],
+        tile_statistics: &[Vec<PixelStatistics>],
+    ) {
+        checkpoint::save(
+            &checkpointing.path,
+            &self.checkpoint_header(),
+            tiles,
+            tile_statistics,
+        )
+        .unwrap_or_else(|err| {
+            eprintln!(
+                "Warning: could not save the checkpoint {}, the render goes on without it: {}",
+                checkpointing.path.display(),
+                err
+            )
+        });
+    }
+
+    /// Renders every tile in parallel, each pixel takes the number of samples given by samples_to_take (Of its current statistics)
+    fn render_pass<T: Hittable + Sync + Send>(
+        &self,
+        tiles: &[Tile],
+        tile_statistics: &mut [Vec<PixelStatistics>],
+        world: &T,
+        lights: &LightList,
+        progress_bar: &ProgressBar,
+        samples_to_take: impl Fn(&PixelStatistics) -> i64 + Sync,
+    ) {
+        tiles
+            .par_iter()
+            .zip(tile_statistics.par_iter_mut())
+            .for_each(|(tile, statistics)| {
+                self.render_tile(tile, statistics, world, lights, &samples_to_take);
+                progress_bar.inc(1);
+            });
+    }
+
+    /// Adds the samples of a single tile into the statistics of its pixels (In the order of [Tile::pixels])
+    fn render_tile<T: Hittable>(
+        &self,
+        tile: &Tile,
+        statistics: &mut [PixelStatistics],
+        world: &T,
+        lights: &LightList,
+        samples_to_take: &impl Fn(&PixelStatistics) -> i64,
+    ) {
+        let mut sampler = self.sampler.build(self.seed, self.samples_per_pixel);
+        for ((y, x), pixel_statistics) in tile.pixels().zip(statistics.iter_mut()) {
+            let first_sample = pixel_statistics.samples;
+            for sample_index in first_sample..first_sample + samples_to_take(pixel_statistics) {
+                sampler.start_pixel_sample(x, y, sample_index);
+                let ray = self.get_ray(y, x, sampler.as_mut());
+                let radiance =
+                    self.color_ray(&ray, world, lights, self.max_depth, None, sampler.as_mut());
+                if !self.aovs.is_empty() {
+                    // The first hit is found again, instead of passing it out of color_ray, as the AOVs are rarely used
+                    let hit_record = world.hit(
+                        &ray,
+                        Interval {
+                            min: 0.001,
+                            max: f64::INFINITY,
                         },
                     );
-                let (pixel_r, pixel_g, pixel_b) =
-                    color_to_rgb(&pixel_color, self.samples_per_pixel);
-                image.put_pixel(
-                    x as u32,
-                    y as u32,
-                    Rgb([pixel_r as u8, pixel_g as u8, pixel_b as u8]),
-                );
+                    pixel_statistics.add_aovs(
+                        self.aovs
+                            .iter()
+                            .map(|aov| aov.value(&ray, hit_record.as_ref(), &radiance)),
+                    );
+                }
+                pixel_statistics.add(radiance.total());
             }
         }
-        image
     }
 
     /// Takes a ray and simulates ray tracing on it
     /// Refer to [render](Self::render)
-    #[allow(clippy::only_used_in_recursion)]
-    fn color_ray<T: Hittable>(&self, ray: &Ray, world: &T, max_depth: i64) -> Vec3 {
+    ///
+    /// material_pdf_value is the pdf of the diffuse material that scattered the ray, or None for camera and specular rays
+    /// Since lights (and sampled backgrounds) are also sampled directly at every diffuse bounce, the light found by the scattered ray is weighted down (Multiple Importance Sampling)
+    // Implementation Details:
+    // Both the shadow ray and the scattered ray can find the same light, so each of them are weighted by the power heuristic
+    // w = pdf^2 / (pdf_light^2 + pdf_material^2), which favours whichever technique was more likely to sample that direction
+    fn color_ray<T: Hittable>(
+        &self,
+        ray: &Ray,
+        world: &T,
+        lights: &LightList,
+        max_depth: i64,
+        material_pdf_value: Option<f64>,
+        sampler: &mut dyn Sampler,
+    ) -> Radiance {
         if max_depth <= 0 {
-            return Vec3::new_int(0, 0, 0);
+            return Radiance::default();
         }
 
         let hit_record = match world.hit(
             ray,
             Interval {
                 min: 0.001,
-                max: INFINITY,
+                max: f64::INFINITY,
             },
         ) {
             Some(hit_record) => hit_record,
             None => {
                 // Did not hit anything in the _world, return background
-                return self.background.clone();
+                let background = self.background.color(&ray.direction);
+                return Radiance::emitted(match material_pdf_value {
+                    Some(material_pdf_value) if self.background.is_sampled() => {
+                        power_heuristic(
+                            material_pdf_value,
+                            self.background.pdf_value(&ray.direction),
+                        ) * background
+                    }
+                    _ => background,
+                });
             }
         };
 
-        match &hit_record.material {
-            Materials::ScatterMaterial(scatter_material) => {
-                match scatter_material.scatter(ray, &hit_record) {
-                    Some(scattered) => {
-                        scattered.attenuation * self.color_ray(&scattered.ray, world, max_depth - 1)
+        let scatter_material = match &hit_record.material {
+            Materials::ScatterMaterial(scatter_material) => scatter_material,
+            // Hit a diffuse light source
+            Materials::LightMaterial(light_material) => {
+                return Radiance::emitted(match material_pdf_value {
+                    Some(material_pdf_value) if !lights.is_empty() => {
+                        power_heuristic(
+                            material_pdf_value,
+                            lights.pdf_value(&ray.origin, &ray.direction),
+                        ) * light_material.emit(ray, &hit_record)
                     }
-                    // Scattered Light absorbed by the material
-                    None => Vec3::new_int(0, 0, 0),
+                    _ => light_material.emit(ray, &hit_record),
+                });
+            }
+        };
+        // The analytic lights light both lobes, the glossy part here and the diffuse part with the other sampled lights
+        let analytic_lights = self.sample_analytic_lights(world, &hit_record, sampler);
+        let mut glossy = Vec3::new_int(0, 0, 0);
+        for (shadow_ray, light) in &analytic_lights {
+            glossy += scatter_material.glossy(ray, &hit_record, shadow_ray) * light.clone();
+        }
+        let radiance = match scatter_material.scatter(ray, &hit_record, sampler) {
+            Some(Scattered::Specular { attenuation, ray }) => self
+                .color_ray(&ray, world, lights, max_depth - 1, None, sampler)
+                .attenuate(&attenuation),
+            Some(Scattered::Diffuse { attenuation, pdf }) => {
+                let mut direct_light = Vec3::new_int(0, 0, 0);
+                for (shadow_ray, light) in &analytic_lights {
+                    direct_light += scatter_material.scattering_pdf(ray, &hit_record, shadow_ray)
+                        * light.clone();
+                }
+                if !lights.is_empty() {
+                    direct_light +=
+                        self.sample_light(ray, world, lights, &hit_record, pdf.as_ref(), sampler);
+                }
+                if self.background.is_sampled() {
+                    direct_light +=
+                        self.sample_background(ray, world, &hit_record, pdf.as_ref(), sampler);
+                }
+
+                let scattered_direction = pdf.generate(sampler);
+                let material_pdf_value = pdf.value(&scattered_direction);
+                if material_pdf_value <= 0_f64 {
+                    Radiance::diffuse_bounce(&attenuation, direct_light, Radiance::default(), 0_f64)
+                } else {
+                    let scattered_ray = Ray {
+                        origin: hit_record.p.clone(),
+                        direction: scattered_direction,
+                    };
+                    let scattering_pdf =
+                        scatter_material.scattering_pdf(ray, &hit_record, &scattered_ray);
+                    let scattered = self.color_ray(
+                        &scattered_ray,
+                        world,
+                        lights,
+                        max_depth - 1,
+                        Some(material_pdf_value),
+                        sampler,
+                    );
+
+                    Radiance::diffuse_bounce(
+                        &attenuation,
+                        direct_light,
+                        scattered,
+                        scattering_pdf / material_pdf_value,
+                    )
                 }
             }
-            // Hit a diffuse light source
-            Materials::LightMaterial(light_material) => light_material.emit(),
+            // Scattered Light absorbed by the material
+            None => Radiance::default(),
+        };
+        radiance.add_direct(glossy)
+    }
+    /// Sends a shadow ray from the hit point towards each of the analytic lights
+    /// Returns the shadow rays with the light they bring (Not yet multiplied by the material), only for the lights that are not blocked
+    // Implementation Details:
+    // The lights can not be hit, so unlike sample_light there is no MIS, and anything hit before the light blocks it (Even emissive surfaces)
+    fn sample_analytic_lights<T: Hittable>(
+        &self,
+        world: &T,
+        hit_record: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Vec<(Ray, Vec3)> {
+        let mut samples = Vec::new();
+        for light in &self.analytic_lights {
+            let light_sample = match light.sample(&hit_record.p, sampler) {
+                Some(light_sample) => light_sample,
+                None => continue,
+            };
+            let shadow_ray = Ray {
+                origin: hit_record.p.clone(),
+                direction: light_sample.direction,
+            };
+            let shadow_interval = Interval {
+                min: 0.001,
+                max: light_sample.distance,
+            };
+            if world.hit_surface(&shadow_ray, shadow_interval).is_some() {
+                continue;
+            }
+            let transmittance = world.transmittance(&shadow_ray, shadow_interval);
+            if transmittance <= 0_f64 {
+                continue;
+            }
+            samples.push((shadow_ray, light_sample.irradiance * transmittance));
         }
+        samples
     }
-    fn get_ray(&self, y: i64, x: i64) -> Ray {
+    /// Next Event Estimation, sends a shadow ray from the hit point towards a sampled point on one of the lights
+    /// Returns the light reaching the hit point (Not yet multiplied by the attenuation of the material)
+    fn sample_light<T: Hittable>(
+        &self,
+        ray: &Ray,
+        world: &T,
+        lights: &LightList,
+        hit_record: &HitRecord,
+        material_pdf: &dyn PDF,
+        sampler: &mut dyn Sampler,
+    ) -> Vec3 {
+        let shadow_ray = Ray {
+            origin: hit_record.p.clone(),
+            direction: lights.random(&hit_record.p, sampler),
+        };
+        let light_pdf_value = lights.pdf_value(&hit_record.p, &shadow_ray.direction);
+        if light_pdf_value <= 0_f64 {
+            return Vec3::new_int(0, 0, 0);
+        }
+
+        // The shadow ray only sees the light if the first surface it hits is emissive, and is dimmed by the media in between
+        let light_hit = match world.hit_surface(
+            &shadow_ray,
+            Interval {
+                min: 0.001,
+                max: f64::INFINITY,
+            },
+        ) {
+            Some(light_hit) => light_hit,
+            None => return Vec3::new_int(0, 0, 0),
+        };
+        let (emitted, light_t) = match &light_hit.material {
+            Materials::LightMaterial(light_material) => {
+                (light_material.emit(&shadow_ray, &light_hit), light_hit.t)
+            }
+            Materials::ScatterMaterial(_) => return Vec3::new_int(0, 0, 0),
+        };
+        if emitted == Vec3::new_int(0, 0, 0) {
+            return Vec3::new_int(0, 0, 0);
+        }
+        let transmittance = world.transmittance(
+            &shadow_ray,
+            Interval {
+                min: 0.001,
+                max: light_t,
+            },
+        );
+        if transmittance <= 0_f64 {
+            return Vec3::new_int(0, 0, 0);
+        }
+        self.weight_direct_light(ray, hit_record, &shadow_ray, material_pdf, light_pdf_value)
+            * transmittance
+            * emitted
+    }
+    /// Same as [sample_light](Self::sample_light), but towards a direction sampled from the background
+    fn sample_background<T: Hittable>(
+        &self,
+        ray: &Ray,
+        world: &T,
+        hit_record: &HitRecord,
+        material_pdf: &dyn PDF,
+        sampler: &mut dyn Sampler,
+    ) -> Vec3 {
+        let shadow_ray = Ray {
+            origin: hit_record.p.clone(),
+            direction: self.background.random(sampler),
+        };
+        let background_pdf_value = self.background.pdf_value(&shadow_ray.direction);
+        if background_pdf_value <= 0_f64 {
+            return Vec3::new_int(0, 0, 0);
+        }
+        // The shadow ray only sees the background if it escapes the world (Through the media)
+        let shadow_interval = Interval {
+            min: 0.001,
+            max: f64::INFINITY,
+        };
+        if world.hit_surface(&shadow_ray, shadow_interval).is_some() {
+            return Vec3::new_int(0, 0, 0);
+        }
+        let transmittance = world.transmittance(&shadow_ray, shadow_interval);
+        if transmittance <= 0_f64 {
+            return Vec3::new_int(0, 0, 0);
+        }
+        self.weight_direct_light(
+            ray,
+            hit_record,
+            &shadow_ray,
+            material_pdf,
+            background_pdf_value,
+        ) * transmittance
+            * self.background.color(&shadow_ray.direction)
+    }
+    /// MIS weighted contribution of a shadow ray sampled with sampled_pdf_value, without the emitted light
+    fn weight_direct_light(
+        &self,
+        ray: &Ray,
+        hit_record: &HitRecord,
+        shadow_ray: &Ray,
+        material_pdf: &dyn PDF,
+        sampled_pdf_value: f64,
+    ) -> f64 {
+        let scattering_pdf = match &hit_record.material {
+            Materials::ScatterMaterial(scatter_material) => {
+                scatter_material.scattering_pdf(ray, hit_record, shadow_ray)
+            }
+            Materials::LightMaterial(_) => 0_f64,
+        };
+        let material_pdf_value = material_pdf.value(&shadow_ray.direction);
+
+        power_heuristic(sampled_pdf_value, material_pdf_value) * scattering_pdf / sampled_pdf_value
+    }
+    fn get_ray(&self, y: i64, x: i64, sampler: &mut dyn Sampler) -> Ray {
         let pixel_center = self.pixel_00_loc.clone()
             + (y as f64) * self.pixel_delta_v.clone()
             + (x as f64) * self.pixel_delta_u.clone();
-        let pixel_center_sample = pixel_center + self.pixel_square_sample();
+        let pixel_center_sample = pixel_center + self.pixel_square_sample(sampler);
 
         let ray_origin = if self.focus_angle > 0_f64 {
-            self.defocus_disk_sample()
+            self.defocus_disk_sample(sampler)
         } else {
             self.center.clone()
         };
@@ -285,16 +769,284 @@ impl Camera {
         }
     }
     /// Samples a random point in the pixel square
-    fn pixel_square_sample(&self) -> Vec3 {
-        let mut rng = thread_rng();
-        self.pixel_delta_u.clone() * rng.gen_range(-0.5_f64..0.5_f64)
-            + self.pixel_delta_v.clone() * rng.gen_range(-0.5_f64..0.5_f64)
+    fn pixel_square_sample(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        let (u, v) = sampler.get_2d();
+        self.pixel_delta_u.clone() * (u - 0.5_f64) + self.pixel_delta_v.clone() * (v - 0.5_f64)
     }
     /// Samples a origin point from the defocus disk
-    fn defocus_disk_sample(&self) -> Vec3 {
-        let mut rng = thread_rng();
+    fn defocus_disk_sample(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        let (u, v) = sampler.get_2d();
         self.center.clone()
-            + self.defocus_disk_u.clone() * rng.gen_range(-1_f64..1_f64)
-            + self.defocus_disk_v.clone() * rng.gen_range(-1_f64..1_f64)
+            + self.defocus_disk_u.clone() * (2_f64 * u - 1_f64)
+            + self.defocus_disk_v.clone() * (2_f64 * v - 1_f64)
+    }
+}
+
+/// Everything rendered by [Camera::render_output]
+pub struct RenderOutput {
+    /// Linear radiance of each pixel
+    pub framebuffer: Rgb32FImage,
+    /// Samples taken by each pixel
+    pub sample_counts: SampleCounts,
+    /// Each of the camera [AOV]s with its pass, in the order they were asked for
+    pub aovs: Vec<(AOV, Rgb32FImage)>,
+}
+impl RenderOutput {
+    /// Pass of the AOV, if it was rendered
+    pub fn aov(&self, aov: AOV) -> Option<&Rgb32FImage> {
+        self.aovs
+            .iter()
+            .find(|(other, _)| *other == aov)
+            .map(|(_, pass)| pass)
+    }
+}
+
+fn to_rgb32f(color: &Vec3) -> Rgb<f32> {
+    let (r, g, b) = color.tuple();
+    Rgb([r as f32, g as f32, b as f32])
+}
+
+/// Power heuristic (beta = 2) for Multiple Importance Sampling
+/// Weight of the sample from the technique with pdf_a, given the other technique has pdf_b
+fn power_heuristic(pdf_a: f64, pdf_b: f64) -> f64 {
+    let a = pdf_a * pdf_a;
+    let b = pdf_b * pdf_b;
+    if a + b == 0_f64 {
+        return 0_f64;
+    }
+    a / (a + b)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f64::consts::PI;
+
+    use crate::{
+        Dielectric, Diffuse, DirectionalLight, HittableWithBBox, Lambertain, PointLight, Quad,
+        SolidColor, Sphere, Tagged, BVH,
+    };
+
+    fn render(seed: u64, threads: usize) -> Rgb32FImage {
+        render_with_params(
+            threads,
+            CameraParams {
+                samples_per_pixel: 4,
+                seed,
+                ..Default::default()
+            },
+        )
+        .framebuffer
+    }
+
+    fn render_with_params(threads: usize, params: CameraParams) -> RenderOutput {
+        try_render_with_params(threads, params).unwrap()
+    }
+
+    fn try_render_with_params(threads: usize, params: CameraParams) -> io::Result<RenderOutput> {
+        let ground = Materials::ScatterMaterial(Arc::new(Lambertain {
+            albedo: Arc::new(SolidColor {
+                color: Vec3::new(0.5, 0.5, 0.5),
+            }),
+        }));
+        let glass = Materials::ScatterMaterial(Arc::new(Dielectric {
+            index_of_reflectance: 1.5,
+        }));
+        let light: Arc<dyn HittableWithBBox> = Arc::new(Quad::new(
+            Vec3::new_int(-1, 3, -2),
+            Vec3::new_int(2, 0, 0),
+            Vec3::new_int(0, 0, 2),
+            Materials::LightMaterial(Arc::new(Diffuse { power: 4.0 })),
+        ));
+        let world = BVH::from_hittables_list(vec![
+            Arc::new(Tagged::new(
+                Arc::new(Sphere::new(Vec3::new(0.0, -100.5, -1.0), 100.0, ground)),
+                1,
+                Some(1),
+            )),
+            Arc::new(Sphere::new(Vec3::new_int(0, 0, -1), 0.5, glass)),
+            light.clone(),
+        ]);
+        let lights = LightList::from_hittables(&[light]);
+        let camera = Camera::new(CameraParams {
+            image_width: 24,
+            max_depth: 8,
+            focus_angle: 2.0,
+            tile_size: 5,
+            ..params
+        });
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap()
+            .install(|| camera.render_output(&world, &lights))
+    }
+
+    #[test]
+    fn test_render_deterministic() {
+        // Bit identical, no matter how the tiles are spread over the threads
+        let single_thread = render(3, 1);
+        assert_eq!(single_thread, render(3, 4));
+        assert_eq!(single_thread, render(3, 2));
+        assert_ne!(single_thread, render(4, 4));
+    }
+
+    #[test]
+    fn test_render_adaptive() {
+        let params = || CameraParams {
+            samples_per_pixel: 64,
+            adaptive_sampling: Some(AdaptiveSampling {
+                min_samples: 8,
+                batch_size: 8,
+                threshold: 0.02,
+                time_budget: None,
+            }),
+            ..Default::default()
+        };
+        let output = render_with_params(4, params());
+        let sample_counts = output.sample_counts;
+        // The sky in the top left corner has no noise, while the glass sphere and the shadows need more samples
+        assert_eq!(sample_counts.get(0, 0), 8);
+        assert_eq!(sample_counts.max(), 64);
+        assert!(sample_counts.average() < 64.0);
+        // Without a time budget, adaptive sampling is still deterministic
+        let single_thread = render_with_params(1, params());
+        assert_eq!(output.framebuffer, single_thread.framebuffer);
+        assert_eq!(sample_counts, single_thread.sample_counts);
+    }
+
+    #[test]
+    fn test_render_aovs() {
+        let aovs = vec![
+            AOV::Normal,
+            AOV::Depth,
+            AOV::ObjectID,
+            AOV::Direct,
+            AOV::Indirect,
+        ];
+        let output = render_with_params(
+            2,
+            CameraParams {
+                samples_per_pixel: 4,
+                aovs: aovs.clone(),
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            output.aovs.iter().map(|(aov, _)| *aov).collect::<Vec<_>>(),
+            aovs
+        );
+        let pass = |aov: AOV, x: u32, y: u32| output.aov(aov).unwrap().get_pixel(x, y).0;
+        // The top left corner sees the sky, and the bottom middle sees the (tagged) ground
+        assert_eq!(pass(AOV::Normal, 0, 0), [0.0, 0.0, 0.0]);
+        assert_eq!(pass(AOV::Depth, 0, 0), [0.0, 0.0, 0.0]);
+        let [_, normal_y, _] = pass(AOV::Normal, 12, 12);
+        assert!(normal_y > 0.99);
+        let [depth, ..] = pass(AOV::Depth, 12, 12);
+        assert!(depth > 0.5 && depth < 2.0);
+        assert_ne!(pass(AOV::ObjectID, 12, 12), [0.0, 0.0, 0.0]);
+        // The light passes add up to the image
+        for (x, y, pixel) in output.framebuffer.enumerate_pixels() {
+            let direct = pass(AOV::Direct, x, y);
+            let indirect = pass(AOV::Indirect, x, y);
+            for channel in 0..3 {
+                let sum = direct[channel] + indirect[channel];
+                assert!((sum - pixel.0[channel]).abs() <= 1e-4 * pixel.0[channel].max(1.0));
+            }
+        }
+    }
+
+    #[test]
+    fn test_render_analytic_lights() {
+        let grey = Materials::ScatterMaterial(Arc::new(Lambertain {
+            albedo: Arc::new(SolidColor {
+                color: Vec3::new(0.5, 0.5, 0.5),
+            }),
+        }));
+        let ground: Arc<dyn HittableWithBBox> = Arc::new(Quad::new(
+            Vec3::new_int(-10, 0, -10),
+            Vec3::new_int(20, 0, 0),
+            Vec3::new_int(0, 0, 20),
+            grey.clone(),
+        ));
+        // Only direct light, from straight above the middle of a black world
+        let center_pixel = |lights: Vec<Arc<dyn Light>>,
+                            objects: Vec<Arc<dyn HittableWithBBox>>| {
+            let camera = Camera::new(CameraParams {
+                image_width: 3,
+                aspect_ratio: 1_f64,
+                samples_per_pixel: 4,
+                max_depth: 1,
+                fov: 1_f64,
+                look_from: Vec3::new_int(0, 2, 0),
+                look_at: Vec3::new_int(0, 0, 0),
+                v_up: Vec3::new_int(0, 0, -1),
+                background: Arc::new(SolidBackground {
+                    color: Vec3::new_int(0, 0, 0),
+                }),
+                analytic_lights: lights,
+                ..Default::default()
+            });
+            let world = BVH::from_hittables_list(objects);
+            camera.render_framebuffer(&world).unwrap().get_pixel(1, 1).0[0] as f64
+        };
+
+        // albedo / pi * intensity / distance^2
+        let point: Arc<dyn Light> = Arc::new(PointLight {
+            position: Vec3::new_int(0, 1, 0),
+            intensity: Vec3::new_int(2, 2, 2) * PI,
+            profile: None,
+        });
+        let lit = center_pixel(vec![point], vec![ground.clone()]);
+        assert!((lit - 1_f64).abs() < 1e-2, "{}", lit);
+
+        // The sun comes in at 45 degrees, until a sphere is in the way
+        let sun: Arc<dyn Light> = Arc::new(DirectionalLight {
+            direction: Vec3::new_int(1, -1, 0),
+            irradiance: Vec3::new_int(1, 1, 1),
+            angular_radius: 0_f64,
+        });
+        let lit = center_pixel(vec![sun.clone()], vec![ground.clone()]);
+        let expected = 0.5 * 0.5_f64.sqrt() / PI;
+        assert!((lit - expected).abs() < 1e-3, "{}", lit);
+        let blocker = Arc::new(Sphere::new(Vec3::new_int(-1, 1, 0), 0.3, grey));
+        assert_eq!(center_pixel(vec![sun], vec![ground, blocker]), 0_f64);
+    }
+
+    #[test]
+    fn test_render_resume() {
+        let path = std::env::temp_dir().join("rsr-test-render-resume.checkpoint");
+        let params = |samples_per_pixel: i64, resume: Option<PathBuf>| CameraParams {
+            samples_per_pixel,
+            aovs: vec![AOV::Albedo],
+            checkpointing: Some(Checkpointing {
+                samples_per_pass: 3,
+                ..Checkpointing::new(path.clone())
+            }),
+            resume,
+            ..Default::default()
+        };
+        let first = render_with_params(2, params(4, None));
+        assert_eq!(first.sample_counts.max(), 4);
+        // Adds 4 more samples to the 4 of the checkpoint
+        let resumed = render_with_params(2, params(8, Some(path.clone())));
+        std::fs::remove_file(&path).unwrap();
+        // Without the checkpoint it errors instead of panicking
+        let err = try_render_with_params(2, params(8, Some(path.clone())))
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+        let uninterrupted = render_with_params(
+            2,
+            CameraParams {
+                samples_per_pixel: 8,
+                aovs: vec![AOV::Albedo],
+                ..Default::default()
+            },
+        );
+        assert_eq!(resumed.framebuffer, uninterrupted.framebuffer);
+        assert_eq!(resumed.sample_counts, uninterrupted.sample_counts);
+        assert_eq!(resumed.aovs, uninterrupted.aovs);
     }
 }
@@ -0,0 +1,214 @@
+use std::time::Duration;
+
+use image::{Rgb, RgbImage};
+
+use crate::Vec3;
+
+/// Settings for adaptive sampling, which stops sampling each pixel once its noise is low enough
+/// Every pixel takes min_samples, then batches of batch_size until the estimated relative error is under the threshold
+/// [samples_per_pixel](crate::CameraParams::samples_per_pixel) becomes the maximum number of samples of a pixel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveSampling {
+    /// Samples taken by every pixel before the noise is estimated
+    pub min_samples: i64,
+    /// Extra samples taken in each batch by the pixels that are still too noisy
+    pub batch_size: i64,
+    /// Relative standard error (of the luminance) that a pixel needs to get under, i.e. 0.01 for 1%
+    pub threshold: f64,
+    /// Wall-clock limit of the render, no more batches are started once it runs out
+    /// The render is no longer deterministic with a time budget, as the samples taken depend on the speed of the machine
+    pub time_budget: Option<Duration>,
+}
+impl Default for AdaptiveSampling {
+    fn default() -> Self {
+        Self {
+            min_samples: 16,
+            batch_size: 16,
+            threshold: 0.01,
+            time_budget: None,
+        }
+    }
+}
+impl AdaptiveSampling {
+    /// Pixels darker than this use it for the relative error, so nearly black pixels are not sampled forever
+    const DARK_LUMINANCE: f64 = 0.05;
+
+    /// Adaptive sampling from optional settings (i.e. from the CLI or a scene file), missing settings use the default
+    /// Returns None if none of the settings are given
+    pub fn from_options(
+        threshold: Option<f64>,
+        min_samples: Option<i64>,
+        time_budget_seconds: Option<f64>,
+    ) -> Option<Self> {
+        if threshold.is_none() && min_samples.is_none() && time_budget_seconds.is_none() {
+            return None;
+        }
+        let default = Self::default();
+        Some(Self {
+            threshold: threshold.unwrap_or(default.threshold),
+            min_samples: min_samples.unwrap_or(default.min_samples),
+            time_budget: time_budget_seconds
+                .map(|seconds| Duration::from_secs_f64(seconds.max(0_f64))),
+            ..default
+        })
+    }
+    /// Number of samples the pixel should take in the next batch (0 once it has converged)
+    pub(super) fn next_batch(&self, statistics: &PixelStatistics, max_samples: i64) -> i64 {
+        let remaining = max_samples - statistics.samples;
+        if remaining <= 0 || statistics.relative_error() <= self.threshold {
+            return 0;
+        }
+        self.batch_size.max(1).min(remaining)
+    }
+}
+
+/// Running sums of the samples of a pixel
+#[derive(Debug, Clone, Default)]
+pub(super) struct PixelStatistics {
+    pub color_sum: Vec3,
+    pub samples: i64,
+    /// Sums of the [AOV](crate::AOV) values, in the order of the camera aovs (Empty without any AOVs)
+    pub aov_sums: Vec<Vec3>,
+    pub luminance_sum: f64,
+    pub luminance_squared_sum: f64,
+}
+impl PixelStatistics {
+    pub fn add(&mut self, color: Vec3) {
+        let luminance = color.luminance();
+        self.luminance_sum += luminance;
+        self.luminance_squared_sum += luminance * luminance;
+        self.color_sum += color;
+        self.samples += 1;
+    }
+    /// Average color of the samples
+    pub fn mean(&self) -> Vec3 {
+        if self.samples == 0 {
+            return Vec3::new_int(0, 0, 0);
+        }
+        self.color_sum.clone() / self.samples as f64
+    }
+    /// Adds the AOV values of the sample that was last added
+    pub fn add_aovs(&mut self, values: impl Iterator<Item = Vec3>) {
+        for (index, value) in values.enumerate() {
+            if index == self.aov_sums.len() {
+                self.aov_sums.push(value);
+            } else {
+                self.aov_sums[index] += value;
+            }
+        }
+    }
+    /// Average of the index-th AOV over the samples
+    pub fn aov_mean(&self, index: usize) -> Vec3 {
+        match self.aov_sums.get(index) {
+            Some(sum) if self.samples > 0 => sum.clone() / self.samples as f64,
+            _ => Vec3::default(),
+        }
+    }
+    /// Standard error of the mean luminance, relative to the mean luminance
+    // Implementation Details:
+    // The variance uses the sums (Instead of Welford's method), which is accurate enough for a stopping criteria
+    fn relative_error(&self) -> f64 {
+        if self.samples < 2 {
+            return f64::INFINITY;
+        }
+        let n = self.samples as f64;
+        let mean = self.luminance_sum / n;
+        let variance =
+            ((self.luminance_squared_sum - mean * self.luminance_sum) / (n - 1_f64)).max(0_f64);
+        (variance / n).sqrt() / mean.max(AdaptiveSampling::DARK_LUMINANCE)
+    }
+}
+
+/// Number of samples taken by each pixel in a render
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SampleCounts {
+    width: u32,
+    height: u32,
+    /// Row by row from the top left
+    counts: Vec<i64>,
+}
+impl SampleCounts {
+    pub(crate) fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            counts: vec![0; (width * height) as usize],
+        }
+    }
+    pub(super) fn set(&mut self, x: u32, y: u32, count: i64) {
+        self.counts[(y * self.width + x) as usize] = count;
+    }
+    pub fn get(&self, x: u32, y: u32) -> i64 {
+        self.counts[(y * self.width + x) as usize]
+    }
+    pub fn max(&self) -> i64 {
+        self.counts.iter().copied().max().unwrap_or(0)
+    }
+    pub fn average(&self) -> f64 {
+        if self.counts.is_empty() {
+            return 0_f64;
+        }
+        self.counts.iter().sum::<i64>() as f64 / self.counts.len() as f64
+    }
+    /// Heatmap of the counts, from dark blue (No samples) through green and yellow to red (The most samples)
+    pub fn heatmap(&self) -> RgbImage {
+        const RAMP: [[f64; 3]; 5] = [
+            [0.0, 0.0, 0.3],
+            [0.0, 0.4, 1.0],
+            [0.0, 0.9, 0.2],
+            [1.0, 0.9, 0.0],
+            [1.0, 0.0, 0.0],
+        ];
+        let max = self.max().max(1) as f64;
+        RgbImage::from_fn(self.width, self.height, |x, y| {
+            let t = self.get(x, y) as f64 / max * (RAMP.len() - 1) as f64;
+            let index = (t as usize).min(RAMP.len() - 2);
+            let fraction = t - index as f64;
+            let (low, high) = (RAMP[index], RAMP[index + 1]);
+            Rgb([0, 1, 2].map(|channel| {
+                ((low[channel] + (high[channel] - low[channel]) * fraction) * 255_f64).round() as u8
+            }))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_adaptive_next_batch() {
+        let adaptive = AdaptiveSampling {
+            min_samples: 4,
+            batch_size: 8,
+            threshold: 0.01,
+            time_budget: None,
+        };
+        // Every sample is the same, so there is no noise
+        let mut flat = PixelStatistics::default();
+        for _ in 0..4 {
+            flat.add(Vec3::new(0.5, 0.5, 0.5));
+        }
+        assert_eq!(adaptive.next_batch(&flat, 64), 0);
+
+        let mut noisy = PixelStatistics::default();
+        for sample in 0..4 {
+            noisy.add(Vec3::new_int(sample % 2, sample % 2, sample % 2));
+        }
+        assert_eq!(adaptive.next_batch(&noisy, 64), 8);
+        // The last batch is cut at the maximum number of samples
+        assert_eq!(adaptive.next_batch(&noisy, 10), 6);
+        assert_eq!(adaptive.next_batch(&noisy, 4), 0);
+    }
+
+    #[test]
+    fn test_sample_counts_heatmap() {
+        let mut sample_counts = SampleCounts::new(2, 1);
+        sample_counts.set(0, 0, 16);
+        sample_counts.set(1, 0, 64);
+        assert_eq!(sample_counts.max(), 64);
+        assert_eq!(sample_counts.average(), 40.0);
+        let heatmap = sample_counts.heatmap();
+        assert_eq!(heatmap.get_pixel(1, 0), &Rgb([255, 0, 0]));
+    }
+}
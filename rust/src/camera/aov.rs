@@ -0,0 +1,204 @@
+use clap::ValueEnum;
+use image::{Rgb, Rgb32FImage, RgbImage};
+
+use crate::{sampler::hash, HitRecord, Materials, Ray, ToneMapping, Vec3};
+
+/// Arbitrary Output Variables, extra passes rendered alongside the image for compositing (or to guide a denoiser)
+/// Each pass is averaged over the samples of the pixel like the image, so edges are anti-aliased the same way
+/// The first hit passes are 0 where the camera ray misses the world
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum AOV {
+    /// Base color of the first surface hit (From its [ColorTexture](crate::ColorTexture)), glass and lights are white
+    Albedo,
+    /// Shading normal of the first hit, facing the camera, in [-1, 1]
+    Normal,
+    /// Distance from the camera to the first hit
+    Depth,
+    /// World position of the first hit
+    Position,
+    /// (u, v, 0) of the first hit
+    #[value(name = "uv")]
+    UV,
+    /// Color picked from the material ID of the first hit, refer to [Tagged](crate::Tagged)
+    #[value(name = "material-id")]
+    MaterialID,
+    /// Color picked from the object ID of the first hit, refer to [Tagged](crate::Tagged)
+    #[value(name = "object-id")]
+    ObjectID,
+    /// Light that reached the camera after at most one diffuse bounce (Including lights and background seen directly or through glass/mirrors)
+    Direct,
+    /// Light that bounced off more than one diffuse surface, direct + indirect is the image
+    Indirect,
+}
+impl AOV {
+    /// Name of the pass, same as the CLI and scene file value (i.e. `object-id`)
+    pub fn name(&self) -> String {
+        self.to_possible_value().unwrap().get_name().to_string()
+    }
+    /// Value of the pass for a single sample, from the camera ray, its first hit and the light it found
+    pub(super) fn value(
+        &self,
+        ray: &Ray,
+        hit_record: Option<&HitRecord>,
+        radiance: &Radiance,
+    ) -> Vec3 {
+        match self {
+            Self::Direct => return radiance.emitted.clone() + radiance.direct.clone(),
+            Self::Indirect => return radiance.indirect.clone(),
+            _ => {}
+        }
+        let hit_record = match hit_record {
+            Some(hit_record) => hit_record,
+            None => return Vec3::default(),
+        };
+        match self {
+            Self::Albedo => match &hit_record.material {
+                Materials::ScatterMaterial(scatter_material) => scatter_material.albedo(hit_record),
+                Materials::LightMaterial(_) => Vec3::new_int(1, 1, 1),
+            },
+            Self::Normal => hit_record.against_normal_unit.clone(),
+            Self::Depth => {
+                // The camera rays are not unit length, so t is scaled back into a distance
+                let depth = hit_record.t * ray.direction.length();
+                Vec3::new(depth, depth, depth)
+            }
+            Self::Position => hit_record.p.clone(),
+            Self::UV => Vec3::new(hit_record.u, hit_record.v, 0_f64),
+            Self::MaterialID => id_color(hit_record.material_id),
+            Self::ObjectID => id_color(hit_record.object_id),
+            Self::Direct | Self::Indirect => unreachable!(),
+        }
+    }
+    /// Converts the pass into an 8-bit image for previewing (png and ppm)
+    /// The light passes use the tone mapping of the image, normals are mapped from [-1, 1] into [0, 1], and depth and position are stretched to fit the range of the image
+    pub fn to_rgbimage(&self, pass: &Rgb32FImage, tone_mapping: &ToneMapping) -> RgbImage {
+        match self {
+            Self::Direct | Self::Indirect => tone_mapping.to_rgbimage(pass),
+            Self::Albedo => ToneMapping::default().to_rgbimage(pass),
+            Self::Normal => map_channels(pass, |_, value| value * 0.5 + 0.5),
+            Self::Depth | Self::Position => {
+                let mut min = [f32::INFINITY; 3];
+                let mut max = [f32::NEG_INFINITY; 3];
+                for pixel in pass.pixels() {
+                    for channel in 0..3 {
+                        min[channel] = min[channel].min(pixel.0[channel]);
+                        max[channel] = max[channel].max(pixel.0[channel]);
+                    }
+                }
+                map_channels(pass, |channel, value| {
+                    let range = max[channel] - min[channel];
+                    if range > 0_f32 {
+                        (value - min[channel]) / range
+                    } else {
+                        0_f32
+                    }
+                })
+            }
+            Self::UV | Self::MaterialID | Self::ObjectID => map_channels(pass, |_, value| value),
+        }
+    }
+}
+
+/// Maps each channel (given its index) into [0, 1], without any transfer function
+fn map_channels(pass: &Rgb32FImage, map: impl Fn(usize, f32) -> f32) -> RgbImage {
+    RgbImage::from_fn(pass.width(), pass.height(), |x, y| {
+        let mut channel = 0;
+        Rgb(pass.get_pixel(x, y).0.map(|value| {
+            let mapped = map(channel, value);
+            channel += 1;
+            (mapped.clamp(0_f32, 1_f32) * 255_f32).round() as u8
+        }))
+    })
+}
+
+/// Color of an ID, 0 (untagged) is black and every other ID is a bright pseudo-random color
+fn id_color(id: u32) -> Vec3 {
+    if id == 0 {
+        return Vec3::default();
+    }
+    let bits = hash(&[id as u64]);
+    let channel = |shift: u64| 0.2 + 0.8 * ((bits >> shift) & 0xff) as f64 / 255_f64;
+    Vec3::new(channel(0), channel(8), channel(16))
+}
+
+/// Light found by a ray, split by how it got there (Sums up to the color of the ray)
+#[derive(Debug, Clone, Default)]
+pub(super) struct Radiance {
+    /// Emitted by the light or background the ray hits (Or sees through specular bounces)
+    pub emitted: Vec3,
+    /// Light that bounced off exactly one diffuse surface (Or one glossy surface, from the [Light](crate::Light)s)
+    pub direct: Vec3,
+    /// Light that bounced off more than one diffuse surface
+    pub indirect: Vec3,
+}
+impl Radiance {
+    pub fn emitted(color: Vec3) -> Self {
+        Self {
+            emitted: color,
+            ..Default::default()
+        }
+    }
+    pub fn total(&self) -> Vec3 {
+        self.emitted.clone() + self.direct.clone() + self.indirect.clone()
+    }
+    /// Passes the light through a specular bounce, which keeps how the light got there
+    pub fn attenuate(self, attenuation: &Vec3) -> Self {
+        Self {
+            emitted: attenuation.clone() * self.emitted,
+            direct: attenuation.clone() * self.direct,
+            indirect: attenuation.clone() * self.indirect,
+        }
+    }
+    /// Adds light that reached the surface straight from a light
+    pub fn add_direct(mut self, light: Vec3) -> Self {
+        self.direct += light;
+        self
+    }
+    /// Light leaving a diffuse bounce, from the sampled lights (direct_light) and the scattered ray (weighted by scattered_weight)
+    /// The light emitted at the end of the scattered ray becomes direct light, and everything else becomes indirect
+    pub fn diffuse_bounce(
+        attenuation: &Vec3,
+        direct_light: Vec3,
+        scattered: Radiance,
+        scattered_weight: f64,
+    ) -> Self {
+        Self {
+            emitted: Vec3::default(),
+            direct: attenuation.clone() * (direct_light + scattered.emitted * scattered_weight),
+            indirect: attenuation.clone()
+                * ((scattered.direct + scattered.indirect) * scattered_weight),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_radiance_diffuse_bounce() {
+        let attenuation = Vec3::new(0.5, 0.5, 0.5);
+        // The scattered ray hit another diffuse surface, which was lit directly by a light
+        let scattered = Radiance::diffuse_bounce(
+            &attenuation,
+            Vec3::new_int(2, 2, 2),
+            Radiance::emitted(Vec3::new_int(4, 4, 4)),
+            1.0,
+        );
+        assert_eq!(scattered.direct, Vec3::new_int(3, 3, 3));
+        let radiance =
+            Radiance::diffuse_bounce(&attenuation, Vec3::new_int(1, 1, 1), scattered, 2.0);
+        assert_eq!(radiance.direct, Vec3::new(0.5, 0.5, 0.5));
+        assert_eq!(radiance.indirect, Vec3::new_int(3, 3, 3));
+        assert_eq!(radiance.total(), Vec3::new(3.5, 3.5, 3.5));
+    }
+
+    #[test]
+    fn test_id_color() {
+        assert_eq!(id_color(0), Vec3::default());
+        assert_eq!(id_color(3), id_color(3));
+        assert_ne!(id_color(3), id_color(4));
+        assert!(id_color(3).xyz().iter().all(|c| (0.2..=1.0).contains(c)));
+    }
+}
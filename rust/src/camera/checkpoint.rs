@@ -0,0 +1,291 @@
+use std::{
+    ffi::OsString,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use clap::ValueEnum;
+
+use super::{adaptive::PixelStatistics, Tile, AOV};
+use crate::{SamplerType, Vec3};
+
+/// Settings for saving the progress of a render into a checkpoint file, which [CameraParams::resume](crate::CameraParams::resume) can continue from
+/// The samples are taken in passes over the whole image, and a checkpoint is saved after a pass once the interval has passed (And when the render is done)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpointing {
+    pub path: PathBuf,
+    /// Minimum time between 2 checkpoints
+    pub interval: Duration,
+    /// Samples every pixel takes in each pass (Without [AdaptiveSampling](crate::AdaptiveSampling), which has its own batches)
+    pub samples_per_pass: i64,
+}
+impl Checkpointing {
+    /// Checkpoints every minute, in passes of 8 samples
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            interval: Duration::from_secs(60),
+            samples_per_pass: 8,
+        }
+    }
+}
+
+/// Settings of the render that a checkpoint has to match to be resumed
+/// Otherwise the new samples would not continue the same sequence (Or land in the wrong pixels)
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct CheckpointHeader {
+    pub width: u32,
+    pub height: u32,
+    pub seed: u64,
+    pub max_depth: i64,
+    /// Hash of where the camera is and how it looks, refer to [view_hash]
+    pub view: u64,
+    pub sampler: SamplerType,
+    pub aovs: Vec<AOV>,
+}
+
+const MAGIC: &[u8; 8] = b"RSRCKPT2";
+
+// Implementation Details:
+// The file is the magic bytes, the header, then the statistics of every pixel row by row from the top left (Not by tile, so the tile size can change)
+// Everything is little endian, the sampler and AOVs are written as their position in the list of values
+// It is written next to the path first, then renamed over it, so a crash while saving keeps the previous checkpoint
+pub(super) fn save(
+    path: &Path,
+    header: &CheckpointHeader,
+    tiles: &[Tile],
+    tile_statistics: &[Vec<PixelStatistics>],
+) -> io::Result<()> {
+    let mut pixels = vec![None; (header.width * header.height) as usize];
+    for (tile, statistics) in tiles.iter().zip(tile_statistics) {
+        for ((y, x), pixel_statistics) in tile.pixels().zip(statistics) {
+            pixels[y as usize * header.width as usize + x as usize] = Some(pixel_statistics);
+        }
+    }
+
+    let mut temporary_path = OsString::from(path);
+    temporary_path.push(".tmp");
+    let mut writer = BufWriter::new(File::create(&temporary_path)?);
+    writer.write_all(MAGIC)?;
+    writer.write_all(&header.width.to_le_bytes())?;
+    writer.write_all(&header.height.to_le_bytes())?;
+    writer.write_all(&header.seed.to_le_bytes())?;
+    writer.write_all(&header.max_depth.to_le_bytes())?;
+    writer.write_all(&header.view.to_le_bytes())?;
+    writer.write_all(&[value_index(&header.sampler)])?;
+    writer.write_all(&(header.aovs.len() as u32).to_le_bytes())?;
+    for aov in &header.aovs {
+        writer.write_all(&[value_index(aov)])?;
+    }
+    for pixel_statistics in pixels {
+        let pixel_statistics = pixel_statistics.expect("the tiles cover every pixel");
+        writer.write_all(&pixel_statistics.samples.to_le_bytes())?;
+        write_vec3(&mut writer, &pixel_statistics.color_sum)?;
+        writer.write_all(&pixel_statistics.luminance_sum.to_le_bytes())?;
+        writer.write_all(&pixel_statistics.luminance_squared_sum.to_le_bytes())?;
+        for index in 0..header.aovs.len() {
+            write_vec3(
+                &mut writer,
+                &pixel_statistics
+                    .aov_sums
+                    .get(index)
+                    .cloned()
+                    .unwrap_or_default(),
+            )?;
+        }
+    }
+    writer.flush()?;
+    drop(writer);
+    std::fs::rename(temporary_path, path)
+}
+
+/// Loads the statistics of every pixel, split into the tiles
+/// Fails if the checkpoint was rendered with a different header
+pub(super) fn load(
+    path: &Path,
+    expected: &CheckpointHeader,
+    tiles: &[Tile],
+) -> io::Result<Vec<Vec<PixelStatistics>>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut magic = [0_u8; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid_data("not a checkpoint file".to_string()));
+    }
+    let width = u32::from_le_bytes(read_bytes(&mut reader)?);
+    let height = u32::from_le_bytes(read_bytes(&mut reader)?);
+    let seed = u64::from_le_bytes(read_bytes(&mut reader)?);
+    let max_depth = i64::from_le_bytes(read_bytes(&mut reader)?);
+    let view = u64::from_le_bytes(read_bytes(&mut reader)?);
+    let sampler = read_value(&mut reader)?;
+    let aov_count = u32::from_le_bytes(read_bytes(&mut reader)?);
+    let aovs = (0..aov_count)
+        .map(|_| read_value(&mut reader))
+        .collect::<io::Result<Vec<AOV>>>()?;
+    let header = CheckpointHeader {
+        width,
+        height,
+        seed,
+        max_depth,
+        view,
+        sampler,
+        aovs,
+    };
+    if header.width != expected.width || header.height != expected.height {
+        return Err(invalid_data(format!(
+            "the checkpoint is {}x{}, but the image is {}x{}",
+            header.width, header.height, expected.width, expected.height
+        )));
+    }
+    if header.max_depth != expected.max_depth {
+        return Err(invalid_data(format!(
+            "the checkpoint was rendered with max_depth {}, but the render uses {}",
+            header.max_depth, expected.max_depth
+        )));
+    }
+    if header.view != expected.view {
+        return Err(invalid_data(
+            "the checkpoint was rendered from another camera (position, direction, field of view or focus)"
+                .to_string(),
+        ));
+    }
+    if header != *expected {
+        return Err(invalid_data(format!(
+            "the checkpoint was rendered with seed {}, the {:?} sampler and AOVs {:?}, which do not match seed {}, the {:?} sampler and AOVs {:?}",
+            header.seed, header.sampler, header.aovs, expected.seed, expected.sampler, expected.aovs
+        )));
+    }
+
+    let mut pixels = Vec::with_capacity((width * height) as usize);
+    for _ in 0..width * height {
+        let samples = i64::from_le_bytes(read_bytes(&mut reader)?);
+        let color_sum = read_vec3(&mut reader)?;
+        let luminance_sum = f64::from_le_bytes(read_bytes(&mut reader)?);
+        let luminance_squared_sum = f64::from_le_bytes(read_bytes(&mut reader)?);
+        let aov_sums = (0..aov_count)
+            .map(|_| read_vec3(&mut reader))
+            .collect::<io::Result<Vec<Vec3>>>()?;
+        pixels.push(PixelStatistics {
+            color_sum,
+            samples,
+            aov_sums,
+            luminance_sum,
+            luminance_squared_sum,
+        });
+    }
+    Ok(tiles
+        .iter()
+        .map(|tile| {
+            tile.pixels()
+                .map(|(y, x)| pixels[y as usize * width as usize + x as usize].clone())
+                .collect()
+        })
+        .collect())
+}
+
+/// Hash of the values (FNV-1a over their bits), so it is the same on every platform and Rust version
+pub(super) fn view_hash(values: &[f64]) -> u64 {
+    values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+fn value_index<T: ValueEnum + PartialEq>(value: &T) -> u8 {
+    T::value_variants()
+        .iter()
+        .position(|variant| variant == value)
+        .unwrap() as u8
+}
+fn read_value<T: ValueEnum + Clone>(reader: &mut impl Read) -> io::Result<T> {
+    let [index] = read_bytes::<1>(reader)?;
+    T::value_variants()
+        .get(index as usize)
+        .cloned()
+        .ok_or_else(|| invalid_data(format!("unknown value {} in the header", index)))
+}
+fn read_bytes<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0_u8; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+fn write_vec3(writer: &mut impl Write, v: &Vec3) -> io::Result<()> {
+    for value in v.xyz() {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    Ok(())
+}
+fn read_vec3(reader: &mut impl Read) -> io::Result<Vec3> {
+    let mut read = || io::Result::Ok(f64::from_le_bytes(read_bytes(reader)?));
+    Ok(Vec3::new(read()?, read()?, read()?))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint_save_load() {
+        let header = CheckpointHeader {
+            width: 4,
+            height: 3,
+            seed: 1,
+            max_depth: 10,
+            view: view_hash(&[0.0, 1.0, 2.0]),
+            sampler: SamplerType::Halton,
+            aovs: vec![AOV::Normal, AOV::Depth],
+        };
+        let tiles = Tile::split_image(4, 3, 2);
+        let mut tile_statistics: Vec<Vec<PixelStatistics>> = tiles
+            .iter()
+            .map(|tile| vec![PixelStatistics::default(); tile.len()])
+            .collect();
+        tile_statistics[1][0].add(Vec3::new(0.5, 1.0, 2.0));
+        tile_statistics[1][0]
+            .add_aovs([Vec3::new_int(0, 1, 0), Vec3::new_int(3, 3, 3)].into_iter());
+        let path = std::env::temp_dir().join("rsr-test-checkpoint-header.checkpoint");
+        save(&path, &header, &tiles, &tile_statistics).unwrap();
+
+        // Loaded back into different tiles, the pixel (2, 0) is now in the first tile
+        let loaded = load(&path, &header, &Tile::split_image(4, 3, 3)).unwrap();
+        assert_eq!(loaded[0][2].samples, 1);
+        assert_eq!(loaded[0][2].mean(), Vec3::new(0.5, 1.0, 2.0));
+        assert_eq!(loaded[0][2].aov_mean(1), Vec3::new_int(3, 3, 3));
+
+        let other_seed = CheckpointHeader {
+            seed: 2,
+            ..header.clone()
+        };
+        assert!(load(&path, &other_seed, &tiles).is_err());
+        let other_depth = CheckpointHeader {
+            max_depth: 20,
+            ..header.clone()
+        };
+        assert_eq!(
+            load(&path, &other_depth, &tiles).unwrap_err().to_string(),
+            "the checkpoint was rendered with max_depth 10, but the render uses 20"
+        );
+        let other_view = CheckpointHeader {
+            view: view_hash(&[0.0, 1.0, 2.5]),
+            ..header.clone()
+        };
+        assert!(load(&path, &other_view, &tiles).is_err());
+        let other_size = CheckpointHeader {
+            width: 5,
+            ..header.clone()
+        };
+        let err = load(&path, &other_size, &tiles).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            err.to_string(),
+            "the checkpoint is 4x3, but the image is 5x3"
+        );
+    }
+}
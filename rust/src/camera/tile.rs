@@ -0,0 +1,89 @@
+/// A rectangular block of pixels in the image, rendered as one unit of work
+/// To split an image into tiles use [Tile::split_image]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    /// x of the top left pixel of the tile
+    pub x: i64,
+    /// y of the top left pixel of the tile
+    pub y: i64,
+    /// Width of the tile in pixels (Smaller than tile_size at the right edge of the image)
+    pub width: i64,
+    /// Height of the tile in pixels (Smaller than tile_size at the bottom edge of the image)
+    pub height: i64,
+}
+impl Tile {
+    /// Splits an image of (image_width, image_height) into square tiles of tile_size
+    /// Tiles are ordered from the top left, going right then down (same order as the scanlines)
+    pub fn split_image(image_width: i64, image_height: i64, tile_size: i64) -> Vec<Tile> {
+        let tile_size = tile_size.max(1);
+        let mut tiles = Vec::new();
+        for y in (0..image_height).step_by(tile_size as usize) {
+            for x in (0..image_width).step_by(tile_size as usize) {
+                tiles.push(Tile {
+                    x,
+                    y,
+                    width: tile_size.min(image_width - x),
+                    height: tile_size.min(image_height - y),
+                });
+            }
+        }
+        tiles
+    }
+    /// Number of pixels in the tile
+    pub fn len(&self) -> usize {
+        (self.width * self.height) as usize
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Iterates through the (y, x) image cordinates of every pixel in the tile, row by row
+    pub fn pixels(&self) -> impl Iterator<Item = (i64, i64)> + '_ {
+        (self.y..self.y + self.height)
+            .flat_map(move |y| (self.x..self.x + self.width).map(move |x| (y, x)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tile_split_image() {
+        let tiles = Tile::split_image(100, 50, 32);
+        assert_eq!(tiles.len(), 4 * 2);
+        assert_eq!(
+            tiles[0],
+            Tile {
+                x: 0,
+                y: 0,
+                width: 32,
+                height: 32
+            }
+        );
+        // Right and bottom edge tiles are cropped to the image
+        assert_eq!(
+            tiles[7],
+            Tile {
+                x: 96,
+                y: 32,
+                width: 4,
+                height: 18
+            }
+        );
+        // Every pixel is covered exactly once
+        let covered: usize = tiles.iter().map(|tile| tile.len()).sum();
+        assert_eq!(covered, 100 * 50);
+    }
+
+    #[test]
+    fn test_tile_pixels() {
+        let tile = Tile {
+            x: 2,
+            y: 3,
+            width: 2,
+            height: 2,
+        };
+        let pixels: Vec<(i64, i64)> = tile.pixels().collect();
+        assert_eq!(pixels, vec![(3, 2), (3, 3), (4, 2), (4, 3)]);
+    }
+}
@@ -1,7 +1,18 @@
-use std::{ffi::OsString, path::PathBuf};
+use std::{
+    ffi::OsString,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use clap::{Parser, ValueEnum};
-use image::{ImageFormat, RgbImage};
+use clap::{Parser, Subcommand, ValueEnum};
+use image::{codecs::hdr::HdrEncoder, DynamicImage, ImageFormat, Rgb32FImage, RgbImage};
+
+use crate::{
+    AdaptiveSampling, CameraParams, Checkpointing, Denoiser, RenderOutput, SampleCounts,
+    SamplerType, ToneMapOperator, ToneMapping, AOV,
+};
 
 #[derive(Debug, Parser)]
 #[command(name = "rsr")]
@@ -11,21 +22,283 @@ pub struct Cli {
     name: OsString,
     #[arg(short, long, default_value_t=OutputFormat::PNG, value_enum)]
     output_format: OutputFormat,
+    /// Tone mapping operator for png and ppm outputs (exr, hdr and pfm keep the raw radiance)
+    #[arg(short, long, default_value = "clamp", value_enum)]
+    tone_map: ToneMapOperator,
+    /// Exposure in stops applied before tone mapping, every +1 doubles the brightness
+    #[arg(short, long, default_value_t = 0.0, allow_negative_numbers = true)]
+    exposure: f64,
+    /// Luminance mapped to white by extended-reinhard
+    #[arg(long, default_value_t = 4.0)]
+    white_point: f64,
+    /// Seed of the random samples (and the randomly generated scenes), the same seed renders the same image
+    #[arg(long)]
+    seed: Option<u64>,
+    /// How the samples of each pixel are spread out (Defaults to sobol)
+    #[arg(long, value_enum)]
+    sampler: Option<SamplerType>,
+    /// Adaptive sampling: stops sampling a pixel once the relative error of its luminance is under the threshold (i.e. 0.01)
+    /// Any of the adaptive options turns it on, and the samples per pixel becomes the maximum
+    #[arg(long)]
+    adaptive_threshold: Option<f64>,
+    /// Adaptive sampling: samples every pixel takes before its noise is estimated
+    #[arg(long)]
+    min_samples: Option<i64>,
+    /// Adaptive sampling: wall-clock limit of the render in seconds
+    #[arg(long)]
+    time_budget: Option<f64>,
+    /// Also saves a heatmap of the samples taken by each pixel, as NAME-samples.png
+    #[arg(long)]
+    heatmap: bool,
+    /// Extra passes to render (i.e. `--aov albedo,normal,depth`)
+    /// Saved as NAME-PASS files, or as extra layers of NAME.exr with `-o exr`
+    #[arg(long, value_enum, value_delimiter = ',')]
+    aov: Vec<AOV>,
+    /// Denoises the image, guided by the albedo, normal and depth AOVs (Which are rendered, but only saved if asked for with `--aov`)
+    #[arg(long)]
+    denoise: bool,
+    /// Saves the progress of the render into this file every --checkpoint-interval, so it can be continued with --resume
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+    /// Seconds between 2 checkpoints
+    #[arg(long, default_value_t = 60.0)]
+    checkpoint_interval: f64,
+    /// Continues the render saved in this checkpoint, until the samples per pixel (Needs the same camera, max depth, seed, sampler and AOVs, changes to the objects are not detected)
+    /// Keeps saving checkpoints into the same file, unless --checkpoint is given
+    #[arg(long)]
+    resume: Option<PathBuf>,
 }
 impl Cli {
-    pub fn save_image(&self, image: RgbImage) {
-        let output_format = match self.output_format {
-            OutputFormat::PNG => ImageFormat::Png,
-            OutputFormat::PPM => ImageFormat::Pnm,
-        };
-        let output_ext = match self.output_format {
-            OutputFormat::PNG => "png",
-            OutputFormat::PPM => "ppm",
+    /// Seed chosen from the CLI arguments, if any
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+    /// Overrides the camera params with the ones given in the CLI arguments (i.e. seed, sampler, adaptive sampling, AOVs and checkpoints)
+    pub fn apply_to(&self, camera_params: &mut CameraParams) {
+        if let Some(seed) = self.seed {
+            camera_params.seed = seed;
+        }
+        if let Some(sampler) = self.sampler {
+            camera_params.sampler = sampler;
+        }
+        if let Some(adaptive_sampling) = AdaptiveSampling::from_options(
+            self.adaptive_threshold,
+            self.min_samples,
+            self.time_budget,
+        ) {
+            camera_params.adaptive_sampling = Some(adaptive_sampling);
+        }
+        if !self.aov.is_empty() {
+            camera_params.aovs = self.aov.clone();
+        }
+        if let Some(path) = self.checkpoint.as_ref().or(self.resume.as_ref()) {
+            camera_params.checkpointing = Some(Checkpointing {
+                interval: Duration::from_secs_f64(self.checkpoint_interval.max(0_f64)),
+                ..Checkpointing::new(path.clone())
+            });
+        }
+        if let Some(path) = &self.resume {
+            camera_params.resume = Some(path.clone());
+        }
+        if self.denoise {
+            for guide in Denoiser::GUIDES {
+                if !camera_params.aovs.contains(&guide) {
+                    camera_params.aovs.push(guide);
+                }
+            }
+        }
+    }
+    /// Saves the render with [save_render](Self::save_render), or prints why it failed (i.e. a checkpoint that can not be resumed) and exits with 1
+    pub fn save_render_or_exit(&self, output: io::Result<RenderOutput>) {
+        match output {
+            Ok(output) => self.save_render(output),
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+    /// Saves everything that was rendered: the image, its AOVs and the sample heatmap (If asked for)
+    /// The image is denoised first with `--denoise`
+    pub fn save_render(&self, mut output: RenderOutput) {
+        self.save_sample_heatmap(&output.sample_counts);
+        if self.denoise {
+            output.framebuffer = Denoiser::default().denoise(&output);
+            // The guides that were only rendered for the denoiser are not saved
+            output
+                .aovs
+                .retain(|(aov, _)| !Denoiser::GUIDES.contains(aov) || self.aov.contains(aov));
+        }
+        if output.aovs.is_empty() {
+            return self.save_image(output.framebuffer);
+        }
+        match self.output_format {
+            OutputFormat::EXR => {
+                write_layered_exr(&self.path(""), &output.framebuffer, &output.aovs).unwrap()
+            }
+            _ => {
+                for (aov, pass) in &output.aovs {
+                    self.save_pass(&self.path(&format!("-{}", aov.name())), pass, |pass| {
+                        aov.to_rgbimage(pass, &self.tone_mapping())
+                    });
+                }
+                self.save_image(output.framebuffer);
+            }
+        }
+    }
+    /// Saves the heatmap of the samples taken by each pixel, if it was asked for with `--heatmap`
+    pub fn save_sample_heatmap(&self, sample_counts: &SampleCounts) {
+        if !self.heatmap {
+            return;
+        }
+        let mut file_name = self.name.clone();
+        file_name.push("-samples.png");
+        sample_counts
+            .heatmap()
+            .save_with_format(PathBuf::from(file_name), ImageFormat::Png)
+            .unwrap();
+    }
+    /// Tone mapping chosen from the CLI arguments
+    pub fn tone_mapping(&self) -> ToneMapping {
+        ToneMapping {
+            operator: self.tone_map,
+            exposure: self.exposure,
+            white_point: self.white_point,
+        }
+    }
+    /// Saves the linear framebuffer in the chosen output format
+    /// `png` and `ppm` are tone mapped into 8-bit sRGB, `exr`, `hdr` and `pfm` keep the unclamped linear values
+    pub fn save_image(&self, framebuffer: Rgb32FImage) {
+        self.save_pass(&self.path(""), &framebuffer, |framebuffer| {
+            self.tone_mapping().to_rgbimage(framebuffer)
+        });
+    }
+    /// Path of the output, with the suffix added to the name (i.e. `NAME-albedo.png`)
+    fn path(&self, suffix: &str) -> PathBuf {
+        let mut file_name = self.name.clone();
+        file_name.push(suffix);
+        file_name.push(".");
+        file_name.push(self.output_format.extension());
+        PathBuf::from(file_name)
+    }
+    /// Saves a single float image in the chosen output format, to_rgbimage converts it for `png` and `ppm`
+    fn save_pass(
+        &self,
+        path: &Path,
+        framebuffer: &Rgb32FImage,
+        to_rgbimage: impl Fn(&Rgb32FImage) -> RgbImage,
+    ) {
+        match self.output_format {
+            OutputFormat::PNG => to_rgbimage(framebuffer)
+                .save_with_format(path, ImageFormat::Png)
+                .unwrap(),
+            OutputFormat::PPM => to_rgbimage(framebuffer)
+                .save_with_format(path, ImageFormat::Pnm)
+                .unwrap(),
+            OutputFormat::EXR => DynamicImage::ImageRgb32F(framebuffer.clone())
+                .save_with_format(path, ImageFormat::OpenExr)
+                .unwrap(),
+            OutputFormat::HDR => {
+                let writer = BufWriter::new(File::create(path).unwrap());
+                HdrEncoder::new(writer)
+                    .encode(
+                        &framebuffer.pixels().cloned().collect::<Vec<_>>(),
+                        framebuffer.width() as usize,
+                        framebuffer.height() as usize,
+                    )
+                    .unwrap();
+            }
+            OutputFormat::PFM => {
+                let mut writer = BufWriter::new(File::create(path).unwrap());
+                write_pfm(&mut writer, framebuffer).unwrap();
+            }
+        }
+    }
+}
+
+/// Writes the framebuffer and its AOVs as a multi-layer OpenEXR, with a `beauty` layer followed by a layer for each AOV
+// Implementation Details:
+// Each layer is a separate part of the file, as the image crate can only write a single layer
+// Readers that do not know about layers (i.e. the image crate) open the first one, which is the beauty layer
+fn write_layered_exr(
+    path: &Path,
+    framebuffer: &Rgb32FImage,
+    aovs: &[(AOV, Rgb32FImage)],
+) -> exr::error::UnitResult {
+    use exr::prelude::*;
+
+    let layer = |name: &str, pass: &Rgb32FImage| {
+        let channel = |index: usize, name: &str| {
+            AnyChannel::new(
+                name,
+                FlatSamples::F32(pass.pixels().map(|pixel| pixel.0[index]).collect()),
+            )
         };
-        let mut path: PathBuf = PathBuf::from(self.name.clone());
-        path.set_extension(output_ext);
-        image.save_with_format(path, output_format).unwrap();
+        Layer::new(
+            (pass.width() as usize, pass.height() as usize),
+            LayerAttributes::named(name),
+            Encoding::FAST_LOSSLESS,
+            AnyChannels::sort(SmallVec::from_vec(vec![
+                channel(0, "R"),
+                channel(1, "G"),
+                channel(2, "B"),
+            ])),
+        )
+    };
+    let mut layers = vec![layer("beauty", framebuffer)];
+    for (aov, pass) in aovs {
+        layers.push(layer(aov.name().as_str(), pass));
+    }
+    Image::from_layers(
+        ImageAttributes::new(IntegerBounds::from_dimensions((
+            framebuffer.width() as usize,
+            framebuffer.height() as usize,
+        ))),
+        layers,
+    )
+    .write()
+    .to_file(path)
+}
+
+/// Writes the framebuffer as a color PFM (Portable Float Map)
+// Implementation Details:
+// The header is `PF`, the size, then a negative scale to mark the floats as little endian
+// The rows are written from the bottom of the image to the top
+fn write_pfm(writer: &mut impl Write, framebuffer: &Rgb32FImage) -> std::io::Result<()> {
+    write!(
+        writer,
+        "PF\n{} {}\n-1.0\n",
+        framebuffer.width(),
+        framebuffer.height()
+    )?;
+    for y in (0..framebuffer.height()).rev() {
+        for x in 0..framebuffer.width() {
+            for channel in framebuffer.get_pixel(x, y).0 {
+                writer.write_all(&channel.to_le_bytes())?;
+            }
+        }
     }
+    writer.flush()
+}
+
+/// CLI of the `rsr` binary, which renders scene files
+#[derive(Debug, Parser)]
+#[command(name = "rsr")]
+#[command(about = "Rust Simple RayTracer(RRT)", long_about=None)]
+pub struct SceneCli {
+    #[command(subcommand)]
+    pub command: SceneCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SceneCommand {
+    /// Render a scene file
+    Render {
+        /// Path to the scene file (.toml)
+        scene: PathBuf,
+        #[command(flatten)]
+        output: Cli,
+    },
 }
 
 #[derive(Debug, Clone, ValueEnum)]
@@ -33,6 +306,69 @@ impl Cli {
 enum OutputFormat {
     PPM,
     PNG,
+    /// OpenEXR, 32-bit float
+    EXR,
+    /// Radiance RGBE
+    HDR,
+    /// Portable Float Map
+    PFM,
+}
+impl OutputFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::PPM => "ppm",
+            Self::PNG => "png",
+            Self::EXR => "exr",
+            Self::HDR => "hdr",
+            Self::PFM => "pfm",
+        }
+    }
 }
 
 // rust-raytracer build scene --ppm(default png) path(default test.png in current dir with override, only do so at the end)
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::Rgb;
+
+    #[test]
+    fn test_write_pfm() {
+        let mut framebuffer = Rgb32FImage::new(2, 1);
+        framebuffer.put_pixel(0, 0, Rgb([1.5, 0.0, 0.25]));
+        framebuffer.put_pixel(1, 0, Rgb([0.0, 20.0, 0.0]));
+        let mut bytes = Vec::new();
+        write_pfm(&mut bytes, &framebuffer).unwrap();
+
+        let header = b"PF\n2 1\n-1.0\n";
+        assert_eq!(&bytes[..header.len()], header);
+        let floats: Vec<f32> = bytes[header.len()..]
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        // Values above 1 are kept as is
+        assert_eq!(floats, vec![1.5, 0.0, 0.25, 0.0, 20.0, 0.0]);
+    }
+
+    #[test]
+    fn test_write_layered_exr() {
+        let mut framebuffer = Rgb32FImage::new(2, 1);
+        framebuffer.put_pixel(1, 0, Rgb([3.0, 0.5, 0.25]));
+        let mut normal = Rgb32FImage::new(2, 1);
+        normal.put_pixel(0, 0, Rgb([0.0, -1.0, 0.0]));
+        let path = std::env::temp_dir().join("rsr-test-write-layered.exr");
+        write_layered_exr(&path, &framebuffer, &[(AOV::Normal, normal)]).unwrap();
+
+        let image = exr::prelude::read_all_flat_layers_from_file(&path).unwrap();
+        let names: Vec<String> = image
+            .layer_data
+            .iter()
+            .map(|layer| layer.attributes.layer_name.as_ref().unwrap().to_string())
+            .collect();
+        assert_eq!(names, vec!["beauty", "normal"]);
+        // Readers that do not know about layers still open the beauty layer
+        let beauty = image::open(&path).unwrap().into_rgb32f();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(beauty, framebuffer);
+    }
+}
@@ -0,0 +1,260 @@
+use image::{Rgb, Rgb32FImage};
+use rayon::prelude::*;
+
+use crate::{RenderOutput, AOV};
+
+/// Post-render denoiser, an edge-avoiding À-Trous wavelet filter (Dammertz et al. 2010)
+/// Blurs the image over wider and wider footprints, but stops at the edges found in the albedo, normal and depth [AOV]s (and in the color itself)
+/// The camera has to render the guides (Any missing ones are not used), refer to [Denoiser::GUIDES]
+///
+/// The lighting is filtered on its own (The color divided by the albedo), so the detail of the textures is kept sharp
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Denoiser {
+    /// Number of passes, each doubles the distance between the taps (5 passes cover 125 pixels across)
+    pub iterations: u32,
+    /// How different the (tone mapped) colors of 2 pixels can be before they stop being blurred together, halved every pass
+    pub color_sigma: f32,
+    /// How different the normals can be
+    pub normal_sigma: f32,
+    /// How different the depths can be, relative to the depth of the pixel
+    pub depth_sigma: f32,
+    /// How different the albedos can be
+    pub albedo_sigma: f32,
+}
+impl Default for Denoiser {
+    fn default() -> Self {
+        Self {
+            iterations: 5,
+            color_sigma: 0.6,
+            normal_sigma: 0.3,
+            depth_sigma: 0.1,
+            albedo_sigma: 0.1,
+        }
+    }
+}
+impl Denoiser {
+    /// AOVs used to find the edges of the image
+    pub const GUIDES: [AOV; 3] = [AOV::Albedo, AOV::Normal, AOV::Depth];
+
+    /// Denoises the framebuffer of the render, guided by the AOVs it has
+    pub fn denoise(&self, output: &RenderOutput) -> Rgb32FImage {
+        let width = output.framebuffer.width() as usize;
+        let height = output.framebuffer.height() as usize;
+        let pixels = |image: &Rgb32FImage| image.pixels().map(|pixel| pixel.0).collect::<Vec<_>>();
+        let guide = |aov: AOV| output.aov(aov).map(pixels);
+        let albedo = guide(AOV::Albedo);
+        let normal = guide(AOV::Normal);
+        let depth = guide(AOV::Depth);
+
+        // Surfaces that are almost black (or missed) keep the color, as dividing by the albedo would blow up the noise
+        let demodulation: Vec<[f32; 3]> = match &albedo {
+            Some(albedo) => albedo
+                .iter()
+                .map(|pixel| pixel.map(|channel| if channel > 0.01 { channel } else { 1_f32 }))
+                .collect(),
+            None => vec![[1_f32; 3]; width * height],
+        };
+        let mut lighting: Vec<[f32; 3]> = pixels(&output.framebuffer)
+            .iter()
+            .zip(&demodulation)
+            .map(|(color, albedo)| [0, 1, 2].map(|channel| color[channel] / albedo[channel]))
+            .collect();
+
+        let guides = Guides {
+            albedo: albedo.as_deref(),
+            normal: normal.as_deref(),
+            depth: depth.as_deref(),
+        };
+        for iteration in 0..self.iterations {
+            lighting = self.filter_pass(&lighting, width, height, iteration, &guides);
+        }
+
+        let mut denoised = Rgb32FImage::new(width as u32, height as u32);
+        for (index, pixel) in denoised.pixels_mut().enumerate() {
+            *pixel =
+                Rgb([0, 1, 2]
+                    .map(|channel| lighting[index][channel] * demodulation[index][channel]));
+        }
+        denoised
+    }
+
+    // Implementation Details:
+    // A single À-Trous pass, the 5x5 B3-spline kernel with holes of 2^iteration pixels between the taps
+    // Each tap is weighted down by how different its color and guides are from the center pixel
+    fn filter_pass(
+        &self,
+        lighting: &[[f32; 3]],
+        width: usize,
+        height: usize,
+        iteration: u32,
+        guides: &Guides,
+    ) -> Vec<[f32; 3]> {
+        const KERNEL: [f32; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+        let step = 1_i64 << iteration;
+        let color_sigma = self.color_sigma / (1 << iteration) as f32;
+        // The colors are compared after a per channel Reinhard, so bright lights do not dominate the distances
+        let mapped = |color: &[f32; 3]| {
+            color.map(|channel| channel.max(0_f32) / (1_f32 + channel.max(0_f32)))
+        };
+
+        let mut filtered = vec![[0_f32; 3]; width * height];
+        filtered
+            .par_chunks_mut(width)
+            .enumerate()
+            .for_each(|(y, row)| {
+                for (x, output) in row.iter_mut().enumerate() {
+                    let center = y * width + x;
+                    let center_color = mapped(&lighting[center]);
+                    let mut sum = [0_f32; 3];
+                    let mut weight_sum = 0_f32;
+                    for (j, kernel_y) in KERNEL.iter().enumerate() {
+                        let tap_y = y as i64 + (j as i64 - 2) * step;
+                        if tap_y < 0 || tap_y >= height as i64 {
+                            continue;
+                        }
+                        for (i, kernel_x) in KERNEL.iter().enumerate() {
+                            let tap_x = x as i64 + (i as i64 - 2) * step;
+                            if tap_x < 0 || tap_x >= width as i64 {
+                                continue;
+                            }
+                            let tap = tap_y as usize * width + tap_x as usize;
+                            let color_weight =
+                                (-distance_squared(&center_color, &mapped(&lighting[tap]))
+                                    / (color_sigma * color_sigma))
+                                    .exp();
+                            let weight = kernel_x
+                                * kernel_y
+                                * color_weight
+                                * self.guide_weight(guides, center, tap);
+                            for channel in 0..3 {
+                                sum[channel] += weight * lighting[tap][channel];
+                            }
+                            weight_sum += weight;
+                        }
+                    }
+                    // The center tap always has a weight, so weight_sum is never 0
+                    *output = sum.map(|channel| channel / weight_sum);
+                }
+            });
+        filtered
+    }
+    /// Edge stopping weight of the guides between the center pixel and the tap
+    fn guide_weight(&self, guides: &Guides, center: usize, tap: usize) -> f32 {
+        let mut exponent = 0_f32;
+        if let Some(normal) = guides.normal {
+            exponent += distance_squared(&normal[center], &normal[tap])
+                / (self.normal_sigma * self.normal_sigma);
+        }
+        if let Some(albedo) = guides.albedo {
+            exponent += distance_squared(&albedo[center], &albedo[tap])
+                / (self.albedo_sigma * self.albedo_sigma);
+        }
+        if let Some(depth) = guides.depth {
+            let (center_depth, tap_depth) = (depth[center][0], depth[tap][0]);
+            exponent += (center_depth - tap_depth).abs()
+                / (self.depth_sigma * center_depth.max(tap_depth)).max(1e-6);
+        }
+        (-exponent).exp()
+    }
+}
+
+/// Guide AOVs, as flat arrays of pixels
+struct Guides<'a> {
+    albedo: Option<&'a [[f32; 3]]>,
+    normal: Option<&'a [[f32; 3]]>,
+    depth: Option<&'a [[f32; 3]]>,
+}
+
+fn distance_squared(a: &[f32; 3], b: &[f32; 3]) -> f32 {
+    (0..3)
+        .map(|channel| (a[channel] - b[channel]).powi(2))
+        .sum()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::SampleCounts;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    /// Noisy grey image, where the left half faces +x and the right half faces +y
+    fn noisy_output() -> RenderOutput {
+        let (width, height) = (32, 16);
+        let mut rng = StdRng::seed_from_u64(0);
+        let framebuffer = Rgb32FImage::from_fn(width, height, |x, _| {
+            let base = if x < width / 2 { 0.2 } else { 0.8 };
+            let value = base * rng.gen_range(0.5..1.5);
+            Rgb([value; 3])
+        });
+        let normal = Rgb32FImage::from_fn(width, height, |x, _| {
+            if x < width / 2 {
+                Rgb([1.0, 0.0, 0.0])
+            } else {
+                Rgb([0.0, 1.0, 0.0])
+            }
+        });
+        RenderOutput {
+            framebuffer,
+            sample_counts: SampleCounts::new(width, height),
+            aovs: vec![
+                (
+                    AOV::Albedo,
+                    Rgb32FImage::from_pixel(width, height, Rgb([0.5; 3])),
+                ),
+                (AOV::Normal, normal),
+                (
+                    AOV::Depth,
+                    Rgb32FImage::from_pixel(width, height, Rgb([2.0; 3])),
+                ),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_denoise_edges() {
+        let output = noisy_output();
+        let denoised = Denoiser::default().denoise(&output);
+        let half_statistics = |image: &Rgb32FImage, left: bool| {
+            let values: Vec<f32> = image
+                .enumerate_pixels()
+                .filter(|(x, _, _)| (*x < 16) == left)
+                .map(|(_, _, pixel)| pixel.0[0])
+                .collect();
+            let mean = values.iter().sum::<f32>() / values.len() as f32;
+            let variance = values
+                .iter()
+                .map(|value| (value - mean).powi(2))
+                .sum::<f32>()
+                / values.len() as f32;
+            (mean, variance)
+        };
+        for left in [true, false] {
+            let (noisy_mean, noisy_variance) = half_statistics(&output.framebuffer, left);
+            let (mean, variance) = half_statistics(&denoised, left);
+            // The noise is mostly gone, but the brightness of each half is kept (Nothing bleeds across the normal edge)
+            assert!(
+                variance < noisy_variance * 0.1,
+                "{} {}",
+                variance,
+                noisy_variance
+            );
+            assert!(
+                (mean - noisy_mean).abs() < 0.02 * noisy_mean.max(0.2),
+                "{} {}",
+                mean,
+                noisy_mean
+            );
+        }
+    }
+
+    #[test]
+    fn test_denoise_without_guides() {
+        let mut output = noisy_output();
+        output.aovs.clear();
+        let denoised = Denoiser::default().denoise(&output);
+        assert_eq!(denoised.dimensions(), output.framebuffer.dimensions());
+        assert!(denoised
+            .pixels()
+            .all(|pixel| pixel.0.iter().all(|c| c.is_finite())));
+    }
+}
@@ -1,5 +1,9 @@
 use std::{f64::consts::PI, fmt::Display};
 
+use image::{Rgb32FImage, RgbImage};
+
+use crate::ToneMapping;
+
 use super::Vec3;
 
 /// Converts a degree value into radians which we use internally
@@ -11,22 +15,133 @@ pub fn rgb_to_color(r: u8, g: u8, b: u8) -> Vec3 {
     Vec3::new(r as f64 / 255.0, g as f64 / 255.0, b as f64 / 255.0)
 }
 
-/// Takes a float color vector and return the rgb int values as a tuple
-pub fn color_to_rgb(color: &Vec3, samples_per_pixel: i64) -> (i64, i64, i64) {
-    let c = color.clone();
-    let scale = 1_f64 / samples_per_pixel as f64;
-    let c = c * scale;
-    let (r, g, b) = c.tuple();
-
-    let r = r.sqrt();
-    let g = g.sqrt();
-    let b = b.sqrt();
-
-    (
-        (r.clamp(0.0, 0.999) * 256_f64) as i64,
-        (g.clamp(0.0, 0.999) * 256_f64) as i64,
-        (b.clamp(0.0, 0.999) * 256_f64) as i64,
-    )
+/// Converts a linear float framebuffer (i.e. from [Camera::render_framebuffer](crate::Camera::render_framebuffer)) into an 8-bit sRGB image
+/// Uses the default [ToneMapping] (Clamp, with no exposure)
+pub fn framebuffer_to_rgbimage(framebuffer: &Rgb32FImage) -> RgbImage {
+    ToneMapping::default().to_rgbimage(framebuffer)
+}
+
+/// Real roots of a * x^2 + b * x + c, smallest first (Both are the same root for a line, when a = 0)
+/// None if there is no real root
+// Implementation Details:
+// Computes the root further from 0 first and gets the other from their product (c / a)
+// So b * b close to the discriminant does not cancel out the precision of the smaller root
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Option<(f64, f64)> {
+    if a == 0_f64 {
+        if b == 0_f64 {
+            return None;
+        }
+        let root = -c / b;
+        return Some((root, root));
+    }
+    let discriminant = b * b - 4_f64 * a * c;
+    if discriminant < 0_f64 {
+        return None;
+    }
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    let (root1, root2) = if q == 0_f64 {
+        // b and c are both 0
+        (0_f64, 0_f64)
+    } else {
+        (q / a, c / q)
+    };
+    Some((root1.min(root2), root1.max(root2)))
+}
+
+/// Real roots of the polynomial coefficients[0] + coefficients[1] * x + coefficients[2] * x^2 + ..., smallest first
+/// Roots where the polynomial only touches 0 without crossing it (i.e. a ray grazing a surface) can be missed
+// Implementation Details:
+// Between two roots of the derivative the polynomial is monotonic, so it holds at most one root, found by a safeguarded Newton's method
+// The derivative is solved the same way (recursively), and every root lies within 1 + max |coefficient / leading coefficient| (Cauchy's bound)
+pub fn solve_polynomial(coefficients: &[f64]) -> Vec<f64> {
+    let degree = match coefficients.iter().rposition(|c| *c != 0_f64) {
+        Some(degree) => degree,
+        None => return Vec::new(),
+    };
+    match degree {
+        0 => return Vec::new(),
+        1 => return vec![-coefficients[0] / coefficients[1]],
+        2 => {
+            return match solve_quadratic(coefficients[2], coefficients[1], coefficients[0]) {
+                Some((root1, root2)) => vec![root1, root2],
+                None => Vec::new(),
+            }
+        }
+        _ => (),
+    }
+    let coefficients = &coefficients[..=degree];
+    let bound = 1_f64
+        + coefficients[..degree]
+            .iter()
+            .fold(0_f64, |acc, c| acc.max((c / coefficients[degree]).abs()));
+    let derivative: Vec<f64> = coefficients
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(power, c)| power as f64 * c)
+        .collect();
+    // Value and slope of the polynomial at x (Horner's method)
+    let evaluate = |x: f64| {
+        coefficients
+            .iter()
+            .rev()
+            .fold((0_f64, 0_f64), |(value, slope), c| {
+                (value * x + c, slope * x + value)
+            })
+    };
+
+    let mut edges = vec![-bound];
+    edges.extend(
+        solve_polynomial(&derivative)
+            .into_iter()
+            .filter(|x| -bound < *x && *x < bound),
+    );
+    edges.push(bound);
+    let mut roots = Vec::new();
+    for pair in edges.windows(2) {
+        let (mut low, mut high) = (pair[0], pair[1]);
+        let (low_value, _) = evaluate(low);
+        let (high_value, _) = evaluate(high);
+        if low_value == 0_f64 {
+            if roots.last() != Some(&low) {
+                roots.push(low);
+            }
+            continue;
+        }
+        if high_value == 0_f64 {
+            roots.push(high);
+            continue;
+        }
+        if (low_value < 0_f64) == (high_value < 0_f64) {
+            continue;
+        }
+        let mut x = 0.5 * (low + high);
+        for _ in 0..100 {
+            let (value, slope) = evaluate(x);
+            if value == 0_f64 {
+                break;
+            }
+            if (value < 0_f64) == (low_value < 0_f64) {
+                low = x;
+            } else {
+                high = x;
+            }
+            // Newton's step, unless it leaves the bracket (then bisect instead)
+            let newton = x - value / slope;
+            let next = if low < newton && newton < high {
+                newton
+            } else {
+                0.5 * (low + high)
+            };
+            let converged = (next - x).abs() <= 1e-14 * x.abs().max(1_f64);
+            x = next;
+            if converged {
+                break;
+            }
+        }
+        roots.push(x);
+    }
+    roots
 }
 
 /// Simple structure for representing Intervals
@@ -96,15 +211,34 @@ mod test {
     }
 
     #[test]
-    fn test_color_to_rgb() {
-        let rgb = color_to_rgb(&Vec3::new_int(1, 1, 1), 1);
-        assert_eq!(rgb, (255, 255, 255));
-
-        let rgb = color_to_rgb(&Vec3::new_int(0, 0, 0), 1);
-        assert_eq!(rgb, (0, 0, 0));
+    fn test_solve_polynomial() {
+        assert_eq!(solve_quadratic(1_f64, -3_f64, 2_f64), Some((1_f64, 2_f64)));
+        assert_eq!(solve_quadratic(0_f64, 2_f64, -4_f64), Some((2_f64, 2_f64)));
+        assert_eq!(solve_quadratic(1_f64, 0_f64, 1_f64), None);
 
-        let rgb = color_to_rgb(&Vec3::new(0.5, 0.5, 0.5), 1);
-        assert_eq!(rgb, (181, 181, 181));
+        let assert_roots = |coefficients: &[f64], expected: &[f64]| {
+            let roots = solve_polynomial(coefficients);
+            assert_eq!(roots.len(), expected.len(), "{:?}", roots);
+            for (root, expected) in roots.iter().zip(expected) {
+                assert!((root - expected).abs() < 1e-9, "{:?}", roots);
+            }
+        };
+        // (x - 1)(x - 2)(x - 3)(x - 4)
+        assert_roots(
+            &[24_f64, -50_f64, 35_f64, -10_f64, 1_f64],
+            &[1_f64, 2_f64, 3_f64, 4_f64],
+        );
+        // (x^2 + 1)(x - 0.5)(x + 7)
+        assert_roots(
+            &[-3.5_f64, 6.5_f64, -2.5_f64, 6.5_f64, 1_f64],
+            &[-7_f64, 0.5_f64],
+        );
+        // x^3 - x with trailing zero coefficients
+        assert_roots(
+            &[0_f64, -1_f64, 0_f64, 1_f64, 0_f64],
+            &[-1_f64, 0_f64, 1_f64],
+        );
+        assert_roots(&[1_f64, 0_f64, 0_f64, 0_f64, 1_f64], &[]);
     }
 
     #[test]
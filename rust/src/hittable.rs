@@ -1,9 +1,11 @@
 use std::sync::Arc;
 
-use crate::{ray::Ray, Interval, Materials, Vec3};
+use crate::{ray::Ray, Interval, Materials, Sampler, Vec3};
 
 mod bvh;
+mod lights;
 pub use bvh::{HittableWithBBox, AABB, BVH};
+pub use lights::LightList;
 
 pub struct HitRecord {
     /// Point at which the hit occurs
@@ -20,6 +22,10 @@ pub struct HitRecord {
     pub against_normal_unit: Vec3,
     /// If the ray hits from the outside
     pub front_face: bool,
+    /// ID of the object hit, set by [Tagged](crate::Tagged) (0 if the object is not tagged)
+    pub object_id: u32,
+    /// ID of the material hit, set by [Tagged](crate::Tagged) (0 if the material is not tagged)
+    pub material_id: u32,
 }
 impl HitRecord {
     /// Used to create the HitRecord object
@@ -47,6 +53,8 @@ impl HitRecord {
             material,
             against_normal_unit,
             front_face,
+            object_id: 0,
+            material_id: 0,
         }
     }
 }
@@ -56,6 +64,41 @@ pub trait Hittable: Sync + Send {
     fn hit(&self, ray: &Ray, valid_t_interval: Interval) -> Option<HitRecord> {
         None
     }
+    /// Probability density (over solid angle) of [Hittable::random] generating the direction from origin
+    /// Only needs to be implemented by objects that can be importance sampled (i.e. lights)
+    #[allow(unused_variables)]
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        0_f64
+    }
+    /// Generates a random direction from origin towards a point on the object
+    #[allow(unused_variables)]
+    fn random(&self, origin: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        Vec3::new_int(1, 0, 0)
+    }
+    /// Material of the object, if the whole object is made of a single material
+    /// Used to find which objects are emissive (see [LightList])
+    fn material(&self) -> Option<&Materials> {
+        None
+    }
+    /// Surface area of the object, used to weigh the lights by their emitted power
+    fn area(&self) -> f64 {
+        0_f64
+    }
+    /// Same as [Hittable::hit], but participating media (i.e. smoke) are ignored, used by shadow rays
+    /// The light passing through the media is found with [Hittable::transmittance] instead
+    fn hit_surface(&self, ray: &Ray, valid_t_interval: Interval) -> Option<HitRecord> {
+        self.hit(ray, valid_t_interval)
+    }
+    /// Fraction of the light that passes through the participating media along the ray in the interval, surfaces are ignored
+    /// Media can return a random (but unbiased) estimate
+    #[allow(unused_variables)]
+    fn transmittance(&self, ray: &Ray, valid_t_interval: Interval) -> f64 {
+        1_f64
+    }
+    /// If the object is (or contains) participating media, so [Hittable::transmittance] can be skipped for everything else
+    fn contains_media(&self) -> bool {
+        false
+    }
 }
 
 /// A Hittable List (extended with a [bvh::aabb::AABB] BBox)
@@ -101,24 +144,37 @@ impl HittablesList {
 }
 impl Hittable for HittablesList {
     fn hit(&self, _ray: &Ray, valid_t_interval: Interval) -> Option<HitRecord> {
-        // For each hittable in the vec, iter through the hittables and run hit, accumulate the hits and return the nearest hit
-        let (_, result) = self
-            .v
+        closest_hit(self.v.iter(), valid_t_interval, |hittable, interval| {
+            hittable.hit(_ray, interval)
+        })
+    }
+    fn hit_surface(&self, _ray: &Ray, valid_t_interval: Interval) -> Option<HitRecord> {
+        closest_hit(self.v.iter(), valid_t_interval, |hittable, interval| {
+            hittable.hit_surface(_ray, interval)
+        })
+    }
+    fn transmittance(&self, _ray: &Ray, valid_t_interval: Interval) -> f64 {
+        self.v
             .iter()
-            .fold((valid_t_interval.max, None), |acc, hittable| {
-                if let Some(hit_record) = hittable.hit(
-                    _ray,
-                    Interval {
-                        min: valid_t_interval.min,
-                        max: acc.0,
-                    },
-                ) {
-                    (hit_record.t, Some(hit_record))
-                } else {
-                    acc
-                }
-            });
-        result
+            .filter(|hittable| hittable.contains_media())
+            .map(|hittable| hittable.transmittance(_ray, valid_t_interval))
+            .product()
+    }
+    fn contains_media(&self) -> bool {
+        self.v.iter().any(|hittable| hittable.contains_media())
+    }
+    /// Average of the pdf_value of all the hittables, as [HittablesList::random] picks any of them uniformly
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        if self.v.is_empty() {
+            return 0_f64;
+        }
+        let weight = 1_f64 / self.v.len() as f64;
+        self.v.iter().fold(0_f64, |acc, hittable| {
+            acc + weight * hittable.pdf_value(origin, direction)
+        })
+    }
+    fn random(&self, origin: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        self.v[sampler.get_index(self.v.len())].random(origin, sampler)
     }
 }
 impl HittableWithBBox for HittablesList {
@@ -129,22 +185,44 @@ impl HittableWithBBox for HittablesList {
 
 impl Hittable for Vec<Arc<dyn Hittable>> {
     fn hit(&self, _ray: &Ray, valid_t_interval: Interval) -> Option<HitRecord> {
-        // For each hittable in the vec, iter through the hittables and run hit, accumulate the hits and return the nearest hit
-        let (_, result) = self
-            .iter()
-            .fold((valid_t_interval.max, None), |acc, hittable| {
-                if let Some(hit_record) = hittable.hit(
-                    _ray,
-                    Interval {
-                        min: valid_t_interval.min,
-                        max: acc.0,
-                    },
-                ) {
-                    (hit_record.t, Some(hit_record))
-                } else {
-                    acc
-                }
-            });
-        result
+        closest_hit(self.iter(), valid_t_interval, |hittable, interval| {
+            hittable.hit(_ray, interval)
+        })
+    }
+    fn hit_surface(&self, _ray: &Ray, valid_t_interval: Interval) -> Option<HitRecord> {
+        closest_hit(self.iter(), valid_t_interval, |hittable, interval| {
+            hittable.hit_surface(_ray, interval)
+        })
     }
+    fn transmittance(&self, _ray: &Ray, valid_t_interval: Interval) -> f64 {
+        self.iter()
+            .filter(|hittable| hittable.contains_media())
+            .map(|hittable| hittable.transmittance(_ray, valid_t_interval))
+            .product()
+    }
+    fn contains_media(&self) -> bool {
+        self.iter().any(|hittable| hittable.contains_media())
+    }
+}
+
+/// For each hittable, run hit (with the interval shrunk to the closest hit so far), and return the nearest hit
+fn closest_hit<'a, H: Hittable + ?Sized + 'a>(
+    hittables: impl Iterator<Item = &'a Arc<H>>,
+    valid_t_interval: Interval,
+    hit: impl Fn(&H, Interval) -> Option<HitRecord>,
+) -> Option<HitRecord> {
+    let (_, result) = hittables.fold((valid_t_interval.max, None), |acc, hittable| {
+        if let Some(hit_record) = hit(
+            hittable,
+            Interval {
+                min: valid_t_interval.min,
+                max: acc.0,
+            },
+        ) {
+            (hit_record.t, Some(hit_record))
+        } else {
+            acc
+        }
+    });
+    result
 }
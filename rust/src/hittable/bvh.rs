@@ -1,7 +1,6 @@
-use rand::{thread_rng, Rng};
-use std::{cmp::Ordering, sync::Arc};
+use std::sync::Arc;
 
-use crate::{Interval, Ray};
+use crate::{Interval, Ray, Vec3};
 
 use super::{HitRecord, Hittable};
 
@@ -12,125 +11,327 @@ pub trait HittableWithBBox: Hittable {
     fn bbox(&self) -> &AABB;
 }
 
+/// Number of bins along the split axis when evaluating SAH splits
+const SAH_BINS: usize = 12;
+/// Cost of traversing a node, relative to intersecting a single hittable
+const TRAVERSAL_COST: f64 = 0.125;
+/// Leaves with at most this many hittables are kept if splitting them is not cheaper
+const MAX_LEAF_SIZE: usize = 4;
+/// Deepest the tree is allowed to go, so traversal can use a fixed size stack
+const MAX_DEPTH: usize = 48;
+/// Subtrees with more hittables than this are built in parallel
+const PARALLEL_BUILD_THRESHOLD: usize = 4096;
+
 /// Bounding volume hierarchy
+/// Built with the surface area heuristic (SAH), and stored as a flat list of nodes (depth first)
 pub struct BVH {
-    left: Option<Box<BVH>>,
-    right: Option<Box<BVH>>,
+    nodes: Vec<Node>,
+    /// Hittables reordered, so that each leaf refers to a contiguous range
+    hittables: Vec<Arc<dyn HittableWithBBox>>,
+    /// Hittables without bounds (i.e. [Plane](crate::Plane)), which would make every node of the tree infinite
+    /// So they are kept out of the tree, and every ray is checked against them first
+    unbounded: Vec<Arc<dyn HittableWithBBox>>,
+    /// Hittables that contain participating media, which shadow rays go through (There are usually only a few, so they are not in a tree)
+    media: Vec<Arc<dyn HittableWithBBox>>,
+    bbox: AABB,
+}
+
+struct Node {
     bbox: AABB,
-    hittable: Option<Arc<dyn HittableWithBBox>>,
+    kind: NodeKind,
+}
+enum NodeKind {
+    /// Range of hittables in [BVH::hittables]
+    Leaf { start: usize, count: usize },
+    /// The first child is always the next node, so only the second child is stored
+    Interior { second_child: usize, axis: usize },
 }
+
+/// Hittable info needed for the build, so the hittables themselves do not have to be moved around
+struct BuildPrimitive {
+    index: usize,
+    bbox: AABB,
+    centroid: Vec3,
+}
+
 impl BVH {
     pub fn from_hittables_list(hittable_list: Vec<Arc<dyn HittableWithBBox>>) -> Self {
-        Self::new(&hittable_list[..], 0, hittable_list.len())
-    }
-    #[allow(clippy::clone_on_copy)]
-    fn new(hittables: &[Arc<dyn HittableWithBBox>], start: usize, end: usize) -> Self {
-        let mut rng = thread_rng();
-        let axis = rng.gen_range(0_i64..3_i64);
-
-        let hittable_comparer =
-            |a: &Arc<dyn HittableWithBBox>, b: &Arc<dyn HittableWithBBox>| -> Ordering {
-                if a.bbox().axis(axis).min < b.bbox().axis(axis).min {
-                    Ordering::Less
-                } else if a.bbox().axis(axis).min > b.bbox().axis(axis).min {
-                    Ordering::Greater
+        let media = hittable_list
+            .iter()
+            .filter(|hittable| hittable.contains_media())
+            .cloned()
+            .collect();
+        let (bounded, unbounded): (Vec<_>, Vec<_>) = hittable_list
+            .into_iter()
+            .partition(|hittable| hittable.bbox().is_bounded());
+        if bounded.is_empty() {
+            return BVH {
+                nodes: Vec::new(),
+                hittables: Vec::new(),
+                bbox: if unbounded.is_empty() {
+                    AABB::default()
                 } else {
-                    Ordering::Equal
-                }
+                    AABB::UNIVERSE
+                },
+                unbounded,
+                media,
             };
-        let list_size = end - start;
-        if list_size == 0 {
-            BVH {
-                left: None,
-                right: None,
-                hittable: None,
-                bbox: AABB::default(),
-            }
-        } else if list_size == 1 {
-            BVH {
-                left: None,
-                right: None,
-                hittable: Some(hittables[start].clone()),
-                bbox: hittables[start].bbox().clone(),
+        }
+        let mut primitives: Vec<BuildPrimitive> = bounded
+            .iter()
+            .enumerate()
+            .map(|(index, hittable)| BuildPrimitive {
+                index,
+                bbox: *hittable.bbox(),
+                centroid: hittable.bbox().centroid(),
+            })
+            .collect();
+        let nodes = Self::build(&mut primitives, 0, 0);
+        let hittables = primitives
+            .iter()
+            .map(|primitive| bounded[primitive.index].clone())
+            .collect();
+        BVH {
+            bbox: if unbounded.is_empty() {
+                nodes[0].bbox
+            } else {
+                AABB::UNIVERSE
+            },
+            nodes,
+            hittables,
+            unbounded,
+            media,
+        }
+    }
+
+    // Implementation Details:
+    // Builds the subtree for primitives, with node indices relative to the subtree (the root is 0)
+    // offset is where primitives starts in the final hittables list, which the leaves refer to
+    // The primitives are partitioned in place, so both halves can be built in parallel on disjoint slices
+    fn build(primitives: &mut [BuildPrimitive], offset: usize, depth: usize) -> Vec<Node> {
+        let bbox = union_bbox(primitives.iter().map(|primitive| &primitive.bbox));
+        let count = primitives.len();
+        let leaf = || {
+            vec![Node {
+                bbox,
+                kind: NodeKind::Leaf {
+                    start: offset,
+                    count,
+                },
+            }]
+        };
+        if count == 1 || depth >= MAX_DEPTH {
+            return leaf();
+        }
+
+        let centroid_bbox = AABB::from_points(
+            primitives.iter().fold(
+                Vec3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+                |acc, p| acc.retain_min(&p.centroid),
+            ),
+            primitives.iter().fold(
+                Vec3::new(-f64::INFINITY, -f64::INFINITY, -f64::INFINITY),
+                |acc, p| acc.retain_max(&p.centroid),
+            ),
+        );
+        let axis = (0..3)
+            .max_by(|&a, &b| {
+                let size = |axis: usize| centroid_bbox.axis(axis as i64).size();
+                size(a).total_cmp(&size(b))
+            })
+            .unwrap();
+        let axis_interval = *centroid_bbox.axis(axis as i64);
+
+        let mid = if axis_interval.size() <= 0_f64 {
+            // All centroids are at the same point, so no split is better than another
+            if count <= MAX_LEAF_SIZE {
+                return leaf();
             }
-        } else if list_size == 2 {
-            BVH {
-                left: Some(Box::new(BVH {
-                    left: None,
-                    right: None,
-                    hittable: Some(hittables[start].clone()),
-                    bbox: hittables[start].bbox().clone(),
-                })),
-                right: Some(Box::new(BVH {
-                    left: None,
-                    right: None,
-                    hittable: Some(hittables[end - 1].clone()),
-                    bbox: hittables[end - 1].bbox().clone(),
-                })),
-                hittable: None,
-                bbox: AABB::from_aabb(hittables[start].bbox(), hittables[end - 1].bbox()),
+            count / 2
+        } else {
+            match Self::sah_split(primitives, axis, &axis_interval, &bbox) {
+                Some(mid) => mid,
+                None => return leaf(),
             }
+        };
+
+        let (left_primitives, right_primitives) = primitives.split_at_mut(mid);
+        let (left, right) = if count > PARALLEL_BUILD_THRESHOLD {
+            rayon::join(
+                || Self::build(left_primitives, offset, depth + 1),
+                || Self::build(right_primitives, offset + mid, depth + 1),
+            )
         } else {
-            let mut hittables = hittables.to_vec();
-            // Small bug, I used to sort the whole vector
-            // hittables.sort_by(hittable_comparer);
-            // Since the vector is sorted everytime by a random axis
-            // Example with 4
-            // The left side places 0, 1 from 0, 1, 2, 3
-            // Then the right side sorts 2, 3, 0, 1 With the chosen
-            // hittables.sort_by(hittable_comparer);
-            hittables[start..end].sort_by(hittable_comparer);
-            let mid = start + list_size / 2;
-            let left = Self::new(&hittables, start, mid);
-            let right = Self::new(&hittables, mid, end);
-            let bbox = AABB::from_aabb(&left.bbox, &right.bbox);
-
-            BVH {
-                left: Some(Box::new(left)),
-                right: Some(Box::new(right)),
-                hittable: None,
-                bbox,
+            (
+                Self::build(left_primitives, offset, depth + 1),
+                Self::build(right_primitives, offset + mid, depth + 1),
+            )
+        };
+
+        // Concatenate into the depth first layout, shifting the child indices of each subtree
+        let mut nodes = Vec::with_capacity(1 + left.len() + right.len());
+        let second_child = 1 + left.len();
+        nodes.push(Node {
+            bbox,
+            kind: NodeKind::Interior { second_child, axis },
+        });
+        for (subtree, shift) in [(left, 1), (right, second_child)] {
+            nodes.extend(subtree.into_iter().map(|mut node| {
+                if let NodeKind::Interior { second_child, .. } = &mut node.kind {
+                    *second_child += shift;
+                }
+                node
+            }));
+        }
+        nodes
+    }
+
+    /// Bins the centroids along axis and finds the cheapest split according to the SAH
+    /// Partitions primitives and returns the split index, or None if a leaf is cheaper
+    fn sah_split(
+        primitives: &mut [BuildPrimitive],
+        axis: usize,
+        axis_interval: &Interval,
+        bbox: &AABB,
+    ) -> Option<usize> {
+        let bin_index = |centroid: &Vec3| {
+            let relative = (centroid[axis] - axis_interval.min) / axis_interval.size();
+            ((relative * SAH_BINS as f64) as usize).min(SAH_BINS - 1)
+        };
+        let mut bin_counts = [0_usize; SAH_BINS];
+        let mut bin_bboxes: [Option<AABB>; SAH_BINS] = [None; SAH_BINS];
+        for primitive in primitives.iter() {
+            let bin = bin_index(&primitive.centroid);
+            bin_counts[bin] += 1;
+            bin_bboxes[bin] = Some(match &bin_bboxes[bin] {
+                Some(bin_bbox) => AABB::from_aabb(bin_bbox, &primitive.bbox),
+                None => primitive.bbox,
+            });
+        }
+
+        // Sweep from the right, so the cost of each split can then be computed in a single sweep from the left
+        let mut right_area_counts = [(0_f64, 0_usize); SAH_BINS];
+        let mut right_bbox: Option<AABB> = None;
+        let mut right_count = 0;
+        for bin in (1..SAH_BINS).rev() {
+            right_bbox = merge_bbox(right_bbox, bin_bboxes[bin]);
+            right_count += bin_counts[bin];
+            right_area_counts[bin] = (
+                right_bbox.map_or(0_f64, |bbox| bbox.surface_area()),
+                right_count,
+            );
+        }
+        let mut best: Option<(usize, f64)> = None;
+        let mut left_bbox: Option<AABB> = None;
+        let mut left_count = 0;
+        for bin in 0..SAH_BINS - 1 {
+            left_bbox = merge_bbox(left_bbox, bin_bboxes[bin]);
+            left_count += bin_counts[bin];
+            let (right_area, right_count) = right_area_counts[bin + 1];
+            if left_count == 0 || right_count == 0 {
+                continue;
+            }
+            let cost = left_bbox.map_or(0_f64, |bbox| bbox.surface_area()) * left_count as f64
+                + right_area * right_count as f64;
+            if best.is_none_or(|(_, best_cost)| cost < best_cost) {
+                best = Some((bin, cost));
+            }
+        }
+        let (best_bin, best_cost) = best?;
+
+        let count = primitives.len();
+        let split_cost = TRAVERSAL_COST + best_cost / bbox.surface_area().max(f64::EPSILON);
+        if count <= MAX_LEAF_SIZE && split_cost >= count as f64 {
+            return None;
+        }
+
+        // Partition in place, primitives in bins up to best_bin go to the left
+        let mut mid = 0;
+        for i in 0..count {
+            if bin_index(&primitives[i].centroid) <= best_bin {
+                primitives.swap(i, mid);
+                mid += 1;
             }
         }
+        Some(mid)
     }
 }
 impl Hittable for BVH {
     fn hit(&self, _ray: &Ray, valid_t_interval: Interval) -> Option<HitRecord> {
-        // Check if we don't hit the bbox of the BVH (Nicer rust code)
-        self.bbox.hit(_ray, valid_t_interval)?;
-
-        // Deal with base case of edge leaf nodes (Just the hittables)
-        // Exit early to prevent computation
-        if let (None, None) = (&self.left, &self.right) {
-            match &self.hittable {
-                Some(hittable) => return hittable.hit(_ray, valid_t_interval),
-                // Used to be an error, now a default for no ndoes in the BVH
-                // So it should hit nothing
-                None => return None,
-            };
+        self.closest_hit(_ray, valid_t_interval, |hittable, interval| {
+            hittable.hit(_ray, interval)
+        })
+    }
+    fn hit_surface(&self, _ray: &Ray, valid_t_interval: Interval) -> Option<HitRecord> {
+        self.closest_hit(_ray, valid_t_interval, |hittable, interval| {
+            hittable.hit_surface(_ray, interval)
+        })
+    }
+    fn transmittance(&self, _ray: &Ray, valid_t_interval: Interval) -> f64 {
+        self.media
+            .iter()
+            .filter(|medium| medium.bbox().hit(_ray, valid_t_interval).is_some())
+            .map(|medium| medium.transmittance(_ray, valid_t_interval))
+            .product()
+    }
+    fn contains_media(&self) -> bool {
+        !self.media.is_empty()
+    }
+}
+impl BVH {
+    /// Checks the unbounded hittables, then traverses the tree, running hit on the hittables of the leaves the ray reaches
+    // Implementation Details:
+    // Iterative traversal with a stack, visiting the near child first (based on the sign of the ray direction on the split axis)
+    // So closer hits shrink the interval early, and the far child can often be skipped by its bbox
+    fn closest_hit(
+        &self,
+        _ray: &Ray,
+        valid_t_interval: Interval,
+        hit: impl Fn(&dyn HittableWithBBox, Interval) -> Option<HitRecord>,
+    ) -> Option<HitRecord> {
+        let mut valid_t_interval = valid_t_interval;
+        let mut closest_hit = None;
+        for hittable in &self.unbounded {
+            if let Some(hit_record) = hit(hittable.as_ref(), valid_t_interval) {
+                valid_t_interval.max = hit_record.t;
+                closest_hit = Some(hit_record);
+            }
         }
-
-        match (&self.left, &self.right) {
-            // BVH nodes which are not edges
-            (Some(left), Some(right)) => {
-                let mut valid_t_interval = valid_t_interval;
-                if let Some(left_hit) = left.hit(_ray, valid_t_interval) {
-                    valid_t_interval.max = left_hit.t;
-                    if let Some(right_hit) = right.hit(_ray, valid_t_interval) {
-                        Some(right_hit)
-                    } else {
-                        Some(left_hit)
+        if self.nodes.is_empty() {
+            return closest_hit;
+        }
+        let mut stack = [0_usize; MAX_DEPTH + 2];
+        let mut stack_size = 1;
+        while stack_size > 0 {
+            stack_size -= 1;
+            let node_index = stack[stack_size];
+            let node = &self.nodes[node_index];
+            if node.bbox.hit(_ray, valid_t_interval).is_none() {
+                continue;
+            }
+            match node.kind {
+                NodeKind::Leaf { start, count } => {
+                    for hittable in &self.hittables[start..start + count] {
+                        if let Some(hit_record) = hit(hittable.as_ref(), valid_t_interval) {
+                            valid_t_interval.max = hit_record.t;
+                            closest_hit = Some(hit_record);
+                        }
                     }
-                } else {
-                    right.hit(_ray, valid_t_interval)
                 }
-            }
-            // If there is a single Node or we reach (None, None) again
-            // Should never reach here
-            _ => {
-                panic!("Trying to match left or right, but either no left or right or both")
+                NodeKind::Interior { second_child, axis } => {
+                    // Push the far child first, so the near child is popped first
+                    let (near, far) = if _ray.direction[axis] < 0_f64 {
+                        (second_child, node_index + 1)
+                    } else {
+                        (node_index + 1, second_child)
+                    };
+                    stack[stack_size] = far;
+                    stack[stack_size + 1] = near;
+                    stack_size += 2;
+                }
             }
         }
+        closest_hit
     }
 }
 impl HittableWithBBox for BVH {
@@ -139,6 +340,18 @@ impl HittableWithBBox for BVH {
     }
 }
 
+fn merge_bbox(a: Option<AABB>, b: Option<AABB>) -> Option<AABB> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(AABB::from_aabb(&a, &b)),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+fn union_bbox<'a>(mut bboxes: impl Iterator<Item = &'a AABB>) -> AABB {
+    let first = *bboxes.next().unwrap();
+    bboxes.fold(first, |acc, bbox| AABB::from_aabb(&acc, bbox))
+}
+
 #[cfg(test)]
 mod test {
     use crate::{materials::test::TestScatterable, Materials, Vec3};
@@ -161,6 +374,8 @@ mod test {
                     material: Materials::ScatterMaterial(Arc::new(TestScatterable {})),
                     against_normal_unit: -self.v.clone(),
                     front_face: false,
+                    object_id: 0,
+                    material_id: 0,
                 })
             } else {
                 None
@@ -232,6 +447,8 @@ mod test {
                 material: Materials::ScatterMaterial(Arc::new(TestScatterable {})),
                 against_normal_unit: Vec3::new(-0.5, -0.5, -0.5),
                 front_face: false,
+                object_id: 0,
+                material_id: 0,
             },
         );
         check_bvh_hit_result(
@@ -250,6 +467,8 @@ mod test {
                 material: Materials::ScatterMaterial(Arc::new(TestScatterable {})),
                 against_normal_unit: Vec3::new(-0.5, -0.5, -0.5),
                 front_face: false,
+                object_id: 0,
+                material_id: 0,
             },
         );
         check_bvh_hit_result(
@@ -268,6 +487,8 @@ mod test {
                 material: Materials::ScatterMaterial(Arc::new(TestScatterable {})),
                 against_normal_unit: Vec3::new(-0.5, -0.5, -0.5),
                 front_face: false,
+                object_id: 0,
+                material_id: 0,
             },
         );
     }
@@ -309,4 +530,116 @@ mod test {
             missed_test_hittable.clone(),
         ]);
     }
+
+    #[test]
+    fn test_bvh_matches_brute_force() {
+        // Closest hit should be the same as checking every sphere
+        use crate::{HittablesList, IndependentSampler, Sphere};
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut sampler = IndependentSampler::new(0);
+        let material = Materials::ScatterMaterial(Arc::new(TestScatterable {}));
+        let spheres: Vec<Arc<dyn HittableWithBBox>> = (0..500)
+            .map(|_| {
+                Arc::new(Sphere::new(
+                    Vec3::random(-10.0, 10.0, &mut rng),
+                    rng.gen_range(0.1..1.0),
+                    material.clone(),
+                )) as Arc<dyn HittableWithBBox>
+            })
+            .collect();
+        let bvh = BVH::from_hittables_list(spheres.clone());
+        let mut hittables_list = HittablesList::new();
+        hittables_list.append(&mut spheres.clone());
+
+        // Every hittable is in exactly one leaf
+        let leaf_count: usize = bvh
+            .nodes
+            .iter()
+            .map(|node| match node.kind {
+                NodeKind::Leaf { count, .. } => count,
+                NodeKind::Interior { .. } => 0,
+            })
+            .sum();
+        assert_eq!(leaf_count, spheres.len());
+
+        let interval = Interval {
+            min: 0.001,
+            max: f64::INFINITY,
+        };
+        for _ in 0..200 {
+            let ray = Ray {
+                origin: Vec3::random(-15.0, 15.0, &mut rng),
+                direction: Vec3::random_unit_vector(&mut sampler),
+            };
+            let bvh_hit = bvh.hit(&ray, interval);
+            let list_hit = hittables_list.hit(&ray, interval);
+            assert_eq!(bvh_hit.is_some(), list_hit.is_some());
+            if let (Some(bvh_hit), Some(list_hit)) = (bvh_hit, list_hit) {
+                assert_eq!(bvh_hit.t, list_hit.t);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bvh_unbounded_planes() {
+        // Planes are kept out of the tree, but still hit
+        use crate::{Plane, Sphere, Transform, Transformed};
+
+        let material = Materials::ScatterMaterial(Arc::new(TestScatterable {}));
+        let floor: Arc<dyn HittableWithBBox> = Arc::new(Plane::new(
+            Vec3::new_int(0, 0, 0),
+            Vec3::new_int(0, 1, 0),
+            material.clone(),
+        ));
+        let wall: Arc<dyn HittableWithBBox> = Arc::new(Transformed::new(
+            Arc::new(Plane::new(
+                Vec3::new_int(0, 0, 0),
+                Vec3::new_int(0, 0, 1),
+                material.clone(),
+            )),
+            Transform::translate(Vec3::new_int(0, 0, -10)),
+        ));
+        assert!(!wall.bbox().is_bounded());
+        let sphere: Arc<dyn HittableWithBBox> =
+            Arc::new(Sphere::new(Vec3::new_int(0, 1, 0), 1_f64, material.clone()));
+        let bvh = BVH::from_hittables_list(vec![floor.clone(), sphere.clone(), wall.clone()]);
+        assert_eq!(bvh.hittables.len(), 1);
+        assert!(!bvh.bbox().is_bounded());
+
+        let interval = Interval {
+            min: 0.001,
+            max: f64::INFINITY,
+        };
+        let hit_t = |bvh: &BVH, origin: Vec3, direction: Vec3| {
+            bvh.hit(&Ray { origin, direction }, interval)
+                .map(|hit_record| hit_record.t)
+        };
+        // The sphere is in front of the floor, the floor in front of the wall
+        assert_eq!(
+            hit_t(&bvh, Vec3::new_int(0, 5, 0), Vec3::new_int(0, -1, 0)),
+            Some(3_f64)
+        );
+        assert_eq!(
+            hit_t(&bvh, Vec3::new_int(5, 5, 0), Vec3::new_int(0, -1, 0)),
+            Some(5_f64)
+        );
+        assert_eq!(
+            hit_t(&bvh, Vec3::new_int(5, 5, 0), Vec3::new_int(0, 0, -1)),
+            Some(10_f64)
+        );
+        assert_eq!(
+            hit_t(&bvh, Vec3::new_int(5, 5, 0), Vec3::new_int(0, 1, 1)),
+            None
+        );
+
+        // Only planes, without a tree
+        let bvh = BVH::from_hittables_list(vec![floor]);
+        assert!(bvh.nodes.is_empty());
+        assert_eq!(
+            hit_t(&bvh, Vec3::new_int(5, 5, 0), Vec3::new_int(0, -1, 0)),
+            Some(5_f64)
+        );
+    }
 }
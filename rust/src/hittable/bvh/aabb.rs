@@ -11,6 +11,21 @@ pub struct AABB {
     pub z: Interval,
 }
 impl AABB {
+    /// AABB covering all of space, for objects without bounds (i.e. [Plane](crate::Plane))
+    pub const UNIVERSE: AABB = AABB {
+        x: Interval {
+            min: f64::NEG_INFINITY,
+            max: f64::INFINITY,
+        },
+        y: Interval {
+            min: f64::NEG_INFINITY,
+            max: f64::INFINITY,
+        },
+        z: Interval {
+            min: f64::NEG_INFINITY,
+            max: f64::INFINITY,
+        },
+    };
     /// Create the AABB given 2 points, with each axis covering from p1 to p2
     pub fn from_points(p1: Vec3, p2: Vec3) -> AABB {
         AABB {
@@ -44,6 +59,25 @@ impl AABB {
             z: self.z.translate(offset.z()),
         }
     }
+    /// Surface area of the AABB, used by the SAH when building the [BVH](crate::BVH)
+    pub fn surface_area(&self) -> f64 {
+        let (x, y, z) = (self.x.size(), self.y.size(), self.z.size());
+        2_f64 * (x * y + y * z + z * x)
+    }
+    /// Checks if every bound is finite, the [BVH](crate::BVH) keeps unbounded objects out of its tree
+    pub fn is_bounded(&self) -> bool {
+        [self.x, self.y, self.z]
+            .iter()
+            .all(|interval| interval.min.is_finite() && interval.max.is_finite())
+    }
+    /// Center point of the AABB
+    pub fn centroid(&self) -> Vec3 {
+        Vec3::new(
+            (self.x.min + self.x.max) / 2_f64,
+            (self.y.min + self.y.max) / 2_f64,
+            (self.z.min + self.z.max) / 2_f64,
+        )
+    }
     /// Get a reference to the intervals in the AABB based on index
     // TODO: Find a better way to deal with people using non-sensical indexes other than 0-2
     // Idea was to use an enum, but having to deal with errors is meh
@@ -115,7 +149,6 @@ impl Display for AABB {
 #[cfg(test)]
 mod test {
     use super::*;
-    use core::f64::INFINITY;
 
     #[test]
     fn test_aabb_default() {
@@ -129,6 +162,16 @@ mod test {
         assert_eq!(bbox.z.max, 0_f64);
     }
 
+    #[test]
+    fn test_aabb_surface_area() {
+        let aabb = AABB::from_points(Vec3::new_int(1, 1, 1), Vec3::new_int(2, 3, 4));
+        assert_eq!(aabb.surface_area(), 22.0);
+        assert_eq!(aabb.centroid(), Vec3::new(1.5, 2.0, 2.5));
+        assert!(aabb.is_bounded());
+        assert!(!AABB::UNIVERSE.is_bounded());
+        assert!(!AABB::from_aabb(&aabb, &AABB::UNIVERSE).is_bounded());
+    }
+
     #[test]
     fn test_aabb_hit() {
         let aabb = AABB::from_points(Vec3::new_int(1, 1, 1), Vec3::new_int(3, 3, 3));
@@ -139,7 +182,7 @@ mod test {
             },
             Interval {
                 min: 0.001,
-                max: INFINITY,
+                max: f64::INFINITY,
             },
         );
 
@@ -151,7 +194,7 @@ mod test {
             },
             Interval {
                 min: 0.001,
-                max: INFINITY,
+                max: f64::INFINITY,
             },
         );
         assert!(hit.is_none());
@@ -0,0 +1,172 @@
+use std::sync::Arc;
+
+use crate::{Hittable, HittableWithBBox, Materials, Sampler, Vec3};
+
+/// List of the emissive objects in the world, used for next event estimation
+/// Sampling picks a light in proportion to its emitted power (luminance * area)
+///
+/// The lights should also be added into the world, the [LightList] is only used to sample directions towards them
+#[derive(Default)]
+pub struct LightList {
+    lights: Vec<Arc<dyn HittableWithBBox>>,
+    /// Probability of picking each light
+    probabilities: Vec<f64>,
+    /// Cumulative distribution of probabilities, used to pick a light
+    cdf: Vec<f64>,
+    /// Sum of the power of all the lights, before normalizing them into probabilities
+    total_power: f64,
+    powers: Vec<f64>,
+}
+impl LightList {
+    /// Create an empty LightList
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Creates a LightList from all the emissive objects ([Materials::LightMaterial]) found in hittables
+    /// Non emissive objects are skipped, emissive objects that can not be sampled are skipped with a warning (Refer to [LightList::add])
+    pub fn from_hittables(hittables: &[Arc<dyn HittableWithBBox>]) -> Self {
+        let mut light_list = Self::new();
+        for hittable in hittables {
+            if Self::power(hittable.as_ref()).is_some() {
+                light_list.add(hittable.clone());
+            }
+        }
+        light_list
+    }
+
+    /// Adds a new light into the list
+    /// Panics if the light is not made of a [Materials::LightMaterial]
+    /// A light without an [area](Hittable::area) can not be sampled, so it is skipped with a warning (It still lights the scene when rays hit it, only noisier)
+    /// A light that emits nothing is skipped, as there is nothing to sample
+    pub fn add(&mut self, light: Arc<dyn HittableWithBBox>) {
+        let power = match Self::power(light.as_ref()) {
+            Some(power) => power,
+            None => panic!("Light added to LightList should have a LightMaterial"),
+        };
+        if light.area() <= 0_f64 {
+            eprintln!("Warning: an emissive object has no area (i.e. a cylinder, cone, torus, capsule, plane or a list of objects), so it is not sampled as a light and what it lights will be noisy");
+            return;
+        }
+        if power <= 0_f64 {
+            return;
+        }
+        self.lights.push(light);
+        self.powers.push(power);
+        self.total_power += power;
+
+        // Recompute the distribution, as every probability changes with the total power
+        self.probabilities = self
+            .powers
+            .iter()
+            .map(|power| power / self.total_power)
+            .collect();
+        self.cdf = self
+            .probabilities
+            .iter()
+            .scan(0_f64, |acc, probability| {
+                *acc += probability;
+                Some(*acc)
+            })
+            .collect();
+    }
+    /// Method to get the number of lights
+    pub fn len(&self) -> usize {
+        self.lights.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+
+    /// Emitted power of an object (luminance * area), if it is an emissive object
+    fn power(hittable: &dyn Hittable) -> Option<f64> {
+        match hittable.material() {
+            Some(Materials::LightMaterial(light_material)) => {
+                Some(light_material.average_emission().luminance() * hittable.area())
+            }
+            _ => None,
+        }
+    }
+    /// Picks a light according to the power distribution
+    /// Returns the index of the light
+    fn sample_index(&self, sampler: &mut dyn Sampler) -> usize {
+        let r = sampler.get_1d();
+        // Float rounding can leave the last cdf value slightly under 1
+        self.cdf
+            .partition_point(|cumulative| *cumulative <= r)
+            .min(self.lights.len() - 1)
+    }
+}
+impl Hittable for LightList {
+    /// Sum of the pdf of each light, weighted by the probability of picking it
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        self.lights
+            .iter()
+            .zip(&self.probabilities)
+            .fold(0_f64, |acc, (light, probability)| {
+                acc + probability * light.pdf_value(origin, direction)
+            })
+    }
+    fn random(&self, origin: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        self.lights[self.sample_index(sampler)].random(origin, sampler)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{materials::test::TestScatterable, Cylinder, Diffuse, Quad};
+
+    fn quad(size: i64, material: Materials) -> Arc<dyn HittableWithBBox> {
+        Arc::new(Quad::new(
+            Vec3::new_int(0, 0, 0),
+            Vec3::new_int(size, 0, 0),
+            Vec3::new_int(0, 0, size),
+            material,
+        ))
+    }
+
+    #[test]
+    fn test_light_list_from_hittables() {
+        let light = Materials::LightMaterial(Arc::new(Diffuse { power: 1.0 }));
+        let not_light = Materials::ScatterMaterial(Arc::new(TestScatterable {}));
+        let light_list = LightList::from_hittables(&[
+            quad(1, light.clone()),
+            quad(1, not_light),
+            quad(3, light),
+        ]);
+        assert_eq!(light_list.len(), 2);
+        // The larger light has 9 times the area, so 9 times the power
+        assert!((light_list.probabilities[0] - 0.1).abs() < 1e-12);
+        assert!((light_list.probabilities[1] - 0.9).abs() < 1e-12);
+        assert!((light_list.cdf[1] - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_light_list_skips_lights_without_area() {
+        let light = Materials::LightMaterial(Arc::new(Diffuse { power: 1.0 }));
+        let cylinder: Arc<dyn HittableWithBBox> = Arc::new(Cylinder::new(
+            Vec3::new_int(0, 0, 0),
+            1_f64,
+            1_f64,
+            true,
+            light.clone(),
+        ));
+        let light_list = LightList::from_hittables(&[cylinder.clone(), quad(1, light)]);
+        assert_eq!(light_list.len(), 1);
+        // Warns instead of panicking, the same as from_hittables
+        let mut light_list = LightList::new();
+        light_list.add(cylinder);
+        assert!(light_list.is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_light_list_add_not_light() {
+        let mut light_list = LightList::new();
+        light_list.add(quad(
+            1,
+            Materials::ScatterMaterial(Arc::new(TestScatterable {})),
+        ));
+    }
+}
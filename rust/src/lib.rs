@@ -1,19 +1,55 @@
+pub mod background;
 pub mod camera;
 pub mod cli;
+pub mod denoise;
 pub mod helper;
 pub mod hittable;
+pub mod lights;
 pub mod materials;
+pub mod noise;
 pub mod objects;
+pub mod pdf;
 pub mod ray;
+pub mod sampler;
+pub mod scene;
 pub mod textures;
+pub mod tonemap;
+pub mod transform;
 pub mod vec3;
+pub mod volume;
 
-pub use camera::{Camera, CameraParams};
-pub use cli::Cli;
-pub use helper::{color_to_rgb, rgb_to_color, Interval};
-pub use hittable::{HitRecord, Hittable, HittableWithBBox, HittablesList, AABB, BVH};
-pub use materials::{Dielectric, Diffuse, Lambertain, Materials, Metal, Scattered};
-pub use objects::{construct_planar_quad_box, Disk, Quad, Rotation, Sphere, Translation, Triangle};
+pub use background::{Background, EnvironmentMap, GradientBackground, SolidBackground};
+pub use camera::{
+    AdaptiveSampling, Camera, CameraParams, Checkpointing, RenderOutput, SampleCounts, AOV,
+};
+pub use cli::{Cli, SceneCli, SceneCommand};
+pub use denoise::Denoiser;
+pub use helper::{framebuffer_to_rgbimage, rgb_to_color, Interval};
+pub use hittable::{HitRecord, Hittable, HittableWithBBox, HittablesList, LightList, AABB, BVH};
+pub use lights::{
+    DirectionalLight, IESProfile, Light, LightSample, OrientedProfile, PointLight, SpotLight,
+};
+pub use materials::{
+    Conductor, Dielectric, Diffuse, Emissive, HenyeyGreenstein, Isotropic, Lambertain, Materials,
+    Metal, Parameter, PrincipledBSDF, RoughDielectric, Scattered, GGX,
+};
+pub use noise::{Perlin, Worley, WorleySample};
+pub use objects::{
+    construct_planar_quad_box, Capsule, Cone, ConstantMedium, Cuboid, Cylinder, Disk,
+    HeterogeneousMedium, MediumParams, MeshData, Plane, Quad, Rotation, Sphere, Tagged, Torus,
+    Transformed, Translation, Triangle, TriangleMesh,
+};
+pub use pdf::{CosinePDF, HenyeyGreensteinPDF, HittablePDF, MixturePDF, SpherePDF, ONB, PDF};
 pub use ray::Ray;
-pub use textures::{CheckeredTexture, ColorTexture, Image, SolidColor, SpatialCheckeredTexture};
+pub use sampler::{
+    HaltonSampler, IndependentSampler, Sampler, SamplerType, SobolSampler, StratifiedSampler,
+};
+pub use scene::{Scene, SceneError};
+pub use textures::{
+    CheckeredTexture, ColorRamp, ColorRampTexture, ColorTexture, Image, MarbleTexture,
+    NoiseTexture, SolidColor, SpatialCheckeredTexture, TextureSpace, VoronoiTexture, WoodTexture,
+};
+pub use tonemap::{srgb_transfer, ToneMapOperator, ToneMapping};
+pub use transform::{Mat4, Quaternion, Transform};
 pub use vec3::{Vec3, Vec3Axis};
+pub use volume::{DensityField, NoiseDensity, VoxelGrid};
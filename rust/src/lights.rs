@@ -0,0 +1,203 @@
+use std::{f64::consts::PI, fmt::Debug};
+
+use crate::{helper::from_fdegree_to_fradian, Sampler, Vec3, ONB};
+
+mod ies;
+pub use ies::{IESProfile, OrientedProfile};
+
+/// Public Trait for lights that are not part of the world (No geometry), so rays can never hit them by chance
+/// Instead the [Camera](crate::Camera) sends a shadow ray towards each of them at every hit, refer to [CameraParams](crate::CameraParams)
+pub trait Light: Sync + Send + Debug {
+    /// Samples the light arriving at p, None when p gets no light from it (i.e. outside the cone of a [SpotLight])
+    fn sample(&self, p: &Vec3, sampler: &mut dyn Sampler) -> Option<LightSample>;
+}
+
+/// Light arriving at a point from a [Light], if nothing is in the way
+#[derive(Debug, Clone, PartialEq)]
+pub struct LightSample {
+    /// Unit vector from the point towards the light
+    pub direction: Vec3,
+    /// Distance to the light, infinite for a [DirectionalLight]
+    pub distance: f64,
+    /// Irradiance on a surface facing the light (Already divided by the squared distance)
+    pub irradiance: Vec3,
+}
+
+/// Light shining equally in every direction from a single point, with a sharp shadow
+#[derive(Debug, Clone)]
+pub struct PointLight {
+    pub position: Vec3,
+    /// Power per solid angle (Falls off with the inverse square of the distance)
+    pub intensity: Vec3,
+    /// Shapes the light like a real fixture, the intensity is then the intensity of the brightest direction
+    pub profile: Option<OrientedProfile>,
+}
+impl Light for PointLight {
+    fn sample(&self, p: &Vec3, _: &mut dyn Sampler) -> Option<LightSample> {
+        to_point(p, &self.position, self.intensity.clone(), &self.profile)
+    }
+}
+
+/// Point light that only shines in a cone around its direction, fading out towards the edge of the cone
+#[derive(Debug, Clone)]
+pub struct SpotLight {
+    pub position: Vec3,
+    /// Direction the light shines in (Not normalized)
+    pub direction: Vec3,
+    /// Power per solid angle in the middle of the cone
+    pub intensity: Vec3,
+    /// Angle (in degrees) from the direction to the edge of the cone
+    pub cone_angle: f64,
+    /// Angle (in degrees) at the edge of the cone over which the light fades out, 0 for a hard edge
+    pub falloff: f64,
+    /// Shapes the light like a real fixture (Within the cone), the intensity is then the intensity of the brightest direction
+    pub profile: Option<OrientedProfile>,
+}
+impl Light for SpotLight {
+    // Implementation Details:
+    // The fade is a smoothstep on the cosine of the angle, from the edge of the cone to falloff degrees inside it
+    fn sample(&self, p: &Vec3, _: &mut dyn Sampler) -> Option<LightSample> {
+        let to_p = (p.clone() - self.position.clone()).unit_vector();
+        let cos_theta = Vec3::dot(&to_p, &self.direction.unit_vector());
+        let cos_edge = from_fdegree_to_fradian(self.cone_angle).cos();
+        let cos_inner = from_fdegree_to_fradian((self.cone_angle - self.falloff).max(0_f64)).cos();
+        if cos_theta <= cos_edge {
+            return None;
+        }
+        let fade = if cos_theta >= cos_inner {
+            1_f64
+        } else {
+            let t = (cos_theta - cos_edge) / (cos_inner - cos_edge);
+            t * t * (3_f64 - 2_f64 * t)
+        };
+        to_point(
+            p,
+            &self.position,
+            self.intensity.clone() * fade,
+            &self.profile,
+        )
+    }
+}
+
+/// Light from infinitely far away in a single direction (i.e. the sun), which lights every point the same
+/// A sun with an angular radius is a small disk in the sky instead of a single direction, which softens the shadows
+#[derive(Debug, Clone)]
+pub struct DirectionalLight {
+    /// Direction the light travels in (Not normalized), (0, -1, 0) shines straight down
+    pub direction: Vec3,
+    /// Irradiance on a surface facing the light
+    pub irradiance: Vec3,
+    /// Angle (in degrees) from the center of the sun to its edge, 0 for sharp shadows (The real sun is about 0.27)
+    pub angular_radius: f64,
+}
+impl Light for DirectionalLight {
+    // Implementation Details:
+    // Samples a direction uniformly over the cone of the disk, every direction carries the same irradiance
+    // The estimate irradiance / solid angle (radiance) * BSDF * cos / (1 / solid angle) is then just irradiance * BSDF * cos
+    fn sample(&self, _: &Vec3, sampler: &mut dyn Sampler) -> Option<LightSample> {
+        let towards_light = -self.direction.unit_vector();
+        let direction = if self.angular_radius > 0_f64 {
+            let cos_max = from_fdegree_to_fradian(self.angular_radius).cos();
+            let (u1, u2) = sampler.get_2d();
+            let cos_theta = 1_f64 - u1 * (1_f64 - cos_max);
+            let sin_theta = (1_f64 - cos_theta * cos_theta).max(0_f64).sqrt();
+            let phi = 2_f64 * PI * u2;
+            ONB::new(&towards_light).local(&Vec3::new(
+                sin_theta * phi.cos(),
+                sin_theta * phi.sin(),
+                cos_theta,
+            ))
+        } else {
+            towards_light
+        };
+        Some(LightSample {
+            direction,
+            distance: f64::INFINITY,
+            irradiance: self.irradiance.clone(),
+        })
+    }
+}
+
+/// Light arriving at p from a point at position with the intensity (Inverse square falloff), shaped by the profile
+/// None where the profile is dark, so no shadow ray is wasted on it
+fn to_point(
+    p: &Vec3,
+    position: &Vec3,
+    intensity: Vec3,
+    profile: &Option<OrientedProfile>,
+) -> Option<LightSample> {
+    let to_light = position.clone() - p.clone();
+    let distance_squared = to_light.length_squared();
+    if distance_squared <= 0_f64 {
+        return None;
+    }
+    let distance = distance_squared.sqrt();
+    let direction = to_light / distance;
+    let intensity = match profile {
+        Some(profile) => {
+            let value = profile.value(&(-direction.clone()));
+            if value <= 0_f64 {
+                return None;
+            }
+            intensity * value
+        }
+        None => intensity,
+    };
+    Some(LightSample {
+        direction,
+        distance,
+        irradiance: intensity / distance_squared,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::IndependentSampler;
+
+    #[test]
+    fn test_light_samples() {
+        let mut sampler = IndependentSampler::new(0);
+        let point = PointLight {
+            position: Vec3::new_int(0, 4, 0),
+            intensity: Vec3::new_int(16, 16, 16),
+            profile: None,
+        };
+        let sample = point.sample(&Vec3::new_int(0, 2, 0), &mut sampler).unwrap();
+        assert_eq!(sample.direction, Vec3::new_int(0, 1, 0));
+        assert_eq!(sample.distance, 2_f64);
+        assert_eq!(sample.irradiance, Vec3::new_int(4, 4, 4));
+
+        let spot = SpotLight {
+            position: Vec3::new_int(0, 4, 0),
+            direction: Vec3::new_int(0, -2, 0),
+            intensity: Vec3::new_int(16, 16, 16),
+            cone_angle: 30_f64,
+            falloff: 10_f64,
+            profile: None,
+        };
+        let irradiance = |x: f64, sampler: &mut IndependentSampler| {
+            spot.sample(&Vec3::new(x, 0_f64, 0_f64), sampler)
+                .map(|sample| sample.irradiance.x() * (16_f64 + x * x))
+        };
+        // Full intensity inside 20 degrees, fading out until 30 degrees
+        assert_eq!(irradiance(0_f64, &mut sampler), Some(16_f64));
+        assert_eq!(irradiance(1_f64, &mut sampler), Some(16_f64));
+        let faded = irradiance(4_f64 * 25_f64.to_radians().tan(), &mut sampler).unwrap();
+        assert!(faded > 0_f64 && faded < 16_f64);
+        assert_eq!(irradiance(3_f64, &mut sampler), None);
+
+        let sun = DirectionalLight {
+            direction: Vec3::new_int(0, -1, 0),
+            irradiance: Vec3::new_int(3, 3, 3),
+            angular_radius: 5_f64,
+        };
+        let cos_max = 5_f64.to_radians().cos();
+        for _ in 0..100 {
+            let sample = sun.sample(&Vec3::new_int(7, 0, -3), &mut sampler).unwrap();
+            assert!(sample.direction.y() >= cos_max - 1e-9);
+            assert!((sample.direction.length() - 1_f64).abs() < 1e-9);
+            assert_eq!(sample.distance, f64::INFINITY);
+        }
+    }
+}
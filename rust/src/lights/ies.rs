@@ -0,0 +1,359 @@
+use std::{f64::consts::PI, fs, io, path::Path, sync::Arc};
+
+use crate::{Vec3, ONB};
+
+/// Angular distribution of the light of a real fixture, from an IES (LM-63) photometric file
+/// Only type C photometry is supported (Which is what almost every architectural fixture uses):
+/// the vertical angle goes from the nadir (0, straight out of the fixture) to the zenith (180),
+/// and the horizontal angle goes around the nadir
+///
+/// Directions are given in world space along with the nadir, and horizontal angle 0 is along the u axis of [ONB::new] of the nadir
+/// (-x for a fixture pointing straight down)
+#[derive(Debug, Clone, PartialEq)]
+pub struct IESProfile {
+    /// In degrees, increasing
+    vertical_angles: Vec<f64>,
+    /// In degrees, increasing, a single angle for rotationally symmetric profiles
+    /// Ending at 90 (Symmetric in each quadrant) or 180 (Symmetric around the 0-180 plane) mirrors the rest
+    horizontal_angles: Vec<f64>,
+    /// Intensity in candela for each horizontal angle, then each vertical angle
+    candela: Vec<f64>,
+    max_candela: f64,
+    hemisphere_average: f64,
+}
+impl IESProfile {
+    /// Loads an `.ies` file (Not UTF-8 characters in the keywords are replaced)
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        Self::parse(&String::from_utf8_lossy(&bytes))
+    }
+    /// Parses the content of an `.ies` file, of any version of LM-63 (1986, 1991, 1995 or 2002)
+    // Implementation Details:
+    // Everything up to the `TILT=` line is the version and the keywords, which are skipped
+    // After it everything is numbers separated by whitespace or commas, without any care for the lines:
+    // the tilt data (Only with TILT=INCLUDE), then 10 numbers (lamps, lumens per lamp, candela multiplier, vertical angle count, horizontal angle count,
+    // photometric type, units, width, length, height), then 3 more (ballast factor, future use, input watts), then the angles and the candela values
+    pub fn parse(source: &str) -> io::Result<Self> {
+        let mut lines = source.lines();
+        let tilt = loop {
+            match lines.next() {
+                Some(line) if line.trim_start().starts_with("TILT=") => {
+                    break line.trim()["TILT=".len()..].trim().to_string();
+                }
+                Some(_) => continue,
+                None => return Err(invalid_data("missing the `TILT=` line".to_string())),
+            }
+        };
+        let mut numbers = Numbers {
+            tokens: lines
+                .flat_map(|line| line.split(|c: char| c.is_whitespace() || c == ','))
+                .filter(|token| !token.is_empty())
+                .collect::<Vec<_>>()
+                .into_iter(),
+        };
+
+        match tilt.as_str() {
+            "NONE" => {}
+            // The lamp to luminaire geometry, then the angles and the multipliers (Only used for lamps mounted at an angle)
+            "INCLUDE" => {
+                numbers.next()?;
+                let pairs = numbers.count("number of tilt angles")?;
+                for _ in 0..2 * pairs {
+                    numbers.next()?;
+                }
+            }
+            other => {
+                return Err(invalid_data(format!(
+                    "tilt data from another file (`TILT={}`) is not supported",
+                    other
+                )))
+            }
+        }
+
+        let _lamps = numbers.next()?;
+        let _lumens_per_lamp = numbers.next()?;
+        let multiplier = numbers.next()?;
+        let vertical_count = numbers.count("number of vertical angles")?;
+        let horizontal_count = numbers.count("number of horizontal angles")?;
+        let photometric_type = numbers.next()?;
+        if photometric_type != 1_f64 {
+            return Err(invalid_data(format!(
+                "only type C photometry is supported, found type {}",
+                photometric_type
+            )));
+        }
+        // Units and the size of the luminaire
+        for _ in 0..4 {
+            numbers.next()?;
+        }
+        let ballast_factor = numbers.next()?;
+        let _future_use = numbers.next()?;
+        let _input_watts = numbers.next()?;
+
+        let vertical_angles = numbers.angles(vertical_count, "vertical")?;
+        let horizontal_angles = numbers.angles(horizontal_count, "horizontal")?;
+        let candela = (0..vertical_count * horizontal_count)
+            .map(|_| {
+                numbers
+                    .next()
+                    .map(|value| (value * multiplier * ballast_factor).max(0_f64))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        Ok(Self::new(vertical_angles, horizontal_angles, candela))
+    }
+    fn new(vertical_angles: Vec<f64>, horizontal_angles: Vec<f64>, candela: Vec<f64>) -> Self {
+        let max_candela = candela.iter().fold(0_f64, |max, value| max.max(*value));
+        let mut profile = Self {
+            vertical_angles,
+            horizontal_angles,
+            candela,
+            max_candela,
+            hemisphere_average: 0_f64,
+        };
+        profile.hemisphere_average = profile.integrate_hemisphere();
+        profile
+    }
+    pub fn vertical_angles(&self) -> &[f64] {
+        &self.vertical_angles
+    }
+    pub fn horizontal_angles(&self) -> &[f64] {
+        &self.horizontal_angles
+    }
+    /// Brightest intensity of the profile, in candela
+    pub fn max_candela(&self) -> f64 {
+        self.max_candela
+    }
+    /// Average of [IESProfile::value] over the hemisphere around the nadir, weighted by the cosine
+    /// It is the fraction of the light an area emitter still gives off with the profile
+    pub fn hemisphere_average(&self) -> f64 {
+        self.hemisphere_average
+    }
+    /// Intensity in candela at the angles (In degrees), bilinearly interpolated, and 0 outside of the vertical angles
+    pub fn candela(&self, vertical: f64, horizontal: f64) -> f64 {
+        let first = self.horizontal_angles[0];
+        let last = self.horizontal_angles[self.horizontal_angles.len() - 1];
+        let horizontal = horizontal.rem_euclid(360_f64);
+        // Folds the angle into the part that is in the file
+        let horizontal = if self.horizontal_angles.len() == 1 {
+            first
+        } else if last - first <= 90_f64 {
+            let half = if horizontal > 180_f64 {
+                360_f64 - horizontal
+            } else {
+                horizontal
+            };
+            if half > 90_f64 {
+                180_f64 - half
+            } else {
+                half
+            }
+        } else if last - first <= 180_f64 && horizontal > 180_f64 {
+            360_f64 - horizontal
+        } else {
+            horizontal
+        };
+        let (h0, h1, ht) = match segment(&self.horizontal_angles, horizontal) {
+            Some(segment) => segment,
+            // Past the last angle of a full profile, wraps around to the first one
+            None => {
+                let span = first + 360_f64 - last;
+                let t = if span > 0_f64 {
+                    ((horizontal - last).rem_euclid(360_f64) / span).clamp(0_f64, 1_f64)
+                } else {
+                    0_f64
+                };
+                (self.horizontal_angles.len() - 1, 0, t)
+            }
+        };
+        let at_horizontal = |h: usize| match segment(&self.vertical_angles, vertical) {
+            Some((v0, v1, vt)) => {
+                let row = &self.candela[h * self.vertical_angles.len()..];
+                (1_f64 - vt) * row[v0] + vt * row[v1]
+            }
+            None => 0_f64,
+        };
+        (1_f64 - ht) * at_horizontal(h0) + ht * at_horizontal(h1)
+    }
+    /// Intensity in [0, 1] (Relative to the brightest direction) towards direction, for a fixture with its nadir pointing at nadir
+    pub fn value(&self, nadir: &Vec3, direction: &Vec3) -> f64 {
+        if self.max_candela <= 0_f64 {
+            return 0_f64;
+        }
+        let uvw = ONB::new(nadir);
+        let direction = direction.unit_vector();
+        let vertical = Vec3::dot(&direction, uvw.w()).clamp(-1_f64, 1_f64).acos();
+        let horizontal = Vec3::dot(&direction, uvw.v()).atan2(Vec3::dot(&direction, uvw.u()));
+        self.candela(vertical.to_degrees(), horizontal.to_degrees()) / self.max_candela
+    }
+    // Implementation Details:
+    // Midpoint rule over a grid of the angles, (1 / pi) * integral of value * cos * sin over the vertical and horizontal angles
+    fn integrate_hemisphere(&self) -> f64 {
+        if self.max_candela <= 0_f64 {
+            return 0_f64;
+        }
+        let (vertical_steps, horizontal_steps) = (90, 72);
+        let d_vertical = 0.5 * PI / vertical_steps as f64;
+        let d_horizontal = 2_f64 * PI / horizontal_steps as f64;
+        let mut total = 0_f64;
+        for i in 0..vertical_steps {
+            let vertical = (i as f64 + 0.5) * d_vertical;
+            for j in 0..horizontal_steps {
+                let horizontal = (j as f64 + 0.5) * d_horizontal;
+                total += self.candela(vertical.to_degrees(), horizontal.to_degrees())
+                    * vertical.cos()
+                    * vertical.sin();
+            }
+        }
+        total * d_vertical * d_horizontal / (PI * self.max_candela)
+    }
+}
+
+/// An [IESProfile] aimed with its nadir along a direction, for the [PointLight](super::PointLight) and [SpotLight](super::SpotLight)
+#[derive(Debug, Clone)]
+pub struct OrientedProfile {
+    pub profile: Arc<IESProfile>,
+    /// Direction the fixture points to (Not normalized)
+    pub nadir: Vec3,
+}
+impl OrientedProfile {
+    /// Refer to [IESProfile::value]
+    pub fn value(&self, direction: &Vec3) -> f64 {
+        self.profile.value(&self.nadir, direction)
+    }
+}
+
+/// The numbers after the `TILT=` line of an `.ies` file
+struct Numbers<'a> {
+    tokens: std::vec::IntoIter<&'a str>,
+}
+impl Numbers<'_> {
+    fn next(&mut self) -> io::Result<f64> {
+        let token = self
+            .tokens
+            .next()
+            .ok_or_else(|| invalid_data("the file ends too early".to_string()))?;
+        token
+            .parse::<f64>()
+            .map_err(|_| invalid_data(format!("expected a number, found `{}`", token)))
+    }
+    fn count(&mut self, name: &str) -> io::Result<usize> {
+        let value = self.next()?;
+        if value < 1_f64 || value.fract() != 0_f64 {
+            return Err(invalid_data(format!("invalid {} `{}`", name, value)));
+        }
+        Ok(value as usize)
+    }
+    fn angles(&mut self, count: usize, name: &str) -> io::Result<Vec<f64>> {
+        let angles = (0..count)
+            .map(|_| self.next())
+            .collect::<io::Result<Vec<_>>>()?;
+        if angles.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(invalid_data(format!(
+                "the {} angles are not increasing",
+                name
+            )));
+        }
+        Ok(angles)
+    }
+}
+
+/// Indices of the angles around angle and how far it is between them, None outside of the angles
+fn segment(angles: &[f64], angle: f64) -> Option<(usize, usize, f64)> {
+    let last = angles.len() - 1;
+    if angle < angles[0] || angle > angles[last] {
+        return None;
+    }
+    if last == 0 {
+        return Some((0, 0, 0_f64));
+    }
+    let next = angles
+        .partition_point(|value| *value <= angle)
+        .clamp(1, last);
+    let (start, end) = (angles[next - 1], angles[next]);
+    Some((next - 1, next, (angle - start) / (end - start)))
+}
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn load(name: &str) -> IESProfile {
+        IESProfile::from_file(&Path::new("assets/ies").join(name)).unwrap()
+    }
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn test_ies_symmetric_profile() {
+        let profile = load("downlight.ies");
+        assert_eq!(profile.vertical_angles().len(), 10);
+        assert_eq!(profile.horizontal_angles(), &[0_f64]);
+        assert_eq!(profile.max_candela(), 1000_f64);
+        assert!(close(profile.candela(0_f64, 0_f64), 1000_f64));
+        // Interpolated, the same all the way around, and nothing above the fixture
+        assert!(close(profile.candela(15_f64, 0_f64), 875_f64));
+        assert!(close(profile.candela(15_f64, 123_f64), 875_f64));
+        assert_eq!(profile.candela(120_f64, 0_f64), 0_f64);
+
+        let down = Vec3::new_int(0, -1, 0);
+        assert!(close(profile.value(&down, &down), 1_f64));
+        let diagonal = Vec3::new_int(1, -1, 0);
+        assert!(close(profile.value(&down, &diagonal), 0.175));
+        assert_eq!(profile.value(&down, &Vec3::new_int(0, 1, 0)), 0_f64);
+        // A narrow beam gives off a small part of the light of a plain area light
+        let average = profile.hemisphere_average();
+        assert!(average > 0.1 && average < 0.5, "{}", average);
+    }
+
+    #[test]
+    fn test_ies_asymmetric_profiles() {
+        // Candela values are scaled by the multiplier (2) and the ballast factor (0.9)
+        let profile = load("wallwasher.ies");
+        assert_eq!(profile.horizontal_angles(), &[0_f64, 90_f64, 180_f64]);
+        assert!(close(profile.max_candela(), 360_f64));
+        assert!(close(profile.candela(45_f64, 90_f64), 216_f64));
+        assert!(close(profile.candela(45_f64, 45_f64), 180_f64));
+        // Mirrored across the 0-180 plane
+        assert!(close(profile.candela(45_f64, 270_f64), 216_f64));
+        assert!(close(profile.candela(45_f64, 315_f64), 180_f64));
+
+        // The tilt data is skipped, and each quadrant is mirrored
+        let profile = load("tilt-include.ies");
+        assert_eq!(profile.vertical_angles(), &[0_f64, 30_f64, 60_f64, 90_f64]);
+        assert!(close(profile.candela(30_f64, 45_f64), 200_f64));
+        assert!(close(profile.candela(30_f64, 135_f64), 200_f64));
+        assert!(close(profile.candela(30_f64, 180_f64), 250_f64));
+        assert!(close(profile.candela(30_f64, 270_f64), 150_f64));
+    }
+
+    #[test]
+    fn test_ies_invalid_files() {
+        let source = fs::read_to_string("assets/ies/downlight.ies").unwrap();
+        let error = |source: &str| IESProfile::parse(source).unwrap_err().to_string();
+        assert_eq!(
+            error(&source[..source.len() - 10]),
+            "the file ends too early"
+        );
+        assert_eq!(
+            error(&source.replace("TILT=NONE", "")),
+            "missing the `TILT=` line"
+        );
+        assert_eq!(
+            error(&source.replace("1 1 2 0.1", "1 2 2 0.1")),
+            "only type C photometry is supported, found type 2"
+        );
+        assert_eq!(
+            error(&source.replace("0 10 20", "0 20 10")),
+            "the vertical angles are not increasing"
+        );
+        assert_eq!(
+            error(&source.replace("1000 950", "1000 lots")),
+            "expected a number, found `lots`"
+        );
+    }
+}
@@ -1,42 +1,86 @@
-use std::sync::Arc;
+use std::{f64::consts::PI, sync::Arc};
 
-use rand::{thread_rng, Rng};
-
-use crate::{ray::Ray, ColorTexture, HitRecord};
+use crate::{
+    ray::Ray, ColorTexture, CosinePDF, HenyeyGreensteinPDF, HitRecord, IESProfile, Sampler,
+    SolidColor, SpherePDF, PDF,
+};
 
 use super::Vec3;
 
-pub struct Scattered {
-    pub attenuation: Vec3,
-    pub ray: Ray,
+mod microfacet;
+mod principled;
+pub use microfacet::{Conductor, RoughDielectric, GGX};
+pub use principled::{Parameter, PrincipledBSDF};
+
+/// Result of a ray scattering off a material
+pub enum Scattered {
+    /// The scattered ray is fully determined (or sampled) by the material (i.e. mirrors and glass)
+    /// These are not importance sampled, as there is only a single (or very narrow) direction that matters
+    Specular { attenuation: Vec3, ray: Ray },
+    /// The scattered direction is distributed according to the given pdf
+    /// Which allows the Camera to mix in other pdfs (i.e. sampling the lights)
+    Diffuse {
+        attenuation: Vec3,
+        pdf: Box<dyn PDF>,
+    },
 }
 
 pub trait Scatterable: Sync + Send {
-    fn scatter(&self, _ray: &Ray, hit_record: &HitRecord) -> Option<Scattered>;
+    fn scatter(
+        &self,
+        _ray: &Ray,
+        hit_record: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<Scattered>;
+    /// Probability density of the material scattering the incoming ray into the scattered ray
+    /// Only used for [Scattered::Diffuse] materials
+    #[allow(unused_variables)]
+    fn scattering_pdf(&self, _ray: &Ray, hit_record: &HitRecord, scattered: &Ray) -> f64 {
+        0_f64
+    }
+    /// BSDF * cosine of the parts of the material that scatter as [Scattered::Specular], from the direction of the scattered ray
+    /// Only used for the [Light](crate::Light)s, which scattered rays never hit, so rough metals still get their highlights
+    /// Perfect mirrors and glass have none (Their BSDF is a delta)
+    #[allow(unused_variables)]
+    fn glossy(&self, _ray: &Ray, hit_record: &HitRecord, scattered: &Ray) -> Vec3 {
+        Vec3::default()
+    }
+    /// Base color of the surface at the hit, without any lighting (Used for the albedo [AOV](crate::AOV))
+    /// Materials that pass the color along (i.e. glass) are white
+    #[allow(unused_variables)]
+    fn albedo(&self, hit_record: &HitRecord) -> Vec3 {
+        Vec3::new_int(1, 1, 1)
+    }
 }
 
 pub struct Lambertain {
     pub albedo: Arc<dyn ColorTexture>,
 }
 impl Scatterable for Lambertain {
-    fn scatter(&self, _ray: &Ray, hit_record: &HitRecord) -> Option<Scattered> {
-        let scattered_direction =
-            hit_record.against_normal_unit.clone() + Vec3::random_vector_in_unit_sphere();
-        let scattered_direction = if scattered_direction.near_zero() {
-            hit_record.against_normal_unit.clone()
-        } else {
-            scattered_direction
-        };
-        Some(Scattered {
+    fn scatter(
+        &self,
+        _ray: &Ray,
+        hit_record: &HitRecord,
+        _: &mut dyn Sampler,
+    ) -> Option<Scattered> {
+        Some(Scattered::Diffuse {
             attenuation: self
                 .albedo
                 .color(hit_record.u, hit_record.v, hit_record.p.clone()),
-            ray: Ray {
-                origin: hit_record.p.clone(),
-                direction: scattered_direction,
-            },
+            pdf: Box::new(CosinePDF::new(&hit_record.against_normal_unit)),
         })
     }
+    fn scattering_pdf(&self, _ray: &Ray, hit_record: &HitRecord, scattered: &Ray) -> f64 {
+        let cosine_theta = Vec3::dot(
+            &hit_record.against_normal_unit,
+            &scattered.direction.unit_vector(),
+        );
+        f64::max(0_f64, cosine_theta / PI)
+    }
+    fn albedo(&self, hit_record: &HitRecord) -> Vec3 {
+        self.albedo
+            .color(hit_record.u, hit_record.v, hit_record.p.clone())
+    }
 }
 
 pub struct Metal {
@@ -57,14 +101,20 @@ impl Metal {
     }
 }
 impl Scatterable for Metal {
-    fn scatter(&self, _ray: &Ray, hit_record: &HitRecord) -> Option<Scattered> {
+    fn scatter(
+        &self,
+        _ray: &Ray,
+        hit_record: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<Scattered> {
         let scattered_direction = Vec3::reflect(
             &_ray.direction.unit_vector(),
             &hit_record.against_normal_unit,
-        ) + self.fuzzy_factor * Vec3::random_vector_in_unit_sphere();
+        ) + self.fuzzy_factor
+            * Vec3::random_vector_in_unit_sphere(sampler);
         // Check if the scattered rays are cancelled out or scattered below the surface, in that case, ray is absorbed
         if Vec3::dot(&scattered_direction, &hit_record.against_normal_unit) > 0_f64 {
-            Some(Scattered {
+            Some(Scattered::Specular {
                 attenuation: self
                     .albedo
                     .color(hit_record.u, hit_record.v, hit_record.p.clone()),
@@ -77,6 +127,10 @@ impl Scatterable for Metal {
             None
         }
     }
+    fn albedo(&self, hit_record: &HitRecord) -> Vec3 {
+        self.albedo
+            .color(hit_record.u, hit_record.v, hit_record.p.clone())
+    }
 }
 
 pub struct Dielectric {
@@ -91,9 +145,12 @@ impl Dielectric {
     }
 }
 impl Scatterable for Dielectric {
-    fn scatter(&self, _ray: &Ray, hit_record: &HitRecord) -> Option<Scattered> {
-        let mut rng = thread_rng();
-
+    fn scatter(
+        &self,
+        _ray: &Ray,
+        hit_record: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<Scattered> {
         // Diaelectric passes the color along
         let albedo = Vec3::new_int(1, 1, 1);
 
@@ -107,9 +164,9 @@ impl Scatterable for Dielectric {
             Vec3::dot(&(-unit_direction.clone()), &hit_record.against_normal_unit).min(1_f64);
         let sin_theta = (1_f64 - cos_theta * cos_theta).sqrt();
         if refraction_ratio * sin_theta > 1_f64
-            || Dielectric::reflectance(cos_theta, refraction_ratio) > rng.gen::<f64>()
+            || Dielectric::reflectance(cos_theta, refraction_ratio) > sampler.get_1d()
         {
-            Some(Scattered {
+            Some(Scattered::Specular {
                 attenuation: albedo,
                 ray: Ray {
                     origin: hit_record.p.clone(),
@@ -117,7 +174,7 @@ impl Scatterable for Dielectric {
                 },
             })
         } else {
-            Some(Scattered {
+            Some(Scattered::Specular {
                 attenuation: albedo,
                 ray: Ray {
                     origin: hit_record.p.clone(),
@@ -132,20 +189,152 @@ impl Scatterable for Dielectric {
     }
 }
 
+/// Phase function of a [ConstantMedium](crate::ConstantMedium), scatters into every direction with the same probability
+pub struct Isotropic {
+    pub albedo: Arc<dyn ColorTexture>,
+}
+impl Scatterable for Isotropic {
+    fn scatter(
+        &self,
+        _ray: &Ray,
+        hit_record: &HitRecord,
+        _: &mut dyn Sampler,
+    ) -> Option<Scattered> {
+        Some(Scattered::Diffuse {
+            attenuation: self
+                .albedo
+                .color(hit_record.u, hit_record.v, hit_record.p.clone()),
+            pdf: Box::new(SpherePDF {}),
+        })
+    }
+    fn scattering_pdf(&self, _ray: &Ray, _hit_record: &HitRecord, _scattered: &Ray) -> f64 {
+        1_f64 / (4_f64 * PI)
+    }
+    fn albedo(&self, hit_record: &HitRecord) -> Vec3 {
+        self.albedo
+            .color(hit_record.u, hit_record.v, hit_record.p.clone())
+    }
+}
+
+/// Henyey-Greenstein phase function of a [HeterogeneousMedium](crate::HeterogeneousMedium), refer to [HenyeyGreensteinPDF]
+/// Clouds scatter mostly forward (g around 0.8), smoke is closer to uniform
+pub struct HenyeyGreenstein {
+    pub g: f64,
+    /// Tint of the scattered light
+    pub albedo: Vec3,
+}
+impl Scatterable for HenyeyGreenstein {
+    fn scatter(
+        &self,
+        _ray: &Ray,
+        _hit_record: &HitRecord,
+        _: &mut dyn Sampler,
+    ) -> Option<Scattered> {
+        Some(Scattered::Diffuse {
+            attenuation: self.albedo.clone(),
+            pdf: Box::new(HenyeyGreensteinPDF::new(&_ray.direction, self.g)),
+        })
+    }
+    fn scattering_pdf(&self, _ray: &Ray, _hit_record: &HitRecord, scattered: &Ray) -> f64 {
+        HenyeyGreensteinPDF::phase(
+            self.g,
+            Vec3::dot(
+                &_ray.direction.unit_vector(),
+                &scattered.direction.unit_vector(),
+            ),
+        )
+    }
+    fn albedo(&self, _hit_record: &HitRecord) -> Vec3 {
+        self.albedo.clone()
+    }
+}
+
 pub trait Emittable: Sync + Send {
-    /// Return the light value of the material
-    fn emit(&self) -> Vec3;
+    /// Return the light value of the material at the hit (Which side was hit is hit_record.front_face)
+    /// The light leaves towards the origin of _ray, the ray that hit the light
+    fn emit(&self, _ray: &Ray, hit_record: &HitRecord) -> Vec3;
+    /// Rough average of the light emitted over the surface, used to sample the brighter lights more often
+    fn average_emission(&self) -> Vec3;
 }
 
+/// White light emitted from both faces
 pub struct Diffuse {
     pub power: f64,
 }
 impl Emittable for Diffuse {
-    fn emit(&self) -> Vec3 {
+    fn emit(&self, _ray: &Ray, _hit_record: &HitRecord) -> Vec3 {
+        self.average_emission()
+    }
+    fn average_emission(&self) -> Vec3 {
         Vec3::new(self.power, self.power, self.power)
     }
 }
 
+/// Light with the color of a texture (i.e. neon panels or screens showing an image) scaled by the intensity
+/// One sided lights only emit from the front face (The side the outward normal points to), i.e. a ceiling light shining downward
+/// A profile shapes the light like a real fixture, with its nadir along the normal of the face (The light is then at most the texture color)
+/// The texture and intensity are only set by [Emissive::new], which averages the texture once for [Emittable::average_emission]
+pub struct Emissive {
+    texture: Arc<dyn ColorTexture>,
+    intensity: f64,
+    pub two_sided: bool,
+    pub profile: Option<Arc<IESProfile>>,
+    /// Average of the texture, refer to [Emittable::average_emission]
+    average_color: Vec3,
+}
+impl Emissive {
+    pub fn new(texture: Arc<dyn ColorTexture>, intensity: f64, two_sided: bool) -> Self {
+        // Implementation Details:
+        // The average is taken over a grid of (u, v), the point is unknown so spatial textures only use the origin
+        let resolution = 16;
+        let mut average_color = Vec3::default();
+        for y in 0..resolution {
+            for x in 0..resolution {
+                average_color += texture.color(
+                    (x as f64 + 0.5) / resolution as f64,
+                    (y as f64 + 0.5) / resolution as f64,
+                    Vec3::default(),
+                );
+            }
+        }
+        average_color /= (resolution * resolution) as f64;
+        Self {
+            texture,
+            intensity,
+            two_sided,
+            profile: None,
+            average_color,
+        }
+    }
+    pub fn from_color(color: Vec3, intensity: f64, two_sided: bool) -> Self {
+        Self::new(Arc::new(SolidColor { color }), intensity, two_sided)
+    }
+}
+impl Emittable for Emissive {
+    fn emit(&self, _ray: &Ray, hit_record: &HitRecord) -> Vec3 {
+        if !self.two_sided && !hit_record.front_face {
+            return Vec3::default();
+        }
+        let profile = match &self.profile {
+            Some(profile) => {
+                profile.value(&hit_record.against_normal_unit, &(-_ray.direction.clone()))
+            }
+            None => 1_f64,
+        };
+        self.texture
+            .color(hit_record.u, hit_record.v, hit_record.p.clone())
+            * (self.intensity * profile)
+    }
+    /// Dimmed by the light the profile holds back, so the lights are sampled by the light they really give off
+    fn average_emission(&self) -> Vec3 {
+        let profile = self
+            .profile
+            .as_ref()
+            .map_or(1_f64, |profile| profile.hemisphere_average());
+        self.average_color.clone() * (self.intensity * profile)
+    }
+}
+
 pub enum Materials {
     ScatterMaterial(Arc<dyn Scatterable>),
     LightMaterial(Arc<dyn Emittable>),
@@ -164,12 +353,76 @@ impl Clone for Materials {
 #[cfg(test)]
 pub(crate) mod test {
     use super::*;
-    use crate::{HitRecord, Ray};
+    use crate::{CheckeredTexture, HitRecord, Ray};
 
     pub struct TestScatterable {}
     impl Scatterable for TestScatterable {
-        fn scatter(&self, _ray: &Ray, hit_record: &HitRecord) -> Option<Scattered> {
+        fn scatter(
+            &self,
+            _ray: &Ray,
+            _hit_record: &HitRecord,
+            _: &mut dyn Sampler,
+        ) -> Option<Scattered> {
             None
         }
     }
+
+    #[test]
+    fn test_emissive_sides() {
+        let ray = Ray {
+            origin: Vec3::new_int(0, 0, 1),
+            direction: Vec3::new_int(0, 0, -1),
+        };
+        let front = HitRecord::new(
+            &ray,
+            &Vec3::new_int(0, 0, 1),
+            1_f64,
+            Materials::ScatterMaterial(Arc::new(TestScatterable {})),
+            0.25,
+            0.75,
+        );
+        let back = HitRecord::new(
+            &ray,
+            &Vec3::new_int(0, 0, -1),
+            1_f64,
+            Materials::ScatterMaterial(Arc::new(TestScatterable {})),
+            0.25,
+            0.75,
+        );
+        let texture = Arc::new(CheckeredTexture::from_colors(
+            4_f64,
+            Vec3::new_int(1, 0, 0),
+            Vec3::new_int(0, 0, 1),
+        ));
+        let one_sided = Emissive::new(texture.clone(), 2_f64, false);
+        assert_eq!(one_sided.emit(&ray, &front), Vec3::new_int(2, 0, 0));
+        assert_eq!(one_sided.emit(&ray, &back), Vec3::new_int(0, 0, 0));
+        let mut two_sided = Emissive::new(texture, 2_f64, true);
+        assert_eq!(two_sided.emit(&ray, &back), Vec3::new_int(2, 0, 0));
+        // Half of the checkers are red and half are blue
+        assert_eq!(two_sided.average_emission(), Vec3::new_int(1, 0, 1));
+
+        // The downlight is brightest straight out of the face, and dark sideways
+        let profile =
+            IESProfile::from_file(std::path::Path::new("assets/ies/downlight.ies")).unwrap();
+        two_sided.profile = Some(Arc::new(profile.clone()));
+        assert_eq!(two_sided.emit(&ray, &back), Vec3::new_int(2, 0, 0));
+        let grazing = Ray {
+            origin: Vec3::new_int(1, 0, 1),
+            direction: Vec3::new_int(-1, 0, -1),
+        };
+        let grazing_hit = HitRecord::new(
+            &grazing,
+            &Vec3::new_int(1, 0, 0),
+            1_f64,
+            Materials::ScatterMaterial(Arc::new(TestScatterable {})),
+            0.25,
+            0.75,
+        );
+        assert!(two_sided.emit(&grazing, &grazing_hit).x() < 1_f64);
+        assert_eq!(
+            two_sided.average_emission(),
+            Vec3::new_int(1, 0, 1) * profile.hemisphere_average()
+        );
+    }
 }
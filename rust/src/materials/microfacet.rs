@@ -0,0 +1,415 @@
+use std::f64::consts::PI;
+
+use super::{Dielectric, Scatterable, Scattered};
+use crate::{HitRecord, Ray, Sampler, Vec3, ONB};
+
+/// GGX (Trowbridge-Reitz) distribution of the microfacet normals, in the shading space where the normal is z
+/// alpha_x and alpha_y are the roughness along the tangent (x) and bitangent (y), equal for an isotropic surface
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GGX {
+    pub alpha_x: f64,
+    pub alpha_y: f64,
+}
+impl GGX {
+    /// Lowest alpha, a perfectly smooth surface would divide by 0
+    const MIN_ALPHA: f64 = 1e-4;
+
+    pub fn new(alpha_x: f64, alpha_y: f64) -> Self {
+        Self {
+            alpha_x: alpha_x.max(Self::MIN_ALPHA),
+            alpha_y: alpha_y.max(Self::MIN_ALPHA),
+        }
+    }
+    /// From the perceptual roughness in [0, 1] (alpha = roughness^2)
+    /// anisotropy in [0, 1) stretches the highlights along the tangent, 0 is isotropic
+    pub fn from_roughness(roughness: f64, anisotropy: f64) -> Self {
+        let alpha = roughness.clamp(0_f64, 1_f64).powi(2);
+        let aspect = (1_f64 - 0.9 * anisotropy.clamp(0_f64, 1_f64)).sqrt();
+        Self::new(alpha / aspect, alpha * aspect)
+    }
+    /// Density of the microfacet normals h
+    pub fn d(&self, h: &Vec3) -> f64 {
+        if h.z() <= 0_f64 {
+            return 0_f64;
+        }
+        let x = h.x() / self.alpha_x;
+        let y = h.y() / self.alpha_y;
+        let denominator = x * x + y * y + h.z() * h.z();
+        1_f64 / (PI * self.alpha_x * self.alpha_y * denominator * denominator)
+    }
+    /// Smith Lambda, the ratio of the microfacet area hidden from w to the visible area
+    fn lambda(&self, w: &Vec3) -> f64 {
+        let cos2 = w.z() * w.z();
+        if cos2 <= 0_f64 {
+            return f64::INFINITY;
+        }
+        let x = self.alpha_x * w.x();
+        let y = self.alpha_y * w.y();
+        0.5 * (-1_f64 + (1_f64 + (x * x + y * y) / cos2).sqrt())
+    }
+    /// Smith masking, the fraction of the microfacets visible from w
+    pub fn g1(&self, w: &Vec3) -> f64 {
+        1_f64 / (1_f64 + self.lambda(w))
+    }
+    /// Height correlated Smith masking-shadowing, the fraction of the microfacets visible from both wo and wi
+    pub fn g2(&self, wo: &Vec3, wi: &Vec3) -> f64 {
+        1_f64 / (1_f64 + self.lambda(wo) + self.lambda(wi))
+    }
+    /// Density of sample_visible_normal picking h
+    pub fn visible_normal_pdf(&self, wo: &Vec3, h: &Vec3) -> f64 {
+        self.g1(wo) * Vec3::dot(wo, h).max(0_f64) * self.d(h) / wo.z().abs()
+    }
+    /// Samples a microfacet normal visible from wo (Which has to be above the surface), with the density of visible_normal_pdf
+    // Implementation Details:
+    // Heitz 2018, "Sampling the GGX Distribution of Visible Normals"
+    // wo is stretched into the space where the distribution is a hemisphere, where the visible normals are a projected disk
+    // The half of the disk hidden by the tilt of wo is squeezed into the visible half, then everything is unstretched
+    pub fn sample_visible_normal(&self, wo: &Vec3, u1: f64, u2: f64) -> Vec3 {
+        let stretched =
+            Vec3::new(self.alpha_x * wo.x(), self.alpha_y * wo.y(), wo.z()).unit_vector();
+        let length_squared = stretched.x() * stretched.x() + stretched.y() * stretched.y();
+        let t1 = if length_squared > 0_f64 {
+            Vec3::new(-stretched.y(), stretched.x(), 0_f64) / length_squared.sqrt()
+        } else {
+            Vec3::new_int(1, 0, 0)
+        };
+        let t2 = Vec3::cross(&stretched, &t1);
+
+        let r = u1.sqrt();
+        let phi = 2_f64 * PI * u2;
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1_f64 + stretched.z());
+        let p2 = (1_f64 - s) * (1_f64 - p1 * p1).sqrt() + s * r * phi.sin();
+        let p3 = (1_f64 - p1 * p1 - p2 * p2).max(0_f64).sqrt();
+        let normal = p1 * t1 + p2 * t2 + p3 * stretched;
+
+        Vec3::new(
+            self.alpha_x * normal.x(),
+            self.alpha_y * normal.y(),
+            normal.z().max(1e-6),
+        )
+        .unit_vector()
+    }
+    /// Reflects wo off a sampled visible microfacet, returns the reflected direction and the microfacet normal
+    /// None when the reflection goes below the surface
+    pub(super) fn sample_reflection(
+        &self,
+        wo: &Vec3,
+        sampler: &mut dyn Sampler,
+    ) -> Option<(Vec3, Vec3)> {
+        let (u1, u2) = sampler.get_2d();
+        let h = self.sample_visible_normal(wo, u1, u2);
+        let wi = Vec3::reflect(&(-wo.clone()), &h);
+        if wi.z() <= 0_f64 {
+            return None;
+        }
+        Some((wi, h))
+    }
+    /// Reflects or refracts wo through a sampled visible microfacet, picked by its Fresnel reflectance
+    /// Returns the direction and whether it was reflected, None when it ends up on the wrong side of the surface
+    pub(super) fn sample_dielectric(
+        &self,
+        wo: &Vec3,
+        refraction_ratio: f64,
+        sampler: &mut dyn Sampler,
+    ) -> Option<(Vec3, bool)> {
+        let (u1, u2) = sampler.get_2d();
+        let h = self.sample_visible_normal(wo, u1, u2);
+        let cos_theta = Vec3::dot(wo, &h).min(1_f64);
+        let sin_theta = (1_f64 - cos_theta * cos_theta).max(0_f64).sqrt();
+
+        let reflects = refraction_ratio * sin_theta > 1_f64
+            || Dielectric::reflectance(cos_theta, refraction_ratio) > sampler.get_1d();
+        let wi = if reflects {
+            Vec3::reflect(&(-wo.clone()), &h)
+        } else {
+            Vec3::refract(&(-wo.clone()), &h, refraction_ratio)
+        };
+        // Reflections have to stay above the surface and refractions below it
+        if (wi.z() > 0_f64) != reflects {
+            return None;
+        }
+        Some((wi, reflects))
+    }
+    /// BRDF * cos of reflecting wo into wi without the Fresnel term, D * G2 / (4 cos_o), along with the microfacet normal between them
+    /// None when either direction is below the surface
+    pub(super) fn reflection(&self, wo: &Vec3, wi: &Vec3) -> Option<(f64, Vec3)> {
+        if wo.z() <= 0_f64 || wi.z() <= 0_f64 {
+            return None;
+        }
+        let h = (wo.clone() + wi.clone()).unit_vector();
+        Some((self.d(&h) * self.g2(wo, wi) / (4_f64 * wo.z()), h))
+    }
+    /// Weight of a direction sampled from the visible normals, the BSDF * cos / pdf without the Fresnel term
+    /// It is never above 1, the light lost is the light that would have bounced more than once between the microfacets
+    pub(super) fn masking_weight(&self, wo: &Vec3, wi: &Vec3) -> f64 {
+        self.g2(wo, wi) / self.g1(wo)
+    }
+}
+
+/// Direction in the shading space of the basis (The normal is z)
+pub(super) fn to_local(uvw: &ONB, direction: &Vec3) -> Vec3 {
+    Vec3::new(
+        Vec3::dot(direction, uvw.u()),
+        Vec3::dot(direction, uvw.v()),
+        Vec3::dot(direction, uvw.w()),
+    )
+}
+
+/// Rough metal with the GGX microfacet distribution, colored by the Fresnel reflectance of a complex index of refraction (eta + ik)
+/// The roughness is physically based, unlike the fuzz of [Metal](crate::Metal), so highlights get wider and dimmer instead of just blurrier
+///
+/// The tangent of anisotropic roughness follows the [ONB] around the normal
+pub struct Conductor {
+    /// Real part of the index of refraction, per color channel
+    pub eta: Vec3,
+    /// Imaginary part of the index of refraction (Absorption), per color channel
+    pub k: Vec3,
+    pub distribution: GGX,
+}
+impl Conductor {
+    pub fn gold(distribution: GGX) -> Self {
+        Self {
+            eta: Vec3::new(0.143, 0.374, 1.442),
+            k: Vec3::new(3.983, 2.385, 1.603),
+            distribution,
+        }
+    }
+    pub fn copper(distribution: GGX) -> Self {
+        Self {
+            eta: Vec3::new(0.200, 0.924, 1.102),
+            k: Vec3::new(3.912, 2.452, 2.142),
+            distribution,
+        }
+    }
+    pub fn aluminium(distribution: GGX) -> Self {
+        Self {
+            eta: Vec3::new(1.657, 0.880, 0.521),
+            k: Vec3::new(9.224, 6.270, 4.837),
+            distribution,
+        }
+    }
+    /// Fresnel reflectance of the conductor, for the cosine between the direction and the (micro)normal
+    pub fn fresnel(&self, cos_theta: f64) -> Vec3 {
+        Vec3::new(
+            fresnel_conductor(cos_theta, self.eta.x(), self.k.x()),
+            fresnel_conductor(cos_theta, self.eta.y(), self.k.y()),
+            fresnel_conductor(cos_theta, self.eta.z(), self.k.z()),
+        )
+    }
+}
+impl Scatterable for Conductor {
+    // Implementation Details:
+    // Samples a visible microfacet normal and reflects off it, the weight F * G2 / G1 is the BRDF * cos / pdf with everything else cancelled out
+    fn scatter(
+        &self,
+        ray: &Ray,
+        hit_record: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<Scattered> {
+        let uvw = ONB::new(&hit_record.against_normal_unit);
+        let wo = to_local(&uvw, &(-ray.direction.unit_vector()));
+        if wo.z() <= 0_f64 {
+            return None;
+        }
+        let (wi, h) = self.distribution.sample_reflection(&wo, sampler)?;
+        let attenuation =
+            self.fresnel(Vec3::dot(&wo, &h)) * self.distribution.masking_weight(&wo, &wi);
+        Some(Scattered::Specular {
+            attenuation,
+            ray: Ray {
+                origin: hit_record.p.clone(),
+                direction: uvw.local(&wi),
+            },
+        })
+    }
+    fn glossy(&self, ray: &Ray, hit_record: &HitRecord, scattered: &Ray) -> Vec3 {
+        let uvw = ONB::new(&hit_record.against_normal_unit);
+        let wo = to_local(&uvw, &(-ray.direction.unit_vector()));
+        let wi = to_local(&uvw, &scattered.direction.unit_vector());
+        match self.distribution.reflection(&wo, &wi) {
+            Some((reflection, h)) => self.fresnel(Vec3::dot(&wo, &h)) * reflection,
+            None => Vec3::default(),
+        }
+    }
+    fn albedo(&self, _hit_record: &HitRecord) -> Vec3 {
+        self.fresnel(1_f64)
+    }
+}
+
+/// Exact Fresnel reflectance of unpolarized light on a conductor, for a single wavelength
+fn fresnel_conductor(cos_theta: f64, eta: f64, k: f64) -> f64 {
+    let cos2 = cos_theta.clamp(0_f64, 1_f64).powi(2);
+    let sin2 = 1_f64 - cos2;
+    let t0 = eta * eta - k * k - sin2;
+    let a2_plus_b2 = (t0 * t0 + 4_f64 * eta * eta * k * k).sqrt();
+    let a = (0.5 * (a2_plus_b2 + t0)).max(0_f64).sqrt();
+    let t1 = a2_plus_b2 + cos2;
+    let t2 = 2_f64 * cos_theta * a;
+    let rs = (t1 - t2) / (t1 + t2);
+    let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+    0.5 * (rs + rp)
+}
+
+/// Frosted glass, the GGX version of [Dielectric], which reflects and refracts off the sampled microfacets
+pub struct RoughDielectric {
+    pub index_of_refraction: f64,
+    pub distribution: GGX,
+}
+impl Scatterable for RoughDielectric {
+    // Implementation Details:
+    // Same as the Conductor, but picks between reflecting and refracting with the Fresnel reflectance of the microfacet
+    // That probability cancels the Fresnel term out of the weight, leaving G2 / G1
+    fn scatter(
+        &self,
+        ray: &Ray,
+        hit_record: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<Scattered> {
+        let refraction_ratio = if hit_record.front_face {
+            1_f64 / self.index_of_refraction
+        } else {
+            self.index_of_refraction
+        };
+        let uvw = ONB::new(&hit_record.against_normal_unit);
+        let wo = to_local(&uvw, &(-ray.direction.unit_vector()));
+        if wo.z() <= 0_f64 {
+            return None;
+        }
+        let (wi, _) = self
+            .distribution
+            .sample_dielectric(&wo, refraction_ratio, sampler)?;
+        Some(Scattered::Specular {
+            attenuation: Vec3::new_int(1, 1, 1) * self.distribution.masking_weight(&wo, &wi),
+            ray: Ray {
+                origin: hit_record.p.clone(),
+                direction: uvw.local(&wi),
+            },
+        })
+    }
+    /// Only the reflection, the light refracted through the glass is not seen
+    fn glossy(&self, ray: &Ray, hit_record: &HitRecord, scattered: &Ray) -> Vec3 {
+        let refraction_ratio = if hit_record.front_face {
+            1_f64 / self.index_of_refraction
+        } else {
+            self.index_of_refraction
+        };
+        let uvw = ONB::new(&hit_record.against_normal_unit);
+        let wo = to_local(&uvw, &(-ray.direction.unit_vector()));
+        let wi = to_local(&uvw, &scattered.direction.unit_vector());
+        match self.distribution.reflection(&wo, &wi) {
+            Some((reflection, h)) => {
+                Vec3::new_int(1, 1, 1)
+                    * (Dielectric::reflectance(Vec3::dot(&wo, &h), refraction_ratio) * reflection)
+            }
+            None => Vec3::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Arc;
+
+    use super::super::test::TestScatterable;
+    use crate::{IndependentSampler, Materials};
+
+    #[test]
+    fn test_ggx_distribution() {
+        let mut sampler = IndependentSampler::new(0);
+        for ggx in [GGX::new(0.3, 0.3), GGX::from_roughness(0.6, 0.8)] {
+            // The projected area of the microfacets is the area of the surface
+            let count = 200000;
+            let projected_area: f64 = (0..count)
+                .map(|_| {
+                    let h = Vec3::random_unit_vector(&mut sampler);
+                    ggx.d(&h) * h.z().max(0_f64) * 4_f64 * PI
+                })
+                .sum::<f64>()
+                / count as f64;
+            assert!((projected_area - 1_f64).abs() < 0.05, "{}", projected_area);
+
+            // Sampled normals are visible, and the density of the visible normals integrates to 1
+            let wo = Vec3::new(0.5, -0.3, 0.8).unit_vector();
+            for _ in 0..100 {
+                let (u1, u2) = sampler.get_2d();
+                let h = ggx.sample_visible_normal(&wo, u1, u2);
+                assert!(Vec3::dot(&wo, &h) >= 0_f64 && h.z() > 0_f64);
+                assert!((h.length() - 1_f64).abs() < 1e-9);
+            }
+            let total: f64 = (0..count)
+                .map(|_| {
+                    let h = Vec3::random_unit_vector(&mut sampler);
+                    ggx.visible_normal_pdf(&wo, &h) * 4_f64 * PI
+                })
+                .sum::<f64>()
+                / count as f64;
+            assert!((total - 1_f64).abs() < 0.05, "{}", total);
+        }
+        assert!(GGX::new(0.3, 0.3).g2(&Vec3::new_int(0, 0, 1), &Vec3::new_int(0, 0, 1)) <= 1_f64);
+    }
+
+    #[test]
+    fn test_microfacet_scatter() {
+        // At normal incidence the reflectance of a conductor is ((eta - 1)^2 + k^2) / ((eta + 1)^2 + k^2)
+        let gold = Conductor::gold(GGX::from_roughness(0.3, 0_f64));
+        let (eta, k) = (gold.eta.x(), gold.k.x());
+        let expected = ((eta - 1_f64).powi(2) + k * k) / ((eta + 1_f64).powi(2) + k * k);
+        assert!((gold.fresnel(1_f64).x() - expected).abs() < 1e-9);
+        // Every metal reflects everything at grazing angles
+        assert!((gold.fresnel(0_f64).z() - 1_f64).abs() < 1e-9);
+
+        let ray = Ray {
+            origin: Vec3::new(1_f64, 1_f64, 1_f64),
+            direction: Vec3::new(-1_f64, -1_f64, -0.5),
+        };
+        let hit_record = HitRecord::new(
+            &ray,
+            &Vec3::new_int(0, 0, 1),
+            1.5,
+            Materials::ScatterMaterial(Arc::new(TestScatterable {})),
+            0_f64,
+            0_f64,
+        );
+        let glass = RoughDielectric {
+            index_of_refraction: 1.5,
+            distribution: GGX::from_roughness(0.4, 0_f64),
+        };
+        let mut sampler = IndependentSampler::new(0);
+        let mut refracted = 0;
+        for _ in 0..1000 {
+            for material in [&gold as &dyn Scatterable, &glass] {
+                if let Some(Scattered::Specular { attenuation, ray }) =
+                    material.scatter(&ray, &hit_record, &mut sampler)
+                {
+                    // Energy is never gained
+                    assert!(attenuation.xyz().iter().all(|value| *value <= 1_f64));
+                    if ray.direction.z() < 0_f64 {
+                        refracted += 1;
+                    }
+                }
+            }
+        }
+        // Only the glass refracts, most of the time at this angle
+        assert!(refracted > 700 && refracted < 1000);
+
+        // The highlight of the lights is brightest in the mirror direction, and there is none below the surface
+        let glossy = |direction: Vec3| {
+            gold.glossy(
+                &ray,
+                &hit_record,
+                &Ray {
+                    origin: hit_record.p.clone(),
+                    direction,
+                },
+            )
+        };
+        let mirror = glossy(Vec3::new(-1_f64, -1_f64, 0.5));
+        assert!(mirror.x() > glossy(Vec3::new(-1_f64, -1_f64, 2_f64)).x());
+        assert!(mirror.x() > glossy(Vec3::new(1_f64, 1_f64, 0.5)).x());
+        assert_eq!(glossy(Vec3::new(-1_f64, -1_f64, -0.5)), Vec3::default());
+    }
+}
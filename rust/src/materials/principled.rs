@@ -0,0 +1,420 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use super::{microfacet::to_local, Dielectric, Scatterable, Scattered};
+use crate::{ColorTexture, CosinePDF, HitRecord, Ray, Sampler, Vec3, GGX, ONB};
+
+/// Input of a [PrincipledBSDF], either the same everywhere or read from a texture at the hit
+/// Scalar parameters use the luminance of their texture (So a grey texture gives its grey level)
+pub enum Parameter<T> {
+    Constant(T),
+    Texture(Arc<dyn ColorTexture>),
+}
+impl Parameter<f64> {
+    pub fn value(&self, hit_record: &HitRecord) -> f64 {
+        match self {
+            Self::Constant(value) => *value,
+            Self::Texture(texture) => texture
+                .color(hit_record.u, hit_record.v, hit_record.p.clone())
+                .luminance(),
+        }
+    }
+}
+impl Parameter<Vec3> {
+    pub fn value(&self, hit_record: &HitRecord) -> Vec3 {
+        match self {
+            Self::Constant(value) => value.clone(),
+            Self::Texture(texture) => {
+                texture.color(hit_record.u, hit_record.v, hit_record.p.clone())
+            }
+        }
+    }
+}
+impl From<f64> for Parameter<f64> {
+    fn from(value: f64) -> Self {
+        Self::Constant(value)
+    }
+}
+impl From<Vec3> for Parameter<Vec3> {
+    fn from(value: Vec3) -> Self {
+        Self::Constant(value)
+    }
+}
+impl<T> From<Arc<dyn ColorTexture>> for Parameter<T> {
+    fn from(texture: Arc<dyn ColorTexture>) -> Self {
+        Self::Texture(texture)
+    }
+}
+
+/// Single material covering plastics, metals, glass and everything in between, with the parameters of the Disney principled BSDF
+/// Every scalar parameter is in [0, 1] (Except the index of refraction), set the ones needed and use the defaults for the rest:
+/// ```
+/// # use rust_simple_raytracer::{PrincipledBSDF, Vec3};
+/// let car_paint = PrincipledBSDF {
+///     base_color: Vec3::new(0.6, 0.05, 0.05).into(),
+///     roughness: 0.4.into(),
+///     clearcoat: 1.0.into(),
+///     ..Default::default()
+/// };
+/// ```
+///
+/// It is a mix of lobes: a clearcoat on top of a GGX specular, over either a diffuse base (With sheen) or rough glass
+/// Only the diffuse lobe is sampled with the lights, the others are sampled like [Conductor](crate::Conductor)
+pub struct PrincipledBSDF {
+    /// Diffuse color, or the reflectance of metals
+    pub base_color: Parameter<Vec3>,
+    /// Blends from a dielectric (0) into a metal (1)
+    pub metallic: Parameter<f64>,
+    /// Perceptual roughness of the specular and the glass, refer to [GGX::from_roughness]
+    pub roughness: Parameter<f64>,
+    /// Strength of the dielectric specular, 0.5 is the reflectance of the index of refraction
+    pub specular: Parameter<f64>,
+    /// Tints the dielectric specular towards the base color
+    pub specular_tint: Parameter<f64>,
+    /// Soft rim at grazing angles (i.e. cloth)
+    pub sheen: Parameter<f64>,
+    /// Tints the sheen towards the base color
+    pub sheen_tint: Parameter<f64>,
+    /// Strength of a clear varnish layer on top (i.e. car paint)
+    pub clearcoat: Parameter<f64>,
+    pub clearcoat_roughness: Parameter<f64>,
+    /// Blends the dielectric base from diffuse (0) into glass (1), tinted by the base color
+    pub transmission: Parameter<f64>,
+    pub index_of_refraction: Parameter<f64>,
+    /// Stretches the specular along the tangent, refer to [GGX::from_roughness]
+    pub anisotropy: Parameter<f64>,
+}
+impl Default for PrincipledBSDF {
+    fn default() -> Self {
+        Self {
+            base_color: Vec3::new(0.8, 0.8, 0.8).into(),
+            metallic: 0_f64.into(),
+            roughness: 0.5.into(),
+            specular: 0.5.into(),
+            specular_tint: 0_f64.into(),
+            sheen: 0_f64.into(),
+            sheen_tint: 0.5.into(),
+            clearcoat: 0_f64.into(),
+            clearcoat_roughness: 0.1.into(),
+            transmission: 0_f64.into(),
+            index_of_refraction: 1.5.into(),
+            anisotropy: 0_f64.into(),
+        }
+    }
+}
+/// Weights and colors of the lobes at a hit, seen from wo
+struct Lobes {
+    base_color: Vec3,
+    transmission: f64,
+    index_of_refraction: f64,
+    distribution: GGX,
+    coat: GGX,
+    /// Reflectance of the specular at normal incidence
+    f0: Vec3,
+    clearcoat: f64,
+    clearcoat_reflectance: f64,
+    specular_weight: f64,
+    glass_weight: f64,
+    diffuse_weight: f64,
+    diffuse_color: Vec3,
+}
+impl PrincipledBSDF {
+    /// The clearcoat is a varnish with an index of refraction of 1.5
+    const CLEARCOAT_F0: f64 = 0.04;
+
+    fn lobes(&self, hit_record: &HitRecord, wo: &Vec3) -> Lobes {
+        let white = Vec3::new_int(1, 1, 1);
+        let base_color = self.base_color.value(hit_record);
+        let metallic = unit(self.metallic.value(hit_record));
+        let transmission = unit(self.transmission.value(hit_record));
+        let index_of_refraction = self.index_of_refraction.value(hit_record).max(1e-3);
+
+        let luminance = base_color.luminance();
+        let tint = if luminance > 0_f64 {
+            base_color.clone() / luminance
+        } else {
+            white.clone()
+        };
+        let ior_reflectance =
+            ((index_of_refraction - 1_f64) / (index_of_refraction + 1_f64)).powi(2);
+        let dielectric_f0 = lerp(&white, &tint, unit(self.specular_tint.value(hit_record)))
+            * (2_f64 * ior_reflectance * self.specular.value(hit_record).max(0_f64));
+        let f0 = lerp(&dielectric_f0, &base_color, metallic);
+
+        let clearcoat = unit(self.clearcoat.value(hit_record));
+        let clearcoat_reflectance = clearcoat * schlick_scalar(Self::CLEARCOAT_F0, wo.z());
+        let base_weight = 1_f64 - clearcoat_reflectance;
+        let sheen_color = lerp(&white, &tint, unit(self.sheen_tint.value(hit_record)))
+            * (self.sheen.value(hit_record).max(0_f64) * (1_f64 - wo.z()).powi(5));
+
+        Lobes {
+            distribution: GGX::from_roughness(
+                self.roughness.value(hit_record),
+                self.anisotropy.value(hit_record),
+            ),
+            coat: GGX::from_roughness(self.clearcoat_roughness.value(hit_record), 0_f64),
+            clearcoat,
+            clearcoat_reflectance,
+            specular_weight: base_weight * (1_f64 - (1_f64 - metallic) * transmission),
+            glass_weight: base_weight * (1_f64 - metallic) * transmission,
+            diffuse_weight: base_weight
+                * (1_f64 - metallic)
+                * (1_f64 - transmission)
+                * (1_f64 - schlick(&dielectric_f0, wo.z()).luminance()),
+            diffuse_color: base_color.clone() + sheen_color,
+            f0,
+            base_color,
+            transmission,
+            index_of_refraction,
+        }
+    }
+}
+impl Scatterable for PrincipledBSDF {
+    // Implementation Details:
+    // Each lobe has a weight, from the Fresnel reflectance (at the view angle) of the layers above it and the metallic and transmission blends
+    // A single lobe is picked with a probability following its (approximate) reflectance, and its weight is divided by that probability
+    // This sums up the lobes without bias, and keeps the diffuse lobe a plain cosine lobe so the Camera can sample the lights for it
+    fn scatter(
+        &self,
+        ray: &Ray,
+        hit_record: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<Scattered> {
+        let uvw = ONB::new(&hit_record.against_normal_unit);
+        let wo = to_local(&uvw, &(-ray.direction.unit_vector()));
+        if wo.z() <= 0_f64 {
+            return None;
+        }
+        let white = Vec3::new_int(1, 1, 1);
+        let lobes = self.lobes(hit_record, &wo);
+        let distribution = lobes.distribution;
+
+        // Inside a transmissive object, only the glass is left (On the way back out)
+        if !hit_record.front_face && lobes.transmission > 0_f64 {
+            let (wi, _) =
+                distribution.sample_dielectric(&wo, lobes.index_of_refraction, sampler)?;
+            return Some(specular(
+                &uvw,
+                hit_record,
+                &wi,
+                white * distribution.masking_weight(&wo, &wi),
+            ));
+        }
+
+        let weights = [
+            lobes.clearcoat_reflectance,
+            lobes.specular_weight * schlick(&lobes.f0, wo.z()).luminance(),
+            lobes.glass_weight,
+            lobes.diffuse_weight * lobes.diffuse_color.luminance(),
+        ];
+        let total: f64 = weights.iter().sum();
+        if total <= 0_f64 {
+            return None;
+        }
+        let mut pick = sampler.get_1d() * total;
+        // Falls back to the last possible lobe, in case of rounding errors
+        let mut lobe = weights.iter().rposition(|weight| *weight > 0_f64).unwrap();
+        for (index, weight) in weights.iter().enumerate() {
+            if pick < *weight {
+                lobe = index;
+                break;
+            }
+            pick -= weight;
+        }
+        let probability = weights[lobe] / total;
+
+        Some(match lobe {
+            0 => {
+                let coat = lobes.coat;
+                let (wi, h) = coat.sample_reflection(&wo, sampler)?;
+                let fresnel = schlick_scalar(Self::CLEARCOAT_F0, Vec3::dot(&wo, &h));
+                specular(
+                    &uvw,
+                    hit_record,
+                    &wi,
+                    white
+                        * (fresnel * lobes.clearcoat * coat.masking_weight(&wo, &wi) / probability),
+                )
+            }
+            1 => {
+                let (wi, h) = distribution.sample_reflection(&wo, sampler)?;
+                let fresnel = schlick(&lobes.f0, Vec3::dot(&wo, &h));
+                specular(
+                    &uvw,
+                    hit_record,
+                    &wi,
+                    fresnel
+                        * (lobes.specular_weight * distribution.masking_weight(&wo, &wi)
+                            / probability),
+                )
+            }
+            2 => {
+                let (wi, reflected) = distribution.sample_dielectric(
+                    &wo,
+                    1_f64 / lobes.index_of_refraction,
+                    sampler,
+                )?;
+                let color = if reflected { white } else { lobes.base_color };
+                specular(
+                    &uvw,
+                    hit_record,
+                    &wi,
+                    color
+                        * (lobes.glass_weight * distribution.masking_weight(&wo, &wi)
+                            / probability),
+                )
+            }
+            _ => Scattered::Diffuse {
+                attenuation: lobes.diffuse_color * (lobes.diffuse_weight / probability),
+                pdf: Box::new(CosinePDF::new(&hit_record.against_normal_unit)),
+            },
+        })
+    }
+    /// Only the diffuse lobe is sampled as [Scattered::Diffuse], so this is its cosine lobe
+    fn scattering_pdf(&self, _ray: &Ray, hit_record: &HitRecord, scattered: &Ray) -> f64 {
+        let cosine_theta = Vec3::dot(
+            &hit_record.against_normal_unit,
+            &scattered.direction.unit_vector(),
+        );
+        f64::max(0_f64, cosine_theta / PI)
+    }
+    /// The clearcoat, the specular and the reflection off the glass (The diffuse lobe is already in scattering_pdf)
+    fn glossy(&self, ray: &Ray, hit_record: &HitRecord, scattered: &Ray) -> Vec3 {
+        let uvw = ONB::new(&hit_record.against_normal_unit);
+        let wo = to_local(&uvw, &(-ray.direction.unit_vector()));
+        let wi = to_local(&uvw, &scattered.direction.unit_vector());
+        if wo.z() <= 0_f64 || !hit_record.front_face {
+            return Vec3::default();
+        }
+        let lobes = self.lobes(hit_record, &wo);
+        let mut glossy = Vec3::default();
+        if let Some((reflection, h)) = lobes.coat.reflection(&wo, &wi) {
+            let fresnel = schlick_scalar(Self::CLEARCOAT_F0, Vec3::dot(&wo, &h));
+            glossy += Vec3::new_int(1, 1, 1) * (lobes.clearcoat * fresnel * reflection);
+        }
+        if let Some((reflection, h)) = lobes.distribution.reflection(&wo, &wi) {
+            let cos_theta = Vec3::dot(&wo, &h);
+            let glass_fresnel =
+                Dielectric::reflectance(cos_theta, 1_f64 / lobes.index_of_refraction);
+            glossy += schlick(&lobes.f0, cos_theta) * (lobes.specular_weight * reflection)
+                + Vec3::new_int(1, 1, 1) * (lobes.glass_weight * glass_fresnel * reflection);
+        }
+        glossy
+    }
+    fn albedo(&self, hit_record: &HitRecord) -> Vec3 {
+        self.base_color.value(hit_record)
+    }
+}
+
+fn unit(value: f64) -> f64 {
+    value.clamp(0_f64, 1_f64)
+}
+fn lerp(a: &Vec3, b: &Vec3, t: f64) -> Vec3 {
+    (1_f64 - t) * a.clone() + t * b.clone()
+}
+/// Schlick's approximation of the Fresnel reflectance, from the reflectance at normal incidence
+fn schlick(f0: &Vec3, cos_theta: f64) -> Vec3 {
+    f0.clone()
+        + (Vec3::new_int(1, 1, 1) - f0.clone()) * (1_f64 - cos_theta.clamp(0_f64, 1_f64)).powi(5)
+}
+fn schlick_scalar(f0: f64, cos_theta: f64) -> f64 {
+    f0 + (1_f64 - f0) * (1_f64 - cos_theta.clamp(0_f64, 1_f64)).powi(5)
+}
+/// Scattered ray of the lobes sampled like [Conductor](crate::Conductor), with the direction in the shading space
+fn specular(uvw: &ONB, hit_record: &HitRecord, wi: &Vec3, attenuation: Vec3) -> Scattered {
+    Scattered::Specular {
+        attenuation,
+        ray: Ray {
+            origin: hit_record.p.clone(),
+            direction: uvw.local(wi),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{materials::test::TestScatterable, IndependentSampler, Materials, SolidColor};
+
+    /// Averages the attenuation of many scatterings of a ray coming in at 60 degrees
+    /// Returns it along with the fraction of rays refracted into the surface and the fraction scattered diffusely
+    fn average_scatter(material: &PrincipledBSDF) -> (Vec3, f64, f64) {
+        let ray = Ray {
+            origin: Vec3::new(3_f64.sqrt(), 0_f64, 1_f64),
+            direction: Vec3::new(-(3_f64.sqrt()), 0_f64, -1_f64),
+        };
+        let hit_record = HitRecord::new(
+            &ray,
+            &Vec3::new_int(0, 0, 1),
+            1_f64,
+            Materials::ScatterMaterial(Arc::new(TestScatterable {})),
+            0.5,
+            0.5,
+        );
+        let mut sampler = IndependentSampler::new(0);
+        let count = 20000;
+        let mut total = Vec3::default();
+        let mut refracted = 0;
+        let mut diffuse = 0;
+        for _ in 0..count {
+            match material.scatter(&ray, &hit_record, &mut sampler) {
+                Some(Scattered::Specular { attenuation, ray }) => {
+                    total += attenuation;
+                    if ray.direction.z() < 0_f64 {
+                        refracted += 1;
+                    }
+                }
+                Some(Scattered::Diffuse { attenuation, .. }) => {
+                    total += attenuation;
+                    diffuse += 1;
+                }
+                None => {}
+            }
+        }
+        (
+            total / count as f64,
+            refracted as f64 / count as f64,
+            diffuse as f64 / count as f64,
+        )
+    }
+
+    #[test]
+    fn test_principled_lobes() {
+        // A white metal loses only the light bouncing between the microfacets
+        let (albedo, refracted, diffuse) = average_scatter(&PrincipledBSDF {
+            base_color: Vec3::new_int(1, 1, 1).into(),
+            metallic: 1.0.into(),
+            roughness: 0.3.into(),
+            ..Default::default()
+        });
+        assert!(albedo
+            .xyz()
+            .iter()
+            .all(|value| *value > 0.9 && *value <= 1.0));
+        assert_eq!((refracted, diffuse), (0.0, 0.0));
+
+        // A plastic is mostly diffuse, and never gains energy
+        let (albedo, refracted, diffuse) = average_scatter(&PrincipledBSDF {
+            base_color: Parameter::Texture(Arc::new(SolidColor {
+                color: Vec3::new(0.8, 0.2, 0.2),
+            })),
+            clearcoat: 1.0.into(),
+            sheen: 1.0.into(),
+            ..Default::default()
+        });
+        assert!(albedo.x() <= 1.0 && albedo.y() < albedo.x());
+        assert!(refracted == 0.0 && diffuse > 0.5);
+
+        // Glass mostly refracts, a little less as it gets metallic
+        let glass = |metallic: f64| PrincipledBSDF {
+            base_color: Vec3::new_int(1, 1, 1).into(),
+            roughness: 0.1.into(),
+            transmission: 1.0.into(),
+            metallic: metallic.into(),
+            ..Default::default()
+        };
+        let (albedo, refracted, diffuse) = average_scatter(&glass(0.0));
+        assert!((albedo.x() - 1.0).abs() < 0.05);
+        assert!(refracted > 0.8 && diffuse == 0.0);
+        assert!(average_scatter(&glass(0.5)).1 < refracted);
+    }
+}
@@ -0,0 +1,216 @@
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+use crate::{
+    sampler::{hash, to_unit_f64},
+    Vec3,
+};
+
+/// Perlin gradient noise (The improved version from 2002), a smooth pseudo-random field over space
+/// The same seed always gives the same field
+pub struct Perlin {
+    /// Shuffled 0..256, repeated twice so lookups of (index + 1) do not need to wrap
+    permutation: Vec<usize>,
+}
+impl Perlin {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut permutation: Vec<usize> = (0..256).collect();
+        permutation.shuffle(&mut rng);
+        permutation.extend_from_within(..);
+        Self { permutation }
+    }
+    /// Noise at p, in [-1, 1] (0 on every integer point)
+    // Implementation Details:
+    // Each corner of the unit cube around p gets a gradient picked by hashing its coordinates through the permutation
+    // The dot products of the gradients with the offsets to p are blended with the quintic fade curve 6t^5 - 15t^4 + 10t^3
+    pub fn noise(&self, p: &Vec3) -> f64 {
+        let cell = p.xyz().map(|value| value.floor());
+        let [x, y, z] = [0, 1, 2].map(|axis| (cell[axis] as i64 & 255) as usize);
+        let [fx, fy, fz] = [0, 1, 2].map(|axis| p[axis] - cell[axis]);
+        let [u, v, w] = [fx, fy, fz].map(fade);
+
+        let permutation = &self.permutation;
+        let a = permutation[x] + y;
+        let aa = permutation[a] + z;
+        let ab = permutation[a + 1] + z;
+        let b = permutation[x + 1] + y;
+        let ba = permutation[b] + z;
+        let bb = permutation[b + 1] + z;
+
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(
+                    u,
+                    gradient(permutation[aa], fx, fy, fz),
+                    gradient(permutation[ba], fx - 1_f64, fy, fz),
+                ),
+                lerp(
+                    u,
+                    gradient(permutation[ab], fx, fy - 1_f64, fz),
+                    gradient(permutation[bb], fx - 1_f64, fy - 1_f64, fz),
+                ),
+            ),
+            lerp(
+                v,
+                lerp(
+                    u,
+                    gradient(permutation[aa + 1], fx, fy, fz - 1_f64),
+                    gradient(permutation[ba + 1], fx - 1_f64, fy, fz - 1_f64),
+                ),
+                lerp(
+                    u,
+                    gradient(permutation[ab + 1], fx, fy - 1_f64, fz - 1_f64),
+                    gradient(permutation[bb + 1], fx - 1_f64, fy - 1_f64, fz - 1_f64),
+                ),
+            ),
+        )
+    }
+    /// Fractal Brownian motion, octaves of noise each with double the frequency and half the amplitude
+    /// Normalized by the total amplitude, so it stays in [-1, 1]
+    pub fn fbm(&self, p: &Vec3, octaves: u32) -> f64 {
+        let mut sum = 0_f64;
+        let mut total_amplitude = 0_f64;
+        let mut amplitude = 1_f64;
+        let mut frequency = 1_f64;
+        for _ in 0..octaves.max(1) {
+            sum += amplitude * self.noise(&(p.clone() * frequency));
+            total_amplitude += amplitude;
+            amplitude *= 0.5;
+            frequency *= 2_f64;
+        }
+        sum / total_amplitude
+    }
+    /// Like [Perlin::fbm], but summing the absolute value of each octave, which gives creases where the noise crosses 0
+    /// Normalized into [0, 1]
+    pub fn turbulence(&self, p: &Vec3, octaves: u32) -> f64 {
+        let mut sum = 0_f64;
+        let mut total_amplitude = 0_f64;
+        let mut amplitude = 1_f64;
+        let mut frequency = 1_f64;
+        for _ in 0..octaves.max(1) {
+            sum += amplitude * self.noise(&(p.clone() * frequency)).abs();
+            total_amplitude += amplitude;
+            amplitude *= 0.5;
+            frequency *= 2_f64;
+        }
+        sum / total_amplitude
+    }
+}
+
+/// Worley (cellular) noise, space is split into the cells of random feature points (One in each unit cube)
+/// The same seed always gives the same points
+pub struct Worley {
+    seed: u64,
+}
+/// Distances from a point to its closest feature points
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WorleySample {
+    /// Distance to the closest feature point
+    pub f1: f64,
+    /// Distance to the second closest feature point (f2 - f1 is 0 on the borders between cells)
+    pub f2: f64,
+    /// Random value in [0, 1) of the closest feature point, the same over its whole cell
+    pub cell_value: f64,
+}
+impl Worley {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+    // Implementation Details:
+    // A feature point can only be closer than the one in the cube of p if it is in one of the 26 neighbouring cubes
+    pub fn sample(&self, p: &Vec3) -> WorleySample {
+        let cell = p.xyz().map(|value| value.floor() as i64);
+        let mut closest = WorleySample {
+            f1: f64::INFINITY,
+            f2: f64::INFINITY,
+            cell_value: 0_f64,
+        };
+        for dz in -1..=1 {
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    let neighbour = [cell[0] + dx, cell[1] + dy, cell[2] + dz];
+                    let cell_hash = hash(&[
+                        self.seed,
+                        neighbour[0] as u64,
+                        neighbour[1] as u64,
+                        neighbour[2] as u64,
+                    ]);
+                    let offset = [1_u64, 2, 3].map(|axis| to_unit_f64(hash(&[cell_hash, axis])));
+                    let feature_point = Vec3::new(
+                        neighbour[0] as f64 + offset[0],
+                        neighbour[1] as f64 + offset[1],
+                        neighbour[2] as f64 + offset[2],
+                    );
+                    let distance = (feature_point - p.clone()).length();
+                    if distance < closest.f1 {
+                        closest.f2 = closest.f1;
+                        closest.f1 = distance;
+                        closest.cell_value = to_unit_f64(cell_hash);
+                    } else if distance < closest.f2 {
+                        closest.f2 = distance;
+                    }
+                }
+            }
+        }
+        closest
+    }
+}
+
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6_f64 - 15_f64) + 10_f64)
+}
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    a + t * (b - a)
+}
+/// Dot product of (x, y, z) with one of the 12 gradients along the edges of a cube, picked by the hash
+fn gradient(hash: usize, x: f64, y: f64, z: f64) -> f64 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_perlin_noise() {
+        let perlin = Perlin::new(1);
+        assert_eq!(perlin.noise(&Vec3::new_int(3, -2, 7)), 0_f64);
+        let p = Vec3::new(1.3, 2.7, -0.4);
+        assert_eq!(perlin.noise(&p), Perlin::new(1).noise(&p));
+        assert_ne!(perlin.noise(&p), Perlin::new(2).noise(&p));
+        // Smooth, close points have close values
+        let nearby = p.clone() + Vec3::new(1e-4, 0_f64, 0_f64);
+        assert!((perlin.noise(&p) - perlin.noise(&nearby)).abs() < 1e-3);
+        for i in 0..1000 {
+            let q = Vec3::new(i as f64 * 0.37, i as f64 * 0.11, i as f64 * -0.23);
+            assert!(perlin.noise(&q).abs() <= 1_f64);
+            assert!(perlin.fbm(&q, 5).abs() <= 1_f64);
+            assert!((0_f64..=1_f64).contains(&perlin.turbulence(&q, 5)));
+        }
+    }
+
+    #[test]
+    fn test_worley_noise() {
+        let worley = Worley::new(1);
+        let p = Vec3::new(2.3, -0.6, 5.1);
+        let sample = worley.sample(&p);
+        assert!(sample.f1 <= sample.f2);
+        // Every point is within a cube diagonal of the feature point of its cube
+        assert!(sample.f1 < 3_f64.sqrt());
+        assert_eq!(sample, Worley::new(1).sample(&p));
+        // Points close together are (almost always) in the same cell
+        let nearby = worley.sample(&(p.clone() + Vec3::new(1e-6, 0_f64, 0_f64)));
+        assert_eq!(sample.cell_value, nearby.cell_value);
+    }
+}
@@ -1,11 +1,33 @@
+mod capsule;
+mod cone;
+mod constant_medium;
+mod cuboid;
+mod cylinder;
+mod heterogeneous_medium;
+mod mesh;
 mod planar;
+mod plane;
 mod rotation;
 mod simple_constructors;
 mod sphere;
+mod tagged;
+mod torus;
+mod transformed;
 mod translation;
 
+pub use capsule::Capsule;
+pub use cone::Cone;
+pub use constant_medium::ConstantMedium;
+pub use cuboid::Cuboid;
+pub use cylinder::Cylinder;
+pub use heterogeneous_medium::{HeterogeneousMedium, MediumParams};
+pub use mesh::{MeshData, TriangleMesh};
 pub use planar::{Disk, Quad, Triangle};
+pub use plane::Plane;
 pub use rotation::Rotation;
 pub use simple_constructors::construct_planar_quad_box;
 pub use sphere::Sphere;
+pub use tagged::Tagged;
+pub use torus::Torus;
+pub use transformed::Transformed;
 pub use translation::Translation;
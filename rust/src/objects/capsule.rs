@@ -0,0 +1,202 @@
+use std::{f64::consts::PI, fmt::Display};
+
+use crate::{
+    helper::solve_quadratic, HitRecord, Hittable, HittableWithBBox, Interval, Materials, Ray, Vec3,
+    AABB,
+};
+
+use super::cylinder::around_y_axis;
+
+/// Capsule standing on the Y axis, a tube from base up to base + height with a hemisphere on each end
+/// So the whole capsule is height + 2 * radius tall, from base - radius to base + height + radius
+/// To tilt it, wrap it in a [Transformed](crate::Transformed)
+///
+/// u goes around the Y axis (as on a [Sphere](crate::Sphere)) and v goes up along the surface, from the bottom pole (0) to the top pole (1)
+///
+/// Not sampled as a light, an emissive capsule is only found by bounced rays
+pub struct Capsule {
+    /// Center of the bottom hemisphere
+    pub base: Vec3,
+    pub radius: f64,
+    pub height: f64,
+    pub material: Materials,
+    bbox: AABB,
+}
+impl Capsule {
+    pub fn new(base: Vec3, radius: f64, height: f64, material: Materials) -> Self {
+        let bbox = AABB::from_points(
+            base.clone() - Vec3::new(radius, radius, radius),
+            base.clone() + Vec3::new(radius, height + radius, radius),
+        );
+        Self {
+            base,
+            radius,
+            height,
+            material,
+            bbox,
+        }
+    }
+}
+impl Hittable for Capsule {
+    // Implementation Details:
+    // Works relative to base, the tube only counts between the ends, and each sphere only beyond its end
+    // The capsule is convex, so the closest of those hits is the surface (from outside or inside)
+    fn hit(&self, _ray: &Ray, valid_t_interval: Interval) -> Option<HitRecord> {
+        let origin = _ray.origin.clone() - self.base.clone();
+        let direction = &_ray.direction;
+        let radius_squared = self.radius * self.radius;
+        let mut valid_t_interval = valid_t_interval;
+        let mut closest: Option<(f64, Vec3)> = None;
+
+        if let Some((root1, root2)) = solve_quadratic(
+            direction.x() * direction.x() + direction.z() * direction.z(),
+            2_f64 * (origin.x() * direction.x() + origin.z() * direction.z()),
+            origin.x() * origin.x() + origin.z() * origin.z() - radius_squared,
+        ) {
+            for t in [root1, root2] {
+                let y = origin.y() + t * direction.y();
+                if valid_t_interval.surrounds(t) && 0_f64 <= y && y <= self.height {
+                    closest = Some((t, Vec3::new(0_f64, y, 0_f64)));
+                    valid_t_interval.max = t;
+                    break;
+                }
+            }
+        }
+        for (end_y, beyond) in [(0_f64, -1_f64), (self.height, 1_f64)] {
+            let end = Vec3::new(0_f64, end_y, 0_f64);
+            let to_origin = origin.clone() - end.clone();
+            if let Some((root1, root2)) = solve_quadratic(
+                direction.length_squared(),
+                2_f64 * Vec3::dot(&to_origin, direction),
+                to_origin.length_squared() - radius_squared,
+            ) {
+                for t in [root1, root2] {
+                    let y = origin.y() + t * direction.y();
+                    if valid_t_interval.surrounds(t) && (y - end_y) * beyond >= 0_f64 {
+                        closest = Some((t, end.clone()));
+                        valid_t_interval.max = t;
+                        break;
+                    }
+                }
+            }
+        }
+
+        // The normal points away from the closest point on the segment between the ends
+        let (t, axis_point) = closest?;
+        let p = origin + t * direction.clone();
+        let outward_normal_unit = (p.clone() - axis_point) / self.radius;
+        let u = around_y_axis(&p);
+        // Length along the surface from the bottom pole, over the quarter circle of each hemisphere
+        let quarter = PI / 2_f64 * self.radius;
+        let along = if p.y() < 0_f64 {
+            quarter - self.radius * (-outward_normal_unit.y()).clamp(-1_f64, 1_f64).asin()
+        } else if p.y() > self.height {
+            quarter
+                + self.height
+                + self.radius * outward_normal_unit.y().clamp(-1_f64, 1_f64).asin()
+        } else {
+            quarter + p.y()
+        };
+        let v = along / (2_f64 * quarter + self.height);
+        Some(HitRecord::new(
+            _ray,
+            &outward_normal_unit,
+            t,
+            self.material.clone(),
+            u,
+            v,
+        ))
+    }
+    fn material(&self) -> Option<&Materials> {
+        Some(&self.material)
+    }
+}
+impl HittableWithBBox for Capsule {
+    fn bbox(&self) -> &AABB {
+        &self.bbox
+    }
+}
+impl Display for Capsule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Capsule(base: {}, radius: {}, height: {})",
+            self.base, self.radius, self.height
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::materials::test::TestScatterable;
+
+    #[test]
+    fn test_capsule_hit() {
+        let mat = Materials::ScatterMaterial(Arc::new(TestScatterable {}));
+        let capsule = Capsule::new(Vec3::new_int(0, 0, 0), 1_f64, 2_f64, mat);
+        let interval = Interval {
+            min: 0.001,
+            max: f64::INFINITY,
+        };
+        let assert_near = |a: f64, b: f64| assert!((a - b).abs() < 1e-9, "{} is not {}", a, b);
+        // The surface is PI / 2 + 2 + PI / 2 long from pole to pole
+        let length = PI + 2_f64;
+
+        // Side
+        let hr = capsule
+            .hit(
+                &Ray {
+                    origin: Vec3::new_int(0, 1, 4),
+                    direction: Vec3::new_int(0, 0, -1),
+                },
+                interval,
+            )
+            .unwrap();
+        assert_eq!(hr.t, 3_f64);
+        assert_eq!(hr.against_normal_unit, Vec3::new_int(0, 0, 1));
+        assert_near(hr.u, 0.25);
+        assert_near(hr.v, (PI / 2_f64 + 1_f64) / length);
+
+        // Down onto the top pole, and out through the bottom pole from inside
+        let down = Ray {
+            origin: Vec3::new_int(0, 5, 0),
+            direction: Vec3::new_int(0, -1, 0),
+        };
+        let hr = capsule.hit(&down, interval).unwrap();
+        assert_eq!(hr.t, 2_f64);
+        assert_eq!(hr.against_normal_unit, Vec3::new_int(0, 1, 0));
+        assert_near(hr.v, 1_f64);
+        let hr = capsule
+            .hit(
+                &down,
+                Interval {
+                    min: 3_f64,
+                    max: f64::INFINITY,
+                },
+            )
+            .unwrap();
+        assert_eq!(hr.t, 6_f64);
+        assert!(!hr.front_face);
+        assert_near(hr.v, 0_f64);
+
+        // Onto the bottom hemisphere at 45 degrees
+        let offset = 0.5_f64.sqrt();
+        let hr = capsule
+            .hit(
+                &Ray {
+                    origin: Vec3::new(3_f64, -offset, 0_f64),
+                    direction: Vec3::new_int(-1, 0, 0),
+                },
+                interval,
+            )
+            .unwrap();
+        assert_near(hr.t, 3_f64 - offset);
+        assert!(
+            (hr.against_normal_unit.clone() - Vec3::new(offset, -offset, 0_f64)).length() < 1e-9
+        );
+        assert_near(hr.v, PI / 4_f64 / length);
+    }
+}
@@ -0,0 +1,186 @@
+use std::fmt::Display;
+
+use crate::{
+    helper::solve_quadratic, HitRecord, Hittable, HittableWithBBox, Interval, Materials, Ray, Vec3,
+    AABB,
+};
+
+use super::cylinder::{around_y_axis, hit_cap};
+
+/// Cone standing on the Y axis, narrowing from a disk of radius at base to its tip at base + height
+/// A closed cone is capped with a disk at the base, an open one is just the slanted side
+/// To tilt it, wrap it in a [Transformed](crate::Transformed)
+///
+/// u goes around the Y axis (as on a [Sphere](crate::Sphere)) and v goes up the side, from 0 at the base to 1 at the tip
+/// The cap is mapped like a [Disk](crate::Disk) lying in the XZ plane
+///
+/// Not sampled as a light, so an emissive cone is only found by bounced rays
+pub struct Cone {
+    /// Center of the bottom disk
+    pub base: Vec3,
+    pub radius: f64,
+    pub height: f64,
+    pub closed: bool,
+    pub material: Materials,
+    bbox: AABB,
+}
+impl Cone {
+    pub fn new(base: Vec3, radius: f64, height: f64, closed: bool, material: Materials) -> Self {
+        let bbox = AABB::from_points(
+            base.clone() - Vec3::new(radius, 0_f64, radius),
+            base.clone() + Vec3::new(radius, height, radius),
+        )
+        .pad();
+        Self {
+            base,
+            radius,
+            height,
+            closed,
+            material,
+            bbox,
+        }
+    }
+}
+impl Hittable for Cone {
+    // Implementation Details:
+    // Works relative to base, where the side is x^2 + z^2 = (k * (height - y))^2 with k = radius / height
+    // That is a double cone meeting at the tip, so only hits between the base and the tip count
+    fn hit(&self, _ray: &Ray, valid_t_interval: Interval) -> Option<HitRecord> {
+        let origin = _ray.origin.clone() - self.base.clone();
+        let direction = &_ray.direction;
+        let k_squared = (self.radius / self.height).powi(2);
+        let below_tip = self.height - origin.y();
+        let mut valid_t_interval = valid_t_interval;
+        let mut closest: Option<(f64, Vec3, f64, f64)> = None;
+
+        if let Some((root1, root2)) = solve_quadratic(
+            direction.x() * direction.x() + direction.z() * direction.z()
+                - k_squared * direction.y() * direction.y(),
+            2_f64
+                * (origin.x() * direction.x()
+                    + origin.z() * direction.z()
+                    + k_squared * below_tip * direction.y()),
+            origin.x() * origin.x() + origin.z() * origin.z() - k_squared * below_tip * below_tip,
+        ) {
+            for t in [root1, root2] {
+                let p = origin.clone() + t * direction.clone();
+                if valid_t_interval.surrounds(t) && 0_f64 <= p.y() && p.y() <= self.height {
+                    // Gradient of the implicit surface
+                    let outward_normal_unit =
+                        Vec3::new(p.x(), k_squared * (self.height - p.y()), p.z()).unit_vector();
+                    closest = Some((
+                        t,
+                        outward_normal_unit,
+                        around_y_axis(&p),
+                        p.y() / self.height,
+                    ));
+                    valid_t_interval.max = t;
+                    break;
+                }
+            }
+        }
+        if self.closed {
+            if let Some((t, p)) = hit_cap(&origin, direction, 0_f64, self.radius, valid_t_interval)
+            {
+                closest = Some((
+                    t,
+                    Vec3::new(0_f64, -1_f64, 0_f64),
+                    (p.x() / self.radius + 1_f64) / 2_f64,
+                    (p.z() / self.radius + 1_f64) / 2_f64,
+                ));
+            }
+        }
+
+        let (t, outward_normal_unit, u, v) = closest?;
+        Some(HitRecord::new(
+            _ray,
+            &outward_normal_unit,
+            t,
+            self.material.clone(),
+            u,
+            v,
+        ))
+    }
+    fn material(&self) -> Option<&Materials> {
+        Some(&self.material)
+    }
+}
+impl HittableWithBBox for Cone {
+    fn bbox(&self) -> &AABB {
+        &self.bbox
+    }
+}
+impl Display for Cone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Cone(base: {}, radius: {}, height: {}, closed: {})",
+            self.base, self.radius, self.height, self.closed
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::materials::test::TestScatterable;
+
+    #[test]
+    fn test_cone_hit() {
+        let mat = Materials::ScatterMaterial(Arc::new(TestScatterable {}));
+        let cone = Cone::new(Vec3::new_int(0, 0, 0), 1_f64, 2_f64, true, mat);
+        let interval = Interval {
+            min: 0.001,
+            max: f64::INFINITY,
+        };
+
+        // Side, halfway up the cone where the radius is 0.5
+        let hr = cone
+            .hit(
+                &Ray {
+                    origin: Vec3::new_int(3, 1, 0),
+                    direction: Vec3::new_int(-1, 0, 0),
+                },
+                interval,
+            )
+            .unwrap();
+        assert!((hr.t - 2.5).abs() < 1e-12);
+        let expected_normal = Vec3::new(2_f64, 1_f64, 0_f64).unit_vector();
+        assert!((hr.against_normal_unit.clone() - expected_normal).length() < 1e-12);
+        assert!(hr.front_face);
+        assert!((hr.u - 0.5).abs() < 1e-12 && (hr.v - 0.5).abs() < 1e-12);
+
+        // Straight up through the base and out of the side
+        let up = Ray {
+            origin: Vec3::new(0.5, -1_f64, 0_f64),
+            direction: Vec3::new_int(0, 1, 0),
+        };
+        let hr = cone.hit(&up, interval).unwrap();
+        assert_eq!(hr.t, 1_f64);
+        assert_eq!(hr.against_normal_unit, Vec3::new_int(0, -1, 0));
+        let hr = cone
+            .hit(
+                &up,
+                Interval {
+                    min: 1.5,
+                    max: f64::INFINITY,
+                },
+            )
+            .unwrap();
+        assert!((hr.t - 2_f64).abs() < 1e-12);
+        assert!(!hr.front_face);
+
+        // The mirrored cone above the tip is not part of it
+        assert!(cone
+            .hit(
+                &Ray {
+                    origin: Vec3::new_int(3, 3, 0),
+                    direction: Vec3::new_int(-1, 0, 0),
+                },
+                interval,
+            )
+            .is_none());
+    }
+}
@@ -0,0 +1,178 @@
+use std::sync::Arc;
+
+use crate::{
+    sampler::RayRandom, ColorTexture, HitRecord, Hittable, HittableWithBBox, Interval, Isotropic,
+    Materials, Ray, Vec3, AABB,
+};
+
+/// Volume of constant density (i.e. smoke, fog or a subsurface look) filling the inside of the boundary
+/// Rays passing through are scattered at a random distance, more likely the denser the medium is, by an [Isotropic] phase function
+/// The boundary has to be closed and convex (A ray enters and leaves it once), i.e. a [Sphere](crate::Sphere) or a box
+pub struct ConstantMedium {
+    boundary: Arc<dyn HittableWithBBox>,
+    /// Chance of scattering per unit of distance
+    density: f64,
+    phase_function: Materials,
+}
+impl ConstantMedium {
+    pub fn new(
+        boundary: Arc<dyn HittableWithBBox>,
+        density: f64,
+        albedo: Arc<dyn ColorTexture>,
+    ) -> Self {
+        Self {
+            boundary,
+            density,
+            phase_function: Materials::ScatterMaterial(Arc::new(Isotropic { albedo })),
+        }
+    }
+    /// Part of the interval where the ray is inside the boundary (Not before the ray origin)
+    fn inside(&self, _ray: &Ray, valid_t_interval: Interval) -> Option<Interval> {
+        let entry = self.boundary.hit(
+            _ray,
+            Interval {
+                min: f64::NEG_INFINITY,
+                max: f64::INFINITY,
+            },
+        )?;
+        let exit = self.boundary.hit(
+            _ray,
+            Interval {
+                min: entry.t + 0.0001,
+                max: f64::INFINITY,
+            },
+        )?;
+        let inside = Interval {
+            min: entry.t.max(valid_t_interval.min).max(0_f64),
+            max: exit.t.min(valid_t_interval.max),
+        };
+        if inside.min >= inside.max {
+            return None;
+        }
+        Some(inside)
+    }
+}
+impl Hittable for ConstantMedium {
+    // Implementation Details:
+    // Finds where the ray enters and leaves the boundary (Starting from the inside if the ray origin is in the medium), and samples the scattering distance in between
+    // Hittable::hit does not get a sampler, so the uniform number comes from a hash of the ray (refer to RayRandom)
+    fn hit(&self, _ray: &Ray, valid_t_interval: Interval) -> Option<HitRecord> {
+        let inside = self.inside(_ray, valid_t_interval)?;
+
+        let ray_length = _ray.direction.length();
+        let distance_inside_boundary = inside.size() * ray_length;
+        // 1 - u is in (0, 1], so the log is finite
+        let hit_distance = -(1_f64 - RayRandom::new(_ray).get_1d()).ln() / self.density;
+        if hit_distance > distance_inside_boundary {
+            return None;
+        }
+
+        let t = inside.min + hit_distance / ray_length;
+        let mut hit_record = HitRecord::new(
+            _ray,
+            &Vec3::new_int(1, 0, 0),
+            t,
+            self.phase_function.clone(),
+            0_f64,
+            0_f64,
+        );
+        // The normal and the face are arbitrary, the phase function does not use them
+        hit_record.against_normal_unit = Vec3::new_int(1, 0, 0);
+        hit_record.front_face = true;
+        Some(hit_record)
+    }
+    fn material(&self) -> Option<&Materials> {
+        Some(&self.phase_function)
+    }
+    fn hit_surface(&self, _ray: &Ray, _valid_t_interval: Interval) -> Option<HitRecord> {
+        None
+    }
+    /// Exact, the density is the same everywhere
+    fn transmittance(&self, _ray: &Ray, valid_t_interval: Interval) -> f64 {
+        match self.inside(_ray, valid_t_interval) {
+            Some(inside) => (-self.density * inside.size() * _ray.direction.length()).exp(),
+            None => 1_f64,
+        }
+    }
+    fn contains_media(&self) -> bool {
+        true
+    }
+}
+impl HittableWithBBox for ConstantMedium {
+    fn bbox(&self) -> &AABB {
+        self.boundary.bbox()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{materials::test::TestScatterable, SolidColor, Sphere};
+
+    fn medium(density: f64) -> ConstantMedium {
+        let boundary = Sphere::new(
+            Vec3::new_int(0, 0, 0),
+            1_f64,
+            Materials::ScatterMaterial(Arc::new(TestScatterable {})),
+        );
+        ConstantMedium::new(
+            Arc::new(boundary),
+            density,
+            Arc::new(SolidColor {
+                color: Vec3::new(0.5, 0.5, 0.5),
+            }),
+        )
+    }
+
+    #[test]
+    fn test_constant_medium_hit() {
+        let interval = Interval {
+            min: 0.001,
+            max: f64::INFINITY,
+        };
+        // A very dense medium scatters right at the boundary
+        let ray = Ray {
+            origin: Vec3::new_int(0, 0, -5),
+            direction: Vec3::new_int(0, 0, 2),
+        };
+        let hit_record = medium(1e9).hit(&ray, interval).unwrap();
+        assert!((hit_record.p.z() + 1_f64).abs() < 1e-6);
+        assert!(matches!(hit_record.material, Materials::ScatterMaterial(_)));
+
+        // Through 2 units of a medium of density 0.5, 1 - e^-1 of the rays scatter
+        let medium = medium(0.5);
+        let count = 10000;
+        let scattered = (0..count)
+            .filter(|index| {
+                let ray = Ray {
+                    origin: Vec3::new(*index as f64 * 1e-7, 0_f64, -5_f64),
+                    direction: Vec3::new_int(0, 0, 1),
+                };
+                let hit_record = medium.hit(&ray, interval);
+                // The same ray always scatters at the same point
+                assert_eq!(
+                    hit_record.as_ref().map(|hit_record| hit_record.t),
+                    medium.hit(&ray, interval).map(|hit_record| hit_record.t)
+                );
+                hit_record.is_some()
+            })
+            .count();
+        let expected = 1_f64 - (-1_f64).exp();
+        assert!((scattered as f64 / count as f64 - expected).abs() < 0.02);
+        let ray = Ray {
+            origin: Vec3::new_int(0, 0, -5),
+            direction: Vec3::new_int(0, 0, 1),
+        };
+        assert!((medium.transmittance(&ray, interval) - (-1_f64).exp()).abs() < 1e-9);
+        assert!(medium.hit_surface(&ray, interval).is_none());
+
+        // Rays starting inside the medium scatter after their origin
+        let ray = Ray {
+            origin: Vec3::new_int(0, 0, 0),
+            direction: Vec3::new_int(1, 0, 0),
+        };
+        if let Some(hit_record) = medium.hit(&ray, interval) {
+            assert!(hit_record.t > 0.001 && hit_record.t < 1_f64);
+        }
+    }
+}
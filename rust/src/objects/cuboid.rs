@@ -0,0 +1,267 @@
+use std::fmt::Display;
+
+use crate::{HitRecord, Hittable, HittableWithBBox, Interval, Materials, Ray, Sampler, Vec3, AABB};
+
+/// Axis aligned box between two opposite corners, hit with a single test (instead of the 6 [Quad](crate::Quad) from [construct_planar_quad_box](crate::construct_planar_quad_box))
+/// To rotate it, wrap it in a [Transformed](crate::Transformed)
+///
+/// Every face is mapped to [0, 1] on its own, as seen from outside the box with v going up
+/// The top face is seen from above with v going towards -Z, and the bottom face from below with v going towards +Z
+///
+/// As a light, a face is picked by its area and a point is picked uniformly on it (the same as the 6 quads)
+pub struct Cuboid {
+    /// Corner with the smallest coordinates
+    pub min: Vec3,
+    /// Corner with the largest coordinates
+    pub max: Vec3,
+    pub material: Materials,
+    bbox: AABB,
+}
+impl Cuboid {
+    /// a and b are any two opposite corners
+    pub fn new(a: Vec3, b: Vec3, material: Materials) -> Self {
+        let min = a.retain_min(&b);
+        let max = a.retain_max(&b);
+        Self {
+            bbox: AABB::from_points(min.clone(), max.clone()).pad(),
+            min,
+            max,
+            material,
+        }
+    }
+    /// (u, v) of the point on the face facing the sign along the axis, refer to [Cuboid]
+    fn face_uv(&self, p: &Vec3, axis: usize, sign: f64) -> (f64, f64) {
+        let relative = |axis: usize| (p[axis] - self.min[axis]) / (self.max[axis] - self.min[axis]);
+        let (x, y, z) = (relative(0), relative(1), relative(2));
+        match (axis, sign > 0_f64) {
+            (0, true) => (1_f64 - z, y),
+            (0, false) => (z, y),
+            (1, true) => (x, 1_f64 - z),
+            (1, false) => (x, z),
+            (_, true) => (x, y),
+            (_, false) => (1_f64 - x, y),
+        }
+    }
+    /// Area of the two faces facing along the axis, each
+    fn face_area(&self, axis: usize) -> f64 {
+        let size = self.max.clone() - self.min.clone();
+        size[(axis + 1) % 3] * size[(axis + 2) % 3]
+    }
+}
+impl Hittable for Cuboid {
+    // Implementation Details:
+    // Slab test (as in [AABB::hit]), keeping track of which axis the ray enters and leaves the box through
+    // The entry is the hit from outside, the exit the hit from inside
+    fn hit(&self, _ray: &Ray, valid_t_interval: Interval) -> Option<HitRecord> {
+        let (mut entry_t, mut entry_axis) = (f64::NEG_INFINITY, 0);
+        let (mut exit_t, mut exit_axis) = (f64::INFINITY, 0);
+        for axis in 0..3 {
+            let inv_direction = 1_f64 / _ray.direction[axis];
+            let t0 = (self.min[axis] - _ray.origin[axis]) * inv_direction;
+            let t1 = (self.max[axis] - _ray.origin[axis]) * inv_direction;
+            let (near, far) = if inv_direction < 0_f64 {
+                (t1, t0)
+            } else {
+                (t0, t1)
+            };
+            if near > entry_t {
+                (entry_t, entry_axis) = (near, axis);
+            }
+            if far < exit_t {
+                (exit_t, exit_axis) = (far, axis);
+            }
+        }
+        if entry_t > exit_t {
+            return None;
+        }
+        // The face the ray enters through faces against the ray, the one it leaves through along it
+        let (t, axis, sign) = if valid_t_interval.surrounds(entry_t) {
+            (entry_t, entry_axis, -_ray.direction[entry_axis].signum())
+        } else if valid_t_interval.surrounds(exit_t) {
+            (exit_t, exit_axis, _ray.direction[exit_axis].signum())
+        } else {
+            return None;
+        };
+
+        let mut outward_normal_unit = Vec3::new_int(0, 0, 0);
+        outward_normal_unit[axis] = sign;
+        let (u, v) = self.face_uv(&_ray.at(t), axis, sign);
+        Some(HitRecord::new(
+            _ray,
+            &outward_normal_unit,
+            t,
+            self.material.clone(),
+            u,
+            v,
+        ))
+    }
+    // Implementation Details:
+    // A direction crosses the surface twice (entering and leaving), and sampling either point gives that direction
+    // So the pdf is the sum of the solid angle pdf of both points (only the exit when origin is inside)
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        let ray = Ray {
+            origin: origin.clone(),
+            direction: direction.clone(),
+        };
+        let mut pdf = 0_f64;
+        let mut valid_t_interval = Interval {
+            min: 0.001,
+            max: f64::INFINITY,
+        };
+        while let Some(hit_record) = self.hit(&ray, valid_t_interval) {
+            let distance_squared = hit_record.t * hit_record.t * direction.length_squared();
+            let cosine =
+                (Vec3::dot(direction, &hit_record.against_normal_unit) / direction.length()).abs();
+            if cosine >= 1e-8_f64 {
+                pdf += distance_squared / (cosine * self.area());
+            }
+            valid_t_interval.min = hit_record.t;
+        }
+        pdf
+    }
+    fn random(&self, origin: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        // Walk the 6 faces until the picked area is used up
+        let mut remaining = sampler.get_1d() * self.area();
+        let mut face = (2, 1);
+        'faces: for axis in 0..3 {
+            for side in 0..2 {
+                remaining -= self.face_area(axis);
+                if remaining < 0_f64 {
+                    face = (axis, side);
+                    break 'faces;
+                }
+            }
+        }
+        let (axis, side) = face;
+        let (a, b) = sampler.get_2d();
+        let mut point = Vec3::new_int(0, 0, 0);
+        point[axis] = if side == 0 {
+            self.min[axis]
+        } else {
+            self.max[axis]
+        };
+        for (other, fraction) in [((axis + 1) % 3, a), ((axis + 2) % 3, b)] {
+            point[other] = self.min[other] + fraction * (self.max[other] - self.min[other]);
+        }
+        point - origin.clone()
+    }
+    fn material(&self) -> Option<&Materials> {
+        Some(&self.material)
+    }
+    fn area(&self) -> f64 {
+        2_f64 * (0..3).map(|axis| self.face_area(axis)).sum::<f64>()
+    }
+}
+impl HittableWithBBox for Cuboid {
+    fn bbox(&self) -> &AABB {
+        &self.bbox
+    }
+}
+impl Display for Cuboid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cuboid(min: {}, max: {})", self.min, self.max)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::materials::test::TestScatterable;
+
+    #[test]
+    fn test_cuboid_hit() {
+        let mat = Materials::ScatterMaterial(Arc::new(TestScatterable {}));
+        let cuboid = Cuboid::new(Vec3::new_int(2, 1, 1), Vec3::new_int(-2, -1, -1), mat);
+        assert_eq!(cuboid.min, Vec3::new_int(-2, -1, -1));
+        let interval = Interval {
+            min: 0.001,
+            max: f64::INFINITY,
+        };
+        let hit = |origin: Vec3, direction: Vec3, interval: Interval| {
+            cuboid.hit(&Ray { origin, direction }, interval).unwrap()
+        };
+
+        // Front face, then the back face from inside
+        let hr = hit(
+            Vec3::new(1_f64, 0.5, 5_f64),
+            Vec3::new_int(0, 0, -1),
+            interval,
+        );
+        assert_eq!(hr.t, 4_f64);
+        assert_eq!(hr.against_normal_unit, Vec3::new_int(0, 0, 1));
+        assert!(hr.front_face);
+        assert_eq!((hr.u, hr.v), (0.75, 0.75));
+        let hr = hit(
+            Vec3::new(1_f64, 0.5, 5_f64),
+            Vec3::new_int(0, 0, -1),
+            Interval {
+                min: 5_f64,
+                max: f64::INFINITY,
+            },
+        );
+        assert_eq!(hr.t, 6_f64);
+        assert_eq!(hr.against_normal_unit, Vec3::new_int(0, 0, 1));
+        assert!(!hr.front_face);
+        assert_eq!((hr.u, hr.v), (0.25, 0.75));
+
+        // Right side, top and bottom
+        let hr = hit(
+            Vec3::new(5_f64, 0.5, 0.5),
+            Vec3::new_int(-1, 0, 0),
+            interval,
+        );
+        assert_eq!(hr.against_normal_unit, Vec3::new_int(1, 0, 0));
+        assert_eq!((hr.u, hr.v), (0.25, 0.75));
+        let hr = hit(
+            Vec3::new(1_f64, 5_f64, 0.5),
+            Vec3::new_int(0, -1, 0),
+            interval,
+        );
+        assert_eq!(hr.against_normal_unit, Vec3::new_int(0, 1, 0));
+        assert_eq!((hr.u, hr.v), (0.75, 0.25));
+        let hr = hit(
+            Vec3::new(1_f64, -5_f64, 0.5),
+            Vec3::new_int(0, 1, 0),
+            interval,
+        );
+        assert_eq!(hr.against_normal_unit, Vec3::new_int(0, -1, 0));
+        assert_eq!((hr.u, hr.v), (0.75, 0.75));
+
+        assert!(cuboid
+            .hit(
+                &Ray {
+                    origin: Vec3::new_int(5, 5, 0),
+                    direction: Vec3::new_int(-1, 0, 0),
+                },
+                interval,
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn test_cuboid_light_sampling() {
+        use crate::IndependentSampler;
+
+        let mat = Materials::ScatterMaterial(Arc::new(TestScatterable {}));
+        let cuboid = Cuboid::new(Vec3::new_int(-1, -1, -1), Vec3::new_int(1, 2, 3), mat);
+        assert_eq!(cuboid.area(), 2_f64 * (12_f64 + 8_f64 + 6_f64));
+
+        // Straight through the front and back faces
+        let origin = Vec3::new_int(0, 0, 5);
+        let pdf = cuboid.pdf_value(&origin, &Vec3::new_int(0, 0, -2));
+        assert!((pdf - (2_f64 * 2_f64 + 6_f64 * 6_f64) / 52_f64).abs() < 1e-9);
+        assert_eq!(cuboid.pdf_value(&origin, &Vec3::new_int(0, 0, 1)), 0_f64);
+
+        let mut sampler = IndependentSampler::new(7);
+        for _ in 0..100 {
+            let direction = cuboid.random(&origin, &mut sampler);
+            let p = origin.clone() + direction.clone();
+            let on_face =
+                (0..3).any(|axis| p[axis] == cuboid.min[axis] || p[axis] == cuboid.max[axis]);
+            assert!(on_face, "{} is not on the surface", p);
+            assert!(cuboid.pdf_value(&origin, &direction) > 0_f64);
+        }
+    }
+}
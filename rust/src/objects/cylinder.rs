@@ -0,0 +1,211 @@
+use std::{f64::consts::PI, fmt::Display};
+
+use crate::{
+    helper::solve_quadratic, HitRecord, Hittable, HittableWithBBox, Interval, Materials, Ray, Vec3,
+    AABB,
+};
+
+/// Cylinder standing on the Y axis, from base up to base + height
+/// A closed cylinder is capped with a disk at both ends, an open one is just the tube (So its inside shows through the ends)
+/// To tilt it, wrap it in a [Transformed](crate::Transformed)
+///
+/// u goes around the Y axis (as on a [Sphere](crate::Sphere)) and v goes up the side
+/// The caps are mapped like a [Disk](crate::Disk) lying in the XZ plane
+///
+/// It is not sampled as a light, an emissive cylinder only lights what finds it by bouncing (noisy, use a [Cuboid](crate::Cuboid) or quads instead)
+pub struct Cylinder {
+    /// Center of the bottom end
+    pub base: Vec3,
+    pub radius: f64,
+    pub height: f64,
+    pub closed: bool,
+    pub material: Materials,
+    bbox: AABB,
+}
+impl Cylinder {
+    pub fn new(base: Vec3, radius: f64, height: f64, closed: bool, material: Materials) -> Self {
+        let bbox = AABB::from_points(
+            base.clone() - Vec3::new(radius, 0_f64, radius),
+            base.clone() + Vec3::new(radius, height, radius),
+        )
+        .pad();
+        Self {
+            base,
+            radius,
+            height,
+            closed,
+            material,
+            bbox,
+        }
+    }
+}
+impl Hittable for Cylinder {
+    // Implementation Details:
+    // Works relative to base, checking the tube (x^2 + z^2 = r^2 between the ends) and then each cap
+    // Every surface hit shrinks the interval, so the closest one is kept
+    fn hit(&self, _ray: &Ray, valid_t_interval: Interval) -> Option<HitRecord> {
+        let origin = _ray.origin.clone() - self.base.clone();
+        let direction = &_ray.direction;
+        let mut valid_t_interval = valid_t_interval;
+        let mut closest: Option<(f64, Vec3, f64, f64)> = None;
+
+        if let Some((root1, root2)) = solve_quadratic(
+            direction.x() * direction.x() + direction.z() * direction.z(),
+            2_f64 * (origin.x() * direction.x() + origin.z() * direction.z()),
+            origin.x() * origin.x() + origin.z() * origin.z() - self.radius * self.radius,
+        ) {
+            for t in [root1, root2] {
+                let p = origin.clone() + t * direction.clone();
+                if valid_t_interval.surrounds(t) && 0_f64 <= p.y() && p.y() <= self.height {
+                    let outward_normal_unit = Vec3::new(p.x(), 0_f64, p.z()) / self.radius;
+                    closest = Some((
+                        t,
+                        outward_normal_unit,
+                        around_y_axis(&p),
+                        p.y() / self.height,
+                    ));
+                    valid_t_interval.max = t;
+                    break;
+                }
+            }
+        }
+        if self.closed {
+            for (cap_y, normal_y) in [(0_f64, -1_f64), (self.height, 1_f64)] {
+                if let Some((t, p)) =
+                    hit_cap(&origin, direction, cap_y, self.radius, valid_t_interval)
+                {
+                    closest = Some((
+                        t,
+                        Vec3::new(0_f64, normal_y, 0_f64),
+                        (p.x() / self.radius + 1_f64) / 2_f64,
+                        (p.z() / self.radius + 1_f64) / 2_f64,
+                    ));
+                    valid_t_interval.max = t;
+                }
+            }
+        }
+
+        let (t, outward_normal_unit, u, v) = closest?;
+        Some(HitRecord::new(
+            _ray,
+            &outward_normal_unit,
+            t,
+            self.material.clone(),
+            u,
+            v,
+        ))
+    }
+    fn material(&self) -> Option<&Materials> {
+        Some(&self.material)
+    }
+}
+impl HittableWithBBox for Cylinder {
+    fn bbox(&self) -> &AABB {
+        &self.bbox
+    }
+}
+impl Display for Cylinder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Cylinder(base: {}, radius: {}, height: {}, closed: {})",
+            self.base, self.radius, self.height, self.closed
+        )
+    }
+}
+
+/// [0, 1] of the angle of p around the Y axis from X=-1, the same u as a [Sphere](crate::Sphere)
+pub(super) fn around_y_axis(p: &Vec3) -> f64 {
+    (f64::atan2(-p.z(), p.x()) + PI) / (2_f64 * PI)
+}
+
+/// Hit (t, point) of the ray (relative to the center of the disk) with a disk of radius lying flat at height y
+pub(super) fn hit_cap(
+    origin: &Vec3,
+    direction: &Vec3,
+    y: f64,
+    radius: f64,
+    valid_t_interval: Interval,
+) -> Option<(f64, Vec3)> {
+    if direction.y() == 0_f64 {
+        return None;
+    }
+    let t = (y - origin.y()) / direction.y();
+    if !valid_t_interval.surrounds(t) {
+        return None;
+    }
+    let p = origin.clone() + t * direction.clone();
+    if p.x() * p.x() + p.z() * p.z() > radius * radius {
+        return None;
+    }
+    Some((t, p))
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::materials::test::TestScatterable;
+
+    #[test]
+    fn test_cylinder_hit() {
+        let mat = Materials::ScatterMaterial(Arc::new(TestScatterable {}));
+        let cylinder = Cylinder::new(Vec3::new_int(0, -1, 0), 1_f64, 2_f64, true, mat.clone());
+        let interval = Interval {
+            min: 0.001,
+            max: f64::INFINITY,
+        };
+
+        // Side, halfway up from the front
+        let hr = cylinder
+            .hit(
+                &Ray {
+                    origin: Vec3::new_int(0, 0, 3),
+                    direction: Vec3::new_int(0, 0, -1),
+                },
+                interval,
+            )
+            .unwrap();
+        assert_eq!(hr.t, 2_f64);
+        assert_eq!(hr.against_normal_unit, Vec3::new_int(0, 0, 1));
+        assert!(hr.front_face);
+        assert_eq!((hr.u, hr.v), (0.25, 0.5));
+
+        // Top cap, and the bottom cap from inside
+        let down = Ray {
+            origin: Vec3::new(0.5, 3_f64, 0_f64),
+            direction: Vec3::new_int(0, -1, 0),
+        };
+        let hr = cylinder.hit(&down, interval).unwrap();
+        assert_eq!(hr.t, 2_f64);
+        assert_eq!(hr.against_normal_unit, Vec3::new_int(0, 1, 0));
+        assert_eq!((hr.u, hr.v), (0.75, 0.5));
+        let hr = cylinder
+            .hit(
+                &down,
+                Interval {
+                    min: 2.5,
+                    max: f64::INFINITY,
+                },
+            )
+            .unwrap();
+        assert_eq!(hr.t, 4_f64);
+        assert!(!hr.front_face);
+
+        // Without caps, the ray goes straight through the ends
+        let open = Cylinder::new(Vec3::new_int(0, -1, 0), 1_f64, 2_f64, false, mat);
+        assert!(open.hit(&down, interval).is_none());
+        let hr = open
+            .hit(
+                &Ray {
+                    origin: Vec3::new_int(0, 3, 0),
+                    direction: Vec3::new_int(1, -3, 0),
+                },
+                interval,
+            )
+            .unwrap();
+        assert_eq!(hr.p, Vec3::new_int(1, 0, 0));
+        assert!(!hr.front_face);
+    }
+}
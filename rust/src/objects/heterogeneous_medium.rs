@@ -0,0 +1,248 @@
+use std::sync::Arc;
+
+use crate::{
+    materials::Emittable, sampler::RayRandom, volume::DensityField, HenyeyGreenstein, HitRecord,
+    Hittable, HittableWithBBox, Interval, Materials, Ray, Vec3, AABB,
+};
+
+/// Coefficients of a [HeterogeneousMedium], per unit of distance at a density of 1 (They are scaled by the density field)
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediumParams {
+    /// Chance of the light being absorbed
+    pub absorption: f64,
+    /// Chance of the light being scattered
+    pub scattering: f64,
+    /// Light emitted where the medium absorbs (i.e. fire), so denser parts glow brighter
+    pub emission: Vec3,
+    /// Tint of the scattered light
+    pub color: Vec3,
+    /// Henyey-Greenstein g, refer to [HenyeyGreenstein]
+    pub anisotropy: f64,
+}
+impl Default for MediumParams {
+    fn default() -> Self {
+        Self {
+            absorption: 0_f64,
+            scattering: 1_f64,
+            emission: Vec3::default(),
+            color: Vec3::new_int(1, 1, 1),
+            anisotropy: 0_f64,
+        }
+    }
+}
+
+/// Medium whose density varies over space (i.e. clouds or smoke plumes), filling the box between 2 corners
+/// Rays are tracked through it with delta tracking (Which stops at absorptions and scatterings), and shadow rays with ratio tracking
+/// The distances are in the space of the medium, so scaling it with a [Transformed](crate::Transformed) also scales how thick it is
+///
+/// The emission is only found by rays passing through, it is not sampled like the lights
+pub struct HeterogeneousMedium {
+    density: Arc<dyn DensityField>,
+    bbox: AABB,
+    params: MediumParams,
+    /// Upper bound of the extinction (absorption + scattering) anywhere in the box
+    majorant: f64,
+    phase_function: Materials,
+    emission: Materials,
+}
+impl HeterogeneousMedium {
+    /// The density field is stretched to fill the box between the corners a and b
+    pub fn new(density: Arc<dyn DensityField>, a: Vec3, b: Vec3, params: MediumParams) -> Self {
+        let majorant = density.max_density() * (params.absorption + params.scattering);
+        Self {
+            density,
+            bbox: AABB::from_points(a, b),
+            phase_function: Materials::ScatterMaterial(Arc::new(HenyeyGreenstein {
+                g: params.anisotropy,
+                albedo: params.color.clone(),
+            })),
+            emission: Materials::LightMaterial(Arc::new(Emission {
+                color: params.emission.clone(),
+            })),
+            majorant,
+            params,
+        }
+    }
+    /// Density at the world point p
+    fn density_at(&self, p: &Vec3) -> f64 {
+        let local = Vec3::new(
+            (p.x() - self.bbox.x.min) / self.bbox.x.size(),
+            (p.y() - self.bbox.y.min) / self.bbox.y.size(),
+            (p.z() - self.bbox.z.min) / self.bbox.z.size(),
+        );
+        self.density.density(&local)
+    }
+    /// Part of the interval where the ray is inside the box (Not before the ray origin)
+    fn inside(&self, _ray: &Ray, valid_t_interval: Interval) -> Option<Interval> {
+        if self.majorant <= 0_f64 {
+            return None;
+        }
+        self.bbox.hit(
+            _ray,
+            Interval {
+                min: valid_t_interval.min.max(0_f64),
+                max: valid_t_interval.max,
+            },
+        )
+    }
+    /// Distance (in t) to the next tentative collision, sampled against the majorant
+    fn step(&self, _ray: &Ray, random: &mut RayRandom) -> f64 {
+        // 1 - u is in (0, 1], so the log is finite
+        -(1_f64 - random.get_1d()).ln() / (self.majorant * _ray.direction.length())
+    }
+}
+impl Hittable for HeterogeneousMedium {
+    // Implementation Details:
+    // Delta tracking: collisions are sampled as if the whole box had the majorant as its extinction
+    // At each one, the real extinction picks between absorbing (absorption / majorant), scattering (scattering / majorant) or continuing through a null collision
+    // This is unbiased, no matter how the density varies between the collisions
+    fn hit(&self, _ray: &Ray, valid_t_interval: Interval) -> Option<HitRecord> {
+        let inside = self.inside(_ray, valid_t_interval)?;
+        let mut random = RayRandom::new(_ray);
+        let mut t = inside.min;
+        loop {
+            t += self.step(_ray, &mut random);
+            if t >= inside.max {
+                return None;
+            }
+            let density = self.density_at(&_ray.at(t));
+            let event = random.get_1d() * self.majorant;
+            let material = if event < density * self.params.absorption {
+                &self.emission
+            } else if event < density * (self.params.absorption + self.params.scattering) {
+                &self.phase_function
+            } else {
+                continue;
+            };
+            // The normal is arbitrary, the phase function does not use it
+            return Some(HitRecord::new(
+                _ray,
+                &Vec3::new_int(1, 0, 0),
+                t,
+                material.clone(),
+                0_f64,
+                0_f64,
+            ));
+        }
+    }
+    fn hit_surface(&self, _ray: &Ray, _valid_t_interval: Interval) -> Option<HitRecord> {
+        None
+    }
+    // Implementation Details:
+    // Ratio tracking: the same tentative collisions as delta tracking, but each multiplies the transmittance by the chance of a null collision
+    // Once the transmittance gets low, russian roulette ends most of the walks early (And boosts the rest to stay unbiased)
+    fn transmittance(&self, _ray: &Ray, valid_t_interval: Interval) -> f64 {
+        let inside = match self.inside(_ray, valid_t_interval) {
+            Some(inside) => inside,
+            None => return 1_f64,
+        };
+        let mut random = RayRandom::new(_ray);
+        let mut transmittance = 1_f64;
+        let mut t = inside.min;
+        loop {
+            t += self.step(_ray, &mut random);
+            if t >= inside.max {
+                return transmittance;
+            }
+            let extinction =
+                self.density_at(&_ray.at(t)) * (self.params.absorption + self.params.scattering);
+            transmittance *= 1_f64 - extinction / self.majorant;
+            if transmittance < 0.1 {
+                if random.get_1d() < 0.75 {
+                    return 0_f64;
+                }
+                transmittance /= 0.25;
+            }
+        }
+    }
+    fn contains_media(&self) -> bool {
+        true
+    }
+}
+impl HittableWithBBox for HeterogeneousMedium {
+    fn bbox(&self) -> &AABB {
+        &self.bbox
+    }
+}
+
+/// Colored light of the absorptions
+struct Emission {
+    color: Vec3,
+}
+impl Emittable for Emission {
+    fn emit(&self, _ray: &Ray, _hit_record: &HitRecord) -> Vec3 {
+        self.average_emission()
+    }
+    fn average_emission(&self) -> Vec3 {
+        self.color.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::VoxelGrid;
+
+    /// 1x1x1 box, where the density goes from 0 at x = 0 to 1 at x = 1
+    fn ramp_medium(params: MediumParams) -> HeterogeneousMedium {
+        let values = (0..64).map(|x| (x as f32 + 0.5) / 64_f32).collect();
+        HeterogeneousMedium::new(
+            Arc::new(VoxelGrid::new([64, 1, 1], values).unwrap()),
+            Vec3::new_int(0, 0, 0),
+            Vec3::new_int(1, 1, 1),
+            params,
+        )
+    }
+
+    #[test]
+    fn test_heterogeneous_medium_tracking() {
+        let medium = ramp_medium(MediumParams {
+            absorption: 1.0,
+            scattering: 1.0,
+            ..Default::default()
+        });
+        let interval = Interval {
+            min: 0.001,
+            max: f64::INFINITY,
+        };
+        // Along x the optical depth is the integral of 2x, so the exact transmittance is e^-1
+        let expected = (-1_f64).exp();
+        let count = 20000;
+        let mut hits = 0;
+        let mut absorbed = 0;
+        let mut transmittance = 0_f64;
+        for index in 0..count {
+            let ray = Ray {
+                origin: Vec3::new(-1_f64, 0.5, 0.25 + 0.5 * index as f64 / count as f64),
+                direction: Vec3::new_int(2, 0, 0),
+            };
+            if let Some(hit_record) = medium.hit(&ray, interval) {
+                hits += 1;
+                assert!((0.0..=1.0).contains(&hit_record.p.x()));
+                if let Materials::LightMaterial(_) = hit_record.material {
+                    absorbed += 1;
+                }
+            }
+            transmittance += medium.transmittance(&ray, interval);
+        }
+        let passed = (count - hits) as f64 / count as f64;
+        assert!((passed - expected).abs() < 0.02, "{} {}", passed, expected);
+        assert!((transmittance / count as f64 - expected).abs() < 0.02);
+        // Absorption and scattering are equally likely
+        assert!((absorbed as f64 / hits as f64 - 0.5).abs() < 0.03);
+
+        // Surfaces only, so shadow rays skip it
+        let ray = Ray {
+            origin: Vec3::new(-1_f64, 0.5, 0.5),
+            direction: Vec3::new_int(1, 0, 0),
+        };
+        assert!(medium.hit_surface(&ray, interval).is_none());
+        // Empty media are skipped
+        let empty = ramp_medium(MediumParams {
+            scattering: 0.0,
+            ..Default::default()
+        });
+        assert!(empty.hit(&ray, interval).is_none());
+        assert_eq!(empty.transmittance(&ray, interval), 1.0);
+    }
+}
@@ -0,0 +1,317 @@
+use std::{fmt::Display, sync::Arc};
+
+use crate::{
+    HitRecord, Hittable, HittableWithBBox, Interval, Materials, Ray, Sampler, Vec3, AABB, BVH,
+};
+
+mod obj;
+
+/// Indexed vertex data of a triangle mesh
+/// To create the hittable mesh use [TriangleMesh::new] or load it from a file with [TriangleMesh::from_obj]
+#[derive(Default)]
+pub struct MeshData {
+    /// Vertex positions
+    pub positions: Vec<Vec3>,
+    /// Vertex normals, either empty (flat shading) or the same length as positions
+    pub normals: Vec<Vec3>,
+    /// Vertex (u, v) texture cordinates, either empty or the same length as positions
+    pub uvs: Vec<(f64, f64)>,
+    /// Indices into the vertex data, for the 3 corners of each triangle (Counter clockwise is the front face)
+    pub triangles: Vec<[usize; 3]>,
+    /// Index into materials for each triangle
+    pub triangle_materials: Vec<usize>,
+    /// Materials used by the triangles of the mesh
+    pub materials: Vec<Materials>,
+}
+
+/// Triangle mesh, which shares the vertex data between all of its triangles
+/// The triangles are stored in their own [BVH], so the mesh can be added into the world like any other object
+pub struct TriangleMesh {
+    triangles: Vec<Arc<dyn HittableWithBBox>>,
+    bvh: BVH,
+}
+impl TriangleMesh {
+    /// Creates the mesh and builds its BVH
+    /// Panics if any of the indices are out of bounds of the vertex data or materials
+    pub fn new(mesh_data: MeshData) -> Self {
+        let vertex_count = mesh_data.positions.len();
+        if !mesh_data.normals.is_empty() && mesh_data.normals.len() != vertex_count {
+            panic!("Mesh normals should be empty or have one normal per vertex");
+        }
+        if !mesh_data.uvs.is_empty() && mesh_data.uvs.len() != vertex_count {
+            panic!("Mesh uvs should be empty or have one uv per vertex");
+        }
+        if mesh_data.triangle_materials.len() != mesh_data.triangles.len() {
+            panic!("Mesh should have a material index for every triangle");
+        }
+        for (triangle, material_index) in mesh_data
+            .triangles
+            .iter()
+            .zip(&mesh_data.triangle_materials)
+        {
+            if triangle.iter().any(|index| *index >= vertex_count) {
+                panic!("Mesh triangle {:?} indexes out of the vertices", triangle);
+            }
+            if *material_index >= mesh_data.materials.len() {
+                panic!("Mesh material index {} is out of bounds", material_index);
+            }
+        }
+
+        let mesh_data = Arc::new(mesh_data);
+        let triangles: Vec<Arc<dyn HittableWithBBox>> = (0..mesh_data.triangles.len())
+            .map(|index| {
+                Arc::new(MeshTriangle::new(mesh_data.clone(), index)) as Arc<dyn HittableWithBBox>
+            })
+            .collect();
+        Self {
+            bvh: BVH::from_hittables_list(triangles.clone()),
+            triangles,
+        }
+    }
+    /// All the triangles of the mesh, as individual hittables
+    /// Useful to add the emissive triangles of the mesh into a [LightList](crate::LightList)
+    pub fn triangles(&self) -> &[Arc<dyn HittableWithBBox>] {
+        &self.triangles
+    }
+    /// Number of triangles in the mesh
+    pub fn len(&self) -> usize {
+        self.triangles.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.triangles.is_empty()
+    }
+}
+impl Hittable for TriangleMesh {
+    fn hit(&self, _ray: &Ray, valid_t_interval: Interval) -> Option<HitRecord> {
+        self.bvh.hit(_ray, valid_t_interval)
+    }
+}
+impl HittableWithBBox for TriangleMesh {
+    fn bbox(&self) -> &AABB {
+        self.bvh.bbox()
+    }
+}
+impl Display for TriangleMesh {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "TriangleMesh(triangles: {})", self.triangles.len())
+    }
+}
+
+/// A single triangle of a [TriangleMesh], referencing the shared vertex data
+struct MeshTriangle {
+    mesh_data: Arc<MeshData>,
+    /// Index of the triangle in the mesh
+    index: usize,
+    bbox: AABB,
+}
+impl MeshTriangle {
+    fn new(mesh_data: Arc<MeshData>, index: usize) -> Self {
+        let [p0, p1, p2] = mesh_data.triangles[index].map(|i| mesh_data.positions[i].clone());
+        // Important Note:
+        // bbox requires padding as triangles can lie on the axis (Size = 0)
+        let bbox = AABB::from_points(
+            p0.retain_min(&p1).retain_min(&p2),
+            p0.retain_max(&p1).retain_max(&p2),
+        )
+        .pad();
+        Self {
+            mesh_data,
+            index,
+            bbox,
+        }
+    }
+    fn vertices(&self) -> [&Vec3; 3] {
+        self.mesh_data.triangles[self.index].map(|i| &self.mesh_data.positions[i])
+    }
+    fn material(&self) -> &Materials {
+        &self.mesh_data.materials[self.mesh_data.triangle_materials[self.index]]
+    }
+}
+impl Hittable for MeshTriangle {
+    // Implementation Details:
+    // Moller-Trumbore intersection, solving for the barycentric cordinates (b1, b2) and t at the same time
+    fn hit(&self, _ray: &Ray, valid_t_interval: Interval) -> Option<HitRecord> {
+        let [p0, p1, p2] = self.vertices();
+        let edge_1 = p1.clone() - p0.clone();
+        let edge_2 = p2.clone() - p0.clone();
+
+        let p_vec = Vec3::cross(&_ray.direction, &edge_2);
+        let determinant = Vec3::dot(&edge_1, &p_vec);
+        // Ray is parallel to the triangle
+        if determinant.abs() < 1e-12_f64 {
+            return None;
+        }
+        let inv_determinant = 1_f64 / determinant;
+
+        let t_vec = _ray.origin.clone() - p0.clone();
+        let b1 = Vec3::dot(&t_vec, &p_vec) * inv_determinant;
+        if !(0_f64..=1_f64).contains(&b1) {
+            return None;
+        }
+        let q_vec = Vec3::cross(&t_vec, &edge_1);
+        let b2 = Vec3::dot(&_ray.direction, &q_vec) * inv_determinant;
+        if b2 < 0_f64 || b1 + b2 > 1_f64 {
+            return None;
+        }
+        let t = Vec3::dot(&edge_2, &q_vec) * inv_determinant;
+        if !valid_t_interval.surrounds(t) {
+            return None;
+        }
+        let b0 = 1_f64 - b1 - b2;
+        let [i0, i1, i2] = self.mesh_data.triangles[self.index];
+
+        let geometric_normal = Vec3::cross(&edge_1, &edge_2).unit_vector();
+        let outward_normal_unit = if self.mesh_data.normals.is_empty() {
+            geometric_normal
+        } else {
+            let normals = &self.mesh_data.normals;
+            let shading_normal =
+                (b0 * normals[i0].clone() + b1 * normals[i1].clone() + b2 * normals[i2].clone())
+                    .unit_vector();
+            // Keep the shading normal on the same side as the winding of the triangle
+            if Vec3::dot(&shading_normal, &geometric_normal) < 0_f64 {
+                -shading_normal
+            } else {
+                shading_normal
+            }
+        };
+        let (u, v) = if self.mesh_data.uvs.is_empty() {
+            (b1, b2)
+        } else {
+            let uvs = &self.mesh_data.uvs;
+            (
+                b0 * uvs[i0].0 + b1 * uvs[i1].0 + b2 * uvs[i2].0,
+                b0 * uvs[i0].1 + b1 * uvs[i1].1 + b2 * uvs[i2].1,
+            )
+        };
+
+        Some(HitRecord::new(
+            _ray,
+            &outward_normal_unit,
+            t,
+            self.material().clone(),
+            u,
+            v,
+        ))
+    }
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        let ray = Ray {
+            origin: origin.clone(),
+            direction: direction.clone(),
+        };
+        let hit_record = match self.hit(
+            &ray,
+            Interval {
+                min: 0.001,
+                max: f64::INFINITY,
+            },
+        ) {
+            Some(hit_record) => hit_record,
+            None => return 0_f64,
+        };
+        let [p0, p1, p2] = self.vertices();
+        let normal =
+            Vec3::cross(&(p1.clone() - p0.clone()), &(p2.clone() - p0.clone())).unit_vector();
+        let distance_squared = hit_record.t * hit_record.t * direction.length_squared();
+        let cosine = (Vec3::dot(direction, &normal) / direction.length()).abs();
+        if cosine < 1e-8_f64 {
+            return 0_f64;
+        }
+        distance_squared / (cosine * self.area())
+    }
+    fn random(&self, origin: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        let (b1, b2) = sampler.get_2d();
+        // Reflect the samples from the other half of the parallelogram back into the triangle
+        let (b1, b2) = if b1 + b2 > 1_f64 {
+            (1_f64 - b1, 1_f64 - b2)
+        } else {
+            (b1, b2)
+        };
+        let [p0, p1, p2] = self.vertices();
+        let point = (1_f64 - b1 - b2) * p0.clone() + b1 * p1.clone() + b2 * p2.clone();
+        point - origin.clone()
+    }
+    fn material(&self) -> Option<&Materials> {
+        Some(MeshTriangle::material(self))
+    }
+    fn area(&self) -> f64 {
+        let [p0, p1, p2] = self.vertices();
+        0.5_f64 * Vec3::cross(&(p1.clone() - p0.clone()), &(p2.clone() - p0.clone())).length()
+    }
+}
+impl HittableWithBBox for MeshTriangle {
+    fn bbox(&self) -> &AABB {
+        &self.bbox
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::materials::test::TestScatterable;
+
+    fn unit_square() -> TriangleMesh {
+        TriangleMesh::new(MeshData {
+            positions: vec![
+                Vec3::new_int(0, 0, 0),
+                Vec3::new_int(1, 0, 0),
+                Vec3::new_int(1, 1, 0),
+                Vec3::new_int(0, 1, 0),
+            ],
+            uvs: vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)],
+            triangles: vec![[0, 1, 2], [0, 2, 3]],
+            triangle_materials: vec![0, 0],
+            materials: vec![Materials::ScatterMaterial(Arc::new(TestScatterable {}))],
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_triangle_mesh_hit() {
+        let mesh = unit_square();
+        assert_eq!(mesh.len(), 2);
+
+        let hit_record = mesh
+            .hit(
+                &Ray {
+                    origin: Vec3::new(0.25, 0.75, 1.0),
+                    direction: Vec3::new_int(0, 0, -1),
+                },
+                Interval {
+                    min: 0.001,
+                    max: f64::INFINITY,
+                },
+            )
+            .unwrap();
+        assert_eq!(hit_record.t, 1.0);
+        assert!(hit_record.front_face);
+        assert_eq!(hit_record.against_normal_unit, Vec3::new_int(0, 0, 1));
+        assert!((hit_record.u - 0.25).abs() < 1e-12);
+        assert!((hit_record.v - 0.75).abs() < 1e-12);
+
+        assert!(mesh
+            .hit(
+                &Ray {
+                    origin: Vec3::new(1.5, 0.5, 1.0),
+                    direction: Vec3::new_int(0, 0, -1),
+                },
+                Interval {
+                    min: 0.001,
+                    max: f64::INFINITY,
+                },
+            )
+            .is_none());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_triangle_mesh_out_of_bounds() {
+        TriangleMesh::new(MeshData {
+            positions: vec![Vec3::new_int(0, 0, 0)],
+            triangles: vec![[0, 1, 2]],
+            triangle_materials: vec![0],
+            materials: vec![Materials::ScatterMaterial(Arc::new(TestScatterable {}))],
+            ..Default::default()
+        });
+    }
+}
@@ -0,0 +1,242 @@
+use std::{path::Path, sync::Arc};
+
+use crate::{
+    ColorTexture, Dielectric, Emissive, Image, Lambertain, Materials, Metal, SolidColor, Vec3,
+};
+
+use super::{MeshData, TriangleMesh};
+
+impl TriangleMesh {
+    /// Loads a Wavefront `.obj` file (and the `.mtl` files it references) into a single mesh
+    /// Faces with more than 3 vertices are triangulated
+    /// Triangles without a material (or if the `.mtl` file can not be loaded) use default_material
+    ///
+    /// `.mtl` materials are mapped to the closest material we have
+    /// - `Ke` (emission) -> [Emissive]
+    /// - `illum` 4, 6, 7 or `d` < 1 (transparent) -> [Dielectric] with `Ni` as the index of refraction
+    /// - `illum` 3 or 5 (reflective) -> [Metal] with `Ks` as the albedo and fuzziness from `Ns`
+    /// - Others -> [Lambertain] with `Kd` or the `map_Kd` image as the albedo (Falling back to `Kd` with a warning if the image can not be loaded)
+    pub fn from_obj(path: &str, default_material: Materials) -> Result<Self, tobj::LoadError> {
+        let (models, mtl_materials) = tobj::load_obj(
+            path,
+            &tobj::LoadOptions {
+                triangulate: true,
+                single_index: true,
+                ..Default::default()
+            },
+        )?;
+        // Texture paths in the .mtl file are relative to the .obj file
+        let obj_directory = Path::new(path).parent().unwrap_or(Path::new(""));
+        let mut materials = vec![default_material];
+        if let Ok(mtl_materials) = mtl_materials {
+            materials.extend(
+                mtl_materials
+                    .iter()
+                    .map(|mtl_material| material_from_mtl(mtl_material, obj_directory)),
+            );
+        }
+
+        let mut mesh_data = MeshData {
+            materials,
+            ..Default::default()
+        };
+        for model in models {
+            let mesh = model.mesh;
+            let vertex_offset = mesh_data.positions.len();
+            let vertex_count = mesh.positions.len() / 3;
+            mesh_data.positions.extend(
+                mesh.positions
+                    .chunks_exact(3)
+                    .map(|p| Vec3::new(p[0] as f64, p[1] as f64, p[2] as f64)),
+            );
+
+            // Each model might not have normals or uvs, so fill them in to keep one per vertex
+            if !mesh.normals.is_empty() || !mesh_data.normals.is_empty() {
+                mesh_data.normals.resize(vertex_offset, Vec3::default());
+                if mesh.normals.is_empty() {
+                    mesh_data
+                        .normals
+                        .resize(vertex_offset + vertex_count, Vec3::default());
+                } else {
+                    mesh_data.normals.extend(
+                        mesh.normals
+                            .chunks_exact(3)
+                            .map(|n| Vec3::new(n[0] as f64, n[1] as f64, n[2] as f64)),
+                    );
+                }
+            }
+            if !mesh.texcoords.is_empty() || !mesh_data.uvs.is_empty() {
+                mesh_data.uvs.resize(vertex_offset, (0_f64, 0_f64));
+                if mesh.texcoords.is_empty() {
+                    mesh_data
+                        .uvs
+                        .resize(vertex_offset + vertex_count, (0_f64, 0_f64));
+                } else {
+                    mesh_data.uvs.extend(
+                        mesh.texcoords
+                            .chunks_exact(2)
+                            .map(|uv| (uv[0] as f64, uv[1] as f64)),
+                    );
+                }
+            }
+
+            // Offset by 1, as index 0 is the default material
+            let material_index = match mesh.material_id {
+                Some(material_id) if material_id + 1 < mesh_data.materials.len() => material_id + 1,
+                _ => 0,
+            };
+            for triangle in mesh.indices.chunks_exact(3) {
+                mesh_data.triangles.push([
+                    vertex_offset + triangle[0] as usize,
+                    vertex_offset + triangle[1] as usize,
+                    vertex_offset + triangle[2] as usize,
+                ]);
+                mesh_data.triangle_materials.push(material_index);
+            }
+        }
+        // Models loaded earlier without normals are filled in with zero normals, which can not be interpolated
+        // Fall back to flat shading for the whole mesh in that case
+        if mesh_data
+            .normals
+            .iter()
+            .any(|normal| normal.length_squared() == 0_f64)
+        {
+            mesh_data.normals.clear();
+        }
+
+        Ok(TriangleMesh::new(mesh_data))
+    }
+}
+
+/// Maps a `.mtl` material to one of our materials, refer to [TriangleMesh::from_obj]
+fn material_from_mtl(mtl_material: &tobj::Material, obj_directory: &Path) -> Materials {
+    let to_color = |c: [f32; 3]| Vec3::new(c[0] as f64, c[1] as f64, c[2] as f64);
+
+    // Newer versions of tobj parse `Ke` themselves, older ones leave it in the unknown parameters
+    let emission = match mtl_material.emissive {
+        Some(emission) => Some(to_color(emission)),
+        None => mtl_material.unknown_param.get("Ke").map(|emission| {
+            let emission: Vec<f64> = emission
+                .split_whitespace()
+                .filter_map(|value| value.parse::<f64>().ok())
+                .collect();
+            match emission.as_slice() {
+                [r, g, b] => Vec3::new(*r, *g, *b),
+                [value] => Vec3::new(*value, *value, *value),
+                _ => Vec3::default(),
+            }
+        }),
+    };
+    if let Some(color) = emission {
+        if color.xyz().iter().any(|value| *value > 0_f64) {
+            return Materials::LightMaterial(Arc::new(Emissive::from_color(color, 1_f64, true)));
+        }
+    }
+
+    let illumination_model = mtl_material.illumination_model.unwrap_or(2);
+    let dissolve = mtl_material.dissolve.unwrap_or(1.0);
+    if matches!(illumination_model, 4 | 6 | 7) || dissolve < 1.0 {
+        return Materials::ScatterMaterial(Arc::new(Dielectric {
+            index_of_reflectance: mtl_material.optical_density.unwrap_or(1.5) as f64,
+        }));
+    }
+    if matches!(illumination_model, 3 | 5) {
+        let albedo = to_color(mtl_material.specular.unwrap_or([1.0, 1.0, 1.0]));
+        // Ns goes from 0 to 1000, with higher values being a sharper reflection
+        let shininess = mtl_material.shininess.unwrap_or(1000.0) as f64;
+        let fuzzy_factor = (1_f64 - shininess / 1000_f64).clamp(0_f64, 1_f64);
+        return Materials::ScatterMaterial(Arc::new(Metal::new(
+            Arc::new(SolidColor { color: albedo }),
+            fuzzy_factor,
+        )));
+    }
+
+    let diffuse_color = to_color(mtl_material.diffuse.unwrap_or([0.8, 0.8, 0.8]));
+    let albedo: Arc<dyn ColorTexture> = match &mtl_material.diffuse_texture {
+        Some(texture_path) => {
+            let image_path = obj_directory.join(texture_path);
+            let image_path = image_path.to_str().unwrap_or(texture_path);
+            let fill_texture = Arc::new(SolidColor {
+                color: diffuse_color.clone(),
+            });
+            // A broken texture should not stop the whole render, the material still has its color
+            match Image::try_new(1.0, image_path, fill_texture.clone()) {
+                Ok(image) => Arc::new(image),
+                Err(err) => {
+                    eprintln!(
+                        "Warning: could not load the texture `{}` of material `{}`, using its `Kd` color instead: {}",
+                        image_path, mtl_material.name, err
+                    );
+                    fill_texture
+                }
+            }
+        }
+        None => Arc::new(SolidColor {
+            color: diffuse_color,
+        }),
+    };
+    Materials::ScatterMaterial(Arc::new(Lambertain { albedo }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{materials::test::TestScatterable, HittableWithBBox};
+
+    #[test]
+    fn test_triangle_mesh_from_obj() {
+        let mesh = TriangleMesh::from_obj(
+            "assets/models/cube.obj",
+            Materials::ScatterMaterial(Arc::new(TestScatterable {})),
+        )
+        .unwrap();
+        // 6 quads, triangulated
+        assert_eq!(mesh.len(), 12);
+        let bbox = mesh.bbox();
+        assert!(bbox.x.min <= -1.0 && bbox.x.max >= 1.0);
+
+        // The top face is mapped to the emissive material
+        let emissive_triangles = mesh
+            .triangles()
+            .iter()
+            .filter(|triangle| matches!(triangle.material(), Some(Materials::LightMaterial(_))))
+            .count();
+        assert_eq!(emissive_triangles, 2);
+    }
+
+    #[test]
+    fn test_triangle_mesh_missing_texture() {
+        // A texture that can not be loaded falls back to the Kd color, instead of failing the whole mesh
+        let directory = std::env::temp_dir().join("rsr-test-missing-texture");
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(
+            directory.join("triangle.obj"),
+            "mtllib triangle.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl Textured\nf 1 2 3\n",
+        )
+        .unwrap();
+        std::fs::write(
+            directory.join("triangle.mtl"),
+            "newmtl Textured\nKd 0.2 0.4 0.6\nmap_Kd does-not-exist.png\n",
+        )
+        .unwrap();
+        let mesh = TriangleMesh::from_obj(
+            directory.join("triangle.obj").to_str().unwrap(),
+            Materials::ScatterMaterial(Arc::new(TestScatterable {})),
+        )
+        .unwrap();
+        assert_eq!(mesh.len(), 1);
+        assert!(matches!(
+            mesh.triangles()[0].material(),
+            Some(Materials::ScatterMaterial(_))
+        ));
+    }
+
+    #[test]
+    fn test_triangle_mesh_from_obj_missing_file() {
+        assert!(TriangleMesh::from_obj(
+            "assets/models/does-not-exist.obj",
+            Materials::ScatterMaterial(Arc::new(TestScatterable {})),
+        )
+        .is_err());
+    }
+}
@@ -1,6 +1,6 @@
 use std::fmt::Display;
 
-use crate::{Interval, Ray, Vec3};
+use crate::{Hittable, Interval, Ray, Sampler, Vec3};
 
 mod disk;
 mod quad;
@@ -27,6 +27,8 @@ struct PlanarBase {
     /// Memorise w vector, which is normal / normal . normal (normal = u x v)
     /// Not to be confused with the plane's unit_normal
     w: Vec3,
+    /// Area of the parallelogram made by u and v (|u x v|)
+    uv_area: f64,
 }
 #[allow(non_snake_case)]
 impl PlanarBase {
@@ -40,8 +42,13 @@ impl PlanarBase {
             plane_unit_normal: plane_unit_normal.clone(),
             D: Vec3::dot(&plane_unit_normal, &Q),
             w: n.clone() / Vec3::dot(&n, &n),
+            uv_area: n.length(),
         }
     }
+    /// Point on the plane at Q + alpha * u + beta * v
+    fn point_at(&self, alpha: f64, beta: f64) -> Vec3 {
+        self.Q.clone() + alpha * self.u.clone() + beta * self.v.clone()
+    }
     fn hit_plane(&self, _ray: &Ray, valid_t_interval: Interval) -> Option<PlanarPlaneHit> {
         // Hit implementation based on plane_unit_normal and incoming Ray
         let denom = Vec3::dot(&self.plane_unit_normal, &_ray.direction);
@@ -75,17 +82,74 @@ struct PlanarPlaneHit {
     alpha: f64,
     beta: f64,
 }
-trait PlanarObject {
+trait PlanarObject: Hittable {
     /// Checks if the given alpha and beta values, based on the plane bases and origin
     /// Lie within the planar object
     fn ab_is_in_planar_object(&self, alpha: f64, beta: f64) -> bool;
+    fn planar_base(&self) -> &PlanarBase;
+    /// Samples random alpha and beta values, uniformly distributed over the planar object
+    fn random_ab(&self, sampler: &mut dyn Sampler) -> (f64, f64);
+
+    /// Converts the area pdf of uniformly sampling the object into a solid angle pdf from origin
+    /// Used to implement [Hittable::pdf_value] (Requires [Hittable::area] to be implemented)
+    fn planar_pdf_value(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        let hit_record = match self.hit(
+            &Ray {
+                origin: origin.clone(),
+                direction: direction.clone(),
+            },
+            Interval {
+                min: 0.001,
+                max: f64::INFINITY,
+            },
+        ) {
+            Some(hit_record) => hit_record,
+            None => return 0_f64,
+        };
+        let distance_squared = hit_record.t * hit_record.t * direction.length_squared();
+        let cosine = (Vec3::dot(direction, &self.planar_base().plane_unit_normal)
+            / direction.length())
+        .abs();
+        if cosine < 1e-8_f64 {
+            return 0_f64;
+        }
+        distance_squared / (cosine * self.area())
+    }
+    /// Direction from origin to a random point on the planar object
+    /// Used to implement [Hittable::random]
+    fn planar_random(&self, origin: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        let (alpha, beta) = self.random_ab(sampler);
+        self.planar_base().point_at(alpha, beta) - origin.clone()
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use std::f64::INFINITY;
-
     use super::*;
+    use crate::IndependentSampler;
+
+    #[test]
+    fn test_planar_quad_pdf_value() {
+        use crate::{materials::test::TestScatterable, Materials, Quad};
+        use std::sync::Arc;
+
+        // Unit quad 1 unit above the origin, directly facing it
+        let quad = Quad::new(
+            Vec3::new(-0.5, 1.0, -0.5),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            Materials::ScatterMaterial(Arc::new(TestScatterable {})),
+        );
+        let origin = Vec3::new_int(0, 0, 0);
+        // distance^2 / (cos * area) = 1 / (1 * 1)
+        assert_eq!(quad.pdf_value(&origin, &Vec3::new_int(0, 1, 0)), 1.0);
+        assert_eq!(quad.pdf_value(&origin, &Vec3::new_int(0, -1, 0)), 0.0);
+        let mut sampler = IndependentSampler::new(0);
+        for _ in 0..100 {
+            let direction = quad.random(&origin, &mut sampler);
+            assert!(quad.pdf_value(&origin, &direction) > 0.0);
+        }
+    }
 
     #[test]
     fn test_planar_disk_hit_plane() {
@@ -103,7 +167,7 @@ mod test {
             &negative_alpha_beta_ray,
             Interval {
                 min: 0.001,
-                max: INFINITY,
+                max: f64::INFINITY,
             },
         ) {
             assert_eq!(negative_alpha_beta_hit.t, 1.0);
@@ -1,6 +1,6 @@
-use std::fmt::Display;
+use std::{f64::consts::PI, fmt::Display};
 
-use crate::{HitRecord, Hittable, HittableWithBBox, Interval, Materials, Ray, Vec3, AABB};
+use crate::{HitRecord, Hittable, HittableWithBBox, Interval, Materials, Ray, Sampler, Vec3, AABB};
 
 use super::{PlanarBase, PlanarObject};
 
@@ -36,6 +36,16 @@ impl PlanarObject for Disk {
     fn ab_is_in_planar_object(&self, alpha: f64, beta: f64) -> bool {
         (alpha * alpha + beta * beta) <= (self.radius * self.radius)
     }
+    fn planar_base(&self) -> &PlanarBase {
+        &self.planar_base
+    }
+    fn random_ab(&self, sampler: &mut dyn Sampler) -> (f64, f64) {
+        // sqrt to spread the samples uniformly over the area, instead of bunching up at the center
+        let (r1, r2) = sampler.get_2d();
+        let r = self.radius * r1.sqrt();
+        let theta = 2_f64 * PI * r2;
+        (r * theta.cos(), r * theta.sin())
+    }
 }
 impl Hittable for Disk {
     fn hit(&self, _ray: &Ray, valid_t_interval: Interval) -> Option<HitRecord> {
@@ -68,6 +78,18 @@ impl Hittable for Disk {
             v,
         ))
     }
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        self.planar_pdf_value(origin, direction)
+    }
+    fn random(&self, origin: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        self.planar_random(origin, sampler)
+    }
+    fn material(&self) -> Option<&Materials> {
+        Some(&self.material)
+    }
+    fn area(&self) -> f64 {
+        PI * self.radius * self.radius * self.planar_base.uv_area
+    }
 }
 impl HittableWithBBox for Disk {
     fn bbox(&self) -> &AABB {
@@ -1,6 +1,6 @@
 use std::fmt::Display;
 
-use crate::{HitRecord, Hittable, HittableWithBBox, Interval, Materials, Ray, Vec3, AABB};
+use crate::{HitRecord, Hittable, HittableWithBBox, Interval, Materials, Ray, Sampler, Vec3, AABB};
 
 use super::{PlanarBase, PlanarObject};
 
@@ -29,6 +29,12 @@ impl PlanarObject for Quad {
         let quad_interval = Interval { min: 0.0, max: 1.0 };
         quad_interval.contains(alpha) && quad_interval.contains(beta)
     }
+    fn planar_base(&self) -> &PlanarBase {
+        &self.planar_base
+    }
+    fn random_ab(&self, sampler: &mut dyn Sampler) -> (f64, f64) {
+        sampler.get_2d()
+    }
 }
 impl HittableWithBBox for Quad {
     fn bbox(&self) -> &AABB {
@@ -59,6 +65,18 @@ impl Hittable for Quad {
             plane_hit.beta,
         ))
     }
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        self.planar_pdf_value(origin, direction)
+    }
+    fn random(&self, origin: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        self.planar_random(origin, sampler)
+    }
+    fn material(&self) -> Option<&Materials> {
+        Some(&self.material)
+    }
+    fn area(&self) -> f64 {
+        self.planar_base.uv_area
+    }
 }
 impl Display for Quad {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -1,6 +1,6 @@
 use std::fmt::Display;
 
-use crate::{HitRecord, Hittable, HittableWithBBox, Interval, Materials, Ray, Vec3, AABB};
+use crate::{HitRecord, Hittable, HittableWithBBox, Interval, Materials, Ray, Sampler, Vec3, AABB};
 
 use super::{PlanarBase, PlanarObject};
 
@@ -50,6 +50,18 @@ impl PlanarObject for Triangle {
         (left_triangle_area + bottom_triangle_area + right_triangle_area - triangle_area).abs()
             < 1e-8_f64
     }
+    fn planar_base(&self) -> &PlanarBase {
+        &self.planar_base
+    }
+    fn random_ab(&self, sampler: &mut dyn Sampler) -> (f64, f64) {
+        let (alpha, beta) = sampler.get_2d();
+        // Reflect the samples from the other half of the uv parallelogram back into the triangle
+        if alpha + beta > 1_f64 {
+            (1_f64 - alpha, 1_f64 - beta)
+        } else {
+            (alpha, beta)
+        }
+    }
 }
 impl HittableWithBBox for Triangle {
     fn bbox(&self) -> &AABB {
@@ -78,6 +90,18 @@ impl Hittable for Triangle {
             plane_hit.beta,
         ))
     }
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        self.planar_pdf_value(origin, direction)
+    }
+    fn random(&self, origin: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        self.planar_random(origin, sampler)
+    }
+    fn material(&self) -> Option<&Materials> {
+        Some(&self.material)
+    }
+    fn area(&self) -> f64 {
+        0.5_f64 * self.planar_base.uv_area
+    }
 }
 impl Display for Triangle {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
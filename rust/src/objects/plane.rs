@@ -0,0 +1,135 @@
+use std::fmt::Display;
+
+use crate::{HitRecord, Hittable, HittableWithBBox, Interval, Materials, Ray, Vec3, AABB};
+
+/// Infinite plane through point, facing normal (i.e. a floor going all the way to the horizon)
+/// It has no bounds, so the [BVH](crate::BVH) keeps it out of its tree and checks every ray against it
+///
+/// u and v are the position along two directions in the plane, wrapped to [0, 1] so textures repeat every unit
+/// For a plane facing up (0, 1, 0), u goes along X and v along Z
+///
+/// Being infinite, it can not be sampled as a light, an emissive plane is only found by bounced rays
+pub struct Plane {
+    pub point: Vec3,
+    /// Unit vector
+    pub normal: Vec3,
+    pub material: Materials,
+    /// Directions in the plane that u and v follow
+    u_axis: Vec3,
+    v_axis: Vec3,
+    bbox: AABB,
+}
+impl Plane {
+    pub fn new(point: Vec3, normal: Vec3, material: Materials) -> Self {
+        let normal = normal.unit_vector();
+        // Project X (or Y if the plane faces X) onto the plane
+        let reference = if normal.x().abs() > 0.9 {
+            Vec3::new_int(0, 1, 0)
+        } else {
+            Vec3::new_int(1, 0, 0)
+        };
+        let u_axis =
+            (reference.clone() - Vec3::dot(&reference, &normal) * normal.clone()).unit_vector();
+        let v_axis = Vec3::cross(&u_axis, &normal);
+        Self {
+            point,
+            normal,
+            material,
+            u_axis,
+            v_axis,
+            bbox: AABB::UNIVERSE,
+        }
+    }
+}
+impl Hittable for Plane {
+    fn hit(&self, _ray: &Ray, valid_t_interval: Interval) -> Option<HitRecord> {
+        let denominator = Vec3::dot(&self.normal, &_ray.direction);
+        // Ray is parallel to the plane
+        if denominator.abs() < 1e-12 {
+            return None;
+        }
+        let t = Vec3::dot(&self.normal, &(self.point.clone() - _ray.origin.clone())) / denominator;
+        if !valid_t_interval.surrounds(t) {
+            return None;
+        }
+        let in_plane = _ray.at(t) - self.point.clone();
+        let u = Vec3::dot(&in_plane, &self.u_axis).rem_euclid(1_f64);
+        let v = Vec3::dot(&in_plane, &self.v_axis).rem_euclid(1_f64);
+        Some(HitRecord::new(
+            _ray,
+            &self.normal,
+            t,
+            self.material.clone(),
+            u,
+            v,
+        ))
+    }
+    fn material(&self) -> Option<&Materials> {
+        Some(&self.material)
+    }
+}
+impl HittableWithBBox for Plane {
+    fn bbox(&self) -> &AABB {
+        &self.bbox
+    }
+}
+impl Display for Plane {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Plane(point: {}, normal: {})", self.point, self.normal)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::materials::test::TestScatterable;
+
+    #[test]
+    fn test_plane_hit() {
+        let mat = Materials::ScatterMaterial(Arc::new(TestScatterable {}));
+        let plane = Plane::new(Vec3::new_int(0, -1, 0), Vec3::new_int(0, 3, 0), mat);
+        assert!(!plane.bbox().is_bounded());
+        let interval = Interval {
+            min: 0.001,
+            max: f64::INFINITY,
+        };
+
+        let hr = plane
+            .hit(
+                &Ray {
+                    origin: Vec3::new(100.25, 1_f64, -40.5),
+                    direction: Vec3::new_int(0, -2, 0),
+                },
+                interval,
+            )
+            .unwrap();
+        assert_eq!(hr.t, 1_f64);
+        assert_eq!(hr.against_normal_unit, Vec3::new_int(0, 1, 0));
+        assert!(hr.front_face);
+        assert_eq!((hr.u, hr.v), (0.25, 0.5));
+
+        // From below, and parallel to it
+        let hr = plane
+            .hit(
+                &Ray {
+                    origin: Vec3::new_int(0, -3, 0),
+                    direction: Vec3::new_int(1, 1, 0),
+                },
+                interval,
+            )
+            .unwrap();
+        assert_eq!(hr.p, Vec3::new_int(2, -1, 0));
+        assert!(!hr.front_face);
+        assert!(plane
+            .hit(
+                &Ray {
+                    origin: Vec3::new_int(0, 0, 0),
+                    direction: Vec3::new_int(1, 0, 1),
+                },
+                interval,
+            )
+            .is_none());
+    }
+}
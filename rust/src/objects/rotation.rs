@@ -1,6 +1,8 @@
-use std::{f64::INFINITY, sync::Arc};
+use std::sync::Arc;
 
-use crate::{HitRecord, Hittable, HittableWithBBox, Interval, Ray, Vec3, Vec3Axis, AABB};
+use crate::{
+    HitRecord, Hittable, HittableWithBBox, Interval, Materials, Ray, Sampler, Vec3, Vec3Axis, AABB,
+};
 
 pub struct Rotation {
     instance: Arc<dyn HittableWithBBox>,
@@ -17,8 +19,17 @@ impl Rotation {
         deg_angle: f64,
     ) -> Self {
         let aabb = instance.bbox();
-        let mut bottom_left_min_aabb_point = Vec3::new(INFINITY, INFINITY, INFINITY);
-        let mut top_right_max_aabb_point = Vec3::new(-INFINITY, -INFINITY, -INFINITY);
+        if !aabb.is_bounded() {
+            return Self {
+                instance,
+                rotation_axis,
+                deg_angle,
+                bbox: AABB::UNIVERSE,
+            };
+        }
+        let mut bottom_left_min_aabb_point = Vec3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        let mut top_right_max_aabb_point =
+            Vec3::new(-f64::INFINITY, -f64::INFINITY, -f64::INFINITY);
 
         // Looping through all 8 points of the aabb
         for x in [aabb.x.min, aabb.x.max] {
@@ -40,30 +51,64 @@ impl Rotation {
             bbox: AABB::from_points(bottom_left_min_aabb_point, top_right_max_aabb_point),
         }
     }
-}
-impl Hittable for Rotation {
-    fn hit(&self, _ray: &Ray, valid_t_interval: Interval) -> Option<HitRecord> {
-        let object_space_ray = Ray {
+    fn object_space_ray(&self, _ray: &Ray) -> Ray {
+        Ray {
             origin: _ray
                 .origin
                 .rotate_about_axis(&self.rotation_axis, -self.deg_angle),
             direction: _ray
                 .direction
                 .rotate_about_axis(&self.rotation_axis, -self.deg_angle),
-        };
-        match self.instance.hit(&object_space_ray, valid_t_interval) {
-            Some(mut hit_record) => {
-                hit_record.p = hit_record
-                    .p
-                    .rotate_about_axis(&self.rotation_axis, self.deg_angle);
-                hit_record.against_normal_unit = hit_record
-                    .against_normal_unit
-                    .rotate_about_axis(&self.rotation_axis, self.deg_angle);
-                Some(hit_record)
-            }
-            None => None,
         }
     }
+    fn rotate_hit(&self, mut hit_record: HitRecord) -> HitRecord {
+        hit_record.p = hit_record
+            .p
+            .rotate_about_axis(&self.rotation_axis, self.deg_angle);
+        hit_record.against_normal_unit = hit_record
+            .against_normal_unit
+            .rotate_about_axis(&self.rotation_axis, self.deg_angle);
+        hit_record
+    }
+}
+impl Hittable for Rotation {
+    fn hit(&self, _ray: &Ray, valid_t_interval: Interval) -> Option<HitRecord> {
+        self.instance
+            .hit(&self.object_space_ray(_ray), valid_t_interval)
+            .map(|hit_record| self.rotate_hit(hit_record))
+    }
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        self.instance.pdf_value(
+            &origin.rotate_about_axis(&self.rotation_axis, -self.deg_angle),
+            &direction.rotate_about_axis(&self.rotation_axis, -self.deg_angle),
+        )
+    }
+    fn random(&self, origin: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        self.instance
+            .random(
+                &origin.rotate_about_axis(&self.rotation_axis, -self.deg_angle),
+                sampler,
+            )
+            .rotate_about_axis(&self.rotation_axis, self.deg_angle)
+    }
+    fn material(&self) -> Option<&Materials> {
+        self.instance.material()
+    }
+    fn area(&self) -> f64 {
+        self.instance.area()
+    }
+    fn hit_surface(&self, _ray: &Ray, valid_t_interval: Interval) -> Option<HitRecord> {
+        self.instance
+            .hit_surface(&self.object_space_ray(_ray), valid_t_interval)
+            .map(|hit_record| self.rotate_hit(hit_record))
+    }
+    fn transmittance(&self, _ray: &Ray, valid_t_interval: Interval) -> f64 {
+        self.instance
+            .transmittance(&self.object_space_ray(_ray), valid_t_interval)
+    }
+    fn contains_media(&self) -> bool {
+        self.instance.contains_media()
+    }
 }
 impl HittableWithBBox for Rotation {
     fn bbox(&self) -> &AABB {
@@ -1,6 +1,8 @@
 use std::{f64::consts::PI, fmt::Display};
 
-use crate::{HitRecord, Hittable, HittableWithBBox, Interval, Materials, Ray, Vec3, AABB};
+use crate::{
+    pdf::ONB, HitRecord, Hittable, HittableWithBBox, Interval, Materials, Ray, Sampler, Vec3, AABB,
+};
 
 /// Simple Sphere object
 /// To initialize use [Sphere::new]
@@ -39,6 +41,17 @@ impl Sphere {
         let v = theta / PI;
         (u, v)
     }
+    /// Samples a direction (around z=1) towards a sphere of radius, distance_squared away
+    /// Uniformly distributed over the solid angle the sphere covers
+    fn random_to_sphere(radius: f64, distance_squared: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        let (r1, r2) = sampler.get_2d();
+        let z = 1_f64 + r2 * ((1_f64 - radius * radius / distance_squared).sqrt() - 1_f64);
+
+        let phi = 2_f64 * PI * r1;
+        let x = phi.cos() * (1_f64 - z * z).sqrt();
+        let y = phi.sin() * (1_f64 - z * z).sqrt();
+        Vec3::new(x, y, z)
+    }
 }
 impl Hittable for Sphere {
     fn hit(&self, ray: &Ray, valid_t_interval: Interval) -> Option<HitRecord> {
@@ -76,6 +89,51 @@ impl Hittable for Sphere {
             v,
         ))
     }
+    /// Uniform over the cone of directions from origin that hit the sphere
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        if self
+            .hit(
+                &Ray {
+                    origin: origin.clone(),
+                    direction: direction.clone(),
+                },
+                Interval {
+                    min: 0.001,
+                    max: f64::INFINITY,
+                },
+            )
+            .is_none()
+        {
+            return 0_f64;
+        }
+        let distance_squared = (self.center.clone() - origin.clone()).length_squared();
+        // origin is inside the sphere, every direction hits it
+        if distance_squared <= self.radius * self.radius {
+            return 1_f64 / (4_f64 * PI);
+        }
+        let cos_theta_max = (1_f64 - self.radius * self.radius / distance_squared).sqrt();
+        let solid_angle = 2_f64 * PI * (1_f64 - cos_theta_max);
+        1_f64 / solid_angle
+    }
+    fn random(&self, origin: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        let direction = self.center.clone() - origin.clone();
+        let distance_squared = direction.length_squared();
+        if distance_squared <= self.radius * self.radius {
+            return Vec3::random_unit_vector(sampler);
+        }
+        let uvw = ONB::new(&direction);
+        uvw.local(&Self::random_to_sphere(
+            self.radius,
+            distance_squared,
+            sampler,
+        ))
+    }
+    fn material(&self) -> Option<&Materials> {
+        Some(&self.material)
+    }
+    fn area(&self) -> f64 {
+        4_f64 * PI * self.radius * self.radius
+    }
 }
 impl HittableWithBBox for Sphere {
     fn bbox(&self) -> &AABB {
@@ -97,7 +155,8 @@ mod test {
     use crate::materials::test::TestScatterable;
 
     use super::*;
-    use std::{f64::INFINITY, sync::Arc};
+    use crate::IndependentSampler;
+    use std::sync::Arc;
 
     #[test]
     fn test_sphere_new() {
@@ -126,7 +185,7 @@ mod test {
                 &r,
                 Interval {
                     min: 0.001,
-                    max: INFINITY,
+                    max: f64::INFINITY,
                 },
             )
             .unwrap();
@@ -141,7 +200,7 @@ mod test {
                 &r,
                 Interval {
                     min: 1.0,
-                    max: INFINITY,
+                    max: f64::INFINITY,
                 },
             )
             .unwrap();
@@ -160,11 +219,30 @@ mod test {
                 &r,
                 Interval {
                     min: 0.001,
-                    max: INFINITY,
+                    max: f64::INFINITY,
                 },
             )
             .is_none());
     }
+    #[test]
+    fn test_sphere_pdf_value() {
+        let mat = Materials::ScatterMaterial(Arc::new(TestScatterable {}));
+        let s = Sphere::new(Vec3::new_int(0, 0, 0), 1.0, mat);
+        let origin = Vec3::new_int(0, 0, -2);
+        // cos_theta_max = sqrt(1 - 1/4)
+        let solid_angle = 2.0 * PI * (1.0 - 0.75_f64.sqrt());
+        assert_eq!(
+            s.pdf_value(&origin, &Vec3::new_int(0, 0, 1)),
+            1.0 / solid_angle
+        );
+        assert_eq!(s.pdf_value(&origin, &Vec3::new_int(0, 0, -1)), 0.0);
+        let mut sampler = IndependentSampler::new(0);
+        for _ in 0..100 {
+            let direction = s.random(&origin, &mut sampler);
+            assert!(s.pdf_value(&origin, &direction) > 0.0);
+        }
+    }
+
     #[test]
     fn test_sphere_uv() {
         let (u, v) = Sphere::get_sphere_uv(Vec3::new(-1.0, 0.0, 0.0));
@@ -0,0 +1,68 @@
+use std::sync::Arc;
+
+use crate::{HitRecord, Hittable, HittableWithBBox, Interval, Materials, Ray, Sampler, Vec3, AABB};
+
+/// Wrapper that marks every hit of the instance with an object ID (and optionally a material ID)
+/// The IDs are only used by the ID passes of the [AOV](crate::AOV)s, so objects can be picked out when compositing
+pub struct Tagged {
+    instance: Arc<dyn HittableWithBBox>,
+    object_id: u32,
+    /// None keeps the material IDs set inside the instance (i.e. meshes with multiple materials)
+    material_id: Option<u32>,
+}
+impl Tagged {
+    /// IDs start from 1, 0 is used for untagged objects (and misses)
+    pub fn new(
+        instance: Arc<dyn HittableWithBBox>,
+        object_id: u32,
+        material_id: Option<u32>,
+    ) -> Self {
+        Self {
+            instance,
+            object_id,
+            material_id,
+        }
+    }
+    fn tag(&self, mut hit_record: HitRecord) -> HitRecord {
+        hit_record.object_id = self.object_id;
+        if let Some(material_id) = self.material_id {
+            hit_record.material_id = material_id;
+        }
+        hit_record
+    }
+}
+impl Hittable for Tagged {
+    fn hit(&self, _ray: &Ray, valid_t_interval: Interval) -> Option<HitRecord> {
+        self.instance
+            .hit(_ray, valid_t_interval)
+            .map(|hit_record| self.tag(hit_record))
+    }
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        self.instance.pdf_value(origin, direction)
+    }
+    fn random(&self, origin: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        self.instance.random(origin, sampler)
+    }
+    fn material(&self) -> Option<&Materials> {
+        self.instance.material()
+    }
+    fn area(&self) -> f64 {
+        self.instance.area()
+    }
+    fn hit_surface(&self, _ray: &Ray, valid_t_interval: Interval) -> Option<HitRecord> {
+        self.instance
+            .hit_surface(_ray, valid_t_interval)
+            .map(|hit_record| self.tag(hit_record))
+    }
+    fn transmittance(&self, _ray: &Ray, valid_t_interval: Interval) -> f64 {
+        self.instance.transmittance(_ray, valid_t_interval)
+    }
+    fn contains_media(&self) -> bool {
+        self.instance.contains_media()
+    }
+}
+impl HittableWithBBox for Tagged {
+    fn bbox(&self) -> &AABB {
+        self.instance.bbox()
+    }
+}
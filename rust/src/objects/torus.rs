@@ -0,0 +1,188 @@
+use std::{f64::consts::PI, fmt::Display};
+
+use crate::{
+    helper::solve_polynomial, HitRecord, Hittable, HittableWithBBox, Interval, Materials, Ray,
+    Vec3, AABB,
+};
+
+use super::cylinder::around_y_axis;
+
+/// Torus (a donut) lying flat around the Y axis through center
+/// The tube has the minor radius, and its middle runs around a circle of the major radius
+/// To tilt it, wrap it in a [Transformed](crate::Transformed)
+///
+/// u goes around the Y axis (as on a [Sphere](crate::Sphere)) and v goes around the tube, from the inner equator (inside the hole) under the bottom and back over the top
+///     The outer equator has v = 0.5, the top of the tube has v = 0.75
+///
+/// Not sampled as a light (it has no [Hittable::area]), an emissive torus is only found by bounced rays
+pub struct Torus {
+    pub center: Vec3,
+    pub major_radius: f64,
+    pub minor_radius: f64,
+    pub material: Materials,
+    bbox: AABB,
+}
+impl Torus {
+    pub fn new(center: Vec3, major_radius: f64, minor_radius: f64, material: Materials) -> Self {
+        let extent = major_radius + minor_radius;
+        let bbox = AABB::from_points(
+            center.clone() - Vec3::new(extent, minor_radius, extent),
+            center.clone() + Vec3::new(extent, minor_radius, extent),
+        );
+        Self {
+            center,
+            major_radius,
+            minor_radius,
+            material,
+            bbox,
+        }
+    }
+}
+impl Hittable for Torus {
+    // Implementation Details:
+    // The surface is (|p|^2 + R^2 - r^2)^2 = 4 R^2 (x^2 + z^2), which along the ray is a quartic in t
+    // The quartic is solved in terms of the distance from the point of the ray closest to the center (with a unit direction)
+    // Keeping its coefficients small, so far away rays do not lose the precision of the roots
+    fn hit(&self, _ray: &Ray, valid_t_interval: Interval) -> Option<HitRecord> {
+        let direction_length = _ray.direction.length();
+        let direction = _ray.direction.clone() / direction_length;
+        let to_center = self.center.clone() - _ray.origin.clone();
+        let closest_distance = Vec3::dot(&to_center, &direction);
+        let origin = closest_distance * direction.clone() - to_center;
+        let extent = self.major_radius + self.minor_radius;
+        // Misses the bounding sphere
+        if origin.length_squared() > extent * extent {
+            return None;
+        }
+
+        let major_squared = self.major_radius * self.major_radius;
+        let e = origin.length_squared() + major_squared - self.minor_radius * self.minor_radius;
+        let f = Vec3::dot(&origin, &direction);
+        let roots = solve_polynomial(&[
+            e * e - 4_f64 * major_squared * (origin.x() * origin.x() + origin.z() * origin.z()),
+            4_f64 * e * f
+                - 8_f64 * major_squared * (origin.x() * direction.x() + origin.z() * direction.z()),
+            4_f64 * f * f + 2_f64 * e
+                - 4_f64
+                    * major_squared
+                    * (direction.x() * direction.x() + direction.z() * direction.z()),
+            4_f64 * f,
+            1_f64,
+        ]);
+        let (s, t) = roots
+            .into_iter()
+            .map(|s| (s, (closest_distance + s) / direction_length))
+            .find(|(_, t)| valid_t_interval.surrounds(*t))?;
+
+        let p = origin + s * direction;
+        let around = Vec3::new(p.x(), 0_f64, p.z());
+        let from_axis = around.length();
+        let tube_center = self.major_radius / from_axis * around;
+        let outward_normal_unit = (p.clone() - tube_center).unit_vector();
+        let u = around_y_axis(&p);
+        let v = (f64::atan2(p.y(), from_axis - self.major_radius) + PI) / (2_f64 * PI);
+        Some(HitRecord::new(
+            _ray,
+            &outward_normal_unit,
+            t,
+            self.material.clone(),
+            u,
+            v,
+        ))
+    }
+    fn material(&self) -> Option<&Materials> {
+        Some(&self.material)
+    }
+}
+impl HittableWithBBox for Torus {
+    fn bbox(&self) -> &AABB {
+        &self.bbox
+    }
+}
+impl Display for Torus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Torus(center: {}, major_radius: {}, minor_radius: {})",
+            self.center, self.major_radius, self.minor_radius
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::materials::test::TestScatterable;
+
+    #[test]
+    fn test_torus_hit() {
+        let mat = Materials::ScatterMaterial(Arc::new(TestScatterable {}));
+        let torus = Torus::new(Vec3::new_int(1, 0, 0), 2_f64, 0.5, mat);
+        let interval = Interval {
+            min: 0.001,
+            max: f64::INFINITY,
+        };
+        let assert_near = |a: f64, b: f64| assert!((a - b).abs() < 1e-9, "{} is not {}", a, b);
+
+        // Through the middle along the X axis, hitting the outside of the tube, then its inside
+        let ray = Ray {
+            origin: Vec3::new_int(-9, 0, 0),
+            direction: Vec3::new_int(2, 0, 0),
+        };
+        let hr = torus.hit(&ray, interval).unwrap();
+        assert_near(hr.t, 3.75);
+        assert!((hr.against_normal_unit.clone() - Vec3::new_int(-1, 0, 0)).length() < 1e-9);
+        assert!(hr.front_face);
+        assert_near(hr.v, 0.5);
+        let hr = torus
+            .hit(
+                &ray,
+                Interval {
+                    min: 4_f64,
+                    max: f64::INFINITY,
+                },
+            )
+            .unwrap();
+        assert_near(hr.t, 4.25);
+        assert!(!hr.front_face);
+        // Inner equator on the other side of the hole
+        let hr = torus
+            .hit(
+                &ray,
+                Interval {
+                    min: 4.5,
+                    max: f64::INFINITY,
+                },
+            )
+            .unwrap();
+        assert_near(hr.t, 5.75);
+        assert!((hr.against_normal_unit.clone() - Vec3::new_int(-1, 0, 0)).length() < 1e-9);
+        assert!(hr.front_face);
+
+        // Straight down onto the top of the tube, and down through the hole
+        let hr = torus
+            .hit(
+                &Ray {
+                    origin: Vec3::new_int(1, 5, 2),
+                    direction: Vec3::new_int(0, -1, 0),
+                },
+                interval,
+            )
+            .unwrap();
+        assert_near(hr.t, 4.5);
+        assert!((hr.against_normal_unit.clone() - Vec3::new_int(0, 1, 0)).length() < 1e-9);
+        assert_near(hr.u, 0.25);
+        assert_near(hr.v, 0.75);
+        assert!(torus
+            .hit(
+                &Ray {
+                    origin: Vec3::new_int(1, 5, 0),
+                    direction: Vec3::new_int(0, -1, 0),
+                },
+                interval,
+            )
+            .is_none());
+    }
+}
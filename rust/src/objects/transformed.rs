@@ -0,0 +1,168 @@
+use std::sync::Arc;
+
+use crate::{
+    HitRecord, Hittable, HittableWithBBox, Interval, Materials, Ray, Sampler, Transform, Vec3, AABB,
+};
+
+/// Object that takes a shallow copy of the original and applies an affine [Transform] to it
+/// Compared to nesting [Rotation](crate::Rotation) and [Translation](crate::Translation), any number of transforms only need one level of indirection
+pub struct Transformed {
+    instance: Arc<dyn HittableWithBBox>,
+    transform: Transform,
+    bbox: AABB,
+}
+impl Transformed {
+    pub fn new(instance: Arc<dyn HittableWithBBox>, transform: Transform) -> Self {
+        let bbox = transform.aabb(instance.bbox());
+        Self {
+            instance,
+            transform,
+            bbox,
+        }
+    }
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+    // Implementation Details:
+    // The direction is not normalized after transforming it into object space, so t is the same in both spaces
+    fn object_space_ray(&self, _ray: &Ray) -> Ray {
+        let inverse = self.transform.inverse_matrix();
+        Ray {
+            origin: inverse.mul_point(&_ray.origin),
+            direction: inverse.mul_vector(&_ray.direction),
+        }
+    }
+    fn transform_hit(&self, mut hit_record: HitRecord) -> HitRecord {
+        hit_record.p = self.transform.point(&hit_record.p);
+        // The inverse transpose keeps the sign of the dot product with the ray, so the normal stays against the ray
+        hit_record.against_normal_unit = self
+            .transform
+            .normal(&hit_record.against_normal_unit)
+            .unit_vector();
+        hit_record
+    }
+}
+impl Hittable for Transformed {
+    fn hit(&self, _ray: &Ray, valid_t_interval: Interval) -> Option<HitRecord> {
+        self.instance
+            .hit(&self.object_space_ray(_ray), valid_t_interval)
+            .map(|hit_record| self.transform_hit(hit_record))
+    }
+    // Implementation Details:
+    // The instance pdf is over the solid angle in object space, which gets stretched by the transform
+    // For the linear map A (world to object), the change of solid angle is |det A| / |A d|^3 (With d as a unit vector)
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        let inverse = self.transform.inverse_matrix();
+        let object_direction = inverse.mul_vector(&direction.unit_vector());
+        let object_pdf = self
+            .instance
+            .pdf_value(&inverse.mul_point(origin), &object_direction);
+        if object_pdf == 0_f64 {
+            return 0_f64;
+        }
+        object_pdf * inverse.determinant_3x3().abs() / object_direction.length().powi(3)
+    }
+    fn random(&self, origin: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        let object_origin = self.transform.inverse_matrix().mul_point(origin);
+        self.transform
+            .vector(&self.instance.random(&object_origin, sampler))
+    }
+    fn material(&self) -> Option<&Materials> {
+        self.instance.material()
+    }
+    /// Only exact for uniform scales, non-uniform scales use the average scaling of the area
+    fn area(&self) -> f64 {
+        self.instance.area()
+            * self
+                .transform
+                .matrix()
+                .determinant_3x3()
+                .abs()
+                .powf(2_f64 / 3_f64)
+    }
+    fn hit_surface(&self, _ray: &Ray, valid_t_interval: Interval) -> Option<HitRecord> {
+        self.instance
+            .hit_surface(&self.object_space_ray(_ray), valid_t_interval)
+            .map(|hit_record| self.transform_hit(hit_record))
+    }
+    fn transmittance(&self, _ray: &Ray, valid_t_interval: Interval) -> f64 {
+        self.instance
+            .transmittance(&self.object_space_ray(_ray), valid_t_interval)
+    }
+    fn contains_media(&self) -> bool {
+        self.instance.contains_media()
+    }
+}
+impl HittableWithBBox for Transformed {
+    fn bbox(&self) -> &AABB {
+        &self.bbox
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{materials::test::TestScatterable, Diffuse, Quad, Sphere, Vec3Axis};
+
+    fn interval() -> Interval {
+        Interval {
+            min: 0.001,
+            max: f64::INFINITY,
+        }
+    }
+
+    #[test]
+    fn test_transformed_hit() {
+        // Unit sphere stretched into an ellipsoid, then moved to (0, 0, -10)
+        let sphere = Arc::new(Sphere::new(
+            Vec3::new_int(0, 0, 0),
+            1.0,
+            Materials::ScatterMaterial(Arc::new(TestScatterable {})),
+        ));
+        let transformed = Transformed::new(
+            sphere,
+            Transform::scale(Vec3::new(1.0, 1.0, 2.0))
+                .then(&Transform::rotate_about_axis(&Vec3Axis::Y, 90.0))
+                .then(&Transform::translate(Vec3::new_int(0, 0, -10))),
+        );
+        // The long axis is now along x
+        let bbox = transformed.bbox();
+        assert!((bbox.x.min + 2.0).abs() < 1e-9 && (bbox.x.max - 2.0).abs() < 1e-9);
+        assert!((bbox.z.min + 11.0).abs() < 1e-9 && (bbox.z.max + 9.0).abs() < 1e-9);
+
+        let hit_record = transformed
+            .hit(
+                &Ray {
+                    origin: Vec3::new_int(10, 0, -10),
+                    direction: Vec3::new_int(-1, 0, 0),
+                },
+                interval(),
+            )
+            .unwrap();
+        assert!((hit_record.t - 8.0).abs() < 1e-9);
+        assert!((hit_record.p.clone() - Vec3::new_int(2, 0, -10)).length() < 1e-9);
+        assert!((hit_record.against_normal_unit.clone() - Vec3::new_int(1, 0, 0)).length() < 1e-9);
+    }
+
+    #[test]
+    fn test_transformed_pdf_value() {
+        // Scaling a light by 2 has the same pdf as the 2 times larger light
+        let light = Materials::LightMaterial(Arc::new(Diffuse { power: 1.0 }));
+        let quad = |size: f64| {
+            Arc::new(Quad::new(
+                Vec3::new(-size / 2.0, 0.0, -size / 2.0),
+                Vec3::new(size, 0.0, 0.0),
+                Vec3::new(0.0, 0.0, size),
+                light.clone(),
+            ))
+        };
+        let transformed = Transformed::new(quad(1.0), Transform::scale(Vec3::new(2.0, 2.0, 2.0)));
+        let larger = quad(2.0);
+        let origin = Vec3::new_int(0, 5, 0);
+        let direction = Vec3::new(0.1, -1.0, 0.15);
+        let expected = larger.pdf_value(&origin, &direction);
+        assert!(expected > 0.0);
+        assert!((transformed.pdf_value(&origin, &direction) - expected).abs() < 1e-9);
+        assert!((transformed.area() - larger.area()).abs() < 1e-9);
+    }
+}
@@ -1,6 +1,6 @@
-use std::{fmt::Display, sync::Arc};
+use std::sync::Arc;
 
-use crate::{HitRecord, Hittable, HittableWithBBox, Interval, Ray, Vec3, AABB};
+use crate::{HitRecord, Hittable, HittableWithBBox, Interval, Materials, Ray, Sampler, Vec3, AABB};
 
 /// Translation Object that takes a shallow copy of the original and translates it position by the offset Vector
 pub struct Translation {
@@ -16,23 +16,52 @@ impl Translation {
             bbox: instance.bbox().translate(offset.clone()),
         }
     }
-}
-impl Hittable for Translation {
-    fn hit(&self, _ray: &Ray, valid_t_interval: Interval) -> Option<HitRecord> {
-        // We are checking the ray that hits where the instance object is (has the offset already applied)
-        // Therefore we will move the ray back to where the original object is, then move the hit back by the offset
-        let translated_ray = Ray {
+    // Implementation Details:
+    // We are checking the ray that hits where the instance object is (has the offset already applied)
+    // Therefore we will move the ray back to where the original object is, then move the hit back by the offset
+    fn translated_ray(&self, _ray: &Ray) -> Ray {
+        Ray {
             origin: _ray.origin.clone() - self.offset.clone(),
             .._ray.clone()
-        };
-        match self.instance.hit(&translated_ray, valid_t_interval) {
-            Some(mut hit_record) => {
-                hit_record.p += self.offset.clone();
-                Some(hit_record)
-            }
-            None => None,
         }
     }
+    fn translate_hit(&self, mut hit_record: HitRecord) -> HitRecord {
+        hit_record.p += self.offset.clone();
+        hit_record
+    }
+}
+impl Hittable for Translation {
+    fn hit(&self, _ray: &Ray, valid_t_interval: Interval) -> Option<HitRecord> {
+        self.instance
+            .hit(&self.translated_ray(_ray), valid_t_interval)
+            .map(|hit_record| self.translate_hit(hit_record))
+    }
+    fn pdf_value(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        self.instance
+            .pdf_value(&(origin.clone() - self.offset.clone()), direction)
+    }
+    fn random(&self, origin: &Vec3, sampler: &mut dyn Sampler) -> Vec3 {
+        self.instance
+            .random(&(origin.clone() - self.offset.clone()), sampler)
+    }
+    fn material(&self) -> Option<&Materials> {
+        self.instance.material()
+    }
+    fn area(&self) -> f64 {
+        self.instance.area()
+    }
+    fn hit_surface(&self, _ray: &Ray, valid_t_interval: Interval) -> Option<HitRecord> {
+        self.instance
+            .hit_surface(&self.translated_ray(_ray), valid_t_interval)
+            .map(|hit_record| self.translate_hit(hit_record))
+    }
+    fn transmittance(&self, _ray: &Ray, valid_t_interval: Interval) -> f64 {
+        self.instance
+            .transmittance(&self.translated_ray(_ray), valid_t_interval)
+    }
+    fn contains_media(&self) -> bool {
+        self.instance.contains_media()
+    }
 }
 impl HittableWithBBox for Translation {
     fn bbox(&self) -> &AABB {
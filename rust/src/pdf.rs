@@ -0,0 +1,231 @@
+use std::f64::consts::PI;
+
+use crate::{Hittable, Sampler, Vec3};
+
+/// Orthonormal Basis, built around a given vector as the w (z-axis)
+/// Used to transform directions sampled around the z-axis to be around any other vector (i.e. a normal)
+pub struct ONB {
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+}
+impl ONB {
+    /// Builds the basis with w pointing in the direction of n
+    pub fn new(n: &Vec3) -> Self {
+        let w = n.unit_vector();
+        // Pick any axis that is not parallel to w to generate the other 2 axes
+        let a = if w.x().abs() > 0.9 {
+            Vec3::new_int(0, 1, 0)
+        } else {
+            Vec3::new_int(1, 0, 0)
+        };
+        let v = Vec3::cross(&w, &a).unit_vector();
+        let u = Vec3::cross(&w, &v);
+        Self { u, v, w }
+    }
+    pub fn u(&self) -> &Vec3 {
+        &self.u
+    }
+    pub fn v(&self) -> &Vec3 {
+        &self.v
+    }
+    pub fn w(&self) -> &Vec3 {
+        &self.w
+    }
+    /// Transforms a vector in the basis cordinates (a, b, c) into world space
+    pub fn local(&self, a: &Vec3) -> Vec3 {
+        a.x() * self.u.clone() + a.y() * self.v.clone() + a.z() * self.w.clone()
+    }
+}
+
+/// Probability Density Function over directions
+/// Used to importance sample the directions of scattered rays
+pub trait PDF: Sync + Send {
+    /// Returns the probability density of generating the given direction
+    fn value(&self, direction: &Vec3) -> f64;
+    /// Generates a random direction, distributed according to the PDF
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3;
+}
+
+/// Uniform PDF over all directions of the unit sphere
+pub struct SpherePDF {}
+impl PDF for SpherePDF {
+    fn value(&self, _direction: &Vec3) -> f64 {
+        1_f64 / (4_f64 * PI)
+    }
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        Vec3::random_unit_vector(sampler)
+    }
+}
+
+/// Cosine weighted PDF over the hemisphere around w (Matches a perfectly diffuse surface)
+pub struct CosinePDF {
+    uvw: ONB,
+}
+impl CosinePDF {
+    pub fn new(w: &Vec3) -> Self {
+        Self { uvw: ONB::new(w) }
+    }
+}
+impl PDF for CosinePDF {
+    fn value(&self, direction: &Vec3) -> f64 {
+        let cosine_theta = Vec3::dot(&direction.unit_vector(), self.uvw.w());
+        f64::max(0_f64, cosine_theta / PI)
+    }
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        self.uvw.local(&Vec3::random_cosine_direction(sampler))
+    }
+}
+
+/// Henyey-Greenstein phase function around the direction the ray was travelling (w)
+/// g in (-1, 1) picks the shape, 0 is uniform, positive g scatters forward and negative g scatters back
+pub struct HenyeyGreensteinPDF {
+    uvw: ONB,
+    g: f64,
+}
+impl HenyeyGreensteinPDF {
+    pub fn new(w: &Vec3, g: f64) -> Self {
+        Self {
+            uvw: ONB::new(w),
+            g,
+        }
+    }
+    /// Density for the cosine of the angle between the ray and the scattered direction
+    pub fn phase(g: f64, cosine_theta: f64) -> f64 {
+        let denominator = 1_f64 + g * g - 2_f64 * g * cosine_theta;
+        (1_f64 - g * g) / (4_f64 * PI * denominator * denominator.sqrt())
+    }
+}
+impl PDF for HenyeyGreensteinPDF {
+    fn value(&self, direction: &Vec3) -> f64 {
+        Self::phase(self.g, Vec3::dot(&direction.unit_vector(), self.uvw.w()))
+    }
+    // Implementation Details:
+    // Inverts the CDF of the cosine, which has a closed form (Uniform in [-1, 1] when g is about 0)
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        let (r1, r2) = sampler.get_2d();
+        let g = self.g;
+        let cosine_theta = if g.abs() < 1e-3 {
+            1_f64 - 2_f64 * r1
+        } else {
+            let term = (1_f64 - g * g) / (1_f64 - g + 2_f64 * g * r1);
+            ((1_f64 + g * g - term * term) / (2_f64 * g)).clamp(-1_f64, 1_f64)
+        };
+        let sine_theta = (1_f64 - cosine_theta * cosine_theta).max(0_f64).sqrt();
+        let phi = 2_f64 * PI * r2;
+        self.uvw.local(&Vec3::new(
+            sine_theta * phi.cos(),
+            sine_theta * phi.sin(),
+            cosine_theta,
+        ))
+    }
+}
+
+/// PDF of directions from origin towards a [Hittable]
+/// Uses [Hittable::pdf_value] and [Hittable::random], so the hittable needs to implement them
+pub struct HittablePDF<'a> {
+    hittable: &'a dyn Hittable,
+    origin: Vec3,
+}
+impl<'a> HittablePDF<'a> {
+    pub fn new(hittable: &'a dyn Hittable, origin: Vec3) -> Self {
+        Self { hittable, origin }
+    }
+}
+impl PDF for HittablePDF<'_> {
+    fn value(&self, direction: &Vec3) -> f64 {
+        self.hittable.pdf_value(&self.origin, direction)
+    }
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        self.hittable.random(&self.origin, sampler)
+    }
+}
+
+/// Equal mixture of 2 PDFs, sampling either one with a probability of 0.5
+pub struct MixturePDF<'a> {
+    pdfs: [&'a dyn PDF; 2],
+}
+impl<'a> MixturePDF<'a> {
+    pub fn new(pdf_0: &'a dyn PDF, pdf_1: &'a dyn PDF) -> Self {
+        Self {
+            pdfs: [pdf_0, pdf_1],
+        }
+    }
+}
+impl PDF for MixturePDF<'_> {
+    fn value(&self, direction: &Vec3) -> f64 {
+        0.5 * self.pdfs[0].value(direction) + 0.5 * self.pdfs[1].value(direction)
+    }
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        if sampler.get_1d() < 0.5 {
+            self.pdfs[0].generate(sampler)
+        } else {
+            self.pdfs[1].generate(sampler)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::IndependentSampler;
+
+    #[test]
+    fn test_onb_local() {
+        let uvw = ONB::new(&Vec3::new_int(0, 0, 2));
+        assert_eq!(uvw.w(), &Vec3::new_int(0, 0, 1));
+        // Basis vectors are orthonormal
+        assert!(Vec3::dot(uvw.u(), uvw.v()).abs() < 1e-8);
+        assert!(Vec3::dot(uvw.u(), uvw.w()).abs() < 1e-8);
+        assert!((uvw.u().length() - 1.0).abs() < 1e-8);
+        assert!((uvw.v().length() - 1.0).abs() < 1e-8);
+        assert_eq!(uvw.local(&Vec3::new_int(0, 0, 1)), Vec3::new_int(0, 0, 1));
+    }
+
+    #[test]
+    fn test_cosine_pdf() {
+        let normal = Vec3::new_int(0, 1, 0);
+        let pdf = CosinePDF::new(&normal);
+        assert_eq!(pdf.value(&Vec3::new_int(0, 1, 0)), 1.0 / PI);
+        assert_eq!(pdf.value(&Vec3::new_int(0, -1, 0)), 0.0);
+        let mut sampler = IndependentSampler::new(0);
+        for _ in 0..100 {
+            let direction = pdf.generate(&mut sampler);
+            assert!(Vec3::dot(&direction, &normal) >= 0.0);
+            assert!(pdf.value(&direction) >= 0.0);
+        }
+    }
+
+    #[test]
+    fn test_henyey_greenstein_pdf() {
+        let forward = Vec3::new_int(0, 0, 1);
+        assert!(
+            (HenyeyGreensteinPDF::new(&forward, 0.0).value(&forward) - 1.0 / (4.0 * PI)).abs()
+                < 1e-12
+        );
+        // The average cosine of the scattered directions is g
+        let mut sampler = IndependentSampler::new(0);
+        for g in [-0.5, 0.0, 0.7] {
+            let pdf = HenyeyGreensteinPDF::new(&forward, g);
+            let count = 20000;
+            let mean_cosine = (0..count)
+                .map(|_| Vec3::dot(&pdf.generate(&mut sampler).unit_vector(), &forward))
+                .sum::<f64>()
+                / count as f64;
+            assert!((mean_cosine - g).abs() < 0.02, "{} {}", g, mean_cosine);
+        }
+        assert!(
+            HenyeyGreensteinPDF::new(&forward, 0.7).value(&forward)
+                > HenyeyGreensteinPDF::new(&forward, 0.7).value(&-forward.clone())
+        );
+    }
+
+    #[test]
+    fn test_mixture_pdf() {
+        let sphere_pdf = SpherePDF {};
+        let cosine_pdf = CosinePDF::new(&Vec3::new_int(0, 1, 0));
+        let mixture_pdf = MixturePDF::new(&sphere_pdf, &cosine_pdf);
+        let direction = Vec3::new_int(0, 1, 0);
+        assert_eq!(mixture_pdf.value(&direction), 0.5 / (4.0 * PI) + 0.5 / PI);
+    }
+}
@@ -0,0 +1,228 @@
+use clap::ValueEnum;
+
+use crate::Ray;
+
+mod halton;
+mod independent;
+mod sobol;
+mod stratified;
+
+pub use halton::HaltonSampler;
+pub use independent::IndependentSampler;
+pub use sobol::SobolSampler;
+pub use stratified::StratifiedSampler;
+
+/// Public Trait for the source of every random number used while rendering (Pixel position, lens, lights and materials)
+/// The samples of a pixel are split into dimensions, each call to [Sampler::get_1d] or [Sampler::get_2d] hands out the next one
+/// Samplers other than [IndependentSampler] spread each dimension well over the samples of the pixel, which lowers the noise
+///
+/// Every value only depends on (seed, pixel, sample index, dimension), so the image does not depend on which thread rendered it
+pub trait Sampler: Send {
+    /// Starts the sample_index-th sample of the pixel (x, y), going back to the first dimension
+    fn start_pixel_sample(&mut self, x: i64, y: i64, sample_index: i64);
+    /// Next dimension, in [0, 1)
+    fn get_1d(&mut self) -> f64;
+    /// Next 2 dimensions, both in [0, 1)
+    /// Use this (instead of 2 calls to [Sampler::get_1d]) for values that are used together, like a point on a square
+    fn get_2d(&mut self) -> (f64, f64);
+
+    /// Picks an index in [0, length) with the next dimension
+    fn get_index(&mut self, length: usize) -> usize {
+        ((self.get_1d() * length as f64) as usize).min(length - 1)
+    }
+}
+
+/// Type of [Sampler] used by the camera
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum SamplerType {
+    /// Uniform random numbers for every dimension (White noise)
+    Independent,
+    /// Jittered samples, with each dimension split into samples_per_pixel strata
+    Stratified,
+    /// Owen scrambled Halton sequence, with a prime base for each dimension
+    Halton,
+    /// Owen scrambled Sobol sequence (Padded 2D points, with a shuffled order per dimension)
+    #[default]
+    Sobol,
+}
+impl SamplerType {
+    /// Creates the sampler, samples_per_pixel is used by the samplers that split the samples into strata
+    pub fn build(&self, seed: u64, samples_per_pixel: i64) -> Box<dyn Sampler> {
+        match self {
+            Self::Independent => Box::new(IndependentSampler::new(seed)),
+            Self::Stratified => Box::new(StratifiedSampler::new(seed, samples_per_pixel)),
+            Self::Halton => Box::new(HaltonSampler::new(seed)),
+            Self::Sobol => Box::new(SobolSampler::new(seed)),
+        }
+    }
+}
+
+/// Largest float below 1, the low discrepancy sequences can round up to 1 otherwise
+const ONE_MINUS_EPSILON: f64 = 1_f64 - f64::EPSILON / 2_f64;
+
+/// SplitMix64 finalizer, scrambles the bits of the value
+fn split_mix(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+/// Hashes all the values together, used to derive the seeds of each pixel and dimension
+pub(crate) fn hash(values: &[u64]) -> u64 {
+    values
+        .iter()
+        .fold(0_u64, |acc, value| split_mix(acc ^ split_mix(*value)))
+}
+/// Uniform float in [0, 1) from the top 53 bits of the value
+pub(crate) fn to_unit_f64(value: u64) -> f64 {
+    (value >> 11) as f64 * (1_f64 / (1_u64 << 53) as f64)
+}
+/// Uniform float in [0, 1) from all 32 bits of the value (Used for the base 2 sequences)
+fn u32_to_unit_f64(value: u32) -> f64 {
+    value as f64 / (1_u64 << 32) as f64
+}
+/// Stream of uniform floats in [0, 1) seeded by a ray, for the participating media
+/// [Hittable::hit](crate::Hittable::hit) does not get a sampler, but every ray of a path starts at a different point, so its hash is as good as a random number
+/// The same ray always gives the same numbers, which keeps the render deterministic (And the AOVs hit the same point as the color)
+pub(crate) struct RayRandom {
+    seed: u64,
+    index: u64,
+}
+impl RayRandom {
+    pub fn new(ray: &Ray) -> Self {
+        let bits: Vec<u64> = ray
+            .origin
+            .xyz()
+            .iter()
+            .chain(ray.direction.xyz().iter())
+            .map(|value| value.to_bits())
+            .collect();
+        Self {
+            seed: hash(&bits),
+            index: 0,
+        }
+    }
+    pub fn get_1d(&mut self) -> f64 {
+        self.index += 1;
+        to_unit_f64(hash(&[self.seed, self.index]))
+    }
+}
+/// Hash of the pixel, which seeds everything of the pixel
+fn pixel_hash(seed: u64, x: i64, y: i64) -> u64 {
+    hash(&[seed, x as u64, y as u64])
+}
+
+// Implementation Details:
+// Kensler's hashed permutation (Correlated Multi-Jittered Sampling, 2013)
+// Returns the position of index in a random permutation of [0, length), the permutation is picked by the seed
+// Works on the next power of 2, and repeats (cycle walking) until the result is inside the length
+fn permutation_element(index: u32, length: u32, seed: u32) -> u32 {
+    let mut w = length - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    let mut i = index;
+    loop {
+        i ^= seed;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= seed >> 16;
+        i ^= (i & w) >> 4;
+        i ^= seed >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= seed >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | seed >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < length {
+            break;
+        }
+    }
+    (i.wrapping_add(seed)) % length
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_permutation_element() {
+        for length in [1, 2, 7, 16, 100] {
+            let mut permuted: Vec<u32> = (0..length)
+                .map(|index| permutation_element(index, length, 1234))
+                .collect();
+            permuted.sort();
+            assert_eq!(permuted, (0..length).collect::<Vec<u32>>());
+        }
+    }
+
+    #[test]
+    fn test_samplers_deterministic() {
+        for sampler_type in SamplerType::value_variants() {
+            let values = |x: i64, sample_index: i64| {
+                let mut sampler = sampler_type.build(7, 16);
+                sampler.start_pixel_sample(x, 20, sample_index);
+                (0..8).map(|_| sampler.get_1d()).collect::<Vec<f64>>()
+            };
+            assert_eq!(values(10, 3), values(10, 3), "{:?}", sampler_type);
+            assert_ne!(values(10, 3), values(11, 3), "{:?}", sampler_type);
+            assert_ne!(values(10, 3), values(10, 4), "{:?}", sampler_type);
+        }
+    }
+
+    #[test]
+    fn test_samplers_stratified() {
+        // Each dimension of the low discrepancy samplers puts exactly one of the 16 samples in each of the 16 strata
+        for sampler_type in [
+            SamplerType::Stratified,
+            SamplerType::Halton,
+            SamplerType::Sobol,
+        ] {
+            let mut sampler = sampler_type.build(3, 16);
+            let mut strata = vec![[0; 16]; 4];
+            for sample_index in 0..16 {
+                sampler.start_pixel_sample(5, 9, sample_index);
+                for dimension_strata in strata.iter_mut() {
+                    let value = sampler.get_1d();
+                    assert!((0.0..1.0).contains(&value));
+                    dimension_strata[(value * 16.0) as usize] += 1;
+                }
+            }
+            if sampler_type == SamplerType::Halton {
+                // Only the base 2 dimension splits evenly into 16
+                assert_eq!(strata[0], [1; 16], "{:?}", sampler_type);
+            } else {
+                assert_eq!(strata, vec![[1; 16]; 4], "{:?}", sampler_type);
+            }
+        }
+    }
+
+    #[test]
+    fn test_samplers_uniform() {
+        for sampler_type in SamplerType::value_variants() {
+            let mut sampler = sampler_type.build(42, 64);
+            let (mut sum, mut count) = (0_f64, 0);
+            for pixel in 0..16 {
+                for sample_index in 0..64 {
+                    sampler.start_pixel_sample(pixel, 0, sample_index);
+                    for _ in 0..40 {
+                        let (u, v) = sampler.get_2d();
+                        sum += u + v;
+                        count += 2;
+                    }
+                }
+            }
+            let mean = sum / count as f64;
+            assert!((mean - 0.5).abs() < 0.01, "{:?} {}", sampler_type, mean);
+        }
+    }
+}
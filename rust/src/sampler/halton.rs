@@ -0,0 +1,90 @@
+use super::{hash, permutation_element, pixel_hash, to_unit_f64, Sampler, ONE_MINUS_EPSILON};
+
+/// Bases of the dimensions, dimensions past the last prime fall back to independent random numbers
+const PRIMES: [u64; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131,
+];
+
+/// Halton sequence, the n-th dimension is the radical inverse of the sample index in the n-th prime base
+/// Each pixel and dimension is Owen scrambled with its own seed, which hides the patterns of the higher bases
+#[derive(Debug, Clone)]
+pub struct HaltonSampler {
+    seed: u64,
+    pixel: u64,
+    sample_index: u64,
+    dimension: usize,
+}
+impl HaltonSampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            pixel: 0,
+            sample_index: 0,
+            dimension: 0,
+        }
+    }
+}
+impl Sampler for HaltonSampler {
+    fn start_pixel_sample(&mut self, x: i64, y: i64, sample_index: i64) {
+        self.pixel = pixel_hash(self.seed, x, y);
+        self.sample_index = sample_index as u64;
+        self.dimension = 0;
+    }
+    fn get_1d(&mut self) -> f64 {
+        let dimension_hash = hash(&[self.pixel, self.dimension as u64]);
+        let value = match PRIMES.get(self.dimension) {
+            Some(base) => {
+                owen_scrambled_radical_inverse(*base, self.sample_index, dimension_hash as u32)
+            }
+            None => to_unit_f64(hash(&[dimension_hash, self.sample_index])),
+        };
+        self.dimension += 1;
+        value
+    }
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.get_1d(), self.get_1d())
+    }
+}
+
+// Implementation Details:
+// Mirrors the digits of index around the decimal point (in the base), 1234 -> 0.4321
+// Owen scrambling permutes every digit, with the permutation depending on all the digits before it (The path in the tree of digits)
+// All the digits are scrambled until the float runs out of precision, as the leading zeros of the index also get permuted
+fn owen_scrambled_radical_inverse(base: u64, index: u64, seed: u32) -> f64 {
+    let limit = u64::MAX / base - base;
+    let inverse_base = 1_f64 / base as f64;
+    let mut inverse_base_m = 1_f64;
+    let mut reversed_digits = 0_u64;
+    let mut remaining = index;
+    while 1_f64 - inverse_base_m < 1_f64 && reversed_digits < limit {
+        let next = remaining / base;
+        let digit = remaining - next * base;
+        let digit_seed = hash(&[seed as u64, reversed_digits]) as u32;
+        let digit = permutation_element(digit as u32, base as u32, digit_seed) as u64;
+        reversed_digits = reversed_digits * base + digit;
+        inverse_base_m *= inverse_base;
+        remaining = next;
+    }
+    (inverse_base_m * reversed_digits as f64).min(ONE_MINUS_EPSILON)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_radical_inverse_strata() {
+        // The first base^2 indices land in different strata of size 1 / base^2
+        for base in [2, 3, 5] {
+            let count = base * base;
+            let mut strata: Vec<usize> = (0..count)
+                .map(|index| {
+                    (owen_scrambled_radical_inverse(base, index, 99) * count as f64) as usize
+                })
+                .collect();
+            strata.sort();
+            assert_eq!(strata, (0..count as usize).collect::<Vec<usize>>());
+        }
+    }
+}
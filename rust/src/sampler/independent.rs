@@ -0,0 +1,62 @@
+use super::{pixel_hash, split_mix, to_unit_f64, Sampler};
+
+/// Uniform random numbers for every dimension, with no stratification across the samples of a pixel
+// Implementation Details:
+// PCG32 (XSH RR variant), which has a small state and supports independent streams through the increment
+// Each pixel gets its own stream, and the sample index picks the starting state inside of it
+#[derive(Debug, Clone)]
+pub struct IndependentSampler {
+    seed: u64,
+    state: u64,
+    /// Selects the stream, always odd
+    increment: u64,
+}
+impl IndependentSampler {
+    const MULTIPLIER: u64 = 6364136223846793005;
+
+    pub fn new(seed: u64) -> Self {
+        let mut sampler = Self {
+            seed,
+            state: 0,
+            increment: 1,
+        };
+        sampler.set_sequence(seed, 0);
+        sampler
+    }
+    /// Restarts the generator on the stream, from a state picked by the initial_state
+    fn set_sequence(&mut self, initial_state: u64, stream: u64) {
+        self.state = 0;
+        self.increment = (stream << 1) | 1;
+        self.step();
+        self.state = self.state.wrapping_add(initial_state);
+        self.step();
+    }
+    fn step(&mut self) {
+        self.state = self
+            .state
+            .wrapping_mul(Self::MULTIPLIER)
+            .wrapping_add(self.increment);
+    }
+    fn next_u32(&mut self) -> u32 {
+        let old_state = self.state;
+        self.step();
+        let xor_shifted = (((old_state >> 18) ^ old_state) >> 27) as u32;
+        let rotation = (old_state >> 59) as u32;
+        xor_shifted.rotate_right(rotation)
+    }
+    fn next_u64(&mut self) -> u64 {
+        ((self.next_u32() as u64) << 32) | self.next_u32() as u64
+    }
+}
+impl Sampler for IndependentSampler {
+    fn start_pixel_sample(&mut self, x: i64, y: i64, sample_index: i64) {
+        let pixel = pixel_hash(self.seed, x, y);
+        self.set_sequence(split_mix(pixel ^ sample_index as u64), pixel);
+    }
+    fn get_1d(&mut self) -> f64 {
+        to_unit_f64(self.next_u64())
+    }
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.get_1d(), self.get_1d())
+    }
+}
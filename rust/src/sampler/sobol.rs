@@ -0,0 +1,115 @@
+use super::{hash, pixel_hash, u32_to_unit_f64, Sampler, ONE_MINUS_EPSILON};
+
+/// Owen scrambled Sobol sampler, following Burley's "Practical Hash-based Owen Scrambling" (2020)
+/// Every 2D dimension is the first 2 dimensions of the Sobol sequence (A (0, 2)-sequence, so any power of 2 samples are stratified in both axes)
+/// The dimensions are decorrelated by shuffling the order of the samples and scrambling the values, with a seed for each pixel and dimension
+#[derive(Debug, Clone)]
+pub struct SobolSampler {
+    seed: u64,
+    pixel: u64,
+    sample_index: u32,
+    dimension: u64,
+}
+impl SobolSampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            pixel: 0,
+            sample_index: 0,
+            dimension: 0,
+        }
+    }
+    /// Seed of the next dimension, and the shuffled sample index for it
+    fn next_dimension(&mut self) -> (u32, u32) {
+        let dimension_seed = hash(&[self.pixel, self.dimension]) as u32;
+        self.dimension += 1;
+        (
+            dimension_seed,
+            nested_uniform_scramble(self.sample_index, dimension_seed),
+        )
+    }
+}
+impl Sampler for SobolSampler {
+    fn start_pixel_sample(&mut self, x: i64, y: i64, sample_index: i64) {
+        self.pixel = pixel_hash(self.seed, x, y);
+        self.sample_index = sample_index as u32;
+        self.dimension = 0;
+    }
+    fn get_1d(&mut self) -> f64 {
+        let (dimension_seed, index) = self.next_dimension();
+        let x = nested_uniform_scramble(index.reverse_bits(), hash_seed(dimension_seed, 0));
+        u32_to_unit_f64(x).min(ONE_MINUS_EPSILON)
+    }
+    fn get_2d(&mut self) -> (f64, f64) {
+        let (dimension_seed, index) = self.next_dimension();
+        let x = nested_uniform_scramble(index.reverse_bits(), hash_seed(dimension_seed, 0));
+        let y =
+            nested_uniform_scramble(sobol_second_dimension(index), hash_seed(dimension_seed, 1));
+        (
+            u32_to_unit_f64(x).min(ONE_MINUS_EPSILON),
+            u32_to_unit_f64(y).min(ONE_MINUS_EPSILON),
+        )
+    }
+}
+
+fn hash_seed(seed: u32, value: u64) -> u32 {
+    hash(&[seed as u64, value]) as u32
+}
+
+// Implementation Details:
+// The generator matrix of the second dimension is the Pascal matrix (mod 2)
+// Its columns are made by xor-ing the previous column with itself shifted by 1, starting from the top bit
+fn sobol_second_dimension(index: u32) -> u32 {
+    let mut result = 0_u32;
+    let mut column = 1_u32 << 31;
+    let mut remaining = index;
+    while remaining != 0 {
+        if remaining & 1 == 1 {
+            result ^= column;
+        }
+        remaining >>= 1;
+        column ^= column >> 1;
+    }
+    result
+}
+
+// Implementation Details:
+// The Laine-Karras permutation only lets each bit depend on the bits below it, which is the same as Owen scrambling the reversed bits
+// So reversing the bits before and after gives an Owen scramble of the binary fraction (Where the top bit is the first digit)
+fn nested_uniform_scramble(value: u32, seed: u32) -> u32 {
+    laine_karras_permutation(value.reverse_bits(), seed).reverse_bits()
+}
+fn laine_karras_permutation(value: u32, seed: u32) -> u32 {
+    let mut x = value.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50b47c);
+    x ^= x.wrapping_mul(0xb82f1e52);
+    x ^= x.wrapping_mul(0xc7afe638);
+    x ^= x.wrapping_mul(0x8d22f6e6);
+    x
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sobol_2d_stratified() {
+        // Any 16 consecutive points (from 0) fall into every cell of the 4x4, 2x8 and 8x2 grids exactly once
+        let mut sampler = SobolSampler::new(11);
+        let points: Vec<(f64, f64)> = (0..16)
+            .map(|sample_index| {
+                sampler.start_pixel_sample(3, 4, sample_index);
+                sampler.get_1d();
+                sampler.get_2d()
+            })
+            .collect();
+        for (x_cells, y_cells) in [(4, 4), (2, 8), (8, 2), (16, 1), (1, 16)] {
+            let mut cells = vec![0; 16];
+            for (u, v) in &points {
+                let cell = (v * y_cells as f64) as usize * x_cells + (u * x_cells as f64) as usize;
+                cells[cell] += 1;
+            }
+            assert_eq!(cells, vec![1; 16], "{}x{}", x_cells, y_cells);
+        }
+    }
+}
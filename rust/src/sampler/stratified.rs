@@ -0,0 +1,71 @@
+use super::{hash, permutation_element, pixel_hash, to_unit_f64, Sampler, ONE_MINUS_EPSILON};
+
+/// Jittered sampling, each dimension is split into samples_per_pixel strata with a random point in each of them
+/// 2D dimensions are split into a grid of (close to square) strata, so both axes are stratified together
+///
+/// The strata are visited in a different (random) order for each dimension, so the dimensions are not correlated
+/// Samples past samples_per_pixel start over with a new order and new points
+#[derive(Debug, Clone)]
+pub struct StratifiedSampler {
+    seed: u64,
+    samples_per_pixel: u32,
+    /// Strata along x and y of the 2D grid, x_strata * y_strata == samples_per_pixel
+    x_strata: u32,
+    y_strata: u32,
+
+    pixel: u64,
+    sample_index: u64,
+    dimension: u64,
+}
+impl StratifiedSampler {
+    pub fn new(seed: u64, samples_per_pixel: i64) -> Self {
+        let samples_per_pixel = samples_per_pixel.max(1) as u32;
+        // Largest divisor that is not over the square root, so the grid is as square as possible
+        let x_strata = (1..=(samples_per_pixel as f64).sqrt() as u32)
+            .rev()
+            .find(|x| samples_per_pixel.is_multiple_of(*x))
+            .unwrap_or(1);
+        Self {
+            seed,
+            samples_per_pixel,
+            x_strata,
+            y_strata: samples_per_pixel / x_strata,
+            pixel: 0,
+            sample_index: 0,
+            dimension: 0,
+        }
+    }
+    /// Stratum of the current sample in the current dimension, and the hash used for the jitter
+    fn next_stratum(&mut self) -> (u32, u64) {
+        let round = self.sample_index / self.samples_per_pixel as u64;
+        let dimension_hash = hash(&[self.pixel, self.dimension, round]);
+        self.dimension += 1;
+        let stratum = permutation_element(
+            (self.sample_index % self.samples_per_pixel as u64) as u32,
+            self.samples_per_pixel,
+            dimension_hash as u32,
+        );
+        (stratum, hash(&[dimension_hash, self.sample_index]))
+    }
+}
+impl Sampler for StratifiedSampler {
+    fn start_pixel_sample(&mut self, x: i64, y: i64, sample_index: i64) {
+        self.pixel = pixel_hash(self.seed, x, y);
+        self.sample_index = sample_index as u64;
+        self.dimension = 0;
+    }
+    fn get_1d(&mut self) -> f64 {
+        let (stratum, jitter_hash) = self.next_stratum();
+        let jitter = to_unit_f64(jitter_hash);
+        ((stratum as f64 + jitter) / self.samples_per_pixel as f64).min(ONE_MINUS_EPSILON)
+    }
+    fn get_2d(&mut self) -> (f64, f64) {
+        let (stratum, jitter_hash) = self.next_stratum();
+        let (x, y) = (stratum % self.x_strata, stratum / self.x_strata);
+        let (jitter_x, jitter_y) = (to_unit_f64(jitter_hash), to_unit_f64(hash(&[jitter_hash])));
+        (
+            ((x as f64 + jitter_x) / self.x_strata as f64).min(ONE_MINUS_EPSILON),
+            ((y as f64 + jitter_y) / self.y_strata as f64).min(ONE_MINUS_EPSILON),
+        )
+    }
+}
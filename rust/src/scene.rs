@@ -0,0 +1,1745 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use clap::ValueEnum;
+use image::Rgb32FImage;
+use toml::Spanned;
+
+use crate::{
+    construct_planar_quad_box, AdaptiveSampling, Background, Camera, CameraParams, Capsule,
+    CheckeredTexture, ColorRamp, ColorRampTexture, ColorTexture, Conductor, Cone, ConstantMedium,
+    Cuboid, Cylinder, DensityField, Dielectric, Diffuse, DirectionalLight, Disk, Emissive,
+    EnvironmentMap, GradientBackground, HeterogeneousMedium, HittableWithBBox, IESProfile, Image,
+    Isotropic, Lambertain, Light, LightList, MarbleTexture, Materials, MediumParams, Metal,
+    NoiseDensity, NoiseTexture, OrientedProfile, Parameter, Plane, PointLight, PrincipledBSDF,
+    Quad, RoughDielectric, SamplerType, SolidBackground, SolidColor, SpatialCheckeredTexture,
+    Sphere, SpotLight, Tagged, TextureSpace, Torus, Transform, Transformed, Triangle, TriangleMesh,
+    Vec3, Vec3Axis, VoronoiTexture, VoxelGrid, WoodTexture, AOV, BVH, GGX,
+};
+
+mod description;
+use description::{
+    BackgroundDescription, LightDescription, MaterialDescription, ObjectDescription,
+    ParameterDescription, SceneDescription, TextureDescription, TransformDescription,
+};
+
+/// Errors from loading a scene file
+#[derive(Debug)]
+pub enum SceneError {
+    /// The scene file could not be read
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// The scene file is not valid TOML, or does not match the scene format (Message includes the location)
+    Parse(toml::de::Error),
+    /// The scene file is valid TOML, but describes an invalid scene (i.e. using a material that does not exist)
+    Invalid {
+        message: String,
+        /// Line in the scene file (Starting from 1)
+        line: usize,
+        /// Column in the scene file (Starting from 1)
+        column: usize,
+    },
+}
+impl Display for SceneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io { path, source } => {
+                write!(
+                    f,
+                    "Could not read scene file {}: {}",
+                    path.display(),
+                    source
+                )
+            }
+            Self::Parse(err) => write!(f, "{}", err),
+            Self::Invalid {
+                message,
+                line,
+                column,
+            } => write!(f, "line {}, column {}: {}", line, column, message),
+        }
+    }
+}
+impl std::error::Error for SceneError {}
+
+/// A scene loaded from a scene file, with everything needed to render it
+/// To load a scene use [Scene::from_file] or [Scene::parse]
+///
+/// Scene files are TOML, with a `[camera]` table (same fields as [CameraParams]), an optional `[background]` table, named `[textures.NAME]` and `[materials.NAME]` tables, a list of `[[objects]]` and a list of `[[lights]]` (Lights without geometry)
+/// Every texture, material, object, light and transform picks what it is with the `type` key
+/// Refer to the scene files in `scenes` for examples
+pub struct Scene {
+    pub camera_params: CameraParams,
+    pub world: BVH,
+    /// Every emissive object in the world, so they can be sampled directly
+    pub lights: LightList,
+}
+impl Scene {
+    /// Loads the scene file at path, any files it uses (images and models) are relative to the scene file
+    pub fn from_file(path: &Path) -> Result<Self, SceneError> {
+        let source = std::fs::read_to_string(path).map_err(|source| SceneError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let base_directory = path.parent().unwrap_or(Path::new(""));
+        Self::parse(&source, base_directory)
+    }
+    /// Loads the scene from the source of a scene file
+    /// Any files the scene uses (images and models) are relative to base_directory
+    pub fn parse(source: &str, base_directory: &Path) -> Result<Self, SceneError> {
+        let description: SceneDescription = toml::from_str(source).map_err(SceneError::Parse)?;
+        SceneBuilder {
+            source,
+            base_directory,
+            description: &description,
+            textures: HashMap::new(),
+            building_textures: Vec::new(),
+            materials: HashMap::new(),
+        }
+        .build()
+    }
+    /// Renders the scene with its camera, refer to [Camera::render_output] for the errors
+    pub fn render(self) -> std::io::Result<Rgb32FImage> {
+        let camera = Camera::new(self.camera_params);
+        camera.render_framebuffer_with_lights(&self.world, &self.lights)
+    }
+}
+
+/// Builds the library types from a [SceneDescription], resolving the names of the textures and materials
+struct SceneBuilder<'a> {
+    source: &'a str,
+    base_directory: &'a Path,
+    description: &'a SceneDescription,
+    textures: HashMap<String, Arc<dyn ColorTexture>>,
+    /// Stack of the textures currently being built, to catch textures that reference themselves
+    building_textures: Vec<String>,
+    materials: HashMap<String, Materials>,
+}
+impl SceneBuilder<'_> {
+    fn build(mut self) -> Result<Scene, SceneError> {
+        let camera = &self.description.camera;
+        let default_params = CameraParams::default();
+        let at_least_one = |value: &Option<Spanned<i64>>, field: &str, default: i64| {
+            value.as_ref().map_or(Ok(default), |value| {
+                self.in_range(value, *value.get_ref() >= 1, field, "at least 1")
+            })
+        };
+        let image_width = at_least_one(
+            &camera.image_width,
+            "image_width",
+            default_params.image_width,
+        )?;
+        let aspect_ratio = camera
+            .aspect_ratio
+            .as_ref()
+            .map_or(default_params.aspect_ratio, |aspect_ratio| {
+                *aspect_ratio.get_ref()
+            });
+        // The height is derived, so a wrong one is blamed on the aspect ratio (or the width, if the ratio is the default)
+        if ((image_width as f64 / aspect_ratio) as i64) < 1 {
+            let span = match (&camera.aspect_ratio, &camera.image_width) {
+                (Some(aspect_ratio), _) => aspect_ratio.span(),
+                (None, Some(image_width)) => image_width.span(),
+                (None, None) => 0..0,
+            };
+            return Err(self.error(
+                span,
+                format!(
+                    "`image_width` {} with `aspect_ratio` {} gives an image height under 1",
+                    image_width, aspect_ratio
+                ),
+            ));
+        }
+        let camera_params = CameraParams {
+            image_width,
+            aspect_ratio,
+            samples_per_pixel: at_least_one(
+                &camera.samples_per_pixel,
+                "samples_per_pixel",
+                default_params.samples_per_pixel,
+            )?,
+            max_depth: at_least_one(&camera.max_depth, "max_depth", default_params.max_depth)?,
+            fov: camera.fov.unwrap_or(default_params.fov),
+            look_from: camera.look_from.map_or(default_params.look_from, to_vec3),
+            look_at: camera.look_at.map_or(default_params.look_at, to_vec3),
+            v_up: camera.v_up.map_or(default_params.v_up, to_vec3),
+            focus_angle: camera.focus_angle.unwrap_or(default_params.focus_angle),
+            focus_distance: camera
+                .focus_distance
+                .unwrap_or(default_params.focus_distance),
+            background: match (&self.description.background, camera.background) {
+                (Some(background), None) => self.background(background)?,
+                (None, Some(color)) => Arc::new(SolidBackground {
+                    color: to_vec3(color),
+                }),
+                (None, None) => default_params.background,
+                (Some(background), Some(_)) => {
+                    return Err(self.error(
+                        background.span(),
+                        "`[background]` can not be used with `camera.background`".to_string(),
+                    ))
+                }
+            },
+            analytic_lights: self
+                .description
+                .lights
+                .iter()
+                .map(|light| self.light(light))
+                .collect::<Result<_, _>>()?,
+            tile_size: at_least_one(&camera.tile_size, "tile_size", default_params.tile_size)?,
+            seed: camera.seed.unwrap_or(default_params.seed),
+            sampler: match &camera.sampler {
+                Some(sampler) => match SamplerType::from_str(sampler.get_ref(), true) {
+                    Ok(sampler_type) => sampler_type,
+                    Err(_) => {
+                        return Err(self.error(
+                            sampler.span(),
+                            format!(
+                                "unknown sampler `{}`, expected one of independent, stratified, halton, sobol",
+                                sampler.get_ref()
+                            ),
+                        ))
+                    }
+                },
+                None => default_params.sampler,
+            },
+            adaptive_sampling: AdaptiveSampling::from_options(
+                camera.adaptive_threshold,
+                camera.min_samples_per_pixel,
+                camera.time_budget,
+            ),
+            aovs: self.aovs()?,
+            // Checkpoints are picked when rendering (i.e. with the CLI), not by the scene
+            checkpointing: None,
+            resume: None,
+        };
+
+        let mut hittables: Vec<Arc<dyn HittableWithBBox>> = Vec::new();
+        let mut light_candidates: Vec<Arc<dyn HittableWithBBox>> = Vec::new();
+        for (index, object) in self.description.objects.iter().enumerate() {
+            let (hittable, mesh_lights) = self.object(object)?;
+            light_candidates.push(hittable.clone());
+            light_candidates.extend(mesh_lights);
+            hittables.push(self.tag(hittable, index, object.get_ref()));
+        }
+
+        Ok(Scene {
+            camera_params,
+            world: BVH::from_hittables_list(hittables),
+            lights: LightList::from_hittables(&light_candidates),
+        })
+    }
+
+    fn aovs(&self) -> Result<Vec<AOV>, SceneError> {
+        let mut aovs = Vec::new();
+        for aov in &self.description.camera.aovs {
+            match AOV::from_str(aov.get_ref(), true) {
+                Ok(parsed) => aovs.push(parsed),
+                Err(_) => {
+                    return Err(self.error(
+                        aov.span(),
+                        format!(
+                            "unknown AOV `{}`, expected one of albedo, normal, depth, position, uv, material-id, object-id, direct, indirect",
+                            aov.get_ref()
+                        ),
+                    ))
+                }
+            }
+        }
+        Ok(aovs)
+    }
+
+    fn background(
+        &self,
+        background: &Spanned<BackgroundDescription>,
+    ) -> Result<Arc<dyn Background>, SceneError> {
+        let background = background.get_ref();
+        let kind = &background.kind;
+        Ok(match kind.get_ref().as_str() {
+            "solid" => Arc::new(SolidBackground {
+                color: to_vec3(self.required(&background.color, kind, "color")?),
+            }),
+            "gradient" => {
+                let sky = GradientBackground::sky();
+                Arc::new(GradientBackground {
+                    bottom: background.bottom.map_or(sky.bottom, to_vec3),
+                    top: background.top.map_or(sky.top, to_vec3),
+                })
+            }
+            "environment" => {
+                let path = self.required(&background.path, kind, "path")?;
+                let resolved_path = self.path(&path)?;
+                Arc::new(
+                    EnvironmentMap::new(
+                        &resolved_path,
+                        background.rotation.unwrap_or(0_f64),
+                        background.intensity.unwrap_or(1_f64),
+                    )
+                    .map_err(|err| {
+                        self.error(
+                            path.span(),
+                            format!(
+                                "could not load environment map `{}`: {}",
+                                resolved_path, err
+                            ),
+                        )
+                    })?,
+                )
+            }
+            other => {
+                return Err(self.error(
+                    kind.span(),
+                    format!(
+                    "unknown background type `{}`, expected one of solid, gradient, environment",
+                    other
+                ),
+                ))
+            }
+        })
+    }
+
+    fn light(&self, light: &Spanned<LightDescription>) -> Result<Arc<dyn Light>, SceneError> {
+        let light = light.get_ref();
+        let kind = &light.kind;
+        let color =
+            light.color.map_or(Vec3::new_int(1, 1, 1), to_vec3) * light.intensity.unwrap_or(1_f64);
+        Ok(match kind.get_ref().as_str() {
+            "point" => {
+                let nadir = light.direction.map_or(Vec3::new_int(0, -1, 0), to_vec3);
+                Arc::new(PointLight {
+                    position: to_vec3(self.required(&light.position, kind, "position")?),
+                    intensity: color,
+                    profile: self.oriented_profile(&light.profile, nadir)?,
+                })
+            }
+            "spot" => {
+                let direction = to_vec3(self.required(&light.direction, kind, "direction")?);
+                Arc::new(SpotLight {
+                    position: to_vec3(self.required(&light.position, kind, "position")?),
+                    profile: self.oriented_profile(&light.profile, direction.clone())?,
+                    direction,
+                    intensity: color,
+                    cone_angle: light.cone_angle.unwrap_or(30_f64),
+                    falloff: light.falloff.unwrap_or(5_f64),
+                })
+            }
+            "directional" => Arc::new(DirectionalLight {
+                direction: to_vec3(self.required(&light.direction, kind, "direction")?),
+                irradiance: color,
+                angular_radius: light.angular_radius.unwrap_or(0_f64),
+            }),
+            other => {
+                return Err(self.error(
+                    kind.span(),
+                    format!(
+                        "unknown light type `{}`, expected one of point, spot, directional",
+                        other
+                    ),
+                ))
+            }
+        })
+    }
+
+    fn ies_profile(&self, path: &Spanned<String>) -> Result<Arc<IESProfile>, SceneError> {
+        let resolved_path = self.path(path)?;
+        let profile = IESProfile::from_file(Path::new(&resolved_path)).map_err(|err| {
+            self.error(
+                path.span(),
+                format!("could not load IES profile `{}`: {}", resolved_path, err),
+            )
+        })?;
+        Ok(Arc::new(profile))
+    }
+    fn oriented_profile(
+        &self,
+        path: &Option<Spanned<String>>,
+        nadir: Vec3,
+    ) -> Result<Option<OrientedProfile>, SceneError> {
+        match path {
+            Some(path) => Ok(Some(OrientedProfile {
+                profile: self.ies_profile(path)?,
+                nadir,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    /// Creates the error, pointing to the start of span in the scene file
+    fn error(&self, span: Range<usize>, message: String) -> SceneError {
+        let before = &self.source[..span.start.min(self.source.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+        SceneError::Invalid {
+            message,
+            line,
+            column,
+        }
+    }
+    /// Gets a field that is required by the `type` of the entry
+    fn required<T: Clone>(
+        &self,
+        value: &Option<T>,
+        kind: &Spanned<String>,
+        field: &str,
+    ) -> Result<T, SceneError> {
+        value.clone().ok_or_else(|| {
+            self.error(
+                kind.span(),
+                format!("`{}` requires the field `{}`", kind.get_ref(), field),
+            )
+        })
+    }
+    /// Gets a number of the scene, or errors at it when it is out of range (i.e. a negative size)
+    fn in_range<T: Copy + Display>(
+        &self,
+        value: &Spanned<T>,
+        valid: bool,
+        field: &str,
+        expected: &str,
+    ) -> Result<T, SceneError> {
+        if !valid {
+            return Err(self.error(
+                value.span(),
+                format!(
+                    "`{}` must be {}, found {}",
+                    field,
+                    expected,
+                    value.get_ref()
+                ),
+            ));
+        }
+        Ok(*value.get_ref())
+    }
+    /// Gets a radius that is required by the `type` of the entry, which has to be positive
+    fn radius(
+        &self,
+        value: &Option<Spanned<f64>>,
+        kind: &Spanned<String>,
+        field: &str,
+    ) -> Result<f64, SceneError> {
+        let radius = self.required(value, kind, field)?;
+        self.in_range(&radius, *radius.get_ref() > 0_f64, field, "greater than 0")
+    }
+    /// Resolves a path in the scene file relative to the scene file, checking that it exists
+    fn path(&self, path: &Spanned<String>) -> Result<String, SceneError> {
+        let resolved = self.base_directory.join(path.get_ref());
+        if !resolved.exists() {
+            return Err(self.error(
+                path.span(),
+                format!("file `{}` does not exist", resolved.display()),
+            ));
+        }
+        Ok(resolved.to_string_lossy().to_string())
+    }
+
+    fn texture(&mut self, name: &Spanned<String>) -> Result<Arc<dyn ColorTexture>, SceneError> {
+        if let Some(texture) = self.textures.get(name.get_ref()) {
+            return Ok(texture.clone());
+        }
+        let description = self.description;
+        let texture = match description.textures.get(name.get_ref()) {
+            Some(texture) => texture,
+            None => {
+                return Err(self.error(name.span(), format!("unknown texture `{}`", name.get_ref())))
+            }
+        };
+        if self.building_textures.contains(name.get_ref()) {
+            return Err(self.error(
+                name.span(),
+                format!("texture `{}` references itself", name.get_ref()),
+            ));
+        }
+
+        self.building_textures.push(name.get_ref().clone());
+        let built_texture = self.build_texture(texture.get_ref());
+        self.building_textures.pop();
+
+        let built_texture = built_texture?;
+        self.textures
+            .insert(name.get_ref().clone(), built_texture.clone());
+        Ok(built_texture)
+    }
+    fn build_texture(
+        &mut self,
+        texture: &TextureDescription,
+    ) -> Result<Arc<dyn ColorTexture>, SceneError> {
+        let kind = &texture.kind;
+        Ok(match kind.get_ref().as_str() {
+            "solid" => Arc::new(SolidColor {
+                color: to_vec3(self.required(&texture.color, kind, "color")?),
+            }),
+            "checkered" | "spatial_checkered" => {
+                let scale = self.required(&texture.scale, kind, "scale")?;
+                let even = self.texture(&self.required(&texture.even, kind, "even")?)?;
+                let odd = self.texture(&self.required(&texture.odd, kind, "odd")?)?;
+                if kind.get_ref() == "checkered" {
+                    Arc::new(CheckeredTexture::new(scale, even, odd))
+                } else {
+                    Arc::new(SpatialCheckeredTexture::new(scale, even, odd))
+                }
+            }
+            "image" => {
+                let path = self.required(&texture.path, kind, "path")?;
+                let resolved_path = self.path(&path)?;
+                let fill: Arc<dyn ColorTexture> = match &texture.fill {
+                    Some(fill) => self.texture(fill)?,
+                    None => Arc::new(SolidColor {
+                        color: Vec3::new(0.0, 1.0, 1.0),
+                    }),
+                };
+                Arc::new(
+                    Image::try_new(texture.scale.unwrap_or(1.0), &resolved_path, fill).map_err(
+                        |err| {
+                            self.error(
+                                path.span(),
+                                format!("could not load image `{}`: {}", resolved_path, err),
+                            )
+                        },
+                    )?,
+                )
+            }
+            "noise" | "turbulence" => {
+                let mut noise = NoiseTexture::new(
+                    texture.seed.unwrap_or(0),
+                    self.texture_space(texture)?,
+                    texture.scale.unwrap_or(1.0),
+                );
+                noise.turbulence = kind.get_ref() == "turbulence";
+                noise.octaves = texture.octaves.unwrap_or(noise.octaves);
+                noise.ramp = self.ramp(texture, noise.ramp)?;
+                Arc::new(noise)
+            }
+            "marble" => {
+                let mut marble = MarbleTexture::new(
+                    texture.seed.unwrap_or(0),
+                    self.texture_space(texture)?,
+                    texture.scale.unwrap_or(1.0),
+                );
+                marble.octaves = texture.octaves.unwrap_or(marble.octaves);
+                marble.turbulence = texture.turbulence.unwrap_or(marble.turbulence);
+                marble.ramp = self.ramp(texture, marble.ramp)?;
+                Arc::new(marble)
+            }
+            "wood" => {
+                let mut wood = WoodTexture::new(
+                    texture.seed.unwrap_or(0),
+                    self.texture_space(texture)?,
+                    texture.scale.unwrap_or(1.0),
+                );
+                wood.octaves = texture.octaves.unwrap_or(wood.octaves);
+                wood.turbulence = texture.turbulence.unwrap_or(wood.turbulence);
+                wood.ramp = self.ramp(texture, wood.ramp)?;
+                Arc::new(wood)
+            }
+            "voronoi" => {
+                let mut voronoi = VoronoiTexture::new(
+                    texture.seed.unwrap_or(0),
+                    self.texture_space(texture)?,
+                    texture.scale.unwrap_or(1.0),
+                );
+                voronoi.border_width = texture.border_width.unwrap_or(voronoi.border_width);
+                if let Some(border_color) = texture.border_color {
+                    voronoi.border_color = to_vec3(border_color);
+                }
+                voronoi.ramp = self.ramp(texture, voronoi.ramp)?;
+                Arc::new(voronoi)
+            }
+            "ramp" => {
+                let input = self.texture(&self.required(&texture.input, kind, "input")?)?;
+                self.required(&texture.colors, kind, "colors")?;
+                // The default is never used, the colors are required
+                let ramp = self.ramp(texture, ColorRamp::from_colors(vec![Vec3::default()]))?;
+                Arc::new(ColorRampTexture { input, ramp })
+            }
+            other => {
+                return Err(self.error(
+                    kind.span(),
+                    format!(
+                        "unknown texture type `{}`, expected one of solid, checkered, spatial_checkered, image, noise, turbulence, marble, wood, voronoi, ramp",
+                        other
+                    ),
+                ))
+            }
+        })
+    }
+    fn texture_space(&self, texture: &TextureDescription) -> Result<TextureSpace, SceneError> {
+        Ok(match &texture.space {
+            None => TextureSpace::Solid,
+            Some(space) => match space.get_ref().as_str() {
+                "solid" => TextureSpace::Solid,
+                "uv" => TextureSpace::UV,
+                other => {
+                    return Err(self.error(
+                        space.span(),
+                        format!("unknown texture space `{}`, expected solid or uv", other),
+                    ))
+                }
+            },
+        })
+    }
+    /// The colors of the texture, or the default ramp
+    fn ramp(
+        &self,
+        texture: &TextureDescription,
+        default: ColorRamp,
+    ) -> Result<ColorRamp, SceneError> {
+        match &texture.colors {
+            None => Ok(default),
+            Some(colors) if colors.is_empty() => Err(self.error(
+                texture.kind.span(),
+                "`colors` needs at least 1 color".to_string(),
+            )),
+            Some(colors) => Ok(ColorRamp::from_colors(
+                colors.iter().map(|color| to_vec3(*color)).collect(),
+            )),
+        }
+    }
+
+    fn material(&mut self, name: &Spanned<String>) -> Result<Materials, SceneError> {
+        if let Some(material) = self.materials.get(name.get_ref()) {
+            return Ok(material.clone());
+        }
+        let description = self.description;
+        let material = match description.materials.get(name.get_ref()) {
+            Some(material) => material,
+            None => {
+                return Err(self.error(
+                    name.span(),
+                    format!("unknown material `{}`", name.get_ref()),
+                ))
+            }
+        };
+        let built_material = self.build_material(material.get_ref())?;
+        self.materials
+            .insert(name.get_ref().clone(), built_material.clone());
+        Ok(built_material)
+    }
+    fn build_material(&mut self, material: &MaterialDescription) -> Result<Materials, SceneError> {
+        let kind = &material.kind;
+        Ok(match kind.get_ref().as_str() {
+            "lambertain" => Materials::ScatterMaterial(Arc::new(Lambertain {
+                albedo: self.albedo(material)?,
+            })),
+            "metal" => Materials::ScatterMaterial(Arc::new(Metal::new(
+                self.albedo(material)?,
+                material.fuzz.unwrap_or(0.0),
+            ))),
+            "dielectric" => Materials::ScatterMaterial(Arc::new(Dielectric {
+                index_of_reflectance: self.required(
+                    &material.index_of_refraction,
+                    kind,
+                    "index_of_refraction",
+                )?,
+            })),
+            "conductor" => {
+                let distribution = self.distribution(material)?;
+                let conductor = match (&material.metal, material.eta, material.k) {
+                    (Some(metal), None, None) => match metal.get_ref().as_str() {
+                        "gold" => Conductor::gold(distribution),
+                        "copper" => Conductor::copper(distribution),
+                        "aluminium" => Conductor::aluminium(distribution),
+                        other => {
+                            return Err(self.error(
+                                metal.span(),
+                                format!(
+                                    "unknown metal `{}`, expected one of gold, copper, aluminium",
+                                    other
+                                ),
+                            ))
+                        }
+                    },
+                    (None, Some(eta), Some(k)) => Conductor {
+                        eta: to_vec3(eta),
+                        k: to_vec3(k),
+                        distribution,
+                    },
+                    _ => {
+                        return Err(self.error(
+                            kind.span(),
+                            "`conductor` requires either the field `metal`, or both `eta` and `k`"
+                                .to_string(),
+                        ))
+                    }
+                };
+                Materials::ScatterMaterial(Arc::new(conductor))
+            }
+            "rough_dielectric" => Materials::ScatterMaterial(Arc::new(RoughDielectric {
+                index_of_refraction: self.required(
+                    &material.index_of_refraction,
+                    kind,
+                    "index_of_refraction",
+                )?,
+                distribution: self.distribution(material)?,
+            })),
+            "principled" => {
+                let defaults = PrincipledBSDF::default();
+                let base_color =
+                    self.parameter(kind, &material.base_color, to_vec3, defaults.base_color)?;
+                let mut scalar = |value: &Option<ParameterDescription<f64>>, default| {
+                    self.parameter(kind, value, |value| value, default)
+                };
+                Materials::ScatterMaterial(Arc::new(PrincipledBSDF {
+                    base_color,
+                    metallic: scalar(&material.metallic, defaults.metallic)?,
+                    roughness: scalar(&material.roughness, defaults.roughness)?,
+                    specular: scalar(&material.specular, defaults.specular)?,
+                    specular_tint: scalar(&material.specular_tint, defaults.specular_tint)?,
+                    sheen: scalar(&material.sheen, defaults.sheen)?,
+                    sheen_tint: scalar(&material.sheen_tint, defaults.sheen_tint)?,
+                    clearcoat: scalar(&material.clearcoat, defaults.clearcoat)?,
+                    clearcoat_roughness: scalar(
+                        &material.clearcoat_roughness,
+                        defaults.clearcoat_roughness,
+                    )?,
+                    transmission: scalar(&material.transmission, defaults.transmission)?,
+                    index_of_refraction: material
+                        .index_of_refraction
+                        .map_or(defaults.index_of_refraction, Parameter::Constant),
+                    anisotropy: scalar(&material.anisotropy, defaults.anisotropy)?,
+                }))
+            }
+            "isotropic" => Materials::ScatterMaterial(Arc::new(Isotropic {
+                albedo: self.albedo(material)?,
+            })),
+            "diffuse_light" => Materials::LightMaterial(Arc::new(Diffuse {
+                power: self.required(&material.power, kind, "power")?,
+            })),
+            "emissive" => {
+                let mut emissive = Emissive::new(
+                    self.albedo(material)?,
+                    material.intensity.unwrap_or(1.0),
+                    material.two_sided.unwrap_or(true),
+                );
+                if let Some(path) = &material.profile {
+                    emissive.profile = Some(self.ies_profile(path)?);
+                }
+                Materials::LightMaterial(Arc::new(emissive))
+            }
+            other => {
+                return Err(self.error(
+                    kind.span(),
+                    format!(
+                        "unknown material type `{}`, expected one of lambertain, metal, dielectric, conductor, rough_dielectric, principled, isotropic, diffuse_light, emissive",
+                        other
+                    ),
+                ))
+            }
+        })
+    }
+    /// Microfacet distribution of a material, smooth unless it has a roughness
+    fn distribution(&self, material: &MaterialDescription) -> Result<GGX, SceneError> {
+        let constant = |value: &Option<ParameterDescription<f64>>, field: &str| match value {
+            None => Ok(0.0),
+            Some(ParameterDescription::Constant(value)) => Ok(*value),
+            Some(ParameterDescription::Texture(_)) => Err(self.error(
+                material.kind.span(),
+                format!(
+                    "`{}` requires a number for `{}`, it can not use a texture",
+                    material.kind.get_ref(),
+                    field
+                ),
+            )),
+        };
+        Ok(GGX::from_roughness(
+            constant(&material.roughness, "roughness")?,
+            constant(&material.anisotropy, "anisotropy")?,
+        ))
+    }
+    /// Parameter of a principled material, the constant (converted) or the named texture, or the default when it is not given
+    fn parameter<T: Clone, U>(
+        &mut self,
+        kind: &Spanned<String>,
+        value: &Option<ParameterDescription<T>>,
+        convert: impl Fn(T) -> U,
+        default: Parameter<U>,
+    ) -> Result<Parameter<U>, SceneError> {
+        Ok(match value {
+            None => default,
+            Some(ParameterDescription::Constant(value)) => {
+                Parameter::Constant(convert(value.clone()))
+            }
+            Some(ParameterDescription::Texture(name)) => {
+                Parameter::Texture(self.texture(&Spanned::new(kind.span(), name.clone()))?)
+            }
+        })
+    }
+    /// Albedo of a material, either the named texture or the solid color shorthand
+    fn albedo(
+        &mut self,
+        material: &MaterialDescription,
+    ) -> Result<Arc<dyn ColorTexture>, SceneError> {
+        match (&material.albedo, material.color) {
+            (Some(albedo), _) => self.texture(albedo),
+            (None, Some(color)) => Ok(Arc::new(SolidColor {
+                color: to_vec3(color),
+            })),
+            (None, None) => Err(self.error(
+                material.kind.span(),
+                format!(
+                    "`{}` requires the field `albedo` or `color`",
+                    material.kind.get_ref()
+                ),
+            )),
+        }
+    }
+
+    /// Builds the object with its transforms applied
+    /// Also returns the emissive triangles of meshes (with the same transforms), so they can be added to the lights
+    #[allow(clippy::type_complexity)]
+    fn object(
+        &mut self,
+        object: &Spanned<ObjectDescription>,
+    ) -> Result<(Arc<dyn HittableWithBBox>, Vec<Arc<dyn HittableWithBBox>>), SceneError> {
+        let object = object.get_ref();
+        let kind = &object.kind;
+        let mut mesh_lights = Vec::new();
+        let hittable: Arc<dyn HittableWithBBox> = match kind.get_ref().as_str() {
+            "sphere" => Arc::new(Sphere::new(
+                to_vec3(self.required(&object.center, kind, "center")?),
+                self.radius(&object.radius, kind, "radius")?,
+                self.object_material(object)?,
+            )),
+            "quad" => Arc::new(Quad::new(
+                to_vec3(self.required(&object.q, kind, "q")?),
+                to_vec3(self.required(&object.u, kind, "u")?),
+                to_vec3(self.required(&object.v, kind, "v")?),
+                self.object_material(object)?,
+            )),
+            "triangle" => Arc::new(Triangle::new(
+                to_vec3(self.required(&object.q, kind, "q")?),
+                to_vec3(self.required(&object.u, kind, "u")?),
+                to_vec3(self.required(&object.v, kind, "v")?),
+                self.object_material(object)?,
+            )),
+            "disk" => Arc::new(Disk::new(
+                to_vec3(self.required(&object.center, kind, "center")?),
+                to_vec3(self.required(&object.u, kind, "u")?),
+                to_vec3(self.required(&object.v, kind, "v")?),
+                self.radius(&object.radius, kind, "radius")?,
+                self.object_material(object)?,
+            )),
+            "cylinder" => Arc::new(Cylinder::new(
+                to_vec3(self.required(&object.base, kind, "base")?),
+                self.radius(&object.radius, kind, "radius")?,
+                self.required(&object.height, kind, "height")?,
+                object.closed.unwrap_or(true),
+                self.object_material(object)?,
+            )),
+            "cone" => Arc::new(Cone::new(
+                to_vec3(self.required(&object.base, kind, "base")?),
+                self.radius(&object.radius, kind, "radius")?,
+                self.required(&object.height, kind, "height")?,
+                object.closed.unwrap_or(true),
+                self.object_material(object)?,
+            )),
+            "capsule" => Arc::new(Capsule::new(
+                to_vec3(self.required(&object.base, kind, "base")?),
+                self.radius(&object.radius, kind, "radius")?,
+                self.required(&object.height, kind, "height")?,
+                self.object_material(object)?,
+            )),
+            "torus" => Arc::new(Torus::new(
+                to_vec3(self.required(&object.center, kind, "center")?),
+                self.radius(&object.major_radius, kind, "major_radius")?,
+                self.radius(&object.minor_radius, kind, "minor_radius")?,
+                self.object_material(object)?,
+            )),
+            "plane" => Arc::new(Plane::new(
+                to_vec3(self.required(&object.point, kind, "point")?),
+                to_vec3(self.required(&object.normal, kind, "normal")?),
+                self.object_material(object)?,
+            )),
+            "cuboid" => Arc::new(Cuboid::new(
+                to_vec3(self.required(&object.a, kind, "a")?),
+                to_vec3(self.required(&object.b, kind, "b")?),
+                self.object_material(object)?,
+            )),
+            "box" => Arc::new(construct_planar_quad_box(
+                &to_vec3(self.required(&object.a, kind, "a")?),
+                &to_vec3(self.required(&object.b, kind, "b")?),
+                self.object_material(object)?,
+            )),
+            "mesh" => {
+                let path = self.required(&object.path, kind, "path")?;
+                let resolved_path = self.path(&path)?;
+                // Faces without a .mtl material fall back to the object material (or grey)
+                let default_material = match &object.material {
+                    Some(material) => self.material(material)?,
+                    None => Materials::ScatterMaterial(Arc::new(Lambertain {
+                        albedo: Arc::new(SolidColor {
+                            color: Vec3::new(0.5, 0.5, 0.5),
+                        }),
+                    })),
+                };
+                let mesh = TriangleMesh::from_obj(&resolved_path, default_material)
+                    .map_err(|err| {
+                        self.error(
+                            path.span(),
+                            format!("could not load mesh `{}`: {}", resolved_path, err),
+                        )
+                    })?;
+                for triangle in mesh.triangles() {
+                    if let Some(Materials::LightMaterial(_)) = triangle.material() {
+                        mesh_lights.push(self.transform(triangle.clone(), &object.transforms)?);
+                    }
+                }
+                Arc::new(mesh)
+            }
+            "volume" => Arc::new(self.volume(object)?),
+            other => {
+                return Err(self.error(
+                    kind.span(),
+                    format!(
+                        "unknown object type `{}`, expected one of sphere, quad, triangle, disk, box, cuboid, cylinder, cone, torus, capsule, plane, mesh, volume",
+                        other
+                    ),
+                ))
+            }
+        };
+        let hittable = self.transform(hittable, &object.transforms)?;
+        match object.density {
+            // Volumes use the density themselves
+            Some(density) if kind.get_ref() != "volume" => {
+                Ok((self.medium(hittable, density, object)?, mesh_lights))
+            }
+            _ => Ok((hittable, mesh_lights)),
+        }
+    }
+    /// Builds a [HeterogeneousMedium] from a density grid file, or from noise
+    fn volume(&self, object: &ObjectDescription) -> Result<HeterogeneousMedium, SceneError> {
+        let kind = &object.kind;
+        let a = to_vec3(self.required(&object.a, kind, "a")?);
+        let b = to_vec3(self.required(&object.b, kind, "b")?);
+        let density: Arc<dyn DensityField> = match &object.path {
+            Some(path) => {
+                let resolved_path = self.path(path)?;
+                let grid = if resolved_path.ends_with(".raw") {
+                    let resolution = self.required(&object.resolution, kind, "resolution")?;
+                    VoxelGrid::from_raw(Path::new(&resolved_path), resolution)
+                } else {
+                    VoxelGrid::from_vol(Path::new(&resolved_path))
+                };
+                Arc::new(grid.map_err(|err| {
+                    self.error(
+                        path.span(),
+                        format!("could not load volume `{}`: {}", resolved_path, err),
+                    )
+                })?)
+            }
+            None => Arc::new(NoiseDensity::new(
+                object.seed.unwrap_or(0),
+                object.frequency.unwrap_or(4.0),
+                object.octaves.unwrap_or(5),
+            )),
+        };
+        let defaults = MediumParams::default();
+        let scale = object.density.unwrap_or(1.0);
+        Ok(HeterogeneousMedium::new(
+            density,
+            a,
+            b,
+            MediumParams {
+                absorption: scale * object.absorption.unwrap_or(defaults.absorption),
+                scattering: scale * object.scattering.unwrap_or(defaults.scattering),
+                emission: object.emission.map_or(defaults.emission, to_vec3),
+                color: object.color.map_or(defaults.color, to_vec3),
+                anisotropy: object.anisotropy.unwrap_or(defaults.anisotropy),
+            },
+        ))
+    }
+    /// Fills the (transformed) object with a [ConstantMedium], using the albedo of its `isotropic` material
+    fn medium(
+        &mut self,
+        boundary: Arc<dyn HittableWithBBox>,
+        density: f64,
+        object: &ObjectDescription,
+    ) -> Result<Arc<dyn HittableWithBBox>, SceneError> {
+        let material = self.required(&object.material, &object.kind, "material")?;
+        let description = self.description;
+        let material_description = match description.materials.get(material.get_ref()) {
+            Some(material_description) => material_description.get_ref(),
+            None => {
+                return Err(self.error(
+                    material.span(),
+                    format!("unknown material `{}`", material.get_ref()),
+                ))
+            }
+        };
+        if material_description.kind.get_ref() != "isotropic" {
+            return Err(self.error(
+                material.span(),
+                format!(
+                    "objects with a `density` require an `isotropic` material, `{}` is `{}`",
+                    material.get_ref(),
+                    material_description.kind.get_ref()
+                ),
+            ));
+        }
+        let albedo = self.albedo(material_description)?;
+        Ok(Arc::new(ConstantMedium::new(boundary, density, albedo)))
+    }
+    /// Tags the object with its position in the scene file, and its material with the position in `[materials]` (Both starting at 1)
+    /// Meshes keep the material IDs untagged, as their faces can use the materials of the `.mtl` file
+    // Implementation Details:
+    // The materials are in a map sorted by name, so the position is found by where each one starts in the file
+    fn tag(
+        &self,
+        hittable: Arc<dyn HittableWithBBox>,
+        index: usize,
+        object: &ObjectDescription,
+    ) -> Arc<dyn HittableWithBBox> {
+        let material_id = match (&object.material, object.kind.get_ref().as_str()) {
+            (_, "mesh") | (None, _) => None,
+            (Some(material), _) => {
+                let materials = &self.description.materials;
+                materials.get(material.get_ref()).map(|used| {
+                    materials
+                        .values()
+                        .filter(|other| other.span().start < used.span().start)
+                        .count() as u32
+                        + 1
+                })
+            }
+        };
+        Arc::new(Tagged::new(hittable, index as u32 + 1, material_id))
+    }
+    fn object_material(&mut self, object: &ObjectDescription) -> Result<Materials, SceneError> {
+        let material = self.required(&object.material, &object.kind, "material")?;
+        self.material(&material)
+    }
+    /// Combines the transforms (applied in order) and wraps the hittable in a single [Transformed]
+    fn transform(
+        &self,
+        hittable: Arc<dyn HittableWithBBox>,
+        transforms: &[Spanned<TransformDescription>],
+    ) -> Result<Arc<dyn HittableWithBBox>, SceneError> {
+        if transforms.is_empty() {
+            return Ok(hittable);
+        }
+        let combined_transform = transforms.iter().try_fold(
+            Transform::identity(),
+            |combined_transform, transform| {
+                let transform = transform.get_ref();
+                let kind = &transform.kind;
+                let next_transform = match kind.get_ref().as_str() {
+                    "translate" => Transform::translate(to_vec3(self.required(
+                        &transform.offset,
+                        kind,
+                        "offset",
+                    )?)),
+                    "rotate" => {
+                        let axis = self.required(&transform.axis, kind, "axis")?;
+                        let rotation_axis = match axis.get_ref().as_str() {
+                            "x" => Vec3Axis::X,
+                            "y" => Vec3Axis::Y,
+                            "z" => Vec3Axis::Z,
+                            other => {
+                                return Err(self.error(
+                                    axis.span(),
+                                    format!("unknown axis `{}`, expected one of x, y, z", other),
+                                ))
+                            }
+                        };
+                        Transform::rotate_about_axis(
+                            &rotation_axis,
+                            self.required(&transform.angle, kind, "angle")?,
+                        )
+                    }
+                    "scale" => {
+                        let factor = self.required(&transform.factor, kind, "factor")?;
+                        if factor.contains(&0_f64) {
+                            return Err(
+                                self.error(kind.span(), "scale factor can not be 0".to_string())
+                            );
+                        }
+                        Transform::scale(to_vec3(factor))
+                    }
+                    other => {
+                        return Err(self.error(
+                            kind.span(),
+                            format!(
+                            "unknown transform type `{}`, expected one of translate, rotate, scale",
+                            other
+                        ),
+                        ))
+                    }
+                };
+                Ok(combined_transform.then(&next_transform))
+            },
+        )?;
+        Ok(Arc::new(Transformed::new(hittable, combined_transform)))
+    }
+}
+
+fn to_vec3(v: [f64; 3]) -> Vec3 {
+    Vec3::new(v[0], v[1], v[2])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(source: &str) -> Result<Scene, SceneError> {
+        Scene::parse(source, Path::new("."))
+    }
+    fn invalid_location(result: Result<Scene, SceneError>) -> (String, usize, usize) {
+        match result {
+            Err(SceneError::Invalid {
+                message,
+                line,
+                column,
+            }) => (message, line, column),
+            Err(err) => panic!("Expected an invalid scene error, got {}", err),
+            Ok(_) => panic!("Expected an invalid scene error, got a scene"),
+        }
+    }
+
+    #[test]
+    fn test_scene_parse() {
+        let scene = parse(
+            r#"
+[camera]
+image_width = 100
+background = [0.0, 0.0, 0.0]
+aovs = ["albedo", "object-id"]
+
+[textures.white]
+type = "solid"
+color = [0.9, 0.9, 0.9]
+
+[textures.checker]
+type = "checkered"
+scale = 10.0
+even = "white"
+odd = "white"
+
+[materials.ground]
+type = "lambertain"
+albedo = "checker"
+
+[materials.light]
+type = "diffuse_light"
+power = 4.0
+
+[[objects]]
+type = "sphere"
+center = [0.0, -1000.0, 0.0]
+radius = 1000.0
+material = "ground"
+
+[[objects]]
+type = "quad"
+q = [0.0, 5.0, 0.0]
+u = [1.0, 0.0, 0.0]
+v = [0.0, 0.0, 1.0]
+material = "light"
+transforms = [
+    { type = "rotate", axis = "y", angle = 45.0 },
+    { type = "translate", offset = [0.0, 1.0, 0.0] },
+]
+"#,
+        )
+        .unwrap();
+        assert_eq!(scene.camera_params.image_width, 100);
+        assert_eq!(scene.camera_params.aovs, vec![AOV::Albedo, AOV::ObjectID]);
+        // Fields that are not given use the defaults
+        assert_eq!(scene.camera_params.samples_per_pixel, 50);
+        assert_eq!(
+            scene
+                .camera_params
+                .background
+                .color(&Vec3::new_int(0, 1, 0)),
+            Vec3::new_int(0, 0, 0)
+        );
+        // The transformed quad is still found as a light
+        assert_eq!(scene.lights.len(), 1);
+    }
+
+    #[test]
+    fn test_scene_material_ids() {
+        use crate::{Hittable, Interval, Ray};
+
+        let scene = parse(
+            r#"
+[materials.zebra]
+type = "lambertain"
+color = [0.5, 0.5, 0.5]
+
+[materials.apple]
+type = "lambertain"
+color = [0.5, 0.5, 0.5]
+
+[[objects]]
+type = "sphere"
+center = [0.0, 0.0, 0.0]
+radius = 1.0
+material = "apple"
+
+[[objects]]
+type = "sphere"
+center = [5.0, 0.0, 0.0]
+radius = 1.0
+material = "zebra"
+"#,
+        )
+        .unwrap();
+        // In the order the materials are written, not by name
+        let material_id = |x: f64| {
+            scene
+                .world
+                .hit(
+                    &Ray {
+                        origin: Vec3::new(x, 0.0, 5.0),
+                        direction: Vec3::new_int(0, 0, -1),
+                    },
+                    Interval {
+                        min: 0.001,
+                        max: f64::INFINITY,
+                    },
+                )
+                .unwrap()
+                .material_id
+        };
+        assert_eq!(material_id(0.0), 2);
+        assert_eq!(material_id(5.0), 1);
+    }
+
+    #[test]
+    fn test_scene_unknown_material() {
+        let (message, line, column) = invalid_location(parse(
+            r#"
+[[objects]]
+type = "sphere"
+center = [0.0, 0.0, 0.0]
+radius = 1.0
+material = "missing"
+"#,
+        ));
+        assert_eq!(message, "unknown material `missing`");
+        assert_eq!((line, column), (6, 12));
+    }
+
+    /// Error of a scene with only the given camera settings
+    fn invalid_camera(settings: &str) -> (String, usize, usize) {
+        invalid_location(parse(&format!("\n[camera]\n{}\n", settings)))
+    }
+
+    #[test]
+    fn test_scene_invalid_image_width() {
+        let (message, line, column) = invalid_camera("image_width = -5");
+        assert_eq!(message, "`image_width` must be at least 1, found -5");
+        assert_eq!((line, column), (3, 15));
+    }
+
+    #[test]
+    fn test_scene_invalid_image_height() {
+        let (message, line, column) = invalid_camera("image_width = 50\naspect_ratio = 100.0");
+        assert_eq!(
+            message,
+            "`image_width` 50 with `aspect_ratio` 100 gives an image height under 1"
+        );
+        assert_eq!((line, column), (4, 16));
+    }
+
+    #[test]
+    fn test_scene_invalid_samples_per_pixel() {
+        let (message, line, column) = invalid_camera("samples_per_pixel = 0");
+        assert_eq!(message, "`samples_per_pixel` must be at least 1, found 0");
+        assert_eq!((line, column), (3, 21));
+    }
+
+    #[test]
+    fn test_scene_invalid_max_depth() {
+        let (message, line, column) = invalid_camera("max_depth = -1");
+        assert_eq!(message, "`max_depth` must be at least 1, found -1");
+        assert_eq!((line, column), (3, 13));
+    }
+
+    #[test]
+    fn test_scene_invalid_tile_size() {
+        let (message, line, column) = invalid_camera("tile_size = 0");
+        assert_eq!(message, "`tile_size` must be at least 1, found 0");
+        assert_eq!((line, column), (3, 13));
+    }
+
+    #[test]
+    fn test_scene_invalid_radius() {
+        let (message, line, column) = invalid_location(parse(
+            r#"
+[[objects]]
+type = "sphere"
+center = [0.0, 0.0, 0.0]
+radius = -1.0
+"#,
+        ));
+        assert_eq!(message, "`radius` must be greater than 0, found -1");
+        assert_eq!((line, column), (5, 10));
+        let (message, line, _) = invalid_location(parse(
+            r#"
+[[objects]]
+type = "cylinder"
+base = [0.0, 0.0, 0.0]
+radius = 0.0
+height = 1.0
+"#,
+        ));
+        assert_eq!(message, "`radius` must be greater than 0, found 0");
+        assert_eq!(line, 5);
+    }
+
+    #[test]
+    fn test_scene_missing_field() {
+        let (message, line, _) = invalid_location(parse(
+            r#"
+[materials.red]
+type = "lambertain"
+
+[[objects]]
+type = "sphere"
+center = [0.0, 0.0, 0.0]
+radius = 1.0
+material = "red"
+"#,
+        ));
+        assert_eq!(
+            message,
+            "`lambertain` requires the field `albedo` or `color`"
+        );
+        assert_eq!(line, 3);
+
+        let (message, line, _) = invalid_location(parse(
+            r#"
+[materials.red]
+type = "lambertain"
+color = [1.0, 0.0, 0.0]
+
+[[objects]]
+type = "sphere"
+center = [0.0, 0.0, 0.0]
+material = "red"
+"#,
+        ));
+        assert_eq!(message, "`sphere` requires the field `radius`");
+        assert_eq!(line, 7);
+    }
+
+    #[test]
+    fn test_scene_primitives() {
+        use crate::{Hittable, Interval, Ray};
+
+        let source = r#"
+[materials.white]
+type = "lambertain"
+color = [0.8, 0.8, 0.8]
+
+[[objects]]
+type = "plane"
+point = [0.0, 0.0, 0.0]
+normal = [0.0, 1.0, 0.0]
+material = "white"
+
+[[objects]]
+type = "cylinder"
+base = [-6.0, 0.0, 0.0]
+radius = 1.0
+height = 2.0
+material = "white"
+
+[[objects]]
+type = "cone"
+base = [-3.0, 0.0, 0.0]
+radius = 1.0
+height = 3.0
+closed = false
+material = "white"
+
+[[objects]]
+type = "torus"
+center = [0.0, 0.5, 0.0]
+major_radius = 1.0
+minor_radius = 0.5
+material = "white"
+
+[[objects]]
+type = "capsule"
+base = [3.0, 1.0, 0.0]
+radius = 0.5
+height = 1.0
+material = "white"
+
+[[objects]]
+type = "cuboid"
+a = [5.0, 0.0, -1.0]
+b = [7.0, 1.5, 1.0]
+material = "white"
+"#;
+        let scene = parse(source).unwrap();
+        // Straight down onto each object, or the plane between them
+        let hit_t = |x: f64| {
+            scene
+                .world
+                .hit(
+                    &Ray {
+                        origin: Vec3::new(x, 10_f64, 0_f64),
+                        direction: Vec3::new_int(0, -1, 0),
+                    },
+                    Interval {
+                        min: 0.001,
+                        max: f64::INFINITY,
+                    },
+                )
+                .unwrap()
+                .t
+        };
+        for (x, t) in [
+            (-6_f64, 8_f64),
+            (-2.5, 8.5),
+            (1_f64, 9_f64),
+            (3_f64, 7.5),
+            (6_f64, 8.5),
+            (10_f64, 10_f64),
+            (-100_f64, 10_f64),
+        ] {
+            assert!((hit_t(x) - t).abs() < 1e-9, "x = {}: {}", x, hit_t(x));
+        }
+
+        let (message, line, _) = invalid_location(parse(&source.replace("major_radius", "radius")));
+        assert_eq!(message, "`torus` requires the field `major_radius`");
+        assert_eq!(line, 28);
+    }
+
+    #[test]
+    fn test_scene_medium_material() {
+        let (message, line, column) = invalid_location(parse(
+            r#"
+[materials.red]
+type = "lambertain"
+color = [1.0, 0.0, 0.0]
+
+[[objects]]
+type = "sphere"
+center = [0.0, 0.0, 0.0]
+radius = 1.0
+material = "red"
+density = 0.5
+"#,
+        ));
+        assert_eq!(
+            message,
+            "objects with a `density` require an `isotropic` material, `red` is `lambertain`"
+        );
+        assert_eq!((line, column), (10, 12));
+    }
+
+    #[test]
+    fn test_scene_conductor_material() {
+        let source = r#"
+[materials.gold]
+type = "conductor"
+METAL
+roughness = 0.3
+anisotropy = 0.5
+
+[materials.frosted]
+type = "rough_dielectric"
+index_of_refraction = 1.5
+roughness = 0.2
+
+[[objects]]
+type = "sphere"
+center = [0.0, 0.0, 0.0]
+radius = 1.0
+material = "gold"
+
+[[objects]]
+type = "sphere"
+center = [3.0, 0.0, 0.0]
+radius = 1.0
+material = "frosted"
+"#;
+        assert!(parse(&source.replace("METAL", r#"metal = "gold""#)).is_ok());
+        assert!(
+            parse(&source.replace("METAL", "eta = [0.2, 0.9, 1.1]\nk = [3.9, 2.5, 2.1]")).is_ok()
+        );
+        let (message, line, column) =
+            invalid_location(parse(&source.replace("METAL", r#"metal = "tin""#)));
+        assert_eq!(
+            message,
+            "unknown metal `tin`, expected one of gold, copper, aluminium"
+        );
+        assert_eq!((line, column), (4, 9));
+        let (message, _, _) =
+            invalid_location(parse(&source.replace("METAL", "eta = [0.2, 0.9, 1.1]")));
+        assert_eq!(
+            message,
+            "`conductor` requires either the field `metal`, or both `eta` and `k`"
+        );
+    }
+
+    #[test]
+    fn test_scene_principled_material() {
+        let source = r#"
+[textures.rust]
+type = "noise"
+scale = 4.0
+
+[materials.paint]
+type = "principled"
+base_color = [0.6, 0.05, 0.05]
+metallic = "METALLIC"
+roughness = 0.4
+clearcoat = 1.0
+
+[[objects]]
+type = "sphere"
+center = [0.0, 0.0, 0.0]
+radius = 1.0
+material = "paint"
+"#;
+        assert!(parse(&source.replace("METALLIC", "rust")).is_ok());
+        let (message, line, _) = invalid_location(parse(&source.replace("METALLIC", "missing")));
+        assert_eq!(message, "unknown texture `missing`");
+        assert_eq!(line, 7);
+    }
+
+    #[test]
+    fn test_scene_lights() {
+        let source = r#"
+[materials.white]
+type = "lambertain"
+color = [0.8, 0.8, 0.8]
+
+[[objects]]
+type = "sphere"
+center = [0.0, 0.0, 0.0]
+radius = 1.0
+material = "white"
+
+[[lights]]
+type = "point"
+position = [0.0, 4.0, 0.0]
+intensity = 20.0
+
+[[lights]]
+type = "spot"
+position = [4.0, 4.0, 0.0]
+direction = [-1.0, -1.0, 0.0]
+color = [1.0, 0.9, 0.8]
+cone_angle = 20.0
+
+[[lights]]
+type = "KIND"
+direction = [0.0, -1.0, -1.0]
+angular_radius = 0.5
+"#;
+        let scene = parse(&source.replace("KIND", "directional")).unwrap();
+        assert_eq!(scene.camera_params.analytic_lights.len(), 3);
+        // Lights are not geometry, they can not be sampled as emissive objects
+        assert!(scene.lights.is_empty());
+        let (message, line, _) = invalid_location(parse(&source.replace("KIND", "area")));
+        assert_eq!(
+            message,
+            "unknown light type `area`, expected one of point, spot, directional"
+        );
+        assert_eq!(line, 25);
+        let (message, _, _) = invalid_location(parse(&source.replace("KIND", "spot")));
+        assert_eq!(message, "`spot` requires the field `position`");
+    }
+
+    #[test]
+    fn test_scene_ies_profiles() {
+        let source = r#"
+[materials.panel]
+type = "emissive"
+color = [1.0, 1.0, 1.0]
+intensity = 4.0
+profile = "PROFILE"
+
+[[objects]]
+type = "quad"
+q = [0.0, 2.0, 0.0]
+u = [1.0, 0.0, 0.0]
+v = [0.0, 0.0, 1.0]
+material = "panel"
+
+[[lights]]
+type = "point"
+position = [0.0, 4.0, 0.0]
+profile = "assets/ies/wallwasher.ies"
+"#;
+        let scene = parse(&source.replace("PROFILE", "assets/ies/downlight.ies")).unwrap();
+        assert_eq!(scene.camera_params.analytic_lights.len(), 1);
+        assert_eq!(scene.lights.len(), 1);
+        let (message, line, _) =
+            invalid_location(parse(&source.replace("PROFILE", "scenes/cornell-box.toml")));
+        assert!(
+            message.starts_with("could not load IES profile"),
+            "{}",
+            message
+        );
+        assert_eq!(line, 6);
+    }
+
+    #[test]
+    fn test_scene_texture_cycle() {
+        let (message, _, _) = invalid_location(parse(
+            r#"
+[textures.a]
+type = "checkered"
+scale = 1.0
+even = "a"
+odd = "a"
+
+[materials.red]
+type = "lambertain"
+albedo = "a"
+
+[[objects]]
+type = "sphere"
+center = [0.0, 0.0, 0.0]
+radius = 1.0
+material = "red"
+"#,
+        ));
+        assert_eq!(message, "texture `a` references itself");
+    }
+
+    #[test]
+    fn test_scene_procedural_textures() {
+        let source = r#"
+[camera]
+image_width = 10
+
+[textures.clouds]
+type = "turbulence"
+space = "uv"
+scale = 4.0
+seed = 3
+
+[textures.sunset]
+type = "ramp"
+input = "clouds"
+colors = [[1.0, 0.5, 0.0], [0.2, 0.0, 0.4]]
+
+[textures.cells]
+type = "voronoi"
+scale = 2.0
+border_width = 0.05
+border_color = [1.0, 1.0, 1.0]
+colors = [[0.2, 0.2, 0.2], [0.8, 0.1, 0.1], [0.1, 0.1, 0.8]]
+
+[textures.wood]
+type = "wood"
+space = "SPACE"
+turbulence = 0.2
+
+[materials.sky]
+type = "lambertain"
+albedo = "sunset"
+
+[materials.tiles]
+type = "lambertain"
+albedo = "cells"
+
+[materials.table]
+type = "lambertain"
+albedo = "wood"
+
+[[objects]]
+type = "sphere"
+center = [0.0, 0.0, 0.0]
+radius = 1.0
+material = "sky"
+
+[[objects]]
+type = "sphere"
+center = [3.0, 0.0, 0.0]
+radius = 1.0
+material = "tiles"
+
+[[objects]]
+type = "sphere"
+center = [6.0, 0.0, 0.0]
+radius = 1.0
+material = "table"
+"#;
+        assert!(parse(&source.replace("SPACE", "solid")).is_ok());
+        let (message, _, _) = invalid_location(parse(&source.replace("SPACE", "planar")));
+        assert_eq!(
+            message,
+            "unknown texture space `planar`, expected solid or uv"
+        );
+    }
+
+    #[test]
+    fn test_scene_parse_error() {
+        assert!(matches!(
+            parse("[camera]\nimage_width = \"wide\"\n"),
+            Err(SceneError::Parse(_))
+        ));
+        // Unknown keys are errors, to catch typos
+        assert!(matches!(
+            parse("[camera]\nimage_widht = 100\n"),
+            Err(SceneError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_scene_unloadable_files() {
+        // Files that exist but can not be decoded are errors at their path, instead of panics
+        let (message, line, _) = invalid_location(parse(
+            r#"
+[background]
+type = "environment"
+path = "assets/models/cube.obj"
+"#,
+        ));
+        assert!(
+            message.starts_with("could not load environment map `./assets/models/cube.obj`"),
+            "{}",
+            message
+        );
+        assert_eq!(line, 4);
+
+        let (message, line, _) = invalid_location(parse(
+            r#"
+[textures.broken]
+type = "image"
+path = "assets/models/cube.mtl"
+
+[materials.broken]
+type = "lambertain"
+albedo = "broken"
+
+[[objects]]
+type = "sphere"
+center = [0.0, 0.0, 0.0]
+radius = 1.0
+material = "broken"
+"#,
+        ));
+        assert!(
+            message.starts_with("could not load image `./assets/models/cube.mtl`"),
+            "{}",
+            message
+        );
+        assert_eq!(line, 4);
+    }
+
+    #[test]
+    fn test_scene_files() {
+        for entry in std::fs::read_dir("scenes").unwrap() {
+            let path = entry.unwrap().path();
+            if let Err(err) = Scene::from_file(&path) {
+                panic!("{}: {}", path.display(), err);
+            }
+        }
+    }
+}
@@ -0,0 +1,276 @@
+// Raw structure of a scene file, as it is deserialized from TOML
+// Every entry is kept with its [Spanned] location, so that validation errors can point back into the file
+// Each entry uses a `type` key with optional fields (Instead of a tagged enum), as serde's tagged enums lose the locations
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use toml::Spanned;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct SceneDescription {
+    #[serde(default)]
+    pub camera: CameraDescription,
+    pub background: Option<Spanned<BackgroundDescription>>,
+    #[serde(default)]
+    pub textures: BTreeMap<String, Spanned<TextureDescription>>,
+    #[serde(default)]
+    pub materials: BTreeMap<String, Spanned<MaterialDescription>>,
+    #[serde(default)]
+    pub objects: Vec<Spanned<ObjectDescription>>,
+    #[serde(default)]
+    pub lights: Vec<Spanned<LightDescription>>,
+}
+
+/// Material parameter that is either a constant, or the name of a texture
+/// The name loses its location (Untagged enums buffer the value), so errors point at the material instead
+#[derive(Deserialize, Clone)]
+#[serde(untagged)]
+pub(super) enum ParameterDescription<T> {
+    Constant(T),
+    Texture(String),
+}
+
+/// Mirrors [CameraParams](crate::CameraParams), any missing field uses the default value
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub(super) struct CameraDescription {
+    pub image_width: Option<Spanned<i64>>,
+    pub aspect_ratio: Option<Spanned<f64>>,
+    pub samples_per_pixel: Option<Spanned<i64>>,
+    pub max_depth: Option<Spanned<i64>>,
+    pub fov: Option<f64>,
+    pub look_from: Option<[f64; 3]>,
+    pub look_at: Option<[f64; 3]>,
+    pub v_up: Option<[f64; 3]>,
+    pub focus_angle: Option<f64>,
+    pub focus_distance: Option<f64>,
+    pub background: Option<[f64; 3]>,
+    pub tile_size: Option<Spanned<i64>>,
+    pub seed: Option<u64>,
+    /// One of `independent`, `stratified`, `halton` or `sobol`
+    pub sampler: Option<Spanned<String>>,
+    /// Any of the adaptive sampling settings turns on adaptive sampling
+    pub adaptive_threshold: Option<f64>,
+    pub min_samples_per_pixel: Option<i64>,
+    /// In seconds
+    pub time_budget: Option<f64>,
+    /// Extra passes, any of `albedo`, `normal`, `depth`, `position`, `uv`, `material-id`, `object-id`, `direct` or `indirect`
+    #[serde(default)]
+    pub aovs: Vec<Spanned<String>>,
+}
+
+/// `type` is one of `solid`, `gradient` or `environment`
+/// Overrides `camera.background`, which is a shorthand for a solid background
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct BackgroundDescription {
+    #[serde(rename = "type")]
+    pub kind: Spanned<String>,
+    /// solid
+    pub color: Option<[f64; 3]>,
+    /// gradient, color looking straight down
+    pub bottom: Option<[f64; 3]>,
+    /// gradient, color looking straight up
+    pub top: Option<[f64; 3]>,
+    /// environment, equirectangular image relative to the scene file
+    pub path: Option<Spanned<String>>,
+    /// environment, in degrees around the y axis
+    pub rotation: Option<f64>,
+    /// environment, multiplier for the brightness
+    pub intensity: Option<f64>,
+}
+
+/// `type` is one of `point`, `spot` or `directional`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct LightDescription {
+    #[serde(rename = "type")]
+    pub kind: Spanned<String>,
+    /// point and spot
+    pub position: Option<[f64; 3]>,
+    /// spot and directional, where the light shines
+    /// point, where the nadir of the `profile` points (Straight down by default)
+    pub direction: Option<[f64; 3]>,
+    /// point and spot, `.ies` photometric profile relative to the scene file
+    pub profile: Option<Spanned<String>>,
+    pub color: Option<[f64; 3]>,
+    /// Multiplier for the color, the intensity of point and spot and the irradiance of directional
+    pub intensity: Option<f64>,
+    /// spot, in degrees
+    pub cone_angle: Option<f64>,
+    /// spot, in degrees
+    pub falloff: Option<f64>,
+    /// directional, in degrees
+    pub angular_radius: Option<f64>,
+}
+
+/// `type` is one of `solid`, `checkered`, `spatial_checkered`, `image`, the procedural `noise`, `turbulence`, `marble`, `wood` or `voronoi`, or `ramp`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct TextureDescription {
+    #[serde(rename = "type")]
+    pub kind: Spanned<String>,
+    /// solid
+    pub color: Option<[f64; 3]>,
+    /// checkered, spatial_checkered and image, and the procedural ones (noise, turbulence, marble, wood and voronoi)
+    pub scale: Option<f64>,
+    /// checkered and spatial_checkered, name of another texture
+    pub even: Option<Spanned<String>>,
+    /// checkered and spatial_checkered, name of another texture
+    pub odd: Option<Spanned<String>>,
+    /// image, relative to the scene file
+    pub path: Option<Spanned<String>>,
+    /// image, name of the texture used outside of the image
+    pub fill: Option<Spanned<String>>,
+    /// procedural, "solid" (default, on the world point) or "uv"
+    pub space: Option<Spanned<String>>,
+    /// procedural, seed of the noise
+    pub seed: Option<u64>,
+    /// noise, turbulence, marble and wood, octaves of noise
+    pub octaves: Option<u32>,
+    /// marble and wood, how much the noise bends the pattern
+    pub turbulence: Option<f64>,
+    /// procedural and ramp, colors spread evenly over the gradient
+    pub colors: Option<Vec<[f64; 3]>>,
+    /// voronoi, width of the borders between cells
+    pub border_width: Option<f64>,
+    /// voronoi
+    pub border_color: Option<[f64; 3]>,
+    /// ramp, name of the texture whose luminance picks the color
+    pub input: Option<Spanned<String>>,
+}
+
+/// `type` is one of `lambertain`, `metal`, `dielectric`, `conductor` (with a `metal` preset of `gold`, `copper` or `aluminium`, or its own `eta` and `k`), `rough_dielectric`, `principled`, `isotropic`, `diffuse_light` or `emissive`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct MaterialDescription {
+    #[serde(rename = "type")]
+    pub kind: Spanned<String>,
+    /// lambertain, metal, isotropic and emissive, name of a texture
+    pub albedo: Option<Spanned<String>>,
+    /// lambertain, metal, isotropic and emissive, shorthand for a solid albedo texture
+    pub color: Option<[f64; 3]>,
+    /// metal
+    pub fuzz: Option<f64>,
+    /// dielectric and rough_dielectric
+    pub index_of_refraction: Option<f64>,
+    /// conductor, preset of gold, copper or aluminium (Instead of eta and k)
+    pub metal: Option<Spanned<String>>,
+    /// conductor, real part of the index of refraction per color channel
+    pub eta: Option<[f64; 3]>,
+    /// conductor, imaginary part of the index of refraction per color channel
+    pub k: Option<[f64; 3]>,
+    /// conductor, rough_dielectric and principled, in [0, 1]
+    pub roughness: Option<ParameterDescription<f64>>,
+    /// conductor, rough_dielectric and principled, in [0, 1)
+    pub anisotropy: Option<ParameterDescription<f64>>,
+    /// principled
+    pub base_color: Option<ParameterDescription<[f64; 3]>>,
+    /// principled
+    pub metallic: Option<ParameterDescription<f64>>,
+    /// principled
+    pub specular: Option<ParameterDescription<f64>>,
+    /// principled
+    pub specular_tint: Option<ParameterDescription<f64>>,
+    /// principled
+    pub sheen: Option<ParameterDescription<f64>>,
+    /// principled
+    pub sheen_tint: Option<ParameterDescription<f64>>,
+    /// principled
+    pub clearcoat: Option<ParameterDescription<f64>>,
+    /// principled
+    pub clearcoat_roughness: Option<ParameterDescription<f64>>,
+    /// principled
+    pub transmission: Option<ParameterDescription<f64>>,
+    /// diffuse_light
+    pub power: Option<f64>,
+    /// emissive, scales the color
+    pub intensity: Option<f64>,
+    /// emissive, false to only emit from the front face
+    pub two_sided: Option<bool>,
+    /// emissive, `.ies` photometric profile relative to the scene file, aimed along the normal
+    pub profile: Option<Spanned<String>>,
+}
+
+/// `type` is one of `sphere`, `quad`, `triangle`, `disk`, `box`, `cuboid`, `cylinder`, `cone`, `torus`, `capsule`, `plane`, `mesh` or `volume`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct ObjectDescription {
+    #[serde(rename = "type")]
+    pub kind: Spanned<String>,
+    /// Name of a material, mesh uses it for faces without a `.mtl` material
+    pub material: Option<Spanned<String>>,
+    /// sphere, disk and torus
+    pub center: Option<[f64; 3]>,
+    /// sphere, disk, cylinder, cone and capsule
+    pub radius: Option<Spanned<f64>>,
+    /// cylinder and cone, center of the bottom end
+    /// capsule, center of the bottom hemisphere
+    pub base: Option<[f64; 3]>,
+    /// cylinder, cone and capsule, standing up along Y from the base
+    pub height: Option<f64>,
+    /// cylinder and cone, capped with a disk at the ends (Defaults to true)
+    pub closed: Option<bool>,
+    /// torus, radius of the circle through the middle of the tube
+    pub major_radius: Option<Spanned<f64>>,
+    /// torus, radius of the tube
+    pub minor_radius: Option<Spanned<f64>>,
+    /// plane, any point on it
+    pub point: Option<[f64; 3]>,
+    /// plane, the direction it faces
+    pub normal: Option<[f64; 3]>,
+    /// quad and triangle, the corner point
+    pub q: Option<[f64; 3]>,
+    /// quad, triangle and disk
+    pub u: Option<[f64; 3]>,
+    /// quad, triangle and disk
+    pub v: Option<[f64; 3]>,
+    /// box, cuboid and volume, one corner
+    pub a: Option<[f64; 3]>,
+    /// box, cuboid and volume, the opposite corner
+    pub b: Option<[f64; 3]>,
+    /// mesh, `.obj` file relative to the scene file
+    /// volume, `.vol` or `.raw` density grid relative to the scene file (Without it, the density is procedural noise)
+    pub path: Option<Spanned<String>>,
+    /// Fills the object with a volume of this density instead (i.e. smoke), the material has to be `isotropic`
+    /// volume, multiplies the density of the grid or noise
+    pub density: Option<f64>,
+    /// volume, size of a `.raw` grid
+    pub resolution: Option<[usize; 3]>,
+    /// volume, seed of the noise
+    pub seed: Option<u64>,
+    /// volume, number of noise cells across the volume
+    pub frequency: Option<f64>,
+    /// volume, octaves of noise
+    pub octaves: Option<u32>,
+    /// volume, absorption coefficient
+    pub absorption: Option<f64>,
+    /// volume, scattering coefficient
+    pub scattering: Option<f64>,
+    /// volume, light emitted where the medium absorbs
+    pub emission: Option<[f64; 3]>,
+    /// volume, tint of the scattered light
+    pub color: Option<[f64; 3]>,
+    /// volume, Henyey-Greenstein g in (-1, 1), positive scatters forward
+    pub anisotropy: Option<f64>,
+    /// Applied in order, after the object is created
+    #[serde(default)]
+    pub transforms: Vec<Spanned<TransformDescription>>,
+}
+
+/// `type` is one of `translate`, `rotate` or `scale`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct TransformDescription {
+    #[serde(rename = "type")]
+    pub kind: Spanned<String>,
+    /// translate
+    pub offset: Option<[f64; 3]>,
+    /// rotate, one of `x`, `y` or `z`
+    pub axis: Option<Spanned<String>>,
+    /// rotate, in degrees
+    pub angle: Option<f64>,
+    /// scale, the factor for each axis
+    pub factor: Option<[f64; 3]>,
+}
@@ -2,9 +2,14 @@ use crate::Vec3;
 
 mod color;
 mod image;
+mod procedural;
 
 pub use color::{CheckeredTexture, SolidColor, SpatialCheckeredTexture};
 pub use image::Image;
+pub use procedural::{
+    ColorRamp, ColorRampTexture, MarbleTexture, NoiseTexture, TextureSpace, VoronoiTexture,
+    WoodTexture,
+};
 
 /// Public Trait to implement a 2D texture onto any object
 pub trait ColorTexture: Sync + Send {
@@ -1,6 +1,10 @@
 use std::sync::Arc;
 
-use image::{io::Reader as ImageReader, GenericImageView, RgbImage};
+use image::{
+    error::{LimitError, LimitErrorKind},
+    io::Reader as ImageReader,
+    GenericImageView, ImageError, RgbImage,
+};
 
 use super::ColorTexture;
 use crate::{rgb_to_color, Interval, SolidColor, Vec3};
@@ -15,25 +19,31 @@ impl Image {
     /// For zoomed in textures, turn up the scale (>1.0)
     /// For a normal texture wrapper make the scale (1.0)
     /// All objects image cordinates (u, v) should be already normalized to between [0, 1]
+    /// Panics if the image can not be loaded, refer to [Image::try_new]
     pub fn new(scale: f64, image_path: &str, fill_texture: Arc<dyn ColorTexture>) -> Self {
-        let image = match ImageReader::open(image_path) {
-            Ok(opened_image) => match opened_image.decode() {
-                Ok(image) => {
-                    let (width, height) = image.dimensions();
-                    if width == 0 || height == 0 {
-                        panic!("Image has no dimensions");
-                    }
-                    image.to_rgb8()
-                }
-                Err(err) => panic!("Could not decode image\n{}", err),
-            },
-            Err(err) => panic!("Could not open image_path: {}\n{}", image_path, err),
-        };
-        Self {
-            image,
+        match Self::try_new(scale, image_path, fill_texture) {
+            Ok(image) => image,
+            Err(err) => panic!("Could not load image_path: {}\n{}", image_path, err),
+        }
+    }
+    /// Same as [Image::new], but returns an error if the image can not be opened, decoded or is empty
+    pub fn try_new(
+        scale: f64,
+        image_path: &str,
+        fill_texture: Arc<dyn ColorTexture>,
+    ) -> Result<Self, ImageError> {
+        let image = ImageReader::open(image_path)?.decode()?;
+        let (width, height) = image.dimensions();
+        if width == 0 || height == 0 {
+            return Err(ImageError::Limits(LimitError::from_kind(
+                LimitErrorKind::DimensionError,
+            )));
+        }
+        Ok(Self {
+            image: image.to_rgb8(),
             inv_scale: 1.0 / scale,
             fill_texture,
-        }
+        })
     }
     pub fn new_with_color(scale: f64, image_path: &str, color: Vec3) -> Self {
         Self::new(scale, image_path, Arc::new(SolidColor { color }))
@@ -0,0 +1,277 @@
+use std::sync::Arc;
+
+use super::ColorTexture;
+use crate::{Perlin, Vec3, Worley};
+
+/// Which coordinates a procedural texture is evaluated on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureSpace {
+    /// The point in the world, so the pattern runs through objects (Like [SpatialCheckeredTexture](crate::SpatialCheckeredTexture))
+    Solid,
+    /// The (u, v) of the object as (x, y, 0), so the pattern follows its surface (Like [CheckeredTexture](crate::CheckeredTexture))
+    UV,
+}
+impl TextureSpace {
+    fn point(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        match self {
+            TextureSpace::Solid => p,
+            TextureSpace::UV => Vec3::new(u, v, 0_f64),
+        }
+    }
+}
+
+/// Gradient between colors placed along [0, 1], linearly interpolated between the stops
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorRamp {
+    /// (position, color), sorted by position
+    stops: Vec<(f64, Vec3)>,
+}
+impl ColorRamp {
+    /// Panics if there are no stops
+    pub fn new(mut stops: Vec<(f64, Vec3)>) -> Self {
+        assert!(!stops.is_empty(), "a color ramp needs at least 1 stop");
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self { stops }
+    }
+    /// Colors spread evenly from 0 to 1
+    pub fn from_colors(colors: Vec<Vec3>) -> Self {
+        let last = colors.len().saturating_sub(1).max(1) as f64;
+        Self::new(
+            colors
+                .into_iter()
+                .enumerate()
+                .map(|(index, color)| (index as f64 / last, color))
+                .collect(),
+        )
+    }
+    /// Color at t, clamped to the first and last stops
+    pub fn color(&self, t: f64) -> Vec3 {
+        let next = self.stops.partition_point(|(position, _)| *position <= t);
+        if next == 0 {
+            return self.stops[0].1.clone();
+        }
+        if next == self.stops.len() {
+            return self.stops[next - 1].1.clone();
+        }
+        let (start, start_color) = &self.stops[next - 1];
+        let (end, end_color) = &self.stops[next];
+        let fraction = (t - start) / (end - start);
+        (1_f64 - fraction) * start_color.clone() + fraction * end_color.clone()
+    }
+}
+
+/// Maps the luminance of another texture (i.e. a [NoiseTexture] or an [Image](crate::Image)) through a [ColorRamp]
+pub struct ColorRampTexture {
+    pub input: Arc<dyn ColorTexture>,
+    pub ramp: ColorRamp,
+}
+impl ColorTexture for ColorRampTexture {
+    fn color(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        self.ramp.color(self.input.color(u, v, p).luminance())
+    }
+}
+
+/// [Perlin] noise, fBm (Soft clouds) or turbulence (Sharp creases), mapped through a [ColorRamp]
+pub struct NoiseTexture {
+    perlin: Perlin,
+    pub space: TextureSpace,
+    /// Number of noise cells per unit
+    pub scale: f64,
+    pub octaves: u32,
+    /// Use [Perlin::turbulence] instead of [Perlin::fbm]
+    pub turbulence: bool,
+    pub ramp: ColorRamp,
+}
+impl NoiseTexture {
+    /// Black to white fBm with 7 octaves
+    pub fn new(seed: u64, space: TextureSpace, scale: f64) -> Self {
+        Self {
+            perlin: Perlin::new(seed),
+            space,
+            scale,
+            octaves: 7,
+            turbulence: false,
+            ramp: ColorRamp::from_colors(vec![Vec3::new_int(0, 0, 0), Vec3::new_int(1, 1, 1)]),
+        }
+    }
+    /// Noise at the point, in [0, 1]
+    pub fn value(&self, u: f64, v: f64, p: Vec3) -> f64 {
+        let q = self.space.point(u, v, p) * self.scale;
+        if self.turbulence {
+            self.perlin.turbulence(&q, self.octaves)
+        } else {
+            0.5 * self.perlin.fbm(&q, self.octaves) + 0.5
+        }
+    }
+}
+impl ColorTexture for NoiseTexture {
+    fn color(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        self.ramp.color(self.value(u, v, p))
+    }
+}
+
+/// Veins of a sine wave along z, bent by turbulence
+pub struct MarbleTexture {
+    perlin: Perlin,
+    pub space: TextureSpace,
+    /// Number of veins per unit (Divided by 2 pi)
+    pub scale: f64,
+    pub octaves: u32,
+    /// How much the veins are bent
+    pub turbulence: f64,
+    pub ramp: ColorRamp,
+}
+impl MarbleTexture {
+    /// White with grey veins
+    pub fn new(seed: u64, space: TextureSpace, scale: f64) -> Self {
+        Self {
+            perlin: Perlin::new(seed),
+            space,
+            scale,
+            octaves: 7,
+            turbulence: 10_f64,
+            ramp: ColorRamp::from_colors(vec![
+                Vec3::new(0.2, 0.2, 0.22),
+                Vec3::new(0.95, 0.95, 0.93),
+            ]),
+        }
+    }
+    /// Position across the veins, in [0, 1]
+    pub fn value(&self, u: f64, v: f64, p: Vec3) -> f64 {
+        let q = self.space.point(u, v, p) * self.scale;
+        0.5 * (1_f64 + (q.z() + self.turbulence * self.perlin.turbulence(&q, self.octaves)).sin())
+    }
+}
+impl ColorTexture for MarbleTexture {
+    fn color(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        self.ramp.color(self.value(u, v, p))
+    }
+}
+
+/// Growth rings around the z axis (The center of the uv square in [TextureSpace::UV]), wobbled by noise
+pub struct WoodTexture {
+    perlin: Perlin,
+    pub space: TextureSpace,
+    /// Number of rings per unit
+    pub scale: f64,
+    pub octaves: u32,
+    /// How far (in rings) the noise pushes the rings around
+    pub turbulence: f64,
+    pub ramp: ColorRamp,
+}
+impl WoodTexture {
+    /// Light to dark brown
+    pub fn new(seed: u64, space: TextureSpace, scale: f64) -> Self {
+        Self {
+            perlin: Perlin::new(seed),
+            space,
+            scale,
+            octaves: 4,
+            turbulence: 0.5,
+            ramp: ColorRamp::from_colors(vec![
+                Vec3::new(0.75, 0.52, 0.3),
+                Vec3::new(0.4, 0.22, 0.1),
+            ]),
+        }
+    }
+    /// Position within the ring, in [0, 1)
+    pub fn value(&self, u: f64, v: f64, p: Vec3) -> f64 {
+        let mut q = self.space.point(u, v, p);
+        if self.space == TextureSpace::UV {
+            q -= Vec3::new(0.5, 0.5, 0_f64);
+        }
+        let q = q * self.scale;
+        let radius = (q.x() * q.x() + q.y() * q.y()).sqrt();
+        let rings = radius + self.turbulence * self.perlin.fbm(&q, self.octaves);
+        rings - rings.floor()
+    }
+}
+impl ColorTexture for WoodTexture {
+    fn color(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        self.ramp.color(self.value(u, v, p))
+    }
+}
+
+/// Cells around random points ([Worley] noise), each of a color picked from a [ColorRamp], with optional borders
+pub struct VoronoiTexture {
+    worley: Worley,
+    pub space: TextureSpace,
+    /// Number of cells per unit (On average)
+    pub scale: f64,
+    /// Width of the borders (In cells), 0 for none
+    pub border_width: f64,
+    pub border_color: Vec3,
+    pub ramp: ColorRamp,
+}
+impl VoronoiTexture {
+    /// Cells of any shade between black and white, without borders
+    pub fn new(seed: u64, space: TextureSpace, scale: f64) -> Self {
+        Self {
+            worley: Worley::new(seed),
+            space,
+            scale,
+            border_width: 0_f64,
+            border_color: Vec3::new_int(0, 0, 0),
+            ramp: ColorRamp::from_colors(vec![Vec3::new_int(0, 0, 0), Vec3::new_int(1, 1, 1)]),
+        }
+    }
+}
+impl ColorTexture for VoronoiTexture {
+    // Implementation Details:
+    // The border is where the 2 closest points are almost as far, f2 - f1 is about twice the distance to the border
+    fn color(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        let sample = self
+            .worley
+            .sample(&(self.space.point(u, v, p) * self.scale));
+        if sample.f2 - sample.f1 < self.border_width {
+            return self.border_color.clone();
+        }
+        self.ramp.color(sample.cell_value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_color_ramp() {
+        let ramp = ColorRamp::new(vec![
+            (1_f64, Vec3::new_int(0, 0, 1)),
+            (0_f64, Vec3::new_int(1, 0, 0)),
+            (0.5, Vec3::new_int(0, 1, 0)),
+        ]);
+        assert_eq!(ramp.color(-1_f64), Vec3::new_int(1, 0, 0));
+        assert_eq!(ramp.color(0.25), Vec3::new(0.5, 0.5, 0_f64));
+        assert_eq!(ramp.color(0.5), Vec3::new_int(0, 1, 0));
+        assert_eq!(ramp.color(2_f64), Vec3::new_int(0, 0, 1));
+        let single = ColorRamp::from_colors(vec![Vec3::new_int(1, 1, 1)]);
+        assert_eq!(single.color(0.3), Vec3::new_int(1, 1, 1));
+    }
+
+    #[test]
+    fn test_procedural_texture_spaces() {
+        let marble = MarbleTexture::new(1, TextureSpace::Solid, 4_f64);
+        let p = Vec3::new(0.3, 1.7, -2.2);
+        // Solid textures only depend on the point, and uv textures only on (u, v)
+        assert_eq!(
+            marble.color(0.1, 0.2, p.clone()),
+            marble.color(0.9, 0.4, p.clone())
+        );
+        let wood = WoodTexture::new(1, TextureSpace::UV, 4_f64);
+        assert_eq!(
+            wood.color(0.1, 0.2, p.clone()),
+            wood.color(0.1, 0.2, Vec3::new_int(5, 5, 5))
+        );
+        for i in 0..100 {
+            let q = Vec3::new(i as f64 * 0.13, i as f64 * -0.07, i as f64 * 0.29);
+            assert!((0_f64..=1_f64).contains(&marble.value(0_f64, 0_f64, q.clone())));
+            assert!((0_f64..1_f64).contains(&wood.value(q.x(), q.y(), Vec3::default())));
+        }
+
+        let mut voronoi = VoronoiTexture::new(1, TextureSpace::Solid, 1_f64);
+        voronoi.border_width = 10_f64;
+        voronoi.border_color = Vec3::new_int(1, 0, 0);
+        assert_eq!(voronoi.color(0_f64, 0_f64, p), Vec3::new_int(1, 0, 0));
+    }
+}
@@ -0,0 +1,190 @@
+use clap::ValueEnum;
+use image::{Rgb, Rgb32FImage, RgbImage};
+
+use crate::Vec3;
+
+/// Operator used to map the unbounded linear radiance into the [0, 1] display range
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum ToneMapOperator {
+    /// Hard clamp of each channel, anything brighter than 1 is blown out
+    #[default]
+    Clamp,
+    /// L / (1 + L), on the luminance so the hue is kept
+    Reinhard,
+    /// Reinhard, with the luminance at the white point mapped to 1 (So highlights can still reach white)
+    ExtendedReinhard,
+    /// Fitted ACES filmic curve (Narkowicz 2015)
+    ACES,
+    /// AgX-style filmic curve, which desaturates bright colors towards white instead of skewing their hue
+    #[value(name = "agx")]
+    AgX,
+}
+
+/// Post-process stage that takes the linear framebuffer to the final 8-bit image
+/// The color is scaled by the exposure, tone mapped by the operator, then encoded with the sRGB transfer function
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ToneMapping {
+    pub operator: ToneMapOperator,
+    /// Exposure in stops, every +1 doubles the brightness
+    pub exposure: f64,
+    /// Luminance that is mapped to white, only used by [ToneMapOperator::ExtendedReinhard]
+    pub white_point: f64,
+}
+impl Default for ToneMapping {
+    fn default() -> Self {
+        Self {
+            operator: ToneMapOperator::default(),
+            exposure: 0_f64,
+            white_point: 4_f64,
+        }
+    }
+}
+impl ToneMapping {
+    /// Maps a linear color into the linear [0, 1] display range (Before the sRGB transfer function)
+    pub fn apply(&self, color: &Vec3) -> Vec3 {
+        let color = color.clone() * 2_f64.powf(self.exposure);
+        let (r, g, b) = color.tuple();
+        // Negative values only come from float errors, and would break the log and power curves
+        let color = Vec3::new(r.max(0_f64), g.max(0_f64), b.max(0_f64));
+        let mapped = match self.operator {
+            ToneMapOperator::Clamp => color,
+            ToneMapOperator::Reinhard => scale_luminance(color, |l| l / (1_f64 + l)),
+            ToneMapOperator::ExtendedReinhard => {
+                let white_squared = self.white_point * self.white_point;
+                scale_luminance(color, |l| l * (1_f64 + l / white_squared) / (1_f64 + l))
+            }
+            ToneMapOperator::ACES => map_channels(color, aces_fitted),
+            ToneMapOperator::AgX => agx(color),
+        };
+        map_channels(mapped, |c| c.clamp(0_f64, 1_f64))
+    }
+    /// Tone maps the whole framebuffer into an 8-bit sRGB image
+    pub fn to_rgbimage(&self, framebuffer: &Rgb32FImage) -> RgbImage {
+        RgbImage::from_fn(framebuffer.width(), framebuffer.height(), |x, y| {
+            let [r, g, b] = framebuffer.get_pixel(x, y).0;
+            let (r, g, b) = self.apply(&Vec3::new(r as f64, g as f64, b as f64)).tuple();
+            Rgb([r, g, b].map(|c| (srgb_transfer(c) * 255_f64).round() as u8))
+        })
+    }
+}
+
+/// sRGB transfer function (OETF), from linear [0, 1] to the encoded [0, 1] value
+pub fn srgb_transfer(linear: f64) -> f64 {
+    if linear <= 0.0031308 {
+        12.92 * linear
+    } else {
+        1.055 * linear.powf(1_f64 / 2.4) - 0.055
+    }
+}
+
+fn map_channels(color: Vec3, f: impl Fn(f64) -> f64) -> Vec3 {
+    let (r, g, b) = color.tuple();
+    Vec3::new(f(r), f(g), f(b))
+}
+/// Scales the color so that its luminance is f(luminance)
+fn scale_luminance(color: Vec3, f: impl Fn(f64) -> f64) -> Vec3 {
+    let luminance = color.luminance();
+    if luminance <= 0_f64 {
+        return color;
+    }
+    color * (f(luminance) / luminance)
+}
+
+fn aces_fitted(x: f64) -> f64 {
+    // The fit expects the input pre-exposed by 0.6, to match the reference curve
+    let x = x * 0.6;
+    (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)
+}
+
+// Implementation Details:
+// Follows the minimal AgX implementation (Wrensch 2023)
+// The color is pulled into a slightly desaturated (inset) space, encoded as log2 between MIN_EV and MAX_EV,
+// then put through a sigmoid (polynomial fit) and back out of the inset space
+fn agx(color: Vec3) -> Vec3 {
+    const INSET: [[f64; 3]; 3] = [
+        [0.842479062253094, 0.0784335999999992, 0.0792237451477643],
+        [0.0423282422610123, 0.878468636469772, 0.0791661274605434],
+        [0.0423756549057051, 0.0784336, 0.879142973793104],
+    ];
+    const OUTSET: [[f64; 3]; 3] = [
+        [1.19687900512017, -0.0980208811401368, -0.0990297440797205],
+        [-0.0528968517574562, 1.15190312990417, -0.0989611768448433],
+        [-0.0529716355144438, -0.0980434501171241, 1.15107367264116],
+    ];
+    const MIN_EV: f64 = -12.47393;
+    const MAX_EV: f64 = 4.026069;
+    let mul = |m: &[[f64; 3]; 3], v: &Vec3| {
+        Vec3::new(
+            m[0][0] * v.x() + m[0][1] * v.y() + m[0][2] * v.z(),
+            m[1][0] * v.x() + m[1][1] * v.y() + m[1][2] * v.z(),
+            m[2][0] * v.x() + m[2][1] * v.y() + m[2][2] * v.z(),
+        )
+    };
+
+    let encoded = map_channels(mul(&INSET, &color), |c| {
+        let ev = c.max(1e-10_f64).log2().clamp(MIN_EV, MAX_EV);
+        let x = (ev - MIN_EV) / (MAX_EV - MIN_EV);
+        let x2 = x * x;
+        let x4 = x2 * x2;
+        15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x
+            - 0.00232
+    });
+    // The sigmoid outputs a display encoded value, which is taken back to linear
+    map_channels(mul(&OUTSET, &encoded), |c| c.max(0_f64).powf(2.2))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_srgb_transfer() {
+        assert_eq!(srgb_transfer(0.0), 0.0);
+        assert!((srgb_transfer(1.0) - 1.0).abs() < 1e-12);
+        // Both pieces meet at the threshold
+        let threshold = 0.0031308;
+        assert!(
+            (12.92 * threshold - (1.055 * f64::powf(threshold, 1.0 / 2.4) - 0.055)).abs() < 1e-6
+        );
+    }
+
+    #[test]
+    fn test_tone_mapping_operators() {
+        let grey = |value: f64| Vec3::new(value, value, value);
+        let tone_mapping = |operator| ToneMapping {
+            operator,
+            ..Default::default()
+        };
+        assert_eq!(
+            tone_mapping(ToneMapOperator::Reinhard).apply(&grey(1.0)),
+            grey(0.5)
+        );
+        let white_point = tone_mapping(ToneMapOperator::ExtendedReinhard).apply(&grey(4.0));
+        assert!((white_point.x() - 1.0).abs() < 1e-9);
+
+        for operator in ToneMapOperator::value_variants() {
+            let tone_mapping = tone_mapping(*operator);
+            // Every operator stays in range and keeps the order of brightness
+            let mut previous = -1_f64;
+            for value in [0.0, 0.01, 0.1, 0.5, 1.0, 4.0, 15.0, 1000.0] {
+                let mapped = tone_mapping.apply(&grey(value));
+                assert!((0.0..=1.0).contains(&mapped.x()), "{:?}", operator);
+                assert!(mapped.x() >= previous, "{:?}", operator);
+                previous = mapped.x();
+            }
+        }
+    }
+
+    #[test]
+    fn test_tone_mapping_exposure() {
+        let tone_mapping = ToneMapping {
+            exposure: 1.0,
+            ..Default::default()
+        };
+        assert_eq!(
+            tone_mapping.apply(&Vec3::new(0.25, 0.1, 0.0)),
+            Vec3::new(0.5, 0.2, 0.0)
+        );
+    }
+}
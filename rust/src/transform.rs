@@ -0,0 +1,375 @@
+use std::ops::Mul;
+
+use crate::{helper::from_fdegree_to_fradian, Vec3, Vec3Axis, AABB};
+
+/// Row major 4x4 matrix, used for affine transforms
+/// Points are treated as column vectors (x, y, z, 1) and directions as (x, y, z, 0)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mat4 {
+    pub m: [[f64; 4]; 4],
+}
+impl Mat4 {
+    pub fn new(m: [[f64; 4]; 4]) -> Self {
+        Self { m }
+    }
+    pub fn identity() -> Self {
+        let mut m = [[0_f64; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            row[i] = 1_f64;
+        }
+        Self { m }
+    }
+    pub fn transpose(&self) -> Self {
+        let mut m = [[0_f64; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.m[j][i];
+            }
+        }
+        Self { m }
+    }
+    /// Determinant of the upper 3x3 (linear) part of the matrix
+    pub fn determinant_3x3(&self) -> f64 {
+        let m = &self.m;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+    /// Returns the inverse of the matrix, or None if the matrix is singular
+    // Implementation Details:
+    // Gauss-Jordan elimination with partial pivoting, on the matrix augmented with the identity
+    pub fn inverse(&self) -> Option<Self> {
+        let mut a = self.m;
+        let mut inverse = Self::identity().m;
+        for column in 0..4 {
+            let pivot = (column..4)
+                .max_by(|&i, &j| a[i][column].abs().total_cmp(&a[j][column].abs()))
+                .unwrap();
+            if a[pivot][column].abs() < 1e-12_f64 {
+                return None;
+            }
+            a.swap(column, pivot);
+            inverse.swap(column, pivot);
+
+            let inv_pivot = 1_f64 / a[column][column];
+            for j in 0..4 {
+                a[column][j] *= inv_pivot;
+                inverse[column][j] *= inv_pivot;
+            }
+            for row in 0..4 {
+                if row == column {
+                    continue;
+                }
+                let factor = a[row][column];
+                for j in 0..4 {
+                    a[row][j] -= factor * a[column][j];
+                    inverse[row][j] -= factor * inverse[column][j];
+                }
+            }
+        }
+        Some(Self { m: inverse })
+    }
+    /// Multiplies the point (x, y, z, 1), including the translation
+    pub fn mul_point(&self, p: &Vec3) -> Vec3 {
+        let m = &self.m;
+        let (x, y, z) = p.tuple();
+        let w = m[3][0] * x + m[3][1] * y + m[3][2] * z + m[3][3];
+        let result = Vec3::new(
+            m[0][0] * x + m[0][1] * y + m[0][2] * z + m[0][3],
+            m[1][0] * x + m[1][1] * y + m[1][2] * z + m[1][3],
+            m[2][0] * x + m[2][1] * y + m[2][2] * z + m[2][3],
+        );
+        if w == 1_f64 {
+            result
+        } else {
+            result / w
+        }
+    }
+    /// Multiplies the direction (x, y, z, 0), ignoring the translation
+    pub fn mul_vector(&self, v: &Vec3) -> Vec3 {
+        let m = &self.m;
+        let (x, y, z) = v.tuple();
+        Vec3::new(
+            m[0][0] * x + m[0][1] * y + m[0][2] * z,
+            m[1][0] * x + m[1][1] * y + m[1][2] * z,
+            m[2][0] * x + m[2][1] * y + m[2][2] * z,
+        )
+    }
+}
+impl Default for Mat4 {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+impl Mul for Mat4 {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self::Output {
+        let mut m = [[0_f64; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[i][k] * rhs.m[k][j]).sum();
+            }
+        }
+        Self { m }
+    }
+}
+
+/// Quaternion (w + xi + yj + zk), used to describe rotations
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quaternion {
+    pub w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+impl Quaternion {
+    pub fn new(w: f64, x: f64, y: f64, z: f64) -> Self {
+        Self { w, x, y, z }
+    }
+    /// Quaternion for a rotation around axis by an angle (Degrees 360), following the right hand rule
+    pub fn from_axis_angle(axis: &Vec3, deg_angle: f64) -> Self {
+        let half_angle = from_fdegree_to_fradian(deg_angle) / 2_f64;
+        let axis = axis.unit_vector() * half_angle.sin();
+        Self::new(half_angle.cos(), axis.x(), axis.y(), axis.z())
+    }
+    /// Rotation matrix of the quaternion, the quaternion is normalized first
+    pub fn to_mat4(&self) -> Mat4 {
+        let length = (self.w * self.w + self.x * self.x + self.y * self.y + self.z * self.z).sqrt();
+        let (w, x, y, z) = (
+            self.w / length,
+            self.x / length,
+            self.y / length,
+            self.z / length,
+        );
+        Mat4::new([
+            [
+                1_f64 - 2_f64 * (y * y + z * z),
+                2_f64 * (x * y - w * z),
+                2_f64 * (x * z + w * y),
+                0_f64,
+            ],
+            [
+                2_f64 * (x * y + w * z),
+                1_f64 - 2_f64 * (x * x + z * z),
+                2_f64 * (y * z - w * x),
+                0_f64,
+            ],
+            [
+                2_f64 * (x * z - w * y),
+                2_f64 * (y * z + w * x),
+                1_f64 - 2_f64 * (x * x + y * y),
+                0_f64,
+            ],
+            [0_f64, 0_f64, 0_f64, 1_f64],
+        ])
+    }
+}
+
+/// Affine transform, which keeps both the matrix and its inverse
+/// Transforms are composed with [Transform::then] (or `*`), so any number of them only need a single matrix
+///
+/// ```
+/// use rust_simple_raytracer::{Transform, Vec3};
+/// // Scale, then move up by 1
+/// let transform = Transform::scale(Vec3::new(2.0, 2.0, 2.0)).then(&Transform::translate(Vec3::new(0.0, 1.0, 0.0)));
+/// assert_eq!(transform.point(&Vec3::new(1.0, 0.0, 0.0)), Vec3::new(2.0, 1.0, 0.0));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Transform {
+    matrix: Mat4,
+    inverse: Mat4,
+}
+impl Transform {
+    /// Creates the transform from a matrix
+    /// Panics if the matrix can not be inverted (i.e. scaling by 0)
+    pub fn from_matrix(matrix: Mat4) -> Self {
+        let inverse = match matrix.inverse() {
+            Some(inverse) => inverse,
+            None => panic!("Transform matrix should be invertible, {:?}", matrix),
+        };
+        Self { matrix, inverse }
+    }
+    pub fn identity() -> Self {
+        Self::default()
+    }
+    pub fn translate(offset: Vec3) -> Self {
+        let (x, y, z) = offset.tuple();
+        let mut matrix = Mat4::identity();
+        let mut inverse = Mat4::identity();
+        for (i, value) in [x, y, z].into_iter().enumerate() {
+            matrix.m[i][3] = value;
+            inverse.m[i][3] = -value;
+        }
+        Self { matrix, inverse }
+    }
+    /// Scales each axis by the factor
+    /// Panics if any of the factors are 0
+    pub fn scale(factor: Vec3) -> Self {
+        let mut matrix = Mat4::identity();
+        for i in 0..3 {
+            matrix.m[i][i] = factor[i];
+        }
+        Self::from_matrix(matrix)
+    }
+    /// Rotation around an arbitrary axis (Through the origin) by an angle (Degrees 360), following the right hand rule
+    pub fn rotate(axis: &Vec3, deg_angle: f64) -> Self {
+        Self::rotate_quaternion(&Quaternion::from_axis_angle(axis, deg_angle))
+    }
+    /// Rotation around one of the principal axes by an angle (Degrees 360)
+    pub fn rotate_about_axis(axis: &Vec3Axis, deg_angle: f64) -> Self {
+        let axis = match axis {
+            Vec3Axis::X => Vec3::new_int(1, 0, 0),
+            Vec3Axis::Y => Vec3::new_int(0, 1, 0),
+            Vec3Axis::Z => Vec3::new_int(0, 0, 1),
+        };
+        Self::rotate(&axis, deg_angle)
+    }
+    pub fn rotate_quaternion(quaternion: &Quaternion) -> Self {
+        let matrix = quaternion.to_mat4();
+        // Rotation matrices are orthogonal, so the inverse is just the transpose
+        Self {
+            matrix,
+            inverse: matrix.transpose(),
+        }
+    }
+
+    /// Returns the transform that applies self, then other
+    pub fn then(&self, other: &Transform) -> Self {
+        Self {
+            matrix: other.matrix * self.matrix,
+            inverse: self.inverse * other.inverse,
+        }
+    }
+    pub fn inverse(&self) -> Self {
+        Self {
+            matrix: self.inverse,
+            inverse: self.matrix,
+        }
+    }
+    pub fn matrix(&self) -> &Mat4 {
+        &self.matrix
+    }
+    pub fn inverse_matrix(&self) -> &Mat4 {
+        &self.inverse
+    }
+
+    pub fn point(&self, p: &Vec3) -> Vec3 {
+        self.matrix.mul_point(p)
+    }
+    pub fn vector(&self, v: &Vec3) -> Vec3 {
+        self.matrix.mul_vector(v)
+    }
+    /// Transforms a surface normal, which has to use the inverse transpose to stay perpendicular to the surface
+    /// The result is not normalized
+    pub fn normal(&self, n: &Vec3) -> Vec3 {
+        self.inverse.transpose().mul_vector(n)
+    }
+    /// Smallest AABB that contains all 8 transformed corners of the aabb
+    pub fn aabb(&self, aabb: &AABB) -> AABB {
+        // The corners of an unbounded AABB are infinite (and turn into NaN), but it stays unbounded anyway
+        if !aabb.is_bounded() {
+            return AABB::UNIVERSE;
+        }
+        let mut min_point = Vec3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        let mut max_point = Vec3::new(-f64::INFINITY, -f64::INFINITY, -f64::INFINITY);
+        for x in [aabb.x.min, aabb.x.max] {
+            for y in [aabb.y.min, aabb.y.max] {
+                for z in [aabb.z.min, aabb.z.max] {
+                    let world_point = self.point(&Vec3::new(x, y, z));
+                    min_point = min_point.retain_min(&world_point);
+                    max_point = max_point.retain_max(&world_point);
+                }
+            }
+        }
+        AABB::from_points(min_point, max_point)
+    }
+}
+/// `a * b` applies b first, then a (Same as the matrices)
+impl Mul for Transform {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self::Output {
+        rhs.then(&self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_near(a: &Vec3, b: &Vec3) {
+        assert!(
+            (a.clone() - b.clone()).length() < 1e-9,
+            "{} is not near {}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn test_mat4_inverse() {
+        let matrix = Mat4::new([
+            [2.0, 0.0, 1.0, 3.0],
+            [1.0, 3.0, 0.0, -1.0],
+            [0.0, 1.0, 4.0, 2.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        let product = matrix * matrix.inverse().unwrap();
+        for i in 0..4 {
+            for j in 0..4 {
+                let expected = if i == j { 1.0 } else { 0.0 };
+                assert!((product.m[i][j] - expected).abs() < 1e-12);
+            }
+        }
+        assert!(Mat4::new([[0.0; 4]; 4]).inverse().is_none());
+    }
+
+    #[test]
+    fn test_transform_rotate() {
+        let transform = Transform::rotate(&Vec3::new_int(0, 0, 1), 90.0);
+        assert_near(
+            &transform.point(&Vec3::new_int(1, 0, 0)),
+            &Vec3::new_int(0, 1, 0),
+        );
+        // Rotating about (1, 1, 1) by 120 degrees cycles the axes
+        let transform = Transform::rotate(&Vec3::new_int(1, 1, 1), 120.0);
+        assert_near(
+            &transform.point(&Vec3::new_int(1, 0, 0)),
+            &Vec3::new_int(0, 1, 0),
+        );
+        assert_near(
+            &transform.inverse().point(&Vec3::new_int(0, 1, 0)),
+            &Vec3::new_int(1, 0, 0),
+        );
+    }
+
+    #[test]
+    fn test_transform_composition() {
+        let transform = Transform::scale(Vec3::new(2.0, 1.0, 1.0))
+            .then(&Transform::rotate_about_axis(&Vec3Axis::Y, 90.0))
+            .then(&Transform::translate(Vec3::new_int(0, 5, 0)));
+        let p = Vec3::new_int(1, 0, 0);
+        assert_near(&transform.point(&p), &Vec3::new_int(0, 5, -2));
+        assert_near(&transform.inverse().point(&transform.point(&p)), &p);
+        assert_eq!(
+            transform,
+            Transform::translate(Vec3::new_int(0, 5, 0))
+                * Transform::rotate_about_axis(&Vec3Axis::Y, 90.0)
+                * Transform::scale(Vec3::new(2.0, 1.0, 1.0))
+        );
+    }
+
+    #[test]
+    fn test_transform_normal() {
+        // Squashing a 45 degree plane along x, the normal has to tilt the other way
+        let transform = Transform::scale(Vec3::new(2.0, 1.0, 1.0));
+        let tangent = transform.vector(&Vec3::new_int(1, 1, 0));
+        let normal = transform.normal(&Vec3::new_int(1, -1, 0));
+        assert!(Vec3::dot(&tangent, &normal).abs() < 1e-12);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_transform_singular() {
+        Transform::scale(Vec3::new(1.0, 0.0, 1.0));
+    }
+}
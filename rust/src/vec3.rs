@@ -1,11 +1,12 @@
 use std::{
+    f64::consts::PI,
     fmt::{Debug, Display},
     ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign},
 };
 
-use rand::{thread_rng, Rng};
+use rand::Rng;
 
-use crate::helper::from_fdegree_to_fradian;
+use crate::{helper::from_fdegree_to_fradian, Sampler};
 
 pub enum Vec3Axis {
     X,
@@ -114,6 +115,10 @@ impl Vec3 {
     pub fn unit_vector(&self) -> Self {
         self.clone() / self.length()
     }
+    /// Relative luminance of the vector as a linear rgb color (Rec. 709 primaries)
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.e[0] + 0.7152 * self.e[1] + 0.0722 * self.e[2]
+    }
     /// Checks if the vector is near zero. Used to prevent float rounding erros
     pub fn near_zero(&self) -> bool {
         let s = -1e8_f64;
@@ -151,8 +156,7 @@ impl Vec3 {
         refracted_vector_perpendicular + refracted_vector_parallel
     }
     /// Generates a random vector with x, y and z in (min, max)
-    pub fn random(min: f64, max: f64) -> Vec3 {
-        let mut rng = thread_rng();
+    pub fn random<R: Rng + ?Sized>(min: f64, max: f64, rng: &mut R) -> Vec3 {
         Vec3::new(
             rng.gen_range(min..max),
             rng.gen_range(min..max),
@@ -160,13 +164,30 @@ impl Vec3 {
         )
     }
     /// Samples a random vector inside a unit sphere with, Center(0, 0, 0) radius=1
-    pub fn random_vector_in_unit_sphere() -> Vec3 {
-        loop {
-            let v = Vec3::random(-1_f64, 1_f64);
-            if v.length_squared() < 1_f64 {
-                break v;
-            }
-        }
+    /// Uses 3 dimensions of the sampler, a direction and the cube root of the radius (So the volume is covered evenly)
+    pub fn random_vector_in_unit_sphere(sampler: &mut dyn Sampler) -> Vec3 {
+        Vec3::random_unit_vector(sampler) * sampler.get_1d().cbrt()
+    }
+    /// Samples a random unit vector, uniformly distributed over the surface of a unit sphere
+    // Implementation Details:
+    // Maps the 2D sample directly onto the sphere (Instead of rejection sampling), so the stratification of the sampler is kept
+    // z is uniform in [-1, 1], as every band of the sphere with the same height has the same area
+    pub fn random_unit_vector(sampler: &mut dyn Sampler) -> Vec3 {
+        let (r1, r2) = sampler.get_2d();
+        let z = 1_f64 - 2_f64 * r1;
+        let r = (1_f64 - z * z).max(0_f64).sqrt();
+        let phi = 2_f64 * PI * r2;
+        Vec3::new(r * phi.cos(), r * phi.sin(), z)
+    }
+    /// Samples a random direction on the hemisphere around z=1, weighted by the cosine to the z axis
+    pub fn random_cosine_direction(sampler: &mut dyn Sampler) -> Vec3 {
+        let (r1, r2) = sampler.get_2d();
+
+        let phi = 2_f64 * PI * r1;
+        let x = phi.cos() * r2.sqrt();
+        let y = phi.sin() * r2.sqrt();
+        let z = (1_f64 - r2).sqrt();
+        Vec3::new(x, y, z)
     }
 }
 
@@ -366,6 +387,13 @@ mod test {
         assert!(!v.near_zero());
     }
 
+    #[test]
+    fn test_vec3_luminance() {
+        let v = Vec3::new_int(1, 1, 1);
+        assert!((v.luminance() - 1.0).abs() < 1e-12);
+        assert_eq!(Vec3::new_int(0, 0, 0).luminance(), 0.0);
+    }
+
     #[test]
     fn test_vec3_clone() {
         let v = Vec3::new_int(1, 1, 1);
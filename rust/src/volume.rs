@@ -0,0 +1,67 @@
+use crate::{Perlin, Vec3};
+
+mod grid;
+pub use grid::VoxelGrid;
+
+/// Public Trait for the density of a [HeterogeneousMedium](crate::HeterogeneousMedium), which varies over space
+pub trait DensityField: Sync + Send {
+    /// Density at p, with the volume mapped into [0, 1] on every axis
+    fn density(&self, p: &Vec3) -> f64;
+    /// Upper bound of the density anywhere in the volume
+    /// The tighter it is, the fewer steps the tracking takes (But it can never be lower than the density)
+    fn max_density(&self) -> f64;
+}
+
+/// Procedural cloud puff from [Perlin] noise, round so the edges of the volume do not show
+/// Densities are in [0, 1], and 0 outside the sphere touching the sides of the volume
+pub struct NoiseDensity {
+    perlin: Perlin,
+    /// Number of noise cells across the volume
+    pub frequency: f64,
+    /// Octaves of fBm, refer to [Perlin::fbm]
+    pub octaves: u32,
+}
+impl NoiseDensity {
+    pub fn new(seed: u64, frequency: f64, octaves: u32) -> Self {
+        Self {
+            perlin: Perlin::new(seed),
+            frequency,
+            octaves,
+        }
+    }
+}
+impl DensityField for NoiseDensity {
+    // Implementation Details:
+    // The noise (mapped into [0, 1]) is faded out by 1 - r^2 from the center, then shifted down so the thin parts become empty space
+    fn density(&self, p: &Vec3) -> f64 {
+        let centered = 2_f64 * p.clone() - Vec3::new_int(1, 1, 1);
+        let falloff = 1_f64 - centered.length_squared();
+        if falloff <= 0_f64 {
+            return 0_f64;
+        }
+        let noise = 0.5 * self.perlin.fbm(&(p.clone() * self.frequency), self.octaves) + 0.5;
+        (2_f64 * noise * falloff - 0.5).clamp(0_f64, 1_f64)
+    }
+    fn max_density(&self) -> f64 {
+        1_f64
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_noise_density() {
+        let field = NoiseDensity::new(3, 4.0, 5);
+        assert_eq!(field.density(&Vec3::new_int(0, 0, 0)), 0.0);
+        assert_eq!(field.density(&Vec3::new(0.5, 0.5, 0.0)), 0.0);
+        let center = Vec3::new(0.5, 0.5, 0.5);
+        assert!(field.density(&center) > 0.0);
+        for i in 0..1000 {
+            let p = Vec3::new(i as f64 / 1000.0, (i * 7 % 1000) as f64 / 1000.0, 0.5);
+            let density = field.density(&p);
+            assert!((0.0..=field.max_density()).contains(&density));
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use std::{fs, io, path::Path};
+
+use super::DensityField;
+use crate::Vec3;
+
+/// Dense 3D grid of densities, trilinearly interpolated between the centers of the voxels
+/// Values are stored x first, then y, then z (Index (z * ny + y) * nx + x)
+pub struct VoxelGrid {
+    resolution: [usize; 3],
+    values: Vec<f32>,
+    max: f64,
+}
+impl VoxelGrid {
+    /// Errors if the number of values does not match the resolution
+    /// Negative (or NaN) values are clamped to 0, a medium can not have a negative density
+    pub fn new(resolution: [usize; 3], values: Vec<f32>) -> io::Result<Self> {
+        let count = resolution[0] * resolution[1] * resolution[2];
+        if values.len() != count {
+            return Err(invalid_data(format!(
+                "the grid has {} values, but the resolution {:?} needs {}",
+                values.len(),
+                resolution,
+                count
+            )));
+        }
+        let values: Vec<f32> = values.into_iter().map(|value| value.max(0_f32)).collect();
+        let max = values.iter().fold(0_f32, |max, value| max.max(*value)) as f64;
+        Ok(Self {
+            resolution,
+            values,
+            max,
+        })
+    }
+    /// Loads a Mitsuba `.vol` grid, with float32 or uint8 values (Mapped into [0, 1])
+    /// Only the first channel is used, and the bounding box in the file is ignored (The medium is placed by its own corners)
+    // Implementation Details:
+    // The header is `VOL` with version 3, then the encoding (1 = float32, 3 = uint8), the resolution and the number of channels as i32, and the bounding box as 6 f32
+    // Everything is little endian
+    pub fn from_vol(path: &Path) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        if bytes.len() < 48 || &bytes[0..4] != b"VOL\x03" {
+            return Err(invalid_data("not a version 3 .vol file".to_string()));
+        }
+        let header = |index: usize| {
+            i32::from_le_bytes(bytes[4 + index * 4..8 + index * 4].try_into().unwrap())
+        };
+        let encoding = header(0);
+        let resolution = [header(1), header(2), header(3)];
+        let channels = header(4);
+        if resolution.iter().any(|n| *n <= 0) || channels <= 0 {
+            return Err(invalid_data(format!(
+                "invalid resolution {:?} with {} channels",
+                resolution, channels
+            )));
+        }
+        let resolution = resolution.map(|n| n as usize);
+        let count = resolution[0] * resolution[1] * resolution[2];
+        let channels = channels as usize;
+        let data = &bytes[48..];
+        let values: Vec<f32> = match encoding {
+            1 => {
+                expect_length(data, count * channels * 4)?;
+                data.chunks_exact(4)
+                    .step_by(channels)
+                    .map(|value| f32::from_le_bytes(value.try_into().unwrap()))
+                    .collect()
+            }
+            3 => {
+                expect_length(data, count * channels)?;
+                data.iter()
+                    .step_by(channels)
+                    .map(|value| *value as f32 / 255_f32)
+                    .collect()
+            }
+            other => {
+                return Err(invalid_data(format!(
+                    "unsupported encoding {}, expected 1 (float32) or 3 (uint8)",
+                    other
+                )))
+            }
+        };
+        Self::new(resolution, values)
+    }
+    /// Loads a headerless grid of uint8 densities (Mapped into [0, 1]), the size of the file has to match the resolution
+    pub fn from_raw(path: &Path, resolution: [usize; 3]) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        expect_length(&bytes, resolution[0] * resolution[1] * resolution[2])?;
+        Self::new(
+            resolution,
+            bytes.iter().map(|value| *value as f32 / 255_f32).collect(),
+        )
+    }
+    pub fn resolution(&self) -> [usize; 3] {
+        self.resolution
+    }
+    /// Value of the voxel, clamped to the edges of the grid
+    fn voxel(&self, index: [i64; 3]) -> f64 {
+        let [x, y, z] =
+            [0, 1, 2].map(|axis| index[axis].clamp(0, self.resolution[axis] as i64 - 1) as usize);
+        self.values[(z * self.resolution[1] + y) * self.resolution[0] + x] as f64
+    }
+}
+impl DensityField for VoxelGrid {
+    fn density(&self, p: &Vec3) -> f64 {
+        // Position in voxels, relative to the centers of the voxels
+        let position = [0, 1, 2].map(|axis| p[axis] * self.resolution[axis] as f64 - 0.5);
+        let cell = position.map(|value| value.floor());
+        let fraction = [0, 1, 2].map(|axis| position[axis] - cell[axis]);
+        let cell = cell.map(|value| value as i64);
+
+        let mut density = 0_f64;
+        for corner in 0..8 {
+            let offset = [corner & 1, (corner >> 1) & 1, (corner >> 2) & 1];
+            let weight: f64 = (0..3)
+                .map(|axis| {
+                    if offset[axis] == 1 {
+                        fraction[axis]
+                    } else {
+                        1_f64 - fraction[axis]
+                    }
+                })
+                .product();
+            density += weight * self.voxel([0, 1, 2].map(|axis| cell[axis] + offset[axis] as i64));
+        }
+        density
+    }
+    fn max_density(&self) -> f64 {
+        self.max
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+fn expect_length(data: &[u8], length: usize) -> io::Result<()> {
+    if data.len() != length {
+        return Err(invalid_data(format!(
+            "expected {} bytes of data, found {}",
+            length,
+            data.len()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_voxel_grid_density() {
+        let grid = VoxelGrid::new([2, 1, 1], vec![0.0, 1.0]).unwrap();
+        assert_eq!(grid.max_density(), 1.0);
+        // Voxel centers are at 0.25 and 0.75, and the edges are clamped
+        assert_eq!(grid.density(&Vec3::new(0.25, 0.5, 0.5)), 0.0);
+        assert_eq!(grid.density(&Vec3::new(0.5, 0.5, 0.5)), 0.5);
+        assert_eq!(grid.density(&Vec3::new(1.0, 0.0, 1.0)), 1.0);
+    }
+
+    #[test]
+    fn test_voxel_grid_invalid_values() {
+        assert!(VoxelGrid::new([2, 2, 1], vec![0.0, 1.0]).is_err());
+        // Negative and NaN densities are clamped to 0
+        let grid = VoxelGrid::new([3, 1, 1], vec![-2.0, f32::NAN, 0.5]).unwrap();
+        assert_eq!(grid.max_density(), 0.5);
+        assert_eq!(grid.voxel([0, 0, 0]), 0.0);
+        assert_eq!(grid.voxel([1, 0, 0]), 0.0);
+    }
+
+    #[test]
+    fn test_voxel_grid_files() {
+        let directory = std::env::temp_dir();
+        let mut vol = b"VOL\x03".to_vec();
+        for value in [1_i32, 2, 1, 2, 1] {
+            vol.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in [0_f32, 0.0, 0.0, 1.0, 1.0, 1.0] {
+            vol.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in [0.1_f32, 0.2, 0.3, 0.4] {
+            vol.extend_from_slice(&value.to_le_bytes());
+        }
+        let vol_path = directory.join("rsr-test-grid.vol");
+        fs::write(&vol_path, &vol).unwrap();
+        let grid = VoxelGrid::from_vol(&vol_path).unwrap();
+        assert_eq!(grid.resolution(), [2, 1, 2]);
+        assert_eq!(grid.voxel([1, 0, 1]), 0.4_f32 as f64);
+
+        vol.pop();
+        fs::write(&vol_path, &vol).unwrap();
+        assert!(VoxelGrid::from_vol(&vol_path).is_err());
+        fs::remove_file(&vol_path).unwrap();
+
+        let raw_path = directory.join("rsr-test-grid.raw");
+        fs::write(&raw_path, [0_u8, 255, 51, 0]).unwrap();
+        let grid = VoxelGrid::from_raw(&raw_path, [1, 2, 2]).unwrap();
+        assert_eq!(grid.voxel([0, 1, 0]), 1.0);
+        assert_eq!(grid.voxel([0, 0, 1]), 0.2_f32 as f64);
+        assert!(VoxelGrid::from_raw(&raw_path, [2, 2, 2]).is_err());
+        fs::remove_file(&raw_path).unwrap();
+    }
+}